@@ -0,0 +1,597 @@
+// The HTTP API through which the command line runs commands on a daemon.
+//
+// A command is run by POSTing to /api/v0/<command path> (e.g.
+// /api/v0/file/ls) with a JSON body listing its arguments and options. The
+// response body is the command's output, sent with chunked transfer encoding
+// as the command writes it, so that long-running commands (pubsub sub)
+// stream their output. Commands failing before writing anything get an error
+// status, and a JSON body with the error message and exit code. Commands
+// failing later end the body with an X-Stream-Error trailer holding the same
+// JSON.
+//
+// Any web page can make the browser POST to the API, so, as in go-ipfs,
+// requests with an Origin or Referer other than the API itself are refused,
+// and so are requests that aren't JSON, which a page can't send elsewhere
+// without a CORS preflight (never answered here). File arguments name paths
+// on the daemon's filesystem, so they're only taken from requests holding
+// the token the daemon writes to the repo (readable by local users of the
+// repo only).
+
+use commands::{self, Command, CommandName};
+use commands::request::{self, Arg, FileArg, Request};
+use core::IpfsNode;
+use crypto;
+use error::Error;
+use ipfs_commands;
+use multiaddr::Multiaddr;
+
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::Arc;
+use std::thread;
+
+// The file in the repo containing the address of the running daemon's API
+pub const API_FILE: &'static str = "api";
+// The file in the repo containing the token that allows file arguments
+pub const API_TOKEN_FILE: &'static str = "api.token";
+
+const API_PATH_PREFIX: &'static str = "/api/v0/";
+const MAX_REQUEST_BODY_SIZE: usize = 1024 * 1024;
+const STREAM_ERROR_TRAILER: &'static str = "X-Stream-Error";
+const TOKEN_HEADER: &'static str = "X-Ipfs-Api-Token";
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ApiRequest {
+    arguments: Vec<ApiArgument>,
+    options: Vec<ApiOption>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ApiArgument {
+    name: String,
+    values: Vec<String>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ApiOption {
+    name: String,
+    value: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct ApiError {
+    message: String,
+    code: i32,
+}
+
+fn api_file_path(repo_dir: &Path) -> PathBuf {
+    let mut path = repo_dir.to_path_buf();
+    path.push(API_FILE);
+    path
+}
+
+// Records the API address in the repo so the command line can find it
+pub fn write_api_file(repo_dir: &Path, addr: &Multiaddr) -> Result<(), Error> {
+    let path = api_file_path(repo_dir);
+    let mut file = try!(File::create(&path).map_err(|e| {
+        Error::Io(format!("Error creating API file {:?}", path), e)
+    }));
+    file.write_all(addr.to_string().as_bytes())
+        .map_err(|e| Error::Io(format!("Error writing API file {:?}", path), e))
+}
+
+// Makes a new token for this run of the daemon and records it in the repo,
+// readable by its owner only
+pub fn write_token_file(repo_dir: &Path) -> Result<String, Error> {
+    let path = repo_dir.join(API_TOKEN_FILE);
+    let token = crypto::random_bytes(32).to_hex();
+    let _ = fs::remove_file(&path); // so that the mode is the one given below
+    let mut file = try!(OpenOptions::new()
+                            .write(true)
+                            .create_new(true)
+                            .mode(0o600)
+                            .open(&path)
+                            .map_err(|e| {
+                                Error::Io(format!("Error creating API token file {:?}", path), e)
+                            }));
+    try!(file.write_all(token.as_bytes()).map_err(|e| {
+        Error::Io(format!("Error writing API token file {:?}", path), e)
+    }));
+    Ok(token)
+}
+
+// The token of the daemon running on the repo, if we can read it
+pub fn read_token_file(repo_dir: &Path) -> Option<String> {
+    let mut token = String::new();
+    match File::open(repo_dir.join(API_TOKEN_FILE)).and_then(|mut f| f.read_to_string(&mut token)) {
+        Ok(_) => Some(token.trim().to_string()),
+        Err(_) => None,
+    }
+}
+
+// The API address of the daemon running on the repo, if any. The file can be
+// left behind by a daemon that was killed, so the daemon may not actually be
+// running.
+pub fn daemon_address(repo_dir: &Path) -> Result<Option<Multiaddr>, Error> {
+    let path = api_file_path(repo_dir);
+    let mut file = match File::open(&path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Io(format!("Error opening API file {:?}", path), e)),
+        Ok(file) => file,
+    };
+
+    let mut addr = String::new();
+    try!(file.read_to_string(&mut addr)
+             .map_err(|e| Error::Io(format!("Error reading API file {:?}", path), e)));
+    Multiaddr::parse(addr.trim()).map(Some)
+}
+
+// Serves API requests until the process exits. Each request is handled in
+// its own thread. `token` is the one written by write_token_file.
+pub fn serve(node: Arc<IpfsNode>, repo_dir: PathBuf, listener: TcpListener, token: String) {
+    let token = Arc::new(token);
+    for stream in listener.incoming() {
+        match stream {
+            Err(e) => warn!("Error accepting API connection: {}", e),
+            Ok(stream) => {
+                let node = node.clone();
+                let repo_dir = repo_dir.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(&node, repo_dir, &token, stream) {
+                        debug!("Error handling API connection: {}", e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn handle_connection(node: &IpfsNode,
+                     repo_dir: PathBuf,
+                     token: &str,
+                     mut stream: TcpStream)
+                     -> io::Result<()> {
+    let local_addr = try!(stream.local_addr());
+    let http_req = {
+        let mut reader = BufReader::new(&mut stream);
+        try!(read_http_request(&mut reader))
+    };
+
+    let mut output = ChunkedWriter::new(&mut stream);
+    let result = check_request(&http_req, &local_addr).and_then(|_| {
+        let path = &http_req.path[API_PATH_PREFIX.len()..];
+        let allow_files = http_req.headers.get(&TOKEN_HEADER.to_lowercase()).map(|t| &t[..]) ==
+                          Some(token);
+        run_request(node, repo_dir, path, &http_req.body, allow_files, &mut output)
+    });
+
+    match result {
+        Ok(()) => output.finish(None),
+        Err(e) => {
+            let api_error = ApiError {
+                message: e.to_string(),
+                code: e.exit_code(),
+            };
+            let body = json::encode(&api_error).unwrap_or(String::new());
+            if output.started {
+                output.finish(Some(&body))
+            } else {
+                write_http_response(&mut *output.writer,
+                                    e.http_status(),
+                                    "application/json",
+                                    body.as_bytes())
+            }
+        }
+    }
+}
+
+// Sends a successful response with each write as a chunk of the body. The
+// response head is only sent with the first write, so that an error can
+// still be reported with its status until then.
+struct ChunkedWriter<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    started: bool,
+}
+
+impl<'a, W: Write> ChunkedWriter<'a, W> {
+    fn new(writer: &'a mut W) -> Self {
+        ChunkedWriter {
+            writer: writer,
+            started: false,
+        }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            try!(write!(self.writer,
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: \
+                         chunked\r\nTrailer: {}\r\nConnection: close\r\n\r\n",
+                        STREAM_ERROR_TRAILER));
+        }
+        Ok(())
+    }
+
+    // Ends the body, with `error` (a JSON ApiError) in the error trailer if
+    // the command failed
+    fn finish(&mut self, error: Option<&str>) -> io::Result<()> {
+        try!(self.start());
+        try!(write!(self.writer, "0\r\n"));
+        if let Some(error) = error {
+            try!(write!(self.writer, "{}: {}\r\n", STREAM_ERROR_TRAILER, error));
+        }
+        try!(write!(self.writer, "\r\n"));
+        self.writer.flush()
+    }
+}
+
+impl<'a, W: Write> Write for ChunkedWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // an empty chunk would end the body
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        try!(self.start());
+        try!(write!(self.writer, "{:x}\r\n", buf.len()));
+        try!(self.writer.write_all(buf));
+        try!(self.writer.write_all(b"\r\n"));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// Refuses requests that aren't API requests, or that could come from a web
+// page
+fn check_request(http_req: &HttpRequest, local_addr: &SocketAddr) -> Result<(), Error> {
+    if http_req.method != "POST" {
+        return Err(Error::InvalidArgument(format!("Unsupported method {}, use POST",
+                                                  http_req.method)));
+    }
+    if !http_req.path.starts_with(API_PATH_PREFIX) {
+        return Err(Error::NotFound(format!("API endpoint {}", http_req.path)));
+    }
+    for header in &["origin", "referer"] {
+        if let Some(value) = http_req.headers.get(*header) {
+            if !is_api_origin(value, local_addr) {
+                return Err(Error::Forbidden(format!("API requests from {} are not allowed",
+                                                    value)));
+            }
+        }
+    }
+    let content_type = http_req.headers.get("content-type").map(|t| {
+        t.split(';').next().unwrap_or("").trim().to_lowercase()
+    });
+    if content_type != Some("application/json".to_string()) {
+        return Err(Error::Forbidden("API requests must have Content-Type application/json"
+                                        .to_string()));
+    }
+    Ok(())
+}
+
+// Whether `url` (an Origin or Referer) is the API at `local_addr` itself
+fn is_api_origin(url: &str, local_addr: &SocketAddr) -> bool {
+    let authority = match url.find("://") {
+        None => return false,
+        Some(i) => url[i + 3..].split('/').next().unwrap_or(""),
+    };
+    if authority == local_addr.to_string() {
+        return true;
+    }
+    local_addr.ip().is_loopback() && authority == format!("localhost:{}", local_addr.port())
+}
+
+// Runs the command at `cmd_path` with the arguments and options in `body`,
+// writing its output to `output`. File arguments are refused unless
+// `allow_files`.
+fn run_request(node: &IpfsNode,
+               repo_dir: PathBuf,
+               cmd_path: &str,
+               body: &[u8],
+               allow_files: bool,
+               output: &mut Write)
+               -> Result<(), Error> {
+    let root = ipfs_commands::root::make_command();
+
+    // options of the commands leading to the command apply to it as well
+    let mut cmd: &Command = &*root;
+    let mut cmd_opts = HashMap::new();
+    cmd_opts.extend(cmd.get_options());
+    for name in cmd_path.split('/').filter(|s| !s.is_empty()) {
+        cmd = match cmd.get_subcommand(name) {
+            None => return Err(Error::NotFound(format!("command {}", cmd_path))),
+            Some(subcmd) => subcmd,
+        };
+        cmd_opts.extend(cmd.get_options());
+    }
+
+    let body = try!(str::from_utf8(body)
+                        .map_err(|e| Error::decode("API request is not UTF-8".to_string(), e)));
+    let api_req: ApiRequest = try!(json::decode(body).map_err(|e| {
+        Error::decode("Error decoding API request".to_string(), e)
+    }));
+
+    let mut args = Vec::new();
+    for api_arg in api_req.arguments {
+        let cmd_arg = match cmd.get_arguments().find(|arg| arg.name() == api_arg.name) {
+            None => {
+                return Err(Error::InvalidArgument(format!("Unknown argument {}", api_arg.name)))
+            }
+            Some(arg) => arg,
+        };
+        let arg = match cmd_arg.arg_type() {
+            commands::ArgumentType::String => Arg::new_string_arg(api_arg.values),
+            commands::ArgumentType::File if !allow_files => {
+                return Err(Error::Forbidden(format!("{} names files on the daemon's \
+                                                     filesystem, which is only allowed from \
+                                                     its command line",
+                                                    api_arg.name)))
+            }
+            commands::ArgumentType::File => {
+                let mut files = Vec::new();
+                for value in api_arg.values {
+                    files.push(try!(FileArg::new(cmd_arg.name(), value)));
+                }
+                Arg::new_file_arg(files)
+            }
+        };
+        args.push((cmd_arg.name(), arg));
+    }
+
+    let mut opts = Vec::new();
+    for api_opt in api_req.options {
+        let cmd_opt = match cmd_opts.get(&*api_opt.name) {
+            None => return Err(Error::InvalidArgument(format!("Unknown option {}", api_opt.name))),
+            Some(opt) => *opt,
+        };
+        opts.push((cmd_opt.get_name(),
+                   try!(request::Opt::parse_string(cmd_opt.get_name(),
+                                                   api_opt.value,
+                                                   cmd_opt.opt_type))));
+    }
+
+    let context = request::Context::new(repo_dir, Some(node));
+    let req = Request::new(cmd, args, opts, context).with_output(Box::new(output));
+    cmd.run(&req)
+}
+
+// Runs the request on the daemon whose API is at `addr`, writing the output
+// to `out`. `token` (see read_token_file) is needed for file arguments.
+// Fails with an `Error::Io` of kind `ConnectionRefused` when no daemon is
+// listening.
+pub fn run_remote<W: Write>(addr: &Multiaddr,
+                            token: Option<&str>,
+                            cmd_path: &[CommandName],
+                            req: &Request,
+                            out: &mut W)
+                            -> Result<(), Error> {
+    let mut api_req = ApiRequest {
+        arguments: Vec::new(),
+        options: Vec::new(),
+    };
+    for (name, arg) in req.args() {
+        let values = match *arg {
+            Arg::Strings(ref v) => v.clone(),
+            // the daemon may not share our working directory
+            Arg::Files(ref v) => {
+                let cwd = try!(env::current_dir().map_err(|e| {
+                    Error::Io("Error getting current directory".to_string(), e)
+                }));
+                v.iter().map(|f| cwd.join(f).to_string_lossy().into_owned()).collect()
+            }
+        };
+        api_req.arguments.push(ApiArgument {
+            name: name.to_string(),
+            values: values,
+        });
+    }
+    for (name, opt) in req.options() {
+        api_req.options.push(ApiOption {
+            name: name.to_string(),
+            value: opt.to_string(),
+        });
+    }
+    let body = try!(json::encode(&api_req)
+                        .map_err(|e| Error::encode("Error encoding API request".to_string(), e)));
+
+    let socket_addrs = try!(addr.to_socket_addrs());
+    let mut stream = try!(TcpStream::connect(&socket_addrs[..]).map_err(|e| {
+        Error::Io(format!("Error connecting to the daemon API at {}", addr), e)
+    }));
+    let token_header = match token {
+        Some(token) => format!("{}: {}\r\n", TOKEN_HEADER, token),
+        None => String::new(),
+    };
+    try!(write!(stream,
+                "POST {}{} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+                 {}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                API_PATH_PREFIX,
+                cmd_path.join("/"),
+                socket_addrs[0],
+                token_header,
+                body.len(),
+                body)
+             .map_err(|e| Error::Io("Error sending API request".to_string(), e)));
+
+    let mut reader = BufReader::new(stream);
+    let (status, chunked) = try!(read_http_response_head(&mut reader).map_err(|e| {
+        Error::Io("Error reading API response".to_string(), e)
+    }));
+    if status == 200 && chunked {
+        return read_chunked_body(&mut reader, out);
+    } else if status == 200 {
+        return io::copy(&mut reader, out)
+                   .map(|_| ())
+                   .map_err(|e| Error::Io("Error reading API response".to_string(), e));
+    }
+
+    let mut body = String::new();
+    try!(reader.read_to_string(&mut body)
+               .map_err(|e| Error::Io("Error reading API response".to_string(), e)));
+    match json::decode::<ApiError>(&body) {
+        Ok(api_error) => Err(Error::Remote(api_error.message, api_error.code)),
+        Err(_) => Err(Error::Remote(format!("The daemon returned HTTP status {}", status), 70)),
+    }
+}
+
+// Copies the chunks of a response body to `out` as they arrive. Fails with
+// the error in the stream error trailer, if there is one.
+fn read_chunked_body<R: BufRead, W: Write>(reader: &mut R, out: &mut W) -> Result<(), Error> {
+    let read_error = |e: io::Error| Error::Io("Error reading API response".to_string(), e);
+    loop {
+        let mut line = String::new();
+        try!(reader.read_line(&mut line).map_err(&read_error));
+        // chunk extensions follow a semicolon
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = try!(usize::from_str_radix(size, 16)
+                            .map_err(|_| read_error(invalid_data("Invalid chunk size"))));
+        if size == 0 {
+            break;
+        }
+        let mut chunk = vec![0; size + 2]; // with the CRLF ending it
+        try!(reader.read_exact(&mut chunk).map_err(&read_error));
+        try!(out.write_all(&chunk[..size])
+                .and_then(|_| out.flush())
+                .map_err(|e| Error::Io("Error writing command output".to_string(), e)));
+    }
+
+    loop {
+        let mut line = String::new();
+        if try!(reader.read_line(&mut line).map_err(&read_error)) == 0 {
+            return Ok(());
+        }
+        let line = line.trim_right();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        if name.to_lowercase() == STREAM_ERROR_TRAILER.to_lowercase() {
+            return match json::decode::<ApiError>(value) {
+                Ok(api_error) => Err(Error::Remote(api_error.message, api_error.code)),
+                Err(_) => Err(Error::Remote(value.to_string(), 70)),
+            };
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Reads the header lines up to the blank line ending them, returning the
+// first line and the headers, keyed by their lowercased names
+fn read_http_head<R: BufRead>(reader: &mut R) -> io::Result<(String, HashMap<String, String>)> {
+    let mut first_line = None;
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if try!(reader.read_line(&mut line)) == 0 {
+            return Err(invalid_data("Unexpected end of HTTP header"));
+        }
+        let line = line.trim_right();
+        if line.is_empty() {
+            break;
+        }
+        if first_line.is_none() {
+            first_line = Some(line.to_string());
+            continue;
+        }
+
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim().to_lowercase();
+        let value = parts.next().unwrap_or("").trim().to_string();
+        headers.insert(name, value);
+    }
+
+    match first_line {
+        None => Err(invalid_data("Empty HTTP header")),
+        Some(line) => Ok((line, headers)),
+    }
+}
+
+fn is_chunked(headers: &HashMap<String, String>) -> bool {
+    headers.get("transfer-encoding").map_or(false, |v| v.to_lowercase().contains("chunked"))
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn read_http_request<R: BufRead>(reader: &mut R) -> io::Result<HttpRequest> {
+    let (request_line, headers) = try!(read_http_head(reader));
+    let mut parts = request_line.split(' ');
+    let method = parts.next().unwrap_or("").to_string();
+    let path = match parts.next() {
+        None => return Err(invalid_data("HTTP request line has no path")),
+        Some(path) => path.to_string(),
+    };
+
+    let length = match headers.get("content-length") {
+        None => 0,
+        Some(value) => try!(value.parse().map_err(|_| invalid_data("Invalid Content-Length"))),
+    };
+    if length > MAX_REQUEST_BODY_SIZE {
+        return Err(invalid_data("HTTP request body too large"));
+    }
+    let mut body = vec![0; length];
+    try!(reader.read_exact(&mut body));
+    Ok(HttpRequest {
+        method: method,
+        path: path,
+        headers: headers,
+        body: body,
+    })
+}
+
+// Returns the status code of the response, and whether its body is chunked
+fn read_http_response_head<R: BufRead>(reader: &mut R) -> io::Result<(u16, bool)> {
+    let (status_line, headers) = try!(read_http_head(reader));
+    let chunked = is_chunked(&headers);
+    match status_line.split(' ').nth(1).and_then(|s| s.parse().ok()) {
+        None => Err(invalid_data("Invalid HTTP status line")),
+        Some(status) => Ok((status, chunked)),
+    }
+}
+
+fn write_http_response<W: Write>(writer: &mut W,
+                                 status: u16,
+                                 content_type: &str,
+                                 body: &[u8])
+                                 -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Internal Server Error",
+    };
+    try!(write!(writer,
+                "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: \
+                 close\r\n\r\n",
+                status,
+                reason,
+                content_type,
+                body.len()));
+    try!(writer.write_all(body));
+    writer.flush()
+}
@@ -0,0 +1,80 @@
+use super::message::WantType;
+use peer::PeerId;
+
+use rust_multihash::Multihash;
+use std::collections::HashMap;
+
+// The most blocks a peer may want from us at once. Further wants are
+// dropped until some are served or cancelled.
+pub const MAX_WANTLIST_SIZE: usize = 1024;
+
+// What we know about our exchanges with a single peer: the blocks it wants
+// from us, and how much data has moved in each direction
+pub struct Ledger {
+    pub partner: PeerId,
+    wantlist: HashMap<Multihash, (i32, WantType)>, // priority and type of each want
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub blocks_sent: u64,
+    pub blocks_received: u64,
+}
+
+impl Ledger {
+    pub fn new(partner: PeerId) -> Self {
+        Ledger {
+            partner: partner,
+            wantlist: HashMap::new(),
+            bytes_sent: 0,
+            bytes_received: 0,
+            blocks_sent: 0,
+            blocks_received: 0,
+        }
+    }
+
+    // Adds a block to the partner's wantlist, returning false if the
+    // wantlist is full
+    pub fn wants(&mut self, block: Multihash, priority: i32, want_type: WantType) -> bool {
+        if self.wantlist.len() >= MAX_WANTLIST_SIZE && !self.wantlist.contains_key(&block) {
+            return false;
+        }
+        self.wantlist.insert(block, (priority, want_type));
+        true
+    }
+
+    pub fn cancel_want(&mut self, block: &Multihash) {
+        self.wantlist.remove(block);
+    }
+
+    pub fn clear_wantlist(&mut self) {
+        self.wantlist.clear();
+    }
+
+    pub fn wantlist_contains(&self, block: &Multihash) -> Option<WantType> {
+        self.wantlist.get(block).map(|&(_, ty)| ty)
+    }
+
+    // The blocks the partner wants, highest priority first
+    pub fn wantlist(&self) -> Vec<(Multihash, i32, WantType)> {
+        let mut wants: Vec<_> = self.wantlist
+                                    .iter()
+                                    .map(|(hash, &(priority, ty))| (hash.clone(), priority, ty))
+                                    .collect();
+        wants.sort_by(|a, b| b.1.cmp(&a.1));
+        wants
+    }
+
+    pub fn sent_block(&mut self, size: usize) {
+        self.blocks_sent += 1;
+        self.bytes_sent += size as u64;
+    }
+
+    pub fn received_block(&mut self, size: usize) {
+        self.blocks_received += 1;
+        self.bytes_received += size as u64;
+    }
+
+    // How much more we've sent the partner than it has sent us
+    pub fn debt_ratio(&self) -> f64 {
+        self.bytes_sent as f64 / (self.bytes_received as f64 + 1.0)
+    }
+}
@@ -0,0 +1,194 @@
+// Bitswap messages, encoded as described in message.proto
+
+use codec::{PbReader, PbWriter};
+use error::Error;
+
+use rust_multihash::Multihash;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WantType {
+    Block,
+    Have,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub block: Multihash,
+    pub priority: i32,
+    pub cancel: bool,
+    pub want_type: WantType,
+    pub send_dont_have: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Presence {
+    Have,
+    DontHave,
+}
+
+#[derive(Debug)]
+pub struct Message {
+    pub wantlist: Vec<Entry>,
+    pub full: bool, // whether the wantlist replaces the one previously sent
+    pub blocks: Vec<Vec<u8>>,
+    pub presences: Vec<(Multihash, Presence)>,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Message {
+            wantlist: Vec::new(),
+            full: false,
+            blocks: Vec::new(),
+            presences: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wantlist.is_empty() && self.blocks.is_empty() && self.presences.is_empty() &&
+        !self.full
+    }
+
+    pub fn add_entry(&mut self, block: Multihash, priority: i32, want_type: WantType) {
+        self.wantlist.push(Entry {
+            block: block,
+            priority: priority,
+            cancel: false,
+            want_type: want_type,
+            send_dont_have: true,
+        });
+    }
+
+    pub fn cancel(&mut self, block: Multihash) {
+        self.wantlist.push(Entry {
+            block: block,
+            priority: 0,
+            cancel: true,
+            want_type: WantType::Block,
+            send_dont_have: false,
+        });
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut msg = PbWriter::new();
+
+        if !self.wantlist.is_empty() || self.full {
+            let mut wantlist = PbWriter::new();
+            for entry in &self.wantlist {
+                let mut e = PbWriter::new();
+                e.bytes(1, &entry.block.clone().into_bytes())
+                 .int32(2, entry.priority)
+                 .bool(3, entry.cancel)
+                 .int32(4,
+                        match entry.want_type {
+                            WantType::Block => 0,
+                            WantType::Have => 1,
+                        })
+                 .bool(5, entry.send_dont_have);
+                wantlist.message(1, &e);
+            }
+            wantlist.bool(2, self.full);
+            msg.message(1, &wantlist);
+        }
+
+        for block in &self.blocks {
+            msg.bytes(2, block);
+        }
+
+        for &(ref hash, presence) in &self.presences {
+            let mut p = PbWriter::new();
+            p.bytes(1, &hash.clone().into_bytes())
+             .int32(2,
+                    match presence {
+                        Presence::Have => 0,
+                        Presence::DontHave => 1,
+                    });
+            msg.message(4, &p);
+        }
+
+        msg.into_bytes()
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut msg = Message::new();
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => {
+                    let mut wantlist = PbReader::new(try!(value.as_bytes()));
+                    while let Some((field, value)) = try!(wantlist.next_field()) {
+                        match field {
+                            1 => msg.wantlist.push(try!(decode_entry(try!(value.as_bytes())))),
+                            2 => msg.full = try!(value.as_bool()),
+                            _ => {}
+                        }
+                    }
+                }
+                2 => msg.blocks.push(try!(value.as_bytes()).to_vec()),
+                4 => msg.presences.push(try!(decode_presence(try!(value.as_bytes())))),
+                _ => {}
+            }
+        }
+        Ok(msg)
+    }
+}
+
+fn decode_entry(buf: &[u8]) -> Result<Entry, Error> {
+    let mut entry = Entry {
+        block: Multihash::from_vec(vec![]),
+        priority: 1,
+        cancel: false,
+        want_type: WantType::Block,
+        send_dont_have: false,
+    };
+    let mut has_block = false;
+
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => {
+                entry.block = Multihash::from_vec(try!(value.as_bytes()).to_vec());
+                has_block = true;
+            }
+            2 => entry.priority = try!(value.as_i32()),
+            3 => entry.cancel = try!(value.as_bool()),
+            4 => {
+                entry.want_type = match try!(value.as_i32()) {
+                    1 => WantType::Have,
+                    _ => WantType::Block,
+                }
+            }
+            5 => entry.send_dont_have = try!(value.as_bool()),
+            _ => {}
+        }
+    }
+
+    if !has_block {
+        return Err(Error::Decode("Bitswap wantlist entry has no block".to_string(), None));
+    }
+    Ok(entry)
+}
+
+fn decode_presence(buf: &[u8]) -> Result<(Multihash, Presence), Error> {
+    let mut hash = None;
+    let mut presence = Presence::Have;
+
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => hash = Some(Multihash::from_vec(try!(value.as_bytes()).to_vec())),
+            2 => {
+                presence = match try!(value.as_i32()) {
+                    1 => Presence::DontHave,
+                    _ => Presence::Have,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match hash {
+        None => Err(Error::Decode("Bitswap block presence has no block".to_string(), None)),
+        Some(hash) => Ok((hash, presence)),
+    }
+}
@@ -0,0 +1,334 @@
+// Bitswap: exchanging blocks with peers. We tell connected peers which
+// blocks we want (our wantlist) and they answer with the blocks, or with
+// whether they have them. We do the same for their wantlists.
+
+use block::Block;
+use blockstore::Blockstore;
+use error::Error;
+use peer::PeerId;
+use swarm::Notifiee;
+use util;
+
+use rust_multihash::Multihash;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use self::ledger::Ledger;
+use self::message::{Message, Presence, WantType};
+use self::network::{Network, Receiver};
+
+pub mod ledger;
+pub mod message;
+pub mod network;
+
+const GET_BLOCK_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_PRIORITY: i32 = 1;
+
+pub struct Bitswap {
+    blockstore: Arc<Blockstore>,
+    network: Arc<Network>,
+    ledgers: Mutex<HashMap<PeerId, Ledger>>,
+    wants: Mutex<HashMap<Multihash, Want>>,
+    wants_changed: Condvar, // signalled when a block arrives or a peer answers
+}
+
+// A block we're waiting for
+struct Want {
+    dont_have: HashSet<PeerId>, // peers that told us they don't have it
+}
+
+// Our exchanges with a peer
+pub struct Receipt {
+    pub peer: PeerId,
+    pub debt_ratio: f64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub blocks_exchanged: u64,
+}
+
+pub struct Stat {
+    pub wantlist: Vec<Multihash>,
+    pub peers: Vec<PeerId>,
+    pub blocks_received: u64,
+    pub blocks_sent: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+impl Bitswap {
+    pub fn new(blockstore: Arc<Blockstore>, network: Arc<Network>) -> Self {
+        Bitswap {
+            blockstore: blockstore,
+            network: network,
+            ledgers: Mutex::new(HashMap::new()),
+            wants: Mutex::new(HashMap::new()),
+            wants_changed: Condvar::new(),
+        }
+    }
+
+    // Fetches a block from connected peers, storing it in the blockstore.
+    // Fails once every connected peer has said it doesn't have the block, or
+    // after a timeout.
+    pub fn get_block(&self, hash: &Multihash) -> Result<Block, Error> {
+        self.wants.lock().unwrap().entry(hash.clone()).or_insert_with(Want::new);
+        let mut msg = Message::new();
+        msg.add_entry(hash.clone(), DEFAULT_PRIORITY, WantType::Block);
+        self.broadcast(&msg);
+
+        let deadline = Instant::now() + Duration::from_secs(GET_BLOCK_TIMEOUT_SECS);
+        let mut wants = self.wants.lock().unwrap();
+        loop {
+            if try!(self.blockstore.has(hash)) {
+                wants.remove(hash);
+                return self.blockstore.get(hash);
+            }
+
+            let peers = self.network.peers();
+            let nobody_has_it = {
+                let want = wants.entry(hash.clone()).or_insert_with(Want::new);
+                peers.iter().all(|p| want.dont_have.contains(p))
+            };
+            if nobody_has_it {
+                wants.remove(hash);
+                return Err(Error::NotFound(format!("block {} (no connected peer has it)", hash)));
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                wants.remove(hash);
+                drop(wants);
+                let mut cancel = Message::new();
+                cancel.cancel(hash.clone());
+                self.broadcast(&cancel);
+                return Err(Error::Timeout(format!("fetching block {} from peers", hash)));
+            }
+            wants = self.wants_changed.wait_timeout(wants, deadline - now).unwrap().0;
+        }
+    }
+
+    // Tells the peers that want a block we now have about it
+    pub fn has_block(&self, hash: &Multihash, data: &[u8]) {
+        let mut to_send = Vec::new();
+        {
+            let mut ledgers = self.ledgers.lock().unwrap();
+            for ledger in ledgers.values_mut() {
+                let mut msg = Message::new();
+                match ledger.wantlist_contains(hash) {
+                    None => continue,
+                    Some(WantType::Block) => {
+                        msg.blocks.push(data.to_vec());
+                        ledger.sent_block(data.len());
+                    }
+                    Some(WantType::Have) => msg.presences.push((hash.clone(), Presence::Have)),
+                }
+                ledger.cancel_want(hash);
+                to_send.push((ledger.partner.clone(), msg));
+            }
+        }
+
+        for (peer, msg) in to_send {
+            self.send(&peer, &msg);
+        }
+    }
+
+    pub fn wantlist(&self) -> Vec<Multihash> {
+        self.wants.lock().unwrap().keys().cloned().collect()
+    }
+
+    // The blocks a peer has asked us for
+    pub fn peer_wantlist(&self, peer: &PeerId) -> Vec<Multihash> {
+        match self.ledgers.lock().unwrap().get(peer) {
+            None => vec![],
+            Some(ledger) => ledger.wantlist().into_iter().map(|(hash, _, _)| hash).collect(),
+        }
+    }
+
+    // Our exchanges with `peer` since it connected, all zero if it isn't
+    // connected
+    pub fn ledger(&self, peer: &PeerId) -> Receipt {
+        let ledgers = self.ledgers.lock().unwrap();
+        let empty = Ledger::new(peer.clone());
+        let ledger = ledgers.get(peer).unwrap_or(&empty);
+        Receipt {
+            peer: peer.clone(),
+            debt_ratio: ledger.debt_ratio(),
+            bytes_sent: ledger.bytes_sent,
+            bytes_received: ledger.bytes_received,
+            blocks_exchanged: ledger.blocks_sent + ledger.blocks_received,
+        }
+    }
+
+    pub fn stat(&self) -> Stat {
+        let ledgers = self.ledgers.lock().unwrap();
+        let mut stat = Stat {
+            wantlist: self.wantlist(),
+            peers: self.network.peers(),
+            blocks_received: 0,
+            blocks_sent: 0,
+            bytes_received: 0,
+            bytes_sent: 0,
+        };
+        for ledger in ledgers.values() {
+            stat.blocks_received += ledger.blocks_received;
+            stat.blocks_sent += ledger.blocks_sent;
+            stat.bytes_received += ledger.bytes_received;
+            stat.bytes_sent += ledger.bytes_sent;
+        }
+        stat
+    }
+
+    fn send(&self, peer: &PeerId, msg: &Message) {
+        if let Err(e) = self.network.send_message(peer, msg) {
+            debug!("Error sending bitswap message to {}: {}", peer, e);
+        }
+    }
+
+    fn broadcast(&self, msg: &Message) {
+        for peer in self.network.peers() {
+            self.send(&peer, msg);
+        }
+    }
+
+    // Answers a peer's wantlist with blocks, or with whether we have them
+    fn handle_wantlist(&self, peer: &PeerId, msg: &Message) -> Message {
+        let mut reply = Message::new();
+        let mut ledgers = self.ledgers.lock().unwrap();
+        let ledger = ledgers.entry(peer.clone()).or_insert_with(|| Ledger::new(peer.clone()));
+        if msg.full {
+            ledger.clear_wantlist();
+        }
+
+        for entry in &msg.wantlist {
+            if entry.cancel {
+                ledger.cancel_want(&entry.block);
+                continue;
+            }
+
+            let block = match self.blockstore.get(&entry.block) {
+                Ok(block) => block,
+                Err(_) => {
+                    // remember it so we can send it if we get it later
+                    if !ledger.wants(entry.block.clone(), entry.priority, entry.want_type) {
+                        debug!("Wantlist of {} is full, dropping want for {}",
+                               peer,
+                               entry.block);
+                        continue;
+                    }
+                    if entry.send_dont_have {
+                        reply.presences.push((entry.block.clone(), Presence::DontHave));
+                    }
+                    continue;
+                }
+            };
+            match entry.want_type {
+                WantType::Block => {
+                    ledger.sent_block(block.get_data().len());
+                    reply.blocks.push(block.take_data());
+                }
+                WantType::Have => reply.presences.push((entry.block.clone(), Presence::Have)),
+            }
+        }
+
+        reply
+    }
+
+    // Stores the blocks in the message that we asked for, returning their
+    // hashes
+    fn handle_blocks(&self, peer: &PeerId, blocks: Vec<Vec<u8>>) -> Vec<Multihash> {
+        let mut received = Vec::new();
+        for data in blocks {
+            let hash = util::hash(&data);
+            if let Some(ledger) = self.ledgers.lock().unwrap().get_mut(peer) {
+                ledger.received_block(data.len());
+            }
+            if !self.wants.lock().unwrap().contains_key(&hash) {
+                debug!("Ignoring unwanted block {} from {}", hash, peer);
+                continue;
+            }
+            if let Err(e) = self.blockstore.put(&hash, &data) {
+                warn!("Error storing block {} from {}: {}", hash, peer, e);
+                continue;
+            }
+            received.push(hash);
+        }
+        received
+    }
+}
+
+impl Want {
+    fn new() -> Self {
+        Want { dont_have: HashSet::new() }
+    }
+}
+
+impl Receiver for Bitswap {
+    fn receive_message(&self, peer: &PeerId, msg: Message) {
+        let reply = self.handle_wantlist(peer, &msg);
+
+        let mut want_blocks = Message::new();
+        {
+            let mut wants = self.wants.lock().unwrap();
+            for &(ref hash, presence) in &msg.presences {
+                match (presence, wants.get_mut(hash)) {
+                    (_, None) => {}
+                    (Presence::DontHave, Some(want)) => {
+                        want.dont_have.insert(peer.clone());
+                    }
+                    (Presence::Have, Some(_)) => {
+                        want_blocks.add_entry(hash.clone(), DEFAULT_PRIORITY, WantType::Block)
+                    }
+                }
+            }
+        }
+        self.wants_changed.notify_all();
+
+        let received = self.handle_blocks(peer, msg.blocks);
+        if !received.is_empty() {
+            let mut cancel = Message::new();
+            {
+                let mut wants = self.wants.lock().unwrap();
+                for hash in &received {
+                    wants.remove(hash);
+                    cancel.cancel(hash.clone());
+                }
+            }
+            self.wants_changed.notify_all();
+            self.broadcast(&cancel);
+        }
+
+        if !reply.is_empty() {
+            self.send(peer, &reply);
+        }
+        if !want_blocks.is_empty() {
+            self.send(peer, &want_blocks);
+        }
+    }
+}
+
+impl Notifiee for Bitswap {
+    fn connected(&self, peer: &PeerId) {
+        self.ledgers
+            .lock()
+            .unwrap()
+            .entry(peer.clone())
+            .or_insert_with(|| Ledger::new(peer.clone()));
+
+        let wantlist = self.wantlist();
+        if wantlist.is_empty() {
+            return;
+        }
+        let mut msg = Message::new();
+        msg.full = true;
+        for hash in wantlist {
+            msg.add_entry(hash, DEFAULT_PRIORITY, WantType::Block);
+        }
+        self.send(peer, &msg);
+    }
+
+    fn disconnected(&self, peer: &PeerId) {
+        self.ledgers.lock().unwrap().remove(peer);
+        // waiters may now have heard from every remaining peer
+        self.wants_changed.notify_all();
+    }
+}
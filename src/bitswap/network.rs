@@ -0,0 +1,73 @@
+use super::message::Message;
+use codec;
+use error::Error;
+use peer::PeerId;
+use swarm::{Notifiee, Stream, Swarm};
+
+use std::sync::Arc;
+
+pub const PROTOCOL_ID: &'static str = "/ipfs/bitswap/1.2.0";
+
+// What Bitswap needs from the network: a way to send messages to peers
+pub trait Network: Send + Sync {
+    fn send_message(&self, peer: &PeerId, msg: &Message) -> Result<(), Error>;
+    fn peers(&self) -> Vec<PeerId>;
+}
+
+// Handles the messages peers send us, and peers coming and going
+pub trait Receiver: Notifiee {
+    fn receive_message(&self, peer: &PeerId, msg: Message);
+}
+
+// Exchanges bitswap messages over swarm streams. Each message is sent on a
+// new stream, length-prefixed.
+pub struct SwarmNetwork {
+    swarm: Arc<Swarm>,
+}
+
+impl SwarmNetwork {
+    pub fn new(swarm: Arc<Swarm>) -> Self {
+        SwarmNetwork { swarm: swarm }
+    }
+
+    // Passes the messages and events for bitswap on the swarm to `receiver`
+    pub fn set_receiver<R: Receiver + 'static>(&self, receiver: Arc<R>) {
+        let handler_receiver = receiver.clone();
+        self.swarm.set_stream_handler(PROTOCOL_ID,
+                                      Arc::new(move |peer: &PeerId, stream: Stream| {
+                                          read_messages(&*handler_receiver, peer, stream)
+                                      }));
+        self.swarm.add_notifiee(receiver);
+    }
+}
+
+fn read_messages<R: Receiver>(receiver: &R, peer: &PeerId, mut stream: Stream) {
+    loop {
+        let frame = match codec::read_length_prefixed(&mut stream) {
+            Err(_) => return, // the peer closed the stream
+            Ok(frame) => frame,
+        };
+        match Message::decode(&frame) {
+            Err(e) => {
+                warn!("Invalid bitswap message from {}: {}", peer, e);
+                stream.reset();
+                return;
+            }
+            Ok(msg) => receiver.receive_message(peer, msg),
+        }
+    }
+}
+
+impl Network for SwarmNetwork {
+    fn send_message(&self, peer: &PeerId, msg: &Message) -> Result<(), Error> {
+        let mut stream = try!(self.swarm.new_stream(peer, PROTOCOL_ID));
+        try!(codec::write_length_prefixed(&mut stream, &msg.encode())
+                 .and_then(|_| stream.close_write())
+                 .map_err(|e| Error::Io(format!("Error sending bitswap message to {}", peer), e)));
+        Ok(())
+    }
+
+    fn peers(&self) -> Vec<PeerId> {
+        self.swarm.peers()
+    }
+}
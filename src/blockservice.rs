@@ -0,0 +1,52 @@
+use bitswap::Bitswap;
+use block::Block;
+use blockstore::Blockstore;
+use error::Error;
+
+use rust_multihash::Multihash;
+use std::sync::{Arc, RwLock};
+
+/// Gets blocks from the local blockstore, falling back to fetching them from
+/// peers when the node is online.
+pub struct BlockService {
+    blockstore: Arc<Blockstore>,
+    exchange: RwLock<Option<Arc<Bitswap>>>, // None when offline
+}
+
+impl BlockService {
+    pub fn new(blockstore: Arc<Blockstore>) -> Self {
+        BlockService {
+            blockstore: blockstore,
+            exchange: RwLock::new(None),
+        }
+    }
+
+    pub fn set_exchange(&self, exchange: Arc<Bitswap>) {
+        *self.exchange.write().unwrap() = Some(exchange);
+    }
+
+    // Only checks the local blockstore
+    pub fn has(&self, hash: &Multihash) -> Result<bool, Error> {
+        self.blockstore.has(hash)
+    }
+
+    pub fn get(&self, hash: &Multihash) -> Result<Block, Error> {
+        match self.blockstore.get(hash) {
+            Err(Error::NotFound(msg)) => {
+                match *self.exchange.read().unwrap() {
+                    None => Err(Error::NotFound(msg)),
+                    Some(ref bitswap) => bitswap.get_block(hash),
+                }
+            }
+            result => result,
+        }
+    }
+
+    pub fn put(&self, hash: &Multihash, data: &[u8]) -> Result<(), Error> {
+        try!(self.blockstore.put(hash, data));
+        if let Some(ref bitswap) = *self.exchange.read().unwrap() {
+            bitswap.has_block(hash, data);
+        }
+        Ok(())
+    }
+}
@@ -1,21 +1,32 @@
 use block::Block;
+use error::Error;
 use util;
 
 use atomicwrites::{AtomicFile, DisallowOverwrite};
 use rust_multihash::Multihash;
-use rustc_serialize::hex::ToHex;
+use rustc_serialize::hex::{FromHex, ToHex};
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{self, Write, Read};
 use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 
 const BLOCKFILE_EXT: &'static str = ".data";
 const BLOCKSTORE_PREFIX_LENGTH: u8 = 4; // in bytes
 pub const BLOCKSTORE_DIR: &'static str = "blocks";
 
+/// Stores blocks keyed by their multihash, either as files in a repo
+/// directory or in memory.
 pub struct Blockstore {
-    path: PathBuf,
-    hex_prefix_length: u8, // length of the prefix in hex digits
+    storage: Storage,
+}
+
+enum Storage {
+    Flatfs {
+        path: PathBuf,
+        hex_prefix_length: u8, // length of the prefix in hex digits
+    },
+    Memory(RwLock<HashMap<Multihash, Vec<u8>>>),
 }
 
 impl Blockstore {
@@ -25,62 +36,142 @@ impl Blockstore {
     // where k = prefix_len
     pub fn new(path: PathBuf) -> Self {
         Blockstore {
-            path: path,
-            hex_prefix_length: 2 * BLOCKSTORE_PREFIX_LENGTH,
+            storage: Storage::Flatfs {
+                path: path,
+                hex_prefix_length: 2 * BLOCKSTORE_PREFIX_LENGTH,
+            },
         }
     }
 
-    pub fn has(&self, multihash: &Multihash) -> Result<bool, String> {
-        util::file_exists(self.block_file(multihash)).map_err(|e| {
-            format!("Error checking for existence of file in Blockstore::has: {}",
-                    e)
-        })
+    /// A Blockstore that keeps blocks in memory and never touches the disk
+    pub fn in_memory() -> Self {
+        Blockstore { storage: Storage::Memory(RwLock::new(HashMap::new())) }
     }
 
-    pub fn get(&self, hash: &Multihash) -> Result<Block, String> {
-        let fname = self.block_file(hash);
-        trace!("Blockstore::get, file name = {:?}", fname);
-        let mut file = try!(File::open(&fname).map_err(|e| {
-            format!("Error opening file {:?} for hash {} in Blockstore::get: {}",
-                    fname,
-                    hash,
-                    e)
-        }));
+    pub fn has(&self, multihash: &Multihash) -> Result<bool, Error> {
+        match self.storage {
+            Storage::Flatfs { .. } => {
+                util::file_exists(self.block_file(multihash)).map_err(|e| {
+                    Error::Io(format!("Error checking for existence of block {}", multihash),
+                              e)
+                })
+            }
+            Storage::Memory(ref blocks) => Ok(blocks.read().unwrap().contains_key(multihash)),
+        }
+    }
 
-        let mut data = Vec::new();
-        try!(file.read_to_end(&mut data)
-                 .map_err(|e| {
-                     format!("Error reading file {:?} for hash {:?} in Blockstore::get: {}",
-                             fname,
-                             hash,
-                             e)
-                 }));
+    pub fn get(&self, hash: &Multihash) -> Result<Block, Error> {
+        let data = match self.storage {
+            Storage::Flatfs { .. } => try!(self.read_block_file(hash)),
+            Storage::Memory(ref blocks) => {
+                match blocks.read().unwrap().get(hash) {
+                    None => return Err(Error::NotFound(format!("block {}", hash))),
+                    Some(data) => data.clone(),
+                }
+            }
+        };
+
+        if util::hash(&data[..]) != *hash {
+            return Err(Error::Corrupt(format!("block data does not match hash {}", hash)));
+        }
         Ok(Block::with_hash(data, hash.clone()))
     }
 
-    pub fn put(&self, multihash: &Multihash, data: &[u8]) -> Result<(), String> {
+    pub fn put(&self, multihash: &Multihash, data: &[u8]) -> Result<(), Error> {
         match self.has(multihash) {
             Ok(true) => return Ok(()),
             _ => {}
         }
 
+        if let Storage::Memory(ref blocks) = self.storage {
+            blocks.write().unwrap().insert(multihash.clone(), data.to_vec());
+            return Ok(());
+        }
+
         let (mut dir, filename) = self.block_dir_and_file(multihash);
         try!(make_prefix_dir(&dir).map_err(|e| {
-            format!("Error making prefix directory for put: {}", e)
+            Error::Io(format!("Error making prefix directory {:?}", dir), e)
         }));
 
 
         dir.push(filename);
         let file_path = dir; // rename for clarity
-        let file = AtomicFile::new(file_path, DisallowOverwrite);
+        let file = AtomicFile::new(&file_path, DisallowOverwrite);
         file.write(|f| f.write_all(data))
-            .map_err(|e| format!("Error writing block file for put: {}", e))
+            .map_err(|e| {
+                Error::Io(format!("Error writing block file {:?}", file_path),
+                          util::atomic_write_error(e))
+            })
     }
 
+    // The hashes of every block in the store
+    pub fn keys(&self) -> Result<Vec<Multihash>, Error> {
+        let path = match self.storage {
+            Storage::Flatfs { ref path, .. } => path,
+            Storage::Memory(ref blocks) => {
+                return Ok(blocks.read().unwrap().keys().cloned().collect())
+            }
+        };
+
+        let read_dir = |dir: &Path| {
+            fs::read_dir(dir).map_err(|e| {
+                Error::Io(format!("Error reading directory {:?}", dir), e)
+            })
+        };
+        let mut keys = Vec::new();
+        for prefix_dir in try!(read_dir(path.as_path())) {
+            let prefix_dir = try!(prefix_dir.map_err(|e| {
+                Error::Io(format!("Error reading directory {:?}", path), e)
+            }));
+            if !prefix_dir.path().is_dir() {
+                continue;
+            }
+            for entry in try!(read_dir(prefix_dir.path().as_path())) {
+                let entry = try!(entry.map_err(|e| {
+                    Error::Io(format!("Error reading directory {:?}", prefix_dir.path()), e)
+                }));
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !name.ends_with(BLOCKFILE_EXT) {
+                    continue;
+                }
+                match name[..name.len() - BLOCKFILE_EXT.len()].from_hex() {
+                    Ok(bytes) => keys.push(Multihash::from_vec(bytes)),
+                    Err(_) => warn!("Unexpected file {:?} in blockstore", entry.path()),
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    fn read_block_file(&self, hash: &Multihash) -> Result<Vec<u8>, Error> {
+        let fname = self.block_file(hash);
+        trace!("Blockstore::get, file name = {:?}", fname);
+        let mut file = try!(File::open(&fname).map_err(|e| {
+            match e.kind() {
+                io::ErrorKind::NotFound => Error::NotFound(format!("block {}", hash)),
+                _ => Error::Io(format!("Error opening file {:?} for hash {}", fname, hash), e),
+            }
+        }));
+
+        let mut data = Vec::new();
+        try!(file.read_to_end(&mut data)
+                 .map_err(|e| {
+                     Error::Io(format!("Error reading file {:?} for hash {}", fname, hash),
+                               e)
+                 }));
+        Ok(data)
+    }
+
+    // Only meaningful for Flatfs storage
     fn block_dir_and_file(&self, multihash: &Multihash) -> (PathBuf, String) {
+        let (path, hex_prefix_length) = match self.storage {
+            Storage::Flatfs { ref path, hex_prefix_length } => (path, hex_prefix_length),
+            Storage::Memory(_) => unreachable!(),
+        };
+
         let hex = multihash.to_hex();
-        let mut dir = self.path.clone();
-        dir.push(&hex[..self.hex_prefix_length as usize]);
+        let mut dir = path.clone();
+        dir.push(&hex[..hex_prefix_length as usize]);
 
         (dir, hex + BLOCKFILE_EXT)
     }
@@ -94,6 +185,10 @@ impl Blockstore {
 
 
 fn make_prefix_dir<P: AsRef<Path>>(path: P) -> Result<(), io::Error> {
+    // other blocks may already share the prefix
+    if try!(util::file_exists(&path)) {
+        return Ok(());
+    }
     try!(fs::create_dir(&path));
 
     // TODO: is this even needed? ensure
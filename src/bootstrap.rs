@@ -0,0 +1,137 @@
+// Bootstrapping: when the daemon starts it connects to a list of known
+// peers, from which it finds others through the DHT. Bootstrap peers are
+// dialed until there are at least `min_connections` open connections. A
+// peer that can't be reached is retried after a backoff that doubles with
+// each failure. The connection count is checked again periodically, so a
+// node that loses its peers reconnects.
+
+use dht::Dht;
+use error::Error;
+use multiaddr::Multiaddr;
+use peer::PeerId;
+use swarm::Swarm;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The go-ipfs bootstrap nodes
+pub const DEFAULT_PEERS: &'static [&'static str] = &[
+    "/ip4/104.131.131.82/tcp/4001/p2p/QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ",
+    "/ip4/104.236.179.241/tcp/4001/p2p/QmSoLPppuBtQSGwKDZT2M73ULpjvfd3aZ6ha4oFGL1KrGM",
+    "/ip4/128.199.219.111/tcp/4001/p2p/QmSoLSafTMBsPKadTEgaXctDQVcqN88CNLHXMkTNwMKPnu",
+    "/ip4/104.236.76.40/tcp/4001/p2p/QmSoLV4Bbm51jM9C4gDYZQ9Cy3U6aXMJDAbzgu2fzaDs64",
+    "/ip4/178.62.158.247/tcp/4001/p2p/QmSoLer265NRgSp2LA3dPaeykiS1J6DifTC88f7uVciLb",
+];
+pub const DEFAULT_MIN_CONNECTIONS: usize = 4;
+
+const CHECK_INTERVAL_SECS: u64 = 30;
+const INITIAL_BACKOFF_SECS: u64 = 1;
+const MAX_BACKOFF_SECS: u64 = 5 * 60;
+
+// Parses the address of a bootstrap peer, which must end with /p2p/<peer id>
+pub fn parse_peer_addr(s: &str) -> Result<Multiaddr, Error> {
+    let addr = try!(Multiaddr::parse(s));
+    if addr.peer_id().is_none() || addr.protocols().len() < 2 {
+        return Err(Error::InvalidArgument(format!("Invalid bootstrap peer {}: expected an \
+                                                   address ending with /p2p/<peer id>",
+                                                  s)));
+    }
+    Ok(addr)
+}
+
+struct Backoff {
+    next_attempt: Instant,
+    delay: Duration,
+}
+
+pub struct Bootstrapper {
+    pub peers: Vec<Multiaddr>,
+    pub min_connections: usize,
+    backoff: Mutex<HashMap<PeerId, Backoff>>,
+}
+
+impl Bootstrapper {
+    pub fn new(peers: Vec<Multiaddr>, min_connections: usize) -> Self {
+        Bootstrapper {
+            peers: peers,
+            min_connections: min_connections,
+            backoff: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Dials the bootstrap peers we aren't connected to, until there are
+    // enough connections. Peers still backing off from a failed attempt are
+    // skipped. Returns how many peers were connected to.
+    pub fn bootstrap(&self, swarm: &Arc<Swarm>) -> usize {
+        let mut connected = 0;
+        for addr in &self.peers {
+            if swarm.peers().len() >= self.min_connections {
+                break;
+            }
+            let peer = addr.peer_id().unwrap();
+            if swarm.is_connected(peer) || *peer == *swarm.local_peer() {
+                continue;
+            }
+            if let Some(backoff) = self.backoff.lock().unwrap().get(peer) {
+                if backoff.next_attempt > Instant::now() {
+                    continue;
+                }
+            }
+
+            match Swarm::dial(swarm, addr) {
+                Ok(_) => {
+                    debug!("Connected to bootstrap peer {}", addr);
+                    self.backoff.lock().unwrap().remove(peer);
+                    connected += 1;
+                }
+                Err(e) => {
+                    debug!("Error connecting to bootstrap peer {}: {}", addr, e);
+                    let mut backoffs = self.backoff.lock().unwrap();
+                    let backoff = backoffs.entry(peer.clone()).or_insert(Backoff {
+                        next_attempt: Instant::now(),
+                        delay: Duration::from_secs(INITIAL_BACKOFF_SECS),
+                    });
+                    backoff.next_attempt = Instant::now() + backoff.delay;
+                    backoff.delay = if backoff.delay * 2 < Duration::from_secs(MAX_BACKOFF_SECS) {
+                        backoff.delay * 2
+                    } else {
+                        Duration::from_secs(MAX_BACKOFF_SECS)
+                    };
+                }
+            }
+        }
+        connected
+    }
+
+    // Keeps the swarm connected to enough peers, for as long as the DHT
+    // exists. The DHT's routing table is filled after connecting to new
+    // bootstrap peers.
+    pub fn start(self, swarm: &Arc<Swarm>, dht: &Arc<Dht>) {
+        let swarm: Weak<Swarm> = Arc::downgrade(swarm);
+        let dht: Weak<Dht> = Arc::downgrade(dht);
+        thread::spawn(move || {
+            loop {
+                let (swarm, dht) = match (swarm.upgrade(), dht.upgrade()) {
+                    (Some(swarm), Some(dht)) => (swarm, dht),
+                    _ => return,
+                };
+                if swarm.peers().len() < self.min_connections && self.bootstrap(&swarm) > 0 {
+                    if let Err(e) = dht.bootstrap() {
+                        debug!("Error bootstrapping the DHT: {}", e);
+                    }
+                }
+
+                let wait = if swarm.peers().len() < self.min_connections {
+                    INITIAL_BACKOFF_SECS
+                } else {
+                    CHECK_INTERVAL_SECS
+                };
+                drop(swarm);
+                drop(dht);
+                thread::sleep(Duration::from_secs(wait));
+            }
+        });
+    }
+}
@@ -0,0 +1,178 @@
+// Content identifiers: the multihash of a block along with the codec its
+// data is in. Version 0 CIDs are bare sha2-256 multihashes of dag-pb nodes,
+// which is what links and hashes have always been. Version 1 CIDs put the
+// version and codec before the multihash, and are shown in base32 with the
+// multibase prefix 'b'. A CID is version 0 whenever it can be.
+//
+// Blocks are stored by multihash alone; the codec only says how to decode
+// them. A block small enough can be inlined in its CID with the identity
+// multihash, whose digest is the block itself, so it's never stored.
+
+use codec;
+use error::Error;
+
+use rust_multihash::Multihash;
+use std::fmt;
+
+pub const CODEC_RAW: u64 = 0x55;
+pub const CODEC_DAG_PB: u64 = 0x70;
+
+const IDENTITY: u8 = 0x00;
+const SHA2_256: u8 = 0x12;
+
+const BASE32_ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Codec {
+    DagProtobuf,
+    Raw, // the block is the data, e.g. a leaf of a file added with raw leaves
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cid {
+    pub codec: Codec,
+    pub hash: Multihash,
+}
+
+impl Cid {
+    pub fn new(codec: Codec, hash: Multihash) -> Self {
+        Cid {
+            codec: codec,
+            hash: hash,
+        }
+    }
+
+    pub fn is_v0(&self) -> bool {
+        let bytes = self.hash.clone().into_bytes();
+        self.codec == Codec::DagProtobuf && bytes.len() == 34 && bytes[0] == SHA2_256 &&
+        bytes[1] == 32
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.is_v0() {
+            return self.hash.clone().into_bytes();
+        }
+        let mut bytes = Vec::new();
+        codec::encode_varint(1, &mut bytes);
+        codec::encode_varint(match self.codec {
+                                 Codec::DagProtobuf => CODEC_DAG_PB,
+                                 Codec::Raw => CODEC_RAW,
+                             },
+                             &mut bytes);
+        bytes.extend(self.hash.clone().into_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() == 34 && bytes[0] == SHA2_256 && bytes[1] == 32 {
+            return Ok(Cid::new(Codec::DagProtobuf, Multihash::from_vec(bytes.to_vec())));
+        }
+        let (version, n) = try!(codec::decode_varint(bytes));
+        if version != 1 {
+            return Err(Error::Decode(format!("Unsupported CID version {}", version), None));
+        }
+        let (code, m) = try!(codec::decode_varint(&bytes[n..]));
+        let codec = match code {
+            CODEC_DAG_PB => Codec::DagProtobuf,
+            CODEC_RAW => Codec::Raw,
+            _ => return Err(Error::Decode(format!("Unsupported CID codec {:#x}", code), None)),
+        };
+        let hash = &bytes[n + m..];
+        if hash.len() < 2 {
+            return Err(Error::Decode("CID without a multihash".to_string(), None));
+        }
+        Ok(Cid::new(codec, Multihash::from_vec(hash.to_vec())))
+    }
+
+    // Parses a base58 version 0 CID, or a base32 version 1 CID
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        if s.starts_with('b') {
+            let bytes = try!(base32_decode(&s[1..]).ok_or_else(|| {
+                Error::InvalidArgument(format!("Invalid base32 CID {}", s))
+            }));
+            return Cid::from_bytes(&bytes);
+        }
+        let hash = try!(Multihash::from_base58_str(s)
+                            .map_err(|e| Error::InvalidArgument(format!("{}: {}", s, e))));
+        Ok(Cid::new(Codec::DagProtobuf, hash))
+    }
+
+    // The block itself, if it's inlined in the CID
+    pub fn inline_data(&self) -> Option<Vec<u8>> {
+        identity_digest(&self.hash)
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_v0() {
+            return write!(f, "{}", self.hash);
+        }
+        write!(f, "b{}", base32_encode(&self.to_bytes()))
+    }
+}
+
+// The identity multihash of `data`, which holds the data itself
+pub fn identity_hash(data: &[u8]) -> Multihash {
+    let mut bytes = vec![IDENTITY];
+    codec::encode_varint(data.len() as u64, &mut bytes);
+    bytes.extend_from_slice(data);
+    Multihash::from_vec(bytes)
+}
+
+// The data held by `hash` if it's an identity multihash
+pub fn identity_digest(hash: &Multihash) -> Option<Vec<u8>> {
+    let bytes = hash.clone().into_bytes();
+    if bytes.first() != Some(&IDENTITY) {
+        return None;
+    }
+    match codec::decode_varint(&bytes[1..]) {
+        Ok((len, n)) if bytes.len() == 1 + n + len as usize => Some(bytes[1 + n..].to_vec()),
+        _ => None,
+    }
+}
+
+// RFC 4648 base32, lowercase and without padding, as multibase uses
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let c = if c >= b'A' && c <= b'Z' {
+            c + (b'a' - b'A')
+        } else {
+            c
+        };
+        let value = match BASE32_ALPHABET.iter().position(|&a| a == c) {
+            Some(value) => value as u32,
+            None => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,238 @@
+// Encoding helpers for the network protocols: unsigned varints, varint
+// length-prefixed frames, and the protobuf wire format.
+//
+// The protocol messages are small, so rather than generating code for each
+// of them (like merkledag::pb and unixfs::pb), they're encoded and decoded by
+// hand with `PbWriter` and `PbReader`. Each protocol module keeps the .proto
+// definition it follows next to its codec.
+
+use error::Error;
+
+use std::io::{self, Read, Write};
+
+// Frames larger than this are refused, so a bad peer can't make us allocate
+// arbitrary amounts of memory
+pub const MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+
+pub fn encode_varint(mut n: u64, buf: &mut Vec<u8>) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+// Decodes a varint from the start of `buf`, returning it along with the
+// number of bytes it took up
+pub fn decode_varint(buf: &[u8]) -> Result<(u64, usize), Error> {
+    let mut n: u64 = 0;
+    for (i, &byte) in buf.iter().enumerate() {
+        if i >= 10 {
+            break;
+        }
+        n |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((n, i + 1));
+        }
+    }
+    Err(Error::Decode("Invalid varint".to_string(), None))
+}
+
+pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut n: u64 = 0;
+    let mut byte = [0u8; 1];
+    for i in 0..10 {
+        try!(reader.read_exact(&mut byte));
+        n |= ((byte[0] & 0x7f) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "varint is too long"))
+}
+
+pub fn write_length_prefixed<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(data.len() + 10);
+    encode_varint(data.len() as u64, &mut buf);
+    buf.extend_from_slice(data);
+    try!(writer.write_all(&buf));
+    writer.flush()
+}
+
+pub fn read_length_prefixed<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = try!(read_varint(reader)) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  format!("frame of {} bytes is too large", len)));
+    }
+    let mut buf = vec![0; len];
+    try!(reader.read_exact(&mut buf));
+    Ok(buf)
+}
+
+const WIRE_VARINT: u64 = 0;
+const WIRE_FIXED64: u64 = 1;
+const WIRE_LENGTH_DELIMITED: u64 = 2;
+const WIRE_FIXED32: u64 = 5;
+
+// Builds a protobuf-encoded message. Fields are written in the order they're
+// added.
+pub struct PbWriter {
+    buf: Vec<u8>,
+}
+
+impl PbWriter {
+    pub fn new() -> Self {
+        PbWriter { buf: Vec::new() }
+    }
+
+    fn tag(&mut self, field: u32, wire_type: u64) {
+        encode_varint(((field as u64) << 3) | wire_type, &mut self.buf);
+    }
+
+    pub fn uint64(&mut self, field: u32, value: u64) -> &mut Self {
+        self.tag(field, WIRE_VARINT);
+        encode_varint(value, &mut self.buf);
+        self
+    }
+
+    // int32 and enum fields. Negative values take ten bytes, as in protobuf
+    pub fn int32(&mut self, field: u32, value: i32) -> &mut Self {
+        self.uint64(field, value as i64 as u64)
+    }
+
+    pub fn bool(&mut self, field: u32, value: bool) -> &mut Self {
+        self.uint64(field, if value { 1 } else { 0 })
+    }
+
+    pub fn bytes(&mut self, field: u32, value: &[u8]) -> &mut Self {
+        self.tag(field, WIRE_LENGTH_DELIMITED);
+        encode_varint(value.len() as u64, &mut self.buf);
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn string(&mut self, field: u32, value: &str) -> &mut Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    pub fn message(&mut self, field: u32, value: &PbWriter) -> &mut Self {
+        self.bytes(field, &value.buf)
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub enum PbValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed64(u64),
+    Fixed32(u32),
+}
+
+impl<'a> PbValue<'a> {
+    pub fn as_u64(&self) -> Result<u64, Error> {
+        match *self {
+            PbValue::Varint(n) | PbValue::Fixed64(n) => Ok(n),
+            PbValue::Fixed32(n) => Ok(n as u64),
+            PbValue::Bytes(_) => Err(wire_type_error()),
+        }
+    }
+
+    pub fn as_i32(&self) -> Result<i32, Error> {
+        self.as_u64().map(|n| n as i64 as i32)
+    }
+
+    pub fn as_bool(&self) -> Result<bool, Error> {
+        self.as_u64().map(|n| n != 0)
+    }
+
+    pub fn as_bytes(&self) -> Result<&'a [u8], Error> {
+        match *self {
+            PbValue::Bytes(b) => Ok(b),
+            _ => Err(wire_type_error()),
+        }
+    }
+
+    pub fn as_string(&self) -> Result<String, Error> {
+        let bytes = try!(self.as_bytes());
+        String::from_utf8(bytes.to_vec()).map_err(|e| {
+            Error::decode("Invalid UTF-8 in protobuf string".to_string(), e)
+        })
+    }
+}
+
+fn wire_type_error() -> Error {
+    Error::Decode("Unexpected protobuf wire type".to_string(), None)
+}
+
+// Iterates over the fields of a protobuf-encoded message. Decoders match on
+// the field numbers they know and skip the rest.
+pub struct PbReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> PbReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        PbReader { buf: buf }
+    }
+
+    pub fn next_field(&mut self) -> Result<Option<(u32, PbValue<'a>)>, Error> {
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+
+        let (tag, n) = try!(decode_varint(self.buf));
+        self.buf = &self.buf[n..];
+        let field = (tag >> 3) as u32;
+
+        let value = match tag & 0x7 {
+            WIRE_VARINT => {
+                let (v, n) = try!(decode_varint(self.buf));
+                self.buf = &self.buf[n..];
+                PbValue::Varint(v)
+            }
+            WIRE_LENGTH_DELIMITED => {
+                let (len, n) = try!(decode_varint(self.buf));
+                let len = len as usize;
+                if self.buf.len() - n < len {
+                    return Err(truncated_error());
+                }
+                let value = &self.buf[n..n + len];
+                self.buf = &self.buf[n + len..];
+                PbValue::Bytes(value)
+            }
+            WIRE_FIXED64 => {
+                if self.buf.len() < 8 {
+                    return Err(truncated_error());
+                }
+                let v = self.buf[..8]
+                            .iter()
+                            .rev()
+                            .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+                self.buf = &self.buf[8..];
+                PbValue::Fixed64(v)
+            }
+            WIRE_FIXED32 => {
+                if self.buf.len() < 4 {
+                    return Err(truncated_error());
+                }
+                let v = self.buf[..4]
+                            .iter()
+                            .rev()
+                            .fold(0u32, |acc, &b| (acc << 8) | b as u32);
+                self.buf = &self.buf[4..];
+                PbValue::Fixed32(v)
+            }
+            _ => return Err(wire_type_error()),
+        };
+
+        Ok(Some((field, value)))
+    }
+}
+
+fn truncated_error() -> Error {
+    Error::Decode("Truncated protobuf message".to_string(), None)
+}
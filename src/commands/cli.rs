@@ -1,15 +1,84 @@
-use super::{Command, OptType};
-use super::request::{self, Request};
+use super::{ArgName, Argument, Command, CommandName, Opt, OptName, OptType};
+use super::request;
 
+use libc;
 use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead};
 use std::slice;
 
-pub type ParseError = String;
-pub type ParseResult<'a> = (&'a Command,
+#[derive(Debug)]
+pub enum ParseError {
+    UnknownOption(String),
+    MissingOptionValue(OptName),
+    InvalidOptionValue(OptName, String),
+    // the unrecognized token, and the closest subcommand name (if any is close)
+    UnknownCommand(String, Option<CommandName>),
+    UnexpectedArgument(String),
+    MissingArgument(ArgName),
+    InvalidArgument(ArgName, String),
+    Stdin(io::Error),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnknownOption(ref name) => write!(f, "Option not recognized: {}", name),
+            ParseError::MissingOptionValue(name) => {
+                write!(f, "Expecting option argument for option {}, but none given", name)
+            }
+            ParseError::InvalidOptionValue(name, ref msg) => {
+                write!(f, "Invalid value for option {}: {}", name, msg)
+            }
+            ParseError::UnknownCommand(ref name, None) => {
+                write!(f, "Subcommand {} not found", name)
+            }
+            ParseError::UnknownCommand(ref name, Some(suggestion)) => {
+                write!(f,
+                       "Subcommand {} not found. Did you mean `{}`?",
+                       name,
+                       suggestion)
+            }
+            ParseError::UnexpectedArgument(ref arg) => write!(f, "Unexpected argument: {}", arg),
+            ParseError::MissingArgument(name) => write!(f, "Missing argument for <{}>", name),
+            ParseError::InvalidArgument(name, ref msg) => {
+                write!(f, "Invalid argument for <{}>: {}", name, msg)
+            }
+            ParseError::Stdin(ref e) => write!(f, "Error reading arguments from stdin: {}", e),
+        }
+    }
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        match *self {
+            ParseError::UnknownOption(_) => "unknown option",
+            ParseError::MissingOptionValue(_) => "missing option value",
+            ParseError::InvalidOptionValue(..) => "invalid option value",
+            ParseError::UnknownCommand(..) => "unknown command",
+            ParseError::UnexpectedArgument(_) => "unexpected argument",
+            ParseError::MissingArgument(_) => "missing argument",
+            ParseError::InvalidArgument(..) => "invalid argument",
+            ParseError::Stdin(_) => "error reading stdin",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ParseError::Stdin(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// The names of the commands leading to the parsed command, the command
+// itself, and the arguments and options given to it
+pub type ParseResult<'a> = (Vec<super::CommandName>,
+                            &'a Command,
                             Vec<(super::ArgName, request::Arg)>,
                             Vec<(super::OptName, request::Opt)>);
-// TODO: support command arguments
-// TODO: support setting option arguments with equal sign (--opt=value)
+
 pub fn parse<I>(mut input: I, root: &Command) -> Result<ParseResult, ParseError>
     where I: Iterator<Item = String>
 {
@@ -20,6 +89,7 @@ pub fn parse<I>(mut input: I, root: &Command) -> Result<ParseResult, ParseError>
     //
     // Then current_cmd will be a reference to (the Command associated with) <cmd3>
     let mut current_cmd = root;
+    let mut cmd_path = Vec::new();
 
     // Options submitted in the request
     let mut opts: Vec<(super::OptName, request::Opt)> = Vec::new();
@@ -34,130 +104,258 @@ pub fn parse<I>(mut input: I, root: &Command) -> Result<ParseResult, ParseError>
     let mut cmd_opts = HashMap::new();
     cmd_opts.extend(root.get_options());
 
-    // Stores the command args when we encounter a command with arguments
-    let mut cmd_args: Option<slice::Iter<super::Argument>> = None;
+    // The arguments of current_cmd that haven't been parsed yet
+    let mut cmd_args: slice::Iter<Argument> = root.get_arguments();
     // used for keeping track of which command arg is currently being parsed
-    let mut curr_arg = None;
+    let mut curr_arg = cmd_args.next();
 
-    let mut token: String;
-
-    loop {
-        token = match input.next() {
-            None => break,
-            Some(s) => s,
-        };
+    // Set once we see `--`. Every token after it is treated as an argument
+    let mut options_done = false;
 
-        // TODO: doesn't parse short args correctly, for now
-        // you should be able to combine them, like -a -b -c can be written
-        // -abc, with the caveat that all but the last option need to be boolean
-        if token.starts_with("--") || token.starts_with("-") {
-            let cmd_opt = {
-                let opt_name = if token.starts_with("--") {
-                    &token[2..]
-                } else {
-                    &token[1..]
-                };
-
-                match cmd_opts.get(opt_name) {
-                    None => return Err(format!("Option not recognized: {}", opt_name)),
-                    Some(opt) => opt,
-                }
+    while let Some(token) = input.next() {
+        if !options_done && token == "--" {
+            options_done = true;
+        } else if !options_done && token.starts_with("--") {
+            let (opt_name, value) = split_opt_value(&token[2..]);
+            let cmd_opt = match cmd_opts.get(opt_name) {
+                None => return Err(ParseError::UnknownOption(opt_name.to_string())),
+                Some(opt) => *opt,
+            };
+            opts.push(try!(parse_opt(cmd_opt, value, &mut input)));
+        } else if !options_done && token.starts_with("-") && token.len() > 1 {
+            try!(parse_short_opts(&token[1..], &cmd_opts, &mut input, &mut opts));
+        } else {
+            // Descend into subcommands until we reach a command that takes
+            // arguments. After that (or after the first argument has been
+            // parsed) every non-option token is an argument.
+            let parsing_args = !args.is_empty() || !args_one.is_empty();
+            let subcmd = if parsing_args || options_done {
+                None
+            } else {
+                current_cmd.get_subcommand(&token)
             };
 
-            match cmd_opt.opt_type {
-                OptType::Bool => {
-                    opts.push((cmd_opt.get_name(), request::Opt::Bool(true)))
+            match subcmd {
+                Some(subcmd) => {
+                    cmd_opts.extend(subcmd.get_options());
+                    current_cmd = subcmd;
+                    cmd_path.push(subcmd.get_name());
+                    cmd_args = current_cmd.get_arguments();
+                    curr_arg = cmd_args.next();
                 }
-                _ => {
-                    // just assume the option argument is the next token.
-                    // eventually this will check if theres an equal sign
-                    // and split the token based on that
-                    token = match input.next() {
-                        None => {
-                            return Err(format!("Expecting option argument for option \
-                                                {}, but no more tokens.",
-                                               cmd_opt.get_name()))
-                        }
-                        Some(s) => s,
+                None if current_cmd.num_args() == 0 => {
+                    let suggestion = suggest_subcommand(current_cmd, &token);
+                    return Err(ParseError::UnknownCommand(token, suggestion));
+                }
+                None => {
+                    let arg = match curr_arg {
+                        // Command arg iterator has been advanced past the end
+                        None => return Err(ParseError::UnexpectedArgument(token)),
+                        Some(arg) => arg,
                     };
 
-                    let req_opt = try!(request::Opt::parse_string(token,
-                                                                  cmd_opt.opt_type));
-                    opts.push((cmd_opt.get_name(), req_opt));
+                    // A lone - stands for the values on stdin
+                    if token == "-" && arg.supports_stdin() {
+                        let mut tokens = try!(read_stdin_tokens());
+                        if tokens.is_empty() {
+                            return Err(ParseError::MissingArgument(arg.name()));
+                        }
+                        if !arg.is_variadic() {
+                            tokens.truncate(1);
+                        }
+                        args_one.extend(tokens);
+                    } else {
+                        args_one.push(token);
+                    }
+
+                    // If it isn't variadic, there are no more arguments to parse, so
+                    // move onto the next
+                    if !arg.is_variadic() {
+                        args.push((arg.name(), try!(parse_arg_tokens(arg, args_one))));
+                        args_one = Vec::new();
+                        curr_arg = cmd_args.next();
+                    }
                 }
             }
+        }
+    }
 
-        } else {
-            // Once current_cmd points to a Command with arguments, stop trying to parse
-            // sub commands and parse arguments instead. (We still parse options, but
-            // any token that isn't an option (i.e. doesn't start with - or --) will
-            // be assumed to be an argument)
-            let num_args = current_cmd.num_args();
-            if num_args == 0 {
-                let subcmd = match current_cmd.get_subcommand(&token) {
-                    None => return Err(format!("Subcommand {} not found", &token)),
-                    Some(cmd) => cmd,
-                };
+    // Finish parsing args
+    if args_one.len() > 0 {
+        let arg = curr_arg.unwrap();
+        args.push((arg.name(), try!(parse_arg_tokens(arg, args_one))));
+        curr_arg = cmd_args.next();
+    }
 
-                cmd_opts.extend(subcmd.get_options());
-                current_cmd = subcmd;
+    // Any remaining arguments were not given. Required ones may still be
+    // supplied through stdin
+    while let Some(arg) = curr_arg {
+        if arg.is_required() {
+            if !(arg.supports_stdin() && stdin_is_piped()) {
+                return Err(ParseError::MissingArgument(arg.name()));
+            }
 
-                // initialize cmd_args if necessary
-                let num_args = current_cmd.num_args();
-                if num_args > 0 {
-                    cmd_args = Some(current_cmd.get_arguments());
-                    curr_arg = cmd_args.as_mut().unwrap().next();
-                }
-            } else {
-                // TODO: handle optional arguments
+            let mut tokens = try!(read_stdin_tokens());
+            if tokens.is_empty() {
+                return Err(ParseError::MissingArgument(arg.name()));
+            }
+            if !arg.is_variadic() {
+                tokens.truncate(1);
+            }
+            args.push((arg.name(), try!(parse_arg_tokens(arg, tokens))));
+        }
+        curr_arg = cmd_args.next();
+    }
 
-                // Command arg index has been incremented past the end of cmd_args slice
-                if curr_arg.is_none() {
-                    return Err(format!("Unexpected argument: {}", token));
-                }
+    Ok((cmd_path, current_cmd, args, opts))
+}
 
-                args_one.push(token);
+// Splits `name=value` into its name and value. Tokens without an equal sign
+// have no value.
+fn split_opt_value(s: &str) -> (&str, Option<&str>) {
+    match s.find('=') {
+        None => (s, None),
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+    }
+}
 
-                // If it isn't variadic, there are no more arguments to parse, so move
-                // onto the next
-                if !curr_arg.unwrap().is_variadic() {
-                    args.push((curr_arg.unwrap().name(),
-                               try!(parse_arg_tokens(curr_arg.unwrap(), args_one))));
-                    args_one = Vec::new();
-                    curr_arg = cmd_args.as_mut().unwrap().next();
-                }
+// Parses the value for an option. If `value` is None, non-boolean options
+// take the next token as their value.
+fn parse_opt<I>(cmd_opt: &Opt,
+                value: Option<&str>,
+                input: &mut I)
+                -> Result<(OptName, request::Opt), ParseError>
+    where I: Iterator<Item = String>
+{
+    let value = match (cmd_opt.opt_type, value) {
+        (_, Some(v)) => v.to_string(),
+        (OptType::Bool, None) => return Ok((cmd_opt.get_name(), request::Opt::Bool(true))),
+        (_, None) => {
+            match input.next() {
+                None => return Err(ParseError::MissingOptionValue(cmd_opt.get_name())),
+                Some(s) => s,
             }
         }
-    }
+    };
 
-    // Finish parsing args
-    if args_one.len() > 0 {
-        args.push((curr_arg.unwrap().name(),
-                   try!(parse_arg_tokens(curr_arg.unwrap(), args_one))));
-        curr_arg = cmd_args.as_mut().unwrap().next();
+    let req_opt = try!(request::Opt::parse_string(cmd_opt.get_name(), value, cmd_opt.opt_type));
+    Ok((cmd_opt.get_name(), req_opt))
+}
+
+// Parses the token following a single dash. Short options can be combined,
+// so `-a -b -c` can be written `-abc`, with the caveat that all but the last
+// option need to be boolean. A non-boolean option takes the rest of the token
+// as its value if there is any (`-n5` or `-n=5`), else the next token.
+//
+// For compatibility, a long option name after a single dash (`-force`) is
+// also accepted.
+fn parse_short_opts<I>(flags: &str,
+                       cmd_opts: &HashMap<OptName, &Opt>,
+                       input: &mut I,
+                       opts: &mut Vec<(OptName, request::Opt)>)
+                       -> Result<(), ParseError>
+    where I: Iterator<Item = String>
+{
+    let (whole_name, whole_value) = split_opt_value(flags);
+    if whole_name.chars().count() > 1 {
+        if let Some(cmd_opt) = cmd_opts.get(whole_name) {
+            opts.push(try!(parse_opt(*cmd_opt, whole_value, input)));
+            return Ok(());
+        }
     }
 
+    for (i, c) in flags.char_indices() {
+        let end = i + c.len_utf8();
+        let opt_name = &flags[i..end];
+        let cmd_opt = match cmd_opts.get(opt_name) {
+            None => return Err(ParseError::UnknownOption(opt_name.to_string())),
+            Some(opt) => *opt,
+        };
 
-    if !curr_arg.is_none() {
-        return Err(format!("Missing argument for <{}>", curr_arg.unwrap().name()));
+        let rest = &flags[end..];
+        match cmd_opt.opt_type {
+            OptType::Bool if !rest.starts_with("=") => {
+                opts.push((cmd_opt.get_name(), request::Opt::Bool(true)));
+            }
+            _ => {
+                let value = if rest.is_empty() {
+                    None
+                } else if rest.starts_with("=") {
+                    Some(&rest[1..])
+                } else {
+                    Some(rest)
+                };
+                opts.push(try!(parse_opt(cmd_opt, value, input)));
+                return Ok(());
+            }
+        }
     }
 
-    Ok((current_cmd, args, opts))
+    Ok(())
 }
 
 fn parse_arg_tokens(cmd_arg: &super::Argument,
                     args: Vec<String>)
-                    -> Result<request::Arg, String> {
+                    -> Result<request::Arg, ParseError> {
     match cmd_arg.arg_type() {
         super::ArgumentType::String => Ok(request::Arg::new_string_arg(args)),
 
         super::ArgumentType::File => {
             let mut file_args = Vec::new();
             for arg in args.into_iter() {
-                let file_arg = try!(request::FileArg::new(arg));
+                let file_arg = try!(request::FileArg::new(cmd_arg.name(), arg));
                 file_args.push(file_arg);
             }
             Ok(request::Arg::new_file_arg(file_args))
         }
     }
 }
+
+// stdin is only read when something is piped in, never from a terminal
+fn stdin_is_piped() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 0 }
+}
+
+// Reads one token per non-empty line of stdin
+fn read_stdin_tokens() -> Result<Vec<String>, ParseError> {
+    let stdin = io::stdin();
+    let mut tokens = Vec::new();
+    for line in stdin.lock().lines() {
+        let line = try!(line.map_err(ParseError::Stdin));
+        let token = line.trim();
+        if !token.is_empty() {
+            tokens.push(token.to_string());
+        }
+    }
+    Ok(tokens)
+}
+
+// Returns the subcommand of `cmd` with a name closest to `name`, if one is
+// close enough to plausibly be a typo
+fn suggest_subcommand(cmd: &Command, name: &str) -> Option<CommandName> {
+    let max_distance = if name.len() <= 3 { 1 } else { 2 };
+    cmd.get_subcommands()
+       .into_iter()
+       .map(|subcmd| (edit_distance(name, subcmd.get_name()), subcmd.get_name()))
+       .filter(|&(dist, _)| dist <= max_distance)
+       .min_by_key(|&(dist, _)| dist)
+       .map(|(_, name)| name)
+}
+
+// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..b.len() + 1).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = *[substitution, prev[j + 1] + 1, curr[j] + 1].iter().min().unwrap();
+        }
+        ::std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
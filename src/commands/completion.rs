@@ -0,0 +1,298 @@
+// Generates shell completion scripts from a command tree.
+//
+// Every command is identified in the generated scripts by its path joined with
+// underscores (`ipfs_file_ls`). The scripts walk the words typed so far,
+// extending the path whenever a word names a subcommand of the current path,
+// and then complete the options, subcommands or files for that path.
+
+use super::{all_commands, ArgumentType, Command, Opt, OptName, OptType};
+
+#[derive(Copy, Clone, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+pub const SUPPORTED_SHELLS: &'static [&'static str] = &["bash", "zsh", "fish"];
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+// Returns the completion script for `program`, whose commands are rooted at `root`
+pub fn generate(root: &Command, program: &str, shell: Shell) -> String {
+    let entries = collect_entries(root, program);
+    match shell {
+        Shell::Bash => generate_bash(&entries, program),
+        Shell::Zsh => generate_zsh(&entries, program),
+        Shell::Fish => generate_fish(&entries, program),
+    }
+}
+
+struct Entry<'a> {
+    id: String,
+    cmd: &'a Command,
+    // options of the command and all of its ancestors, since the parser accepts
+    // those anywhere after the command that declares them
+    options: Vec<(OptName, &'a Opt)>,
+}
+
+impl<'a> Entry<'a> {
+    fn has_file_arg(&self) -> bool {
+        self.cmd.get_arguments().any(|arg| arg.arg_type() == ArgumentType::File)
+    }
+
+    fn subcommand_names(&self) -> Vec<&'static str> {
+        self.cmd.get_subcommands().iter().map(|cmd| cmd.get_name()).collect()
+    }
+
+    fn option_flags(&self) -> Vec<String> {
+        self.options.iter().map(|&(name, _)| option_flag(name)).collect()
+    }
+}
+
+fn collect_entries<'a>(root: &'a Command, program: &str) -> Vec<Entry<'a>> {
+    all_commands(root)
+        .into_iter()
+        .map(|(path, cmd)| {
+            let mut options: Vec<(OptName, &Opt)> = root.get_options().collect();
+            let mut ancestor = root;
+            for name in &path {
+                ancestor = ancestor.get_subcommand(name).unwrap();
+                options.extend(ancestor.get_options());
+            }
+
+            let mut id = program.to_string();
+            for name in &path {
+                id.push('_');
+                id.push_str(name);
+            }
+
+            Entry {
+                id: id,
+                cmd: cmd,
+                options: options,
+            }
+        })
+        .collect()
+}
+
+fn option_flag(name: OptName) -> String {
+    if name.chars().count() == 1 {
+        format!("-{}", name)
+    } else {
+        format!("--{}", name)
+    }
+}
+
+// First non-empty line of a help text or description
+fn summary(text: &str) -> &str {
+    text.lines().map(|line| line.trim()).find(|line| !line.is_empty()).unwrap_or("")
+}
+
+// Quotes a string for bash or zsh
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace("\\", "\\\\").replace("'", "\\'"))
+}
+
+// `case` pattern matching the id of every command except the root
+fn subcommand_pattern(entries: &[Entry]) -> String {
+    entries.iter().skip(1).map(|e| &e.id[..]).collect::<Vec<_>>().join("|")
+}
+
+fn generate_bash(entries: &[Entry], program: &str) -> String {
+    let mut s = String::new();
+    s.push_str(&format!("# bash completion for {}\n", program));
+    s.push_str(&format!("# Load with: source <({} commands completion bash)\n\n", program));
+
+    s.push_str(&format!("_{}() {{\n", program));
+    s.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    s.push_str(&format!("    local cmd_path={} word i\n", program));
+    s.push_str("    COMPREPLY=()\n\n");
+
+    if entries.len() > 1 {
+        s.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+        s.push_str("        word=\"${COMP_WORDS[i]}\"\n");
+        s.push_str("        case \"${cmd_path}_${word}\" in\n");
+        s.push_str(&format!("            {}) cmd_path=\"${{cmd_path}}_${{word}}\" ;;\n",
+                            subcommand_pattern(entries)));
+        s.push_str("        esac\n");
+        s.push_str("    done\n\n");
+    }
+
+    s.push_str("    case \"$cmd_path\" in\n");
+    for entry in entries {
+        s.push_str(&format!("        {})\n", entry.id));
+        s.push_str("            if [[ \"$cur\" == -* ]]; then\n");
+        s.push_str(&format!("                COMPREPLY=($(compgen -W {} -- \"$cur\"))\n",
+                            sh_quote(&entry.option_flags().join(" "))));
+        let subcmds = entry.subcommand_names();
+        if !subcmds.is_empty() {
+            s.push_str("            else\n");
+            s.push_str(&format!("                COMPREPLY=($(compgen -W {} -- \"$cur\"))\n",
+                                sh_quote(&subcmds.join(" "))));
+        } else if entry.has_file_arg() {
+            s.push_str("            else\n");
+            s.push_str("                compopt -o filenames 2>/dev/null\n");
+            s.push_str("                COMPREPLY=($(compgen -f -- \"$cur\"))\n");
+        }
+        s.push_str("            fi\n");
+        s.push_str("            ;;\n");
+    }
+    s.push_str("    esac\n");
+    s.push_str("}\n\n");
+
+    s.push_str(&format!("complete -F _{} {}\n", program, program));
+    s
+}
+
+fn generate_zsh(entries: &[Entry], program: &str) -> String {
+    let func = format!("_{}", program);
+    let mut s = String::new();
+    s.push_str(&format!("#compdef {}\n", program));
+    s.push_str(&format!("# zsh completion for {}\n", program));
+    s.push_str(&format!("# Load with: source <({} commands completion zsh)\n\n", program));
+
+    s.push_str(&format!("{}() {{\n", func));
+    // `path` is special in zsh (it's tied to $PATH), hence `cmd_path`
+    s.push_str(&format!("    local cmd_path={} word files=0\n", program));
+    s.push_str("    local -a opts subcmds\n\n");
+
+    if entries.len() > 1 {
+        s.push_str("    for word in \"${(@)words[2,CURRENT-1]}\"; do\n");
+        s.push_str("        case \"${cmd_path}_${word}\" in\n");
+        s.push_str(&format!("            ({}) cmd_path=\"${{cmd_path}}_${{word}}\" ;;\n",
+                            subcommand_pattern(entries)));
+        s.push_str("        esac\n");
+        s.push_str("    done\n\n");
+    }
+
+    s.push_str("    case \"$cmd_path\" in\n");
+    for entry in entries {
+        s.push_str(&format!("        ({})\n", entry.id));
+
+        let opts: Vec<String> = entry.options
+                                     .iter()
+                                     .map(|&(name, opt)| {
+                                         sh_quote(&format!("{}:{}",
+                                                           option_flag(name),
+                                                           summary(opt.description())))
+                                     })
+                                     .collect();
+        s.push_str(&format!("            opts=({})\n", opts.join(" ")));
+
+        let subcmds: Vec<String> = entry.cmd
+                                        .get_subcommands()
+                                        .iter()
+                                        .map(|cmd| {
+                                            sh_quote(&format!("{}:{}",
+                                                              cmd.get_name(),
+                                                              summary(cmd.get_help_text()
+                                                                         .tagline)))
+                                        })
+                                        .collect();
+        s.push_str(&format!("            subcmds=({})\n", subcmds.join(" ")));
+
+        if entry.has_file_arg() {
+            s.push_str("            files=1\n");
+        }
+        s.push_str("            ;;\n");
+    }
+    s.push_str("    esac\n\n");
+
+    s.push_str("    if [[ \"$PREFIX\" == -* ]]; then\n");
+    s.push_str("        _describe -t options 'option' opts\n");
+    s.push_str("    elif (( ${#subcmds} )); then\n");
+    s.push_str("        _describe -t commands 'command' subcmds\n");
+    s.push_str("    elif (( files )); then\n");
+    s.push_str("        _files\n");
+    s.push_str("    fi\n");
+    s.push_str("}\n\n");
+
+    // Works both when autoloaded from $fpath and when sourced
+    s.push_str(&format!("if [ \"$funcstack[1]\" = \"{}\" ]; then\n", func));
+    s.push_str(&format!("    {} \"$@\"\n", func));
+    s.push_str("else\n");
+    s.push_str(&format!("    compdef {} {}\n", func, program));
+    s.push_str("fi\n");
+    s
+}
+
+fn generate_fish(entries: &[Entry], program: &str) -> String {
+    let path_fn = format!("__fish_{}_command_path", program);
+    let using_fn = format!("__fish_{}_using_command", program);
+    let commands_var = format!("__fish_{}_commands", program);
+
+    let mut s = String::new();
+    s.push_str(&format!("# fish completion for {}\n", program));
+    s.push_str(&format!("# Load with: {} commands completion fish | source\n\n", program));
+
+    let ids: Vec<&str> = entries.iter().map(|e| &e.id[..]).collect();
+    s.push_str(&format!("set -g {} {}\n\n", commands_var, ids.join(" ")));
+
+    s.push_str(&format!("function {}\n", path_fn));
+    s.push_str("    set -l tokens (commandline -opc)\n");
+    s.push_str("    set -e tokens[1]\n");
+    s.push_str(&format!("    set -l cmd_path {}\n", program));
+    s.push_str("    for token in $tokens\n");
+    s.push_str(&format!("        if contains -- {{$cmd_path}}_$token ${}\n", commands_var));
+    s.push_str("            set cmd_path {$cmd_path}_$token\n");
+    s.push_str("        end\n");
+    s.push_str("    end\n");
+    s.push_str("    echo $cmd_path\n");
+    s.push_str("end\n\n");
+
+    s.push_str(&format!("function {}\n", using_fn));
+    s.push_str(&format!("    test ({}) = $argv[1]\n", path_fn));
+    s.push_str("end\n\n");
+
+    // no file completion unless a command takes file arguments
+    s.push_str(&format!("complete -c {} -f\n", program));
+
+    for entry in entries {
+        let prefix = format!("complete -c {} -n {}",
+                             program,
+                             fish_quote(&format!("{} {}", using_fn, entry.id)));
+
+        for subcmd in entry.cmd.get_subcommands() {
+            s.push_str(&format!("{} -a {} -d {}\n",
+                                prefix,
+                                subcmd.get_name(),
+                                fish_quote(summary(subcmd.get_help_text().tagline))));
+        }
+
+        for &(name, opt) in &entry.options {
+            let flag = if name.chars().count() == 1 {
+                format!("-s {}", name)
+            } else {
+                format!("-l {}", name)
+            };
+            let takes_value = match opt.opt_type {
+                OptType::Bool => "",
+                _ => " -r",
+            };
+            s.push_str(&format!("{} {}{} -d {}\n",
+                                prefix,
+                                flag,
+                                takes_value,
+                                fish_quote(summary(opt.description()))));
+        }
+
+        if entry.has_file_arg() {
+            s.push_str(&format!("{} -F\n", prefix));
+        }
+    }
+    s
+}
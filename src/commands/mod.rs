@@ -1,7 +1,10 @@
+use error::Error;
+
 use std::collections::{hash_map, HashMap};
 use std::slice;
 
 pub mod cli;
+pub mod completion;
 pub mod request;
 
 pub struct HelpText {
@@ -17,18 +20,19 @@ pub struct HelpText {
      * */
 }
 
-pub type RunFn = fn(&request::Request) -> Result<(), String>;
+pub type RunFn = fn(&request::Request) -> Result<(), Error>;
 
 pub type ArgName = &'static str;
 pub type OptName = &'static str;
 pub type CommandName = &'static str;
 
 pub trait Command {
-    fn run(&self, &request::Request) -> Result<(), String>;
+    fn run(&self, &request::Request) -> Result<(), Error>;
     fn get_name(&self) -> CommandName;
     fn get_help_text(&self) -> &HelpText;
     fn get_options(&self) -> CommandOptions;
     fn get_subcommand(&self, &str) -> Option<&Command>;
+    fn get_subcommands(&self) -> Vec<&Command>; // sorted by name
     fn num_args(&self) -> usize;
     fn get_arguments(&self) -> slice::Iter<Argument>; // TODO: wrap in iterator?
 
@@ -118,6 +122,14 @@ impl Opt {
         Self::new(names, OptType::Bool, desc)
     }
 
+    pub fn new_string(names: Vec<OptName>, desc: &'static str) -> Self {
+        Self::new(names, OptType::String, desc)
+    }
+
+    pub fn new_int(names: Vec<OptName>, desc: &'static str) -> Self {
+        Self::new(names, OptType::Int, desc)
+    }
+
     fn new(mut names: Vec<OptName>, opt_type: OptType, desc: &'static str) -> Self {
         let canonical = names[0];
         names.sort_by(|a, b| a.len().cmp(&b.len()));
@@ -136,10 +148,14 @@ impl Opt {
     pub fn get_names(&self) -> slice::Iter<OptName> {
         self.names.iter()
     }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
 }
 
-#[derive(Copy, Clone)]
-enum ArgumentType {
+#[derive(Copy, Clone, PartialEq)]
+pub enum ArgumentType {
     String,
     File,
 }
@@ -149,6 +165,7 @@ pub struct Argument {
     ty: ArgumentType,
     required: bool,
     variadic: bool,
+    supports_stdin: bool, // values can be read from stdin when omitted or given as -
     description: &'static str,
 }
 
@@ -180,10 +197,27 @@ impl Argument {
             ty: ty,
             required: required,
             variadic: variadic,
+            supports_stdin: false,
             description: desc,
         }
     }
 
+    // Allows the argument to be read from stdin (one value per line) when
+    // it's given as - or, if stdin isn't a terminal, left out, e.g.
+    // `echo <hash> | ipfs object get`
+    pub fn enable_stdin(mut self) -> Self {
+        self.supports_stdin = true;
+        self
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    pub fn supports_stdin(&self) -> bool {
+        self.supports_stdin
+    }
+
     pub fn is_variadic(&self) -> bool {
         self.variadic
     }
@@ -195,4 +229,28 @@ impl Argument {
     pub fn arg_type(&self) -> ArgumentType {
         self.ty
     }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+}
+
+// Walks the command tree depth-first, returning every command along with the
+// path of names leading to it from `root` (the root itself has an empty path).
+// Subcommands are visited in order of name.
+pub fn all_commands<'a>(root: &'a Command) -> Vec<(Vec<CommandName>, &'a Command)> {
+    fn walk<'a>(cmd: &'a Command,
+                path: Vec<CommandName>,
+                out: &mut Vec<(Vec<CommandName>, &'a Command)>) {
+        out.push((path.clone(), cmd));
+        for subcmd in cmd.get_subcommands() {
+            let mut subpath = path.clone();
+            subpath.push(subcmd.get_name());
+            walk(subcmd, subpath, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(root, vec![], &mut out);
+    out
 }
@@ -1,11 +1,15 @@
 use super::Command;
+use super::cli::ParseError;
 use blockstore::{self, Blockstore};
 use config;
 use core::IpfsNode;
+use error::Error;
 use util;
 
+use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::collections::hash_map;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::fmt;
 
@@ -18,7 +22,10 @@ pub enum Opt {
 }
 
 impl Opt {
-    pub fn parse_string(s: String, opt_type: super::OptType) -> Result<Self, String> {
+    pub fn parse_string(name: super::OptName,
+                        s: String,
+                        opt_type: super::OptType)
+                        -> Result<Self, ParseError> {
         match opt_type {
             super::OptType::String => Ok(Opt::String(s)),
             super::OptType::Bool => {
@@ -27,30 +34,54 @@ impl Opt {
                 } else if s == "false" {
                     Ok(Opt::Bool(false))
                 } else {
-                    Err("Expected boolean value for boolean option.".to_string())
+                    Err(ParseError::InvalidOptionValue(name,
+                                                       "Expected boolean value for \
+                                                        boolean option."
+                                                           .to_string()))
                 }
             }
             super::OptType::Int => {
                 let x = try!(s.parse::<i32>()
-                              .map_err(|e| format!("{}", e)));
+                              .map_err(|e| {
+                                  ParseError::InvalidOptionValue(name, format!("{}", e))
+                              }));
                 Ok(Opt::Int(x))
             }
         }
     }
 }
 
+// Formats the value the way it would be given on the command line
+impl fmt::Display for Opt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Opt::String(ref s) => write!(f, "{}", s),
+            Opt::Bool(b) => write!(f, "{}", b),
+            Opt::Int(x) => write!(f, "{}", x),
+        }
+    }
+}
+
 pub struct FileArg {
     path: PathBuf,
 }
 
 impl FileArg {
-    pub fn new(s: String) -> Result<Self, String> {
+    pub fn new(name: super::ArgName, s: String) -> Result<Self, ParseError> {
         let path = PathBuf::from(s);
         match util::file_exists(&path) {
             Ok(true) => {}
-            Ok(false) => return Err(format!("File {:?} does not exist", path)),
+            Ok(false) => {
+                return Err(ParseError::InvalidArgument(name,
+                                                       format!("File {:?} does not exist",
+                                                               path)))
+            }
             Err(e) => {
-                return Err(format!("Error checking existence of file {:?}: {}", path, e))
+                return Err(ParseError::InvalidArgument(name,
+                                                       format!("Error checking existence \
+                                                                of file {:?}: {}",
+                                                               path,
+                                                               e)))
             }
         }
 
@@ -102,7 +133,7 @@ impl Arg {
     }
 }
 
-pub type NodeConstructor = fn(PathBuf) -> Result<IpfsNode, String>;
+pub type NodeConstructor = fn(PathBuf) -> Result<IpfsNode, Error>;
 
 pub struct Context<'a> {
     pub repo_dir: PathBuf,
@@ -118,13 +149,10 @@ impl<'a> Context<'a> {
         }
     }
 
-    pub fn get_node(&self) -> Result<&IpfsNode, String> {
+    pub fn get_node(&self) -> Result<&IpfsNode, Error> {
         match self.node {
-            None => {
-                Err(format!("No ipfs repo found at {:?}. Please run `ipfs init`",
-                            self.repo_dir))
-            }
-            Some(node) => Ok(self.node.unwrap()),
+            None => Err(Error::NotInitialized(self.repo_dir.clone())),
+            Some(node) => Ok(node),
         }
     }
 }
@@ -135,6 +163,7 @@ pub struct Request<'a, 'b> {
     arguments: HashMap<super::ArgName, Arg>,
     options: HashMap<super::OptName, Opt>,
     pub context: Context<'b>,
+    output: RefCell<Box<Write + 'b>>,
 }
 
 impl<'a, 'b> Request<'a, 'b> {
@@ -148,9 +177,29 @@ impl<'a, 'b> Request<'a, 'b> {
             arguments: args.into_iter().collect(),
             options: opts.into_iter().collect(),
             context: context,
+            output: RefCell::new(Box::new(io::stdout())),
         }
     }
 
+    // Sends the command's output somewhere other than stdout
+    pub fn with_output(mut self, output: Box<Write + 'b>) -> Self {
+        self.output = RefCell::new(output);
+        self
+    }
+
+    // Where commands write their output
+    pub fn output(&self) -> RefMut<Box<Write + 'b>> {
+        self.output.borrow_mut()
+    }
+
+    // Lets commands use `write!` and `writeln!` on the request
+    pub fn write_fmt(&self, args: fmt::Arguments) -> Result<(), Error> {
+        self.output
+            .borrow_mut()
+            .write_fmt(args)
+            .map_err(|e| Error::Io("Error writing command output".to_string(), e))
+    }
+
     pub fn args(&self) -> hash_map::Iter<super::ArgName, Arg> {
         self.arguments.iter()
     }
@@ -170,4 +219,31 @@ impl<'a, 'b> Request<'a, 'b> {
     pub fn option(&self, name: super::OptName) -> Option<&Opt> {
         self.options.get(&name)
     }
+
+    // The value of a boolean option, false if it wasn't given
+    pub fn bool_option(&self, name: super::OptName) -> bool {
+        match self.option(name) {
+            Some(&Opt::Bool(b)) => b,
+            _ => false,
+        }
+    }
+
+    pub fn string_option(&self, name: super::OptName) -> Option<&str> {
+        match self.option(name) {
+            Some(&Opt::String(ref s)) => Some(&s[..]),
+            _ => None,
+        }
+    }
+
+    // The value of an integer option that can't be negative, such as a size
+    // or an offset
+    pub fn int_option(&self, name: super::OptName) -> Result<Option<u64>, Error> {
+        match self.option(name) {
+            Some(&Opt::Int(n)) if n < 0 => {
+                Err(Error::InvalidArgument(format!("--{} can't be negative", name)))
+            }
+            Some(&Opt::Int(n)) => Ok(Some(n as u64)),
+            _ => Ok(None),
+        }
+    }
 }
@@ -1,4 +1,15 @@
+use bootstrap::{self, Bootstrapper};
 use crypto;
+use error::Error;
+use mdns;
+use multiaddr::Multiaddr;
+use provider::{self, Strategy};
+use pubsub::{self, Router};
+use swarm::ConnManager;
+use swarm::connmgr;
+use swarm::filter::IpFilter;
+use swarm::relay::{self, Limit, Resources};
+use unixfs::directory;
 use util;
 
 use rustc_serialize::Decodable;
@@ -7,6 +18,7 @@ use rustc_serialize::json::{self, Json};
 use rust_multihash::Multihash;
 use std::io::Read;
 use std::path::PathBuf;
+use std::time::Duration;
 
 pub const DEFAULT_REPO_ROOT: &'static str = "~/";
 pub const DEFAULT_REPO_PATH: &'static str = ".rust-ipfs";
@@ -15,6 +27,9 @@ pub const ENV_NAME_REPO_DIR: &'static str = "IPFS_PATH";
 
 pub const DEFAULT_KEYPAIR_NUM_BITS: usize = 2048;
 
+pub const DEFAULT_SWARM_ADDR: &'static str = "/ip4/0.0.0.0/tcp/4001";
+pub const DEFAULT_API_ADDR: &'static str = "/ip4/127.0.0.1/tcp/5001";
+
 
 #[derive(RustcEncodable, RustcDecodable)]
 pub struct Identity {
@@ -22,23 +37,327 @@ pub struct Identity {
     pub private_key: String,
 }
 
+// Multiaddrs the daemon listens on
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct Addresses {
+    pub swarm: Vec<String>,
+    pub api: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct SwarmConfig {
+    // CIDR ranges (/ip4/<address>/ipcidr/<prefix length>) not to dial or
+    // accept connections from
+    pub addr_filters: Vec<String>,
+    pub conn_mgr: ConnMgrConfig,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct ConnMgrConfig {
+    pub low_water: usize,
+    pub high_water: usize,
+    pub grace_period_secs: u64,
+}
+
+// Announcing our content to the DHT again every interval. An interval of 0
+// turns reproviding off.
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct ReproviderConfig {
+    pub strategy: String, // all, pinned or roots
+    pub interval_secs: u64,
+}
+
+// Peers connected to when the daemon starts. Addresses must end with
+// /p2p/<peer id>.
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct BootstrapConfig {
+    pub peers: Vec<String>,
+    // bootstrap peers are dialed until there are this many connections
+    pub min_connections: usize,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct DiscoveryConfig {
+    pub mdns: MdnsConfig,
+}
+
+// Finding peers on the local network. Queries are sent every interval.
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct MdnsConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+}
+
+// The pubsub router: floodsub or gossipsub
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct PubsubConfig {
+    pub router: String,
+}
+
+// Circuit relays: the relay service lets peers that can't be dialed
+// directly reserve a slot, and relays circuits to them within the limits.
+// Static relays are relays we keep a reservation on, so that we can be
+// reached through them. Their addresses must end with /p2p/<peer id>.
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct RelayConfig {
+    pub service: RelayServiceConfig,
+    pub static_relays: Vec<String>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct RelayServiceConfig {
+    pub enabled: bool,
+    pub max_reservations: usize,
+    pub max_circuits: usize, // per peer
+    pub reservation_ttl_secs: u64,
+    pub limit_duration_secs: u64, // per circuit
+    pub limit_data_bytes: u64, // per circuit, in each direction
+}
+
+// Directories whose links are estimated to take more than
+// sharding_threshold_bytes are stored as HAMT shards
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct UnixfsConfig {
+    pub sharding_threshold_bytes: u64,
+}
+
 #[derive(RustcEncodable, RustcDecodable)]
 pub struct Config {
     pub identity: Identity,
+    // Sections added after the first release are optional, so that older
+    // config files still load
+    pub addresses: Option<Addresses>,
+    pub swarm: Option<SwarmConfig>,
+    pub reprovider: Option<ReproviderConfig>,
+    pub bootstrap: Option<BootstrapConfig>,
+    pub discovery: Option<DiscoveryConfig>,
+    pub pubsub: Option<PubsubConfig>,
+    pub relay: Option<RelayConfig>,
+    pub unixfs: Option<UnixfsConfig>,
 }
 
 impl Config {
-    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Config, String> {
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Config, Error> {
         let json = try!(Json::from_reader(reader)
-                            .map_err(|e| format!("Error parsing Json: {}", e)));
+                            .map_err(|e| Error::decode("Error parsing Json".to_string(), e)));
         let mut decoder = json::Decoder::new(json);
-        Decodable::decode(&mut decoder)
-            .map_err(|e| format!("Error decoding Config from reader: {}", e))
+        let config: Config = try!(Decodable::decode(&mut decoder).map_err(|e| {
+            Error::decode("Error decoding Config from reader".to_string(), e)
+        }));
+        if let Some(ref swarm) = config.swarm {
+            let conn_mgr = &swarm.conn_mgr;
+            if conn_mgr.low_water > conn_mgr.high_water {
+                return Err(Error::InvalidArgument(format!("swarm.conn_mgr.low_water ({}) is \
+                                                           above high_water ({})",
+                                                          conn_mgr.low_water,
+                                                          conn_mgr.high_water)));
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn swarm_addrs(&self) -> Result<Vec<Multiaddr>, Error> {
+        match self.addresses {
+            None => Ok(vec![try!(Multiaddr::parse(DEFAULT_SWARM_ADDR))]),
+            Some(ref addrs) => addrs.swarm.iter().map(|s| Multiaddr::parse(s)).collect(),
+        }
+    }
+
+    pub fn api_addr(&self) -> Result<Multiaddr, Error> {
+        match self.addresses {
+            None => Multiaddr::parse(DEFAULT_API_ADDR),
+            Some(ref addrs) => Multiaddr::parse(&addrs.api),
+        }
     }
 
-    pub fn to_json_string(&self) -> json::EncodeResult<String> {
-        json::encode(self)
+    pub fn addr_filters(&self) -> Result<Vec<IpFilter>, Error> {
+        match self.swarm {
+            None => Ok(vec![]),
+            Some(ref swarm) => swarm.addr_filters.iter().map(|s| IpFilter::parse(s)).collect(),
+        }
+    }
+
+    pub fn conn_manager(&self) -> ConnManager {
+        match self.swarm {
+            None => ConnManager::default(),
+            Some(ref swarm) => {
+                ConnManager::new(swarm.conn_mgr.low_water,
+                                 swarm.conn_mgr.high_water,
+                                 Duration::from_secs(swarm.conn_mgr.grace_period_secs))
+            }
+        }
+    }
+
+    pub fn reprovider_strategy(&self) -> Result<Strategy, Error> {
+        match self.reprovider {
+            None => Strategy::parse(provider::DEFAULT_STRATEGY),
+            Some(ref reprovider) => Strategy::parse(&reprovider.strategy),
+        }
+    }
+
+    pub fn reprovider_interval(&self) -> Duration {
+        match self.reprovider {
+            None => Duration::from_secs(provider::DEFAULT_INTERVAL_SECS),
+            Some(ref reprovider) => Duration::from_secs(reprovider.interval_secs),
+        }
+    }
+
+    // The mDNS query interval, or None if mDNS is disabled
+    pub fn mdns_interval(&self) -> Option<Duration> {
+        match self.discovery {
+            None => Some(Duration::from_secs(mdns::DEFAULT_INTERVAL_SECS)),
+            Some(ref discovery) if discovery.mdns.enabled => {
+                Some(Duration::from_secs(discovery.mdns.interval_secs))
+            }
+            Some(_) => None,
+        }
+    }
+
+    pub fn pubsub_router(&self) -> Result<Router, Error> {
+        match self.pubsub {
+            None => Router::parse(pubsub::DEFAULT_ROUTER),
+            Some(ref pubsub) => Router::parse(&pubsub.router),
+        }
+    }
+
+    // The relay service's resources, or None if the service is disabled
+    pub fn relay_resources(&self) -> Option<Resources> {
+        match self.relay {
+            Some(ref relay) if relay.service.enabled => {
+                let service = &relay.service;
+                Some(Resources {
+                    max_reservations: service.max_reservations,
+                    max_circuits: service.max_circuits,
+                    reservation_ttl: Duration::from_secs(service.reservation_ttl_secs),
+                    limit: Limit {
+                        duration: Duration::from_secs(service.limit_duration_secs),
+                        data: service.limit_data_bytes,
+                    },
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn static_relays(&self) -> Result<Vec<Multiaddr>, Error> {
+        match self.relay {
+            None => Ok(vec![]),
+            Some(ref relay) => relay.static_relays.iter().map(|s| parse_relay_addr(s)).collect(),
+        }
+    }
+
+    pub fn sharding_threshold(&self) -> u64 {
+        match self.unixfs {
+            None => directory::DEFAULT_SHARDING_THRESHOLD,
+            Some(ref unixfs) => unixfs.sharding_threshold_bytes,
+        }
+    }
+
+    pub fn bootstrapper(&self) -> Result<Bootstrapper, Error> {
+        let default = BootstrapConfig::default();
+        let cfg = self.bootstrap.as_ref().unwrap_or(&default);
+        let peers: Result<Vec<Multiaddr>, Error> = cfg.peers
+                                                      .iter()
+                                                      .map(|s| bootstrap::parse_peer_addr(s))
+                                                      .collect();
+        Ok(Bootstrapper::new(try!(peers), cfg.min_connections))
+    }
+
+    // The swarm section, added with its defaults if missing
+    pub fn swarm_mut(&mut self) -> &mut SwarmConfig {
+        if self.swarm.is_none() {
+            self.swarm = Some(SwarmConfig::default());
+        }
+        self.swarm.as_mut().unwrap()
+    }
+
+    // The bootstrap section, added with its defaults if missing
+    pub fn bootstrap_mut(&mut self) -> &mut BootstrapConfig {
+        if self.bootstrap.is_none() {
+            self.bootstrap = Some(BootstrapConfig::default());
+        }
+        self.bootstrap.as_mut().unwrap()
+    }
+
+    // The DER encoding of the identity's private key
+    pub fn private_key(&self) -> Result<Vec<u8>, Error> {
+        crypto::decode_private_key(&self.identity.private_key)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, Error> {
+        json::encode(self).map_err(|e| Error::encode("Error encoding config as Json".to_string(), e))
+    }
+}
+
+impl Default for SwarmConfig {
+    fn default() -> Self {
+        SwarmConfig {
+            addr_filters: vec![],
+            conn_mgr: ConnMgrConfig {
+                low_water: connmgr::DEFAULT_LOW_WATER,
+                high_water: connmgr::DEFAULT_HIGH_WATER,
+                grace_period_secs: connmgr::DEFAULT_GRACE_PERIOD_SECS,
+            },
+        }
+    }
+}
+
+impl Default for BootstrapConfig {
+    fn default() -> Self {
+        BootstrapConfig {
+            peers: bootstrap::DEFAULT_PEERS.iter().map(|s| s.to_string()).collect(),
+            min_connections: bootstrap::DEFAULT_MIN_CONNECTIONS,
+        }
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        DiscoveryConfig {
+            mdns: MdnsConfig {
+                enabled: true,
+                interval_secs: mdns::DEFAULT_INTERVAL_SECS,
+            },
+        }
+    }
+}
+
+impl Default for PubsubConfig {
+    fn default() -> Self {
+        PubsubConfig { router: pubsub::DEFAULT_ROUTER.to_string() }
+    }
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig {
+            service: RelayServiceConfig {
+                enabled: false,
+                max_reservations: relay::DEFAULT_MAX_RESERVATIONS,
+                max_circuits: relay::DEFAULT_MAX_CIRCUITS,
+                reservation_ttl_secs: relay::DEFAULT_RESERVATION_TTL_SECS,
+                limit_duration_secs: relay::DEFAULT_LIMIT_DURATION_SECS,
+                limit_data_bytes: relay::DEFAULT_LIMIT_DATA_BYTES,
+            },
+            static_relays: vec![],
+        }
+    }
+}
+
+impl Default for UnixfsConfig {
+    fn default() -> Self {
+        UnixfsConfig { sharding_threshold_bytes: directory::DEFAULT_SHARDING_THRESHOLD }
+    }
+}
+
+fn parse_relay_addr(s: &str) -> Result<Multiaddr, Error> {
+    let addr = try!(Multiaddr::parse(s));
+    if addr.peer_id().is_none() || addr.is_circuit() {
+        return Err(Error::InvalidArgument(format!("Invalid static relay {}: expected an address \
+                                                   ending with /p2p/<peer id>",
+                                                  s)));
     }
+    Ok(addr)
 }
 
 pub fn repo_path_to_config_file(mut repo_path: PathBuf) -> PathBuf {
@@ -56,5 +375,19 @@ pub fn init(num_key_pair_bits: usize) -> Config {
             peer_id: util::hash(&pub_bytes[..]),
             private_key: priv_b64_string,
         },
+        addresses: Some(Addresses {
+            swarm: vec![DEFAULT_SWARM_ADDR.to_string()],
+            api: DEFAULT_API_ADDR.to_string(),
+        }),
+        swarm: Some(SwarmConfig::default()),
+        reprovider: Some(ReproviderConfig {
+            strategy: provider::DEFAULT_STRATEGY.to_string(),
+            interval_secs: provider::DEFAULT_INTERVAL_SECS,
+        }),
+        bootstrap: Some(BootstrapConfig::default()),
+        discovery: Some(DiscoveryConfig::default()),
+        pubsub: Some(PubsubConfig::default()),
+        relay: Some(RelayConfig::default()),
+        unixfs: Some(UnixfsConfig::default()),
     }
 }
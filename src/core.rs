@@ -1,22 +1,458 @@
-use blockstore::Blockstore;
-use config::Config;
-use merkledag::DagService;
+use bitswap::Bitswap;
+use bitswap::network::SwarmNetwork;
+use blockservice::BlockService;
+use blockstore::{self, Blockstore};
+use bootstrap;
+use cid::{Cid, Codec};
+use config::{self, Config};
+use crypto::{self, PrivateKey};
+use datastore::{self, Datastore};
+use dht::Dht;
+use dns::{Resolver, SystemResolver};
+use error::Error;
+use exporter::{self, ExportOptions};
+use fsrepo;
+use importer::{self, AddOptions};
+use ipns::{self, IpnsEntry, IpnsValidator, NameSystem};
+use keystore::{self, Keystore};
+use mdns::MdnsService;
+use merkledag::{DagService, Node};
+use mfs::Mfs;
+use multiaddr::Multiaddr;
+use path;
+use peer::PeerId;
+use pin::PinSet;
+use provider::Reprovider;
+use pubsub::PubSub;
+use swarm::{autonat, identify, ping, pnet, relay, Swarm};
+use swarm::autonat::Reachability;
+use swarm::relay::{RelayClient, RelayService};
+use swarm::pnet::Psk;
+use unixfs::io::DagReader;
 
+use rust_multihash::Multihash;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
+/// An ipfs node: its configuration along with the services for storing and
+/// retrieving content.
 pub struct IpfsNode {
-    pub config: Config,
-    pub blockstore: Arc<Blockstore>,
-    pub dagservice: Arc<DagService>,
+    config: Config,
+    blockstore: Arc<Blockstore>,
+    datastore: Arc<Datastore>,
+    blockservice: Arc<BlockService>,
+    dagservice: Arc<DagService>,
+    pins: Arc<PinSet>,
+    // the mutable file system, whose root is kept in the datastore
+    mfs: Arc<Mfs>,
+    // the private network key. Without one, the node joins the public
+    // network.
+    swarm_key: Option<Psk>,
+    // named keys, for publishing IPNS names other than the node's own
+    keystore: Keystore,
+    // looks up the DNSLink records of `/ipns/<domain>` paths
+    dns_resolver: Arc<Resolver>,
+    // set once the node is online
+    swarm: Option<Arc<Swarm>>,
+    bitswap: Option<Arc<Bitswap>>,
+    dht: Option<Arc<Dht>>,
+    reprovider: Option<Arc<Reprovider>>,
+    pubsub: Option<Arc<PubSub>>,
 }
 
 impl IpfsNode {
+    /// A node whose other state (DHT records, ...) is kept in memory
     pub fn new(blockstore: Blockstore, cfg: Config) -> Self {
+        IpfsNode::with_datastore(blockstore, Datastore::in_memory(), cfg)
+    }
+
+    pub fn with_datastore(blockstore: Blockstore, datastore: Datastore, cfg: Config) -> Self {
         let bs = Arc::new(blockstore);
+        let datastore = Arc::new(datastore);
+        let blockservice = Arc::new(BlockService::new(bs.clone()));
+        let dagservice = Arc::new(DagService::new(blockservice.clone()));
+        let mfs = Mfs::new(dagservice.clone(), datastore.clone(), cfg.sharding_threshold());
         IpfsNode {
             config: cfg,
-            blockstore: bs.clone(),
-            dagservice: Arc::new(DagService::new(bs)),
+            blockstore: bs,
+            datastore: datastore.clone(),
+            blockservice: blockservice.clone(),
+            dagservice: dagservice,
+            pins: Arc::new(PinSet::new(datastore)),
+            mfs: Arc::new(mfs),
+            swarm_key: None,
+            keystore: Keystore::in_memory(),
+            dns_resolver: Arc::new(SystemResolver::new()),
+            swarm: None,
+            bitswap: None,
+            dht: None,
+            reprovider: None,
+            pubsub: None,
+        }
+    }
+
+    /// Opens the repo at `repo_path`. Fails with `Error::NotInitialized` if
+    /// there's no repo there.
+    pub fn open(repo_path: PathBuf) -> Result<Self, Error> {
+        if !try!(fsrepo::is_initialized(repo_path.clone())) {
+            return Err(Error::NotInitialized(repo_path));
+        }
+        let config_path = config::repo_path_to_config_file(repo_path.clone());
+        let config = try!(fsrepo::read_config_file(&config_path));
+        let swarm_key = try!(fsrepo::read_swarm_key(&repo_path));
+        let mut blockstore_path = repo_path.clone();
+        blockstore_path.push(blockstore::BLOCKSTORE_DIR);
+        let mut datastore_path = repo_path.clone();
+        datastore_path.push(datastore::DATASTORE_DIR);
+        let mut keystore_path = repo_path;
+        keystore_path.push(keystore::KEYSTORE_DIR);
+        let mut node = IpfsNode::with_datastore(Blockstore::new(blockstore_path),
+                                                Datastore::new(datastore_path),
+                                                config);
+        node.swarm_key = swarm_key;
+        node.keystore = Keystore::new(keystore_path);
+        Ok(node)
+    }
+
+    /// Initializes a repo at `repo_path` with a newly generated identity, and
+    /// opens it
+    pub fn init(repo_path: PathBuf) -> Result<Self, Error> {
+        let config = config::init(config::DEFAULT_KEYPAIR_NUM_BITS);
+        try!(fsrepo::init(repo_path.clone(), &config));
+        IpfsNode::open(repo_path)
+    }
+
+    /// A node with a newly generated identity that keeps everything in
+    /// memory. Nothing is persisted once it's dropped.
+    pub fn in_memory() -> Self {
+        let config = config::init(config::DEFAULT_KEYPAIR_NUM_BITS);
+        IpfsNode::new(Blockstore::in_memory(), config)
+    }
+
+    pub fn peer_id(&self) -> &PeerId {
+        &self.config.identity.peer_id
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The config can be changed before the node is brought online, to
+    /// change which services it starts with
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    pub fn blockstore(&self) -> &Arc<Blockstore> {
+        &self.blockstore
+    }
+
+    pub fn datastore(&self) -> &Arc<Datastore> {
+        &self.datastore
+    }
+
+    pub fn dagservice(&self) -> &Arc<DagService> {
+        &self.dagservice
+    }
+
+    /// The roots pinned against garbage collection
+    pub fn pins(&self) -> &Arc<PinSet> {
+        &self.pins
+    }
+
+    /// The mutable file system, whose root is kept in the datastore
+    pub fn mfs(&self) -> &Arc<Mfs> {
+        &self.mfs
+    }
+
+    /// The private network key. Without one, the node joins the public
+    /// network.
+    pub fn swarm_key(&self) -> Option<&Psk> {
+        self.swarm_key.as_ref()
+    }
+
+    /// Named keys, for publishing IPNS names other than the node's own
+    pub fn keystore(&self) -> &Keystore {
+        &self.keystore
+    }
+
+    /// Brings the node online, listening for peers on each of
+    /// `listen_addrs` with the services and settings of the config. Returns
+    /// the addresses actually listened on.
+    pub fn go_online(&mut self, listen_addrs: &[Multiaddr]) -> Result<Vec<Multiaddr>, Error> {
+        // with a swarm key, only peers with the same key are connected to.
+        // LIBP2P_FORCE_PNET=1 makes one required.
+        if self.swarm_key.is_none() && pnet::is_forced() {
+            return Err(Error::Network(format!("{} is set, but there is no swarm key",
+                                              pnet::ENV_NAME_FORCE_PNET)));
+        }
+        let swarm = Swarm::new(self.peer_id().clone(),
+                               try!(self.config.private_key()),
+                               self.swarm_key.clone());
+        for filter in try!(self.config.addr_filters()) {
+            swarm.filters.add(filter);
+        }
+        identify::register(&swarm);
+        ping::register(&swarm);
+        relay::register(&swarm);
+        autonat::register(&swarm);
+        let mut addrs = Vec::new();
+        for addr in listen_addrs {
+            addrs.push(try!(Swarm::listen(&swarm, addr)));
+        }
+        // peers can reach us through circuit relays, and if the relay
+        // service is enabled we relay circuits for other peers
+        if let Some(resources) = self.config.relay_resources() {
+            RelayService::enable(&swarm, resources);
+        }
+        let static_relays = try!(self.config.static_relays());
+
+        // blocks missing locally are fetched from connected peers, and
+        // content and peers are found through the DHT, on which the content
+        // we have is announced
+        let network = Arc::new(SwarmNetwork::new(swarm.clone()));
+        let bitswap = Arc::new(Bitswap::new(self.blockstore.clone(), network.clone()));
+        network.set_receiver(bitswap.clone());
+        let dht = Dht::new(swarm.clone(), self.datastore.clone());
+        dht.validator.add(ipns::NAMESPACE, Arc::new(IpnsValidator));
+        let reprovider = Arc::new(Reprovider::new(dht.clone(),
+                                                  self.blockstore.clone(),
+                                                  self.dagservice.clone(),
+                                                  self.pins.clone(),
+                                                  self.datastore.clone(),
+                                                  try!(self.config.reprovider_strategy()),
+                                                  self.config.reprovider_interval()));
+        if self.config.reprovider_interval().as_secs() > 0 {
+            Reprovider::start(&reprovider);
+        }
+
+        let pubsub = PubSub::new(swarm.clone(),
+                                 try!(self.get_key(keystore::SELF_KEY_NAME)),
+                                 try!(self.config.pubsub_router()));
+
+        self.blockservice.set_exchange(bitswap.clone());
+        self.config.conn_manager().start(&swarm);
+        // reservations on the static relays let us be reached through them
+        if !static_relays.is_empty() {
+            RelayClient::new(static_relays).start(&swarm);
+        }
+        if let Some(interval) = self.config.mdns_interval() {
+            // peers can still be found through the DHT without mDNS
+            if let Err(e) = MdnsService::start(&swarm, interval) {
+                warn!("Error starting mDNS discovery: {}", e);
+            }
+        }
+        self.swarm = Some(swarm);
+        self.bitswap = Some(bitswap);
+        self.dht = Some(dht);
+        self.reprovider = Some(reprovider);
+        self.pubsub = Some(pubsub);
+        Ok(addrs)
+    }
+
+    /// The swarm of an online node
+    pub fn get_swarm(&self) -> Result<&Arc<Swarm>, Error> {
+        match self.swarm {
+            None => Err(Error::Network("This command must be run with the daemon running"
+                                           .to_string())),
+            Some(ref swarm) => Ok(swarm),
+        }
+    }
+
+    /// The bitswap exchange of an online node
+    pub fn get_bitswap(&self) -> Result<&Arc<Bitswap>, Error> {
+        match self.bitswap {
+            None => Err(Error::Network("This command must be run with the daemon running"
+                                           .to_string())),
+            Some(ref bitswap) => Ok(bitswap),
+        }
+    }
+
+    /// The DHT of an online node
+    pub fn get_dht(&self) -> Result<&Arc<Dht>, Error> {
+        match self.dht {
+            None => Err(Error::Network("This command must be run with the daemon running"
+                                           .to_string())),
+            Some(ref dht) => Ok(dht),
+        }
+    }
+
+    /// The pubsub router of an online node
+    pub fn get_pubsub(&self) -> Result<&Arc<PubSub>, Error> {
+        match self.pubsub {
+            None => Err(Error::Network("This command must be run with the daemon running"
+                                           .to_string())),
+            Some(ref pubsub) => Ok(pubsub),
         }
     }
+
+    /// Connects to the peer at `addr`, returning its peer ID. The node must
+    /// be online.
+    pub fn connect(&self, addr: &Multiaddr) -> Result<PeerId, Error> {
+        Swarm::dial(try!(self.get_swarm()), addr)
+    }
+
+    /// Connects to the bootstrap peers in the config in the background, and
+    /// keeps reconnecting to them while the node has too few connections.
+    /// The node must be online. On a private network the public default
+    /// bootstrap peers are skipped.
+    pub fn bootstrap(&self) -> Result<(), Error> {
+        let mut bootstrapper = try!(self.config.bootstrapper());
+        if self.swarm_key.is_some() {
+            let public: Vec<Multiaddr> =
+                bootstrap::DEFAULT_PEERS.iter().filter_map(|s| Multiaddr::parse(s).ok()).collect();
+            bootstrapper.peers.retain(|addr| {
+                if public.contains(addr) {
+                    warn!("Skipping public bootstrap peer {} on a private network", addr);
+                    return false;
+                }
+                true
+            });
+        }
+        bootstrapper.start(try!(self.get_swarm()), try!(self.get_dht()));
+        Ok(())
+    }
+
+    /// Asks connected peers whether they can dial us back, to tell whether
+    /// the node can be reached directly. The node must be online.
+    pub fn reachability(&self) -> Result<Reachability, Error> {
+        autonat::probe(try!(self.get_swarm()))
+    }
+
+    /// Connects to `peer`, looking up its addresses in the DHT if they
+    /// aren't known. The node must be online.
+    pub fn connect_peer(&self, peer: &PeerId) -> Result<(), Error> {
+        let swarm = try!(self.get_swarm());
+        if swarm.is_connected(peer) {
+            return Ok(());
+        }
+        let dht = try!(self.get_dht());
+        if swarm.peer_addrs(peer).is_empty() {
+            try!(dht.find_peer(peer));
+        }
+        dht.connect(peer)
+    }
+
+    /// The name system, publishing to and resolving through the DHT when
+    /// the node is online
+    pub fn namesys(&self) -> NameSystem {
+        NameSystem::new(self.datastore.clone(), self.dht.clone(), self.dns_resolver.clone())
+    }
+
+    /// The private key named `name`: the node's own for "self" (or our peer
+    /// ID), and a key of the keystore otherwise
+    pub fn get_key(&self, name: &str) -> Result<PrivateKey, Error> {
+        if name == keystore::SELF_KEY_NAME || name == self.peer_id().to_string() {
+            Ok(PrivateKey::Rsa(crypto::load_private_key(&try!(self.config.private_key()))))
+        } else {
+            self.keystore.get(name)
+        }
+    }
+
+    /// Points the IPNS name of the key `key_name` (see `get_key`) to `path`
+    /// for `lifetime`, returning the name and the published record
+    pub fn publish_name(&self,
+                        key_name: &str,
+                        path: &str,
+                        lifetime: Duration,
+                        ttl: Duration)
+                        -> Result<(PeerId, IpnsEntry), Error> {
+        let key = try!(self.get_key(key_name));
+        let value = if path.starts_with(path::IPNS_PREFIX) {
+            path.to_string()
+        } else {
+            try!(path::IpfsPath::parse(path));
+            if path.starts_with('/') {
+                path.to_string()
+            } else {
+                format!("{}{}", path::IPFS_PREFIX, path)
+            }
+        };
+        let entry = try!(self.namesys().publish(&key, &value, lifetime, ttl));
+        Ok((key.public_key().id(), entry))
+    }
+
+    /// The path the IPNS name `name` (`<peer id>`, `<domain>` or
+    /// `/ipns/<name>`) points to, with any further names resolved
+    pub fn resolve_name(&self, name: &str) -> Result<String, Error> {
+        if name.starts_with(path::IPNS_PREFIX) {
+            self.namesys().resolve(name)
+        } else {
+            self.namesys().resolve(&format!("{}{}", path::IPNS_PREFIX, name))
+        }
+    }
+
+    /// Resolves an ipfs path (`/ipfs/<hash>/<name>/...`) to a DAG node.
+    /// `/ipns/<name>/...` paths, where the name is a peer ID or a domain with
+    /// a DNSLink record, are resolved through the name system first.
+    pub fn resolve_path(&self, path: &str) -> Result<Arc<Node>, Error> {
+        let path = try!(self.namesys().resolve(path));
+        path::resolve(&self.dagservice, &path)
+    }
+
+    /// Adds the file at `path` as a unixfs file, returning its CID. The
+    /// file is pinned, and announced to the DHT if the node is online.
+    pub fn add_file<P: AsRef<Path>>(&self,
+                                    path: P,
+                                    options: &AddOptions)
+                                    -> Result<Cid, Error> {
+        let root = try!(importer::add_file(path, &self.dagservice, options));
+        try!(self.pin(&root));
+        Ok(root)
+    }
+
+    /// Adds the directory at `path` and everything under it, returning the
+    /// paths added with their CIDs (the directory itself last). Only the
+    /// directory is pinned.
+    pub fn add_directory<P: AsRef<Path>>(&self,
+                                         path: P,
+                                         options: &AddOptions)
+                                         -> Result<Vec<(PathBuf, Cid)>, Error> {
+        let added = try!(importer::add_directory(path,
+                                                 &self.dagservice,
+                                                 self.config.sharding_threshold(),
+                                                 options));
+        if let Some(&(_, ref root)) = added.last() {
+            try!(self.pin(root));
+        }
+        Ok(added)
+    }
+
+    /// Adds everything read from `reader` as a unixfs file, returning its
+    /// hash. The file is pinned, and announced to the DHT if the node is
+    /// online.
+    pub fn add_reader<R: Read>(&self, reader: &mut R) -> Result<Multihash, Error> {
+        let root = try!(importer::build_dag_from_reader(reader, &self.dagservice));
+        try!(self.pin(&Cid::new(Codec::DagProtobuf, root.clone())));
+        Ok(root)
+    }
+
+    /// Pins the DAG under `root`, and announces it to the DHT if the node is
+    /// online
+    pub fn pin(&self, root: &Cid) -> Result<(), Error> {
+        try!(self.pins.pin(root));
+        if let Some(ref reprovider) = self.reprovider {
+            Reprovider::provide_dag(reprovider, root);
+        }
+        Ok(())
+    }
+
+    /// Returns a reader over the contents of the unixfs file at `path`
+    pub fn cat(&self, path: &str) -> Result<DagReader, Error> {
+        let node = try!(self.resolve_path(path));
+        DagReader::new(node, self.dagservice.clone())
+    }
+
+    /// Writes the unixfs file or directory at `path` to `output`, which must
+    /// not exist
+    pub fn get<P: AsRef<Path>>(&self,
+                               path: &str,
+                               output: P,
+                               options: &ExportOptions)
+                               -> Result<(), Error> {
+        let node = try!(self.resolve_path(path));
+        exporter::export(&self.dagservice, node, output.as_ref(), options)
+    }
 }
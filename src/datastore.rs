@@ -0,0 +1,208 @@
+use error::Error;
+use util;
+
+use atomicwrites::{AtomicFile, AllowOverwrite};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+const VALUE_FILE_EXT: &'static str = ".data";
+pub const DATASTORE_DIR: &'static str = "datastore";
+
+/// Stores values under hierarchical keys such as `/providers/<key>/<peer>`,
+/// either as files in a repo directory or in memory. Used for the state a
+/// node keeps besides blocks (DHT records, provider records, ...).
+///
+/// Each segment of a key must be non-empty and made up of ASCII letters,
+/// digits, `-`, `_` and `.`, so that keys map directly to file paths.
+pub struct Datastore {
+    storage: Storage,
+}
+
+enum Storage {
+    Flatfs(PathBuf),
+    Memory(RwLock<BTreeMap<String, Vec<u8>>>),
+}
+
+impl Datastore {
+    // A value with key /a/b/c is stored in the file <datastore dir>/a/b/c.data
+    pub fn new(path: PathBuf) -> Self {
+        Datastore { storage: Storage::Flatfs(path) }
+    }
+
+    /// A Datastore that keeps values in memory and never touches the disk
+    pub fn in_memory() -> Self {
+        Datastore { storage: Storage::Memory(RwLock::new(BTreeMap::new())) }
+    }
+
+    pub fn has(&self, key: &str) -> Result<bool, Error> {
+        try!(check_key(key));
+        match self.storage {
+            Storage::Flatfs(_) => {
+                util::file_exists(self.value_file(key)).map_err(|e| {
+                    Error::Io(format!("Error checking for existence of key {}", key), e)
+                })
+            }
+            Storage::Memory(ref values) => Ok(values.read().unwrap().contains_key(key)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        try!(check_key(key));
+        match self.storage {
+            Storage::Flatfs(_) => read_value_file(&self.value_file(key), key),
+            Storage::Memory(ref values) => {
+                match values.read().unwrap().get(key) {
+                    None => Err(Error::NotFound(format!("key {}", key))),
+                    Some(value) => Ok(value.clone()),
+                }
+            }
+        }
+    }
+
+    // Replaces any value already stored under `key`
+    pub fn put(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        try!(check_key(key));
+        if let Storage::Memory(ref values) = self.storage {
+            values.write().unwrap().insert(key.to_string(), value.to_vec());
+            return Ok(());
+        }
+
+        let file_path = self.value_file(key);
+        if let Some(dir) = file_path.parent() {
+            try!(fs::create_dir_all(dir).map_err(|e| {
+                Error::Io(format!("Error making directory {:?}", dir), e)
+            }));
+        }
+        let file = AtomicFile::new(&file_path, AllowOverwrite);
+        file.write(|f| f.write_all(value))
+            .map_err(|e| {
+                Error::Io(format!("Error writing datastore file {:?}", file_path),
+                          util::atomic_write_error(e))
+            })
+    }
+
+    // Deleting a key that isn't there is not an error
+    pub fn delete(&self, key: &str) -> Result<(), Error> {
+        try!(check_key(key));
+        match self.storage {
+            Storage::Flatfs(_) => {
+                match fs::remove_file(self.value_file(key)) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                    Err(e) => Err(Error::Io(format!("Error deleting key {}", key), e)),
+                    Ok(()) => Ok(()),
+                }
+            }
+            Storage::Memory(ref values) => {
+                values.write().unwrap().remove(key);
+                Ok(())
+            }
+        }
+    }
+
+    // Every key under `prefix` (e.g. /providers/<key>) along with its value,
+    // sorted by key
+    pub fn query(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        try!(check_key(prefix));
+        match self.storage {
+            Storage::Flatfs(ref path) => {
+                let mut dir = path.clone();
+                for segment in prefix.split('/').skip(1) {
+                    dir.push(segment);
+                }
+                let mut results = Vec::new();
+                try!(walk(&dir, prefix, &mut results));
+                results.sort_by(|a, b| a.0.cmp(&b.0));
+                Ok(results)
+            }
+            Storage::Memory(ref values) => {
+                let dir_prefix = format!("{}/", prefix);
+                Ok(values.read()
+                         .unwrap()
+                         .iter()
+                         .filter(|&(k, _)| k.starts_with(&dir_prefix))
+                         .map(|(k, v)| (k.clone(), v.clone()))
+                         .collect())
+            }
+        }
+    }
+
+    // Only meaningful for Flatfs storage
+    fn value_file(&self, key: &str) -> PathBuf {
+        let mut file_path = match self.storage {
+            Storage::Flatfs(ref path) => path.clone(),
+            Storage::Memory(_) => unreachable!(),
+        };
+        for segment in key.split('/').skip(1) {
+            file_path.push(segment);
+        }
+        // keys are checked to be ASCII, and have at least one segment
+        let file_name = format!("{}{}",
+                                file_path.file_name().unwrap().to_str().unwrap(),
+                                VALUE_FILE_EXT);
+        file_path.set_file_name(file_name);
+        file_path
+    }
+}
+
+fn check_key(key: &str) -> Result<(), Error> {
+    let valid_segment = |s: &str| {
+        !s.is_empty() && s != "." && s != ".." &&
+        s.chars().all(|c| {
+            match c {
+                'a'...'z' | 'A'...'Z' | '0'...'9' | '-' | '_' | '.' => true,
+                _ => false,
+            }
+        })
+    };
+    if !key.starts_with('/') || !key.split('/').skip(1).all(valid_segment) {
+        return Err(Error::InvalidArgument(format!("Invalid datastore key {}", key)));
+    }
+    Ok(())
+}
+
+fn read_value_file(path: &PathBuf, key: &str) -> Result<Vec<u8>, Error> {
+    let mut file = try!(File::open(path).map_err(|e| {
+        match e.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(format!("key {}", key)),
+            _ => Error::Io(format!("Error opening file {:?} for key {}", path, key), e),
+        }
+    }));
+
+    let mut value = Vec::new();
+    try!(file.read_to_end(&mut value).map_err(|e| {
+        Error::Io(format!("Error reading file {:?} for key {}", path, key), e)
+    }));
+    Ok(value)
+}
+
+// Collects the values in `dir`, whose key is `prefix`, and its
+// subdirectories
+fn walk(dir: &PathBuf, prefix: &str, results: &mut Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+    let entries = match fs::read_dir(dir) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Error::Io(format!("Error reading directory {:?}", dir), e)),
+        Ok(entries) => entries,
+    };
+
+    for entry in entries {
+        let entry = try!(entry.map_err(|e| {
+            Error::Io(format!("Error reading directory {:?}", dir), e)
+        }));
+        let name = match entry.file_name().into_string() {
+            Err(_) => continue,
+            Ok(name) => name,
+        };
+        let path = entry.path();
+        if path.is_dir() {
+            try!(walk(&path, &format!("{}/{}", prefix, name), results));
+        } else if name.ends_with(VALUE_FILE_EXT) {
+            let key = format!("{}/{}", prefix, &name[..name.len() - VALUE_FILE_EXT.len()]);
+            let value = try!(read_value_file(&path, &key));
+            results.push((key, value));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+// The routing table: peers sorted into k-buckets by how many leading bits
+// the SHA-256 of their ID has in common with ours. Distances between keys
+// are their XOR, compared as big-endian numbers.
+
+use crypto;
+use peer::PeerId;
+
+use std::time::Instant;
+
+pub const K: usize = 20; // bucket size, and the number of peers a lookup returns
+
+const NUM_BUCKETS: usize = 256;
+
+// The point in the keyspace of `data` (a peer ID or content key)
+pub fn key_for(data: &[u8]) -> Vec<u8> {
+    crypto::sha256(data)
+}
+
+pub fn peer_key(peer: &PeerId) -> Vec<u8> {
+    key_for(&peer.clone().into_bytes())
+}
+
+pub fn distance(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect()
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    for (i, byte) in distance(a, b).into_iter().enumerate() {
+        if byte != 0 {
+            return i * 8 + byte.leading_zeros() as usize;
+        }
+    }
+    a.len() * 8
+}
+
+struct Entry {
+    peer: PeerId,
+    key: Vec<u8>,
+    last_seen: Instant,
+}
+
+pub struct RoutingTable {
+    local_key: Vec<u8>,
+    buckets: Vec<Vec<Entry>>, // bucket i holds peers sharing a prefix of i bits with us
+}
+
+impl RoutingTable {
+    pub fn new(local_peer: &PeerId) -> Self {
+        RoutingTable {
+            local_key: peer_key(local_peer),
+            buckets: (0..NUM_BUCKETS).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    // Records that we've heard from `peer`. When its bucket is full the peer
+    // isn't added, since peers that have been around longer are more likely
+    // to stay. Returns whether the peer is in the table.
+    pub fn update(&mut self, peer: &PeerId) -> bool {
+        let key = peer_key(peer);
+        let cpl = common_prefix_len(&self.local_key, &key);
+        if cpl >= NUM_BUCKETS {
+            return false; // ourselves
+        }
+
+        let bucket = &mut self.buckets[cpl];
+        if let Some(entry) = bucket.iter_mut().find(|e| e.peer == *peer) {
+            entry.last_seen = Instant::now();
+            return true;
+        }
+        if bucket.len() >= K {
+            return false;
+        }
+        bucket.push(Entry {
+            peer: peer.clone(),
+            key: key,
+            last_seen: Instant::now(),
+        });
+        true
+    }
+
+    pub fn remove(&mut self, peer: &PeerId) {
+        let cpl = common_prefix_len(&self.local_key, &peer_key(peer));
+        if cpl < NUM_BUCKETS {
+            self.buckets[cpl].retain(|e| e.peer != *peer);
+        }
+    }
+
+    // Up to `count` peers closest to `key`, closest first
+    pub fn closest(&self, key: &[u8], count: usize) -> Vec<PeerId> {
+        let mut entries: Vec<(Vec<u8>, &PeerId)> = self.buckets
+                                                        .iter()
+                                                        .flat_map(|b| b.iter())
+                                                        .map(|e| (distance(&e.key, key), &e.peer))
+                                                        .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.into_iter().take(count).map(|(_, peer)| peer.clone()).collect()
+    }
+
+    pub fn peers(&self) -> Vec<PeerId> {
+        self.buckets.iter().flat_map(|b| b.iter()).map(|e| e.peer.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(|b| b.len()).sum()
+    }
+}
@@ -0,0 +1,198 @@
+// DHT messages, encoded as described in message.proto
+
+use codec::{PbReader, PbWriter};
+use error::Error;
+use multiaddr::Multiaddr;
+use peer::PeerId;
+
+use rust_multihash::Multihash;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MessageType {
+    PutValue,
+    GetValue,
+    AddProvider,
+    GetProviders,
+    FindNode,
+    Ping,
+}
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub time_received: String,
+}
+
+// A peer along with the addresses it can be reached at
+#[derive(Clone, Debug)]
+pub struct PeerInfo {
+    pub id: PeerId,
+    pub addrs: Vec<Multiaddr>,
+    pub connected: bool, // whether the sender is connected to the peer
+}
+
+#[derive(Debug)]
+pub struct Message {
+    pub msg_type: MessageType,
+    pub key: Vec<u8>,
+    pub record: Option<Record>,
+    pub closer_peers: Vec<PeerInfo>,
+    pub provider_peers: Vec<PeerInfo>,
+}
+
+impl Message {
+    pub fn new(msg_type: MessageType, key: Vec<u8>) -> Self {
+        Message {
+            msg_type: msg_type,
+            key: key,
+            record: None,
+            closer_peers: Vec::new(),
+            provider_peers: Vec::new(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut msg = PbWriter::new();
+        msg.int32(1,
+                  match self.msg_type {
+                      MessageType::PutValue => 0,
+                      MessageType::GetValue => 1,
+                      MessageType::AddProvider => 2,
+                      MessageType::GetProviders => 3,
+                      MessageType::FindNode => 4,
+                      MessageType::Ping => 5,
+                  })
+           .bytes(2, &self.key);
+
+        if let Some(ref record) = self.record {
+            msg.message(3, &encode_record(record));
+        }
+
+        for info in &self.closer_peers {
+            msg.message(8, &encode_peer(info));
+        }
+        for info in &self.provider_peers {
+            msg.message(9, &encode_peer(info));
+        }
+
+        msg.into_bytes()
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut msg = Message::new(MessageType::Ping, vec![]);
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => {
+                    msg.msg_type = match try!(value.as_i32()) {
+                        0 => MessageType::PutValue,
+                        1 => MessageType::GetValue,
+                        2 => MessageType::AddProvider,
+                        3 => MessageType::GetProviders,
+                        4 => MessageType::FindNode,
+                        5 => MessageType::Ping,
+                        t => {
+                            return Err(Error::Decode(format!("Unknown DHT message type {}", t),
+                                                     None))
+                        }
+                    }
+                }
+                2 => msg.key = try!(value.as_bytes()).to_vec(),
+                3 => msg.record = Some(try!(decode_record(try!(value.as_bytes())))),
+                8 => msg.closer_peers.push(try!(decode_peer(try!(value.as_bytes())))),
+                9 => msg.provider_peers.push(try!(decode_peer(try!(value.as_bytes())))),
+                _ => {}
+            }
+        }
+        Ok(msg)
+    }
+}
+
+fn encode_peer(info: &PeerInfo) -> PbWriter {
+    let mut p = PbWriter::new();
+    p.bytes(1, &info.id.clone().into_bytes());
+    for addr in &info.addrs {
+        p.bytes(2, &addr.to_bytes());
+    }
+    p.int32(3, if info.connected { 1 } else { 0 });
+    p
+}
+
+fn decode_peer(buf: &[u8]) -> Result<PeerInfo, Error> {
+    let mut info = PeerInfo {
+        id: Multihash::from_vec(vec![]),
+        addrs: Vec::new(),
+        connected: false,
+    };
+    let mut has_id = false;
+
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => {
+                info.id = Multihash::from_vec(try!(value.as_bytes()).to_vec());
+                has_id = true;
+            }
+            2 => {
+                // skip addresses using protocols we don't support
+                if let Ok(addr) = Multiaddr::from_bytes(try!(value.as_bytes())) {
+                    info.addrs.push(addr);
+                }
+            }
+            3 => info.connected = try!(value.as_i32()) == 1,
+            _ => {}
+        }
+    }
+
+    if !has_id {
+        return Err(Error::Decode("DHT peer has no ID".to_string(), None));
+    }
+    Ok(info)
+}
+
+fn encode_record(record: &Record) -> PbWriter {
+    let mut r = PbWriter::new();
+    r.bytes(1, &record.key)
+     .bytes(2, &record.value)
+     .string(5, &record.time_received);
+    r
+}
+
+fn decode_record(buf: &[u8]) -> Result<Record, Error> {
+    let mut record = Record {
+        key: Vec::new(),
+        value: Vec::new(),
+        time_received: String::new(),
+    };
+
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => record.key = try!(value.as_bytes()).to_vec(),
+            2 => record.value = try!(value.as_bytes()).to_vec(),
+            5 => record.time_received = try!(value.as_string()),
+            _ => {}
+        }
+    }
+    Ok(record)
+}
+
+impl Record {
+    pub fn new(key: Vec<u8>, value: Vec<u8>) -> Self {
+        Record {
+            key: key,
+            value: value,
+            time_received: String::new(),
+        }
+    }
+
+    // Records are stored in the datastore in their wire format
+    pub fn encode(&self) -> Vec<u8> {
+        encode_record(self).into_bytes()
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        decode_record(buf)
+    }
+}
@@ -0,0 +1,467 @@
+// A Kademlia DHT, compatible with go-libp2p-kad-dht's wire format. Peers
+// and keys live in the same keyspace (the SHA-256 of their bytes), and each
+// peer keeps a routing table of other peers. Lookups repeatedly ask the
+// closest peers we know of for peers closer still, until the closest ones
+// found have all answered. The DHT stores provider records (which peers
+// have some content) and validated records (/pk/..., /ipns/...).
+
+use datastore::Datastore;
+use error::Error;
+use multiaddr::{Multiaddr, Protocol};
+use peer::PeerId;
+use swarm::{Notifiee, Stream, Swarm};
+use codec;
+use util;
+
+use rust_multihash::Multihash;
+use rustc_serialize::hex::ToHex;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use self::kbucket::{K, RoutingTable};
+use self::message::{Message, MessageType, PeerInfo, Record};
+use self::providers::ProviderStore;
+use self::record::{NamespacedValidator, Validator};
+
+pub mod kbucket;
+pub mod message;
+pub mod providers;
+pub mod record;
+
+pub const PROTOCOL_ID: &'static str = "/ipfs/kad/1.0.0";
+
+// How long records received from other peers are kept
+const RECORD_MAX_AGE_SECS: u64 = 36 * 60 * 60;
+const RECORDS_PREFIX: &'static str = "/dht/records";
+
+pub struct Dht {
+    swarm: Arc<Swarm>,
+    datastore: Arc<Datastore>,
+    routing_table: Mutex<RoutingTable>,
+    providers: ProviderStore,
+    pub validator: NamespacedValidator,
+}
+
+impl Dht {
+    // Handles DHT streams on the swarm, adding the peers we connect to to
+    // the routing table
+    pub fn new(swarm: Arc<Swarm>, datastore: Arc<Datastore>) -> Arc<Self> {
+        let dht = Arc::new(Dht {
+            routing_table: Mutex::new(RoutingTable::new(swarm.local_peer())),
+            swarm: swarm.clone(),
+            providers: ProviderStore::new(datastore.clone()),
+            datastore: datastore,
+            validator: NamespacedValidator::new(),
+        });
+
+        let handler_dht = dht.clone();
+        swarm.set_stream_handler(PROTOCOL_ID,
+                                 Arc::new(move |peer: &PeerId, stream: Stream| {
+                                     handler_dht.handle_stream(peer, stream)
+                                 }));
+        swarm.add_notifiee(dht.clone());
+        dht
+    }
+
+    pub fn routing_table_peers(&self) -> Vec<PeerId> {
+        self.routing_table.lock().unwrap().peers()
+    }
+
+    // Fills the routing table by looking up our own ID
+    pub fn bootstrap(&self) -> Result<(), Error> {
+        let local_peer = self.swarm.local_peer().clone().into_bytes();
+        try!(self.get_closest_peers(&local_peer));
+        Ok(())
+    }
+
+    // The K peers closest to `key` that answered us
+    pub fn get_closest_peers(&self, key: &[u8]) -> Result<Vec<PeerId>, Error> {
+        self.lookup(key, |peer| {
+            let msg = Message::new(MessageType::FindNode, key.to_vec());
+            let reply = try!(self.request(peer, &msg));
+            Ok((reply.closer_peers, false))
+        })
+    }
+
+    // The addresses of `peer`
+    pub fn find_peer(&self, peer: &PeerId) -> Result<Vec<Multiaddr>, Error> {
+        if *peer == *self.swarm.local_peer() {
+            return Ok(self.swarm.listen_addrs());
+        }
+        if self.swarm.is_connected(peer) {
+            return Ok(self.swarm.peer_addrs(peer));
+        }
+
+        let key = peer.clone().into_bytes();
+        let mut found = false;
+        try!(self.lookup(&key, |p| {
+            let msg = Message::new(MessageType::FindNode, key.clone());
+            let reply = try!(self.request(p, &msg));
+            found = reply.closer_peers
+                         .iter()
+                         .any(|info| info.id == *peer && !info.addrs.is_empty());
+            Ok((reply.closer_peers, found))
+        }));
+
+        let addrs = self.swarm.peer_addrs(peer);
+        if !found && addrs.is_empty() {
+            return Err(Error::NotFound(format!("peer {}", peer)));
+        }
+        Ok(addrs)
+    }
+
+    // Announces that we can provide `key` to the peers closest to it,
+    // returning how many were told
+    pub fn provide(&self, key: &Multihash) -> Result<usize, Error> {
+        let key = key.clone().into_bytes();
+        try!(self.providers.add_provider(&key, self.swarm.local_peer()));
+
+        let mut msg = Message::new(MessageType::AddProvider, key.clone());
+        msg.provider_peers.push(PeerInfo {
+            id: self.swarm.local_peer().clone(),
+            addrs: self.swarm.listen_addrs(),
+            connected: true,
+        });
+
+        let mut told = 0;
+        for peer in try!(self.get_closest_peers(&key)) {
+            match self.send(&peer, &msg) {
+                Ok(()) => told += 1,
+                Err(e) => debug!("Error sending provider record to {}: {}", peer, e),
+            }
+        }
+        Ok(told)
+    }
+
+    // Up to `count` peers that can provide `key`. Their addresses are added
+    // to the swarm.
+    pub fn find_providers(&self, key: &Multihash, count: usize) -> Result<Vec<PeerId>, Error> {
+        let key = key.clone().into_bytes();
+        let mut providers = try!(self.providers.get_providers(&key));
+        if providers.len() >= count {
+            providers.truncate(count);
+            return Ok(providers);
+        }
+
+        let lookup = self.lookup(&key, |peer| {
+            let msg = Message::new(MessageType::GetProviders, key.clone());
+            let reply = try!(self.request(peer, &msg));
+            for info in reply.provider_peers {
+                self.swarm.add_peer_addrs(&info.id, &info.addrs);
+                if !providers.contains(&info.id) {
+                    providers.push(info.id);
+                }
+            }
+            Ok((reply.closer_peers, providers.len() >= count))
+        });
+        if let Err(e) = lookup {
+            // we may still have found providers locally
+            if providers.is_empty() {
+                return Err(e);
+            }
+        }
+        providers.truncate(count);
+        Ok(providers)
+    }
+
+    // Stores a record with the peers closest to its key, returning how many
+    // stored it
+    pub fn put_value(&self, key: &[u8], value: &[u8]) -> Result<usize, Error> {
+        try!(self.validator.validate(key, value));
+        let record = Record::new(key.to_vec(), value.to_vec());
+        try!(self.store_record(record.clone()));
+
+        let mut msg = Message::new(MessageType::PutValue, key.to_vec());
+        msg.record = Some(record);
+        let mut stored = 0;
+        for peer in try!(self.get_closest_peers(key)) {
+            match self.request(&peer, &msg) {
+                Ok(_) => stored += 1,
+                Err(e) => debug!("Error storing record with {}: {}", peer, e),
+            }
+        }
+        Ok(stored)
+    }
+
+    // The best valid value for `key`, from the peers closest to it and our
+    // own store
+    pub fn get_value(&self, key: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut values = Vec::new();
+        if let Some(record) = try!(self.get_local_record(key)) {
+            values.push(record.value);
+        }
+
+        let lookup = self.lookup(key, |peer| {
+            let msg = Message::new(MessageType::GetValue, key.to_vec());
+            let reply = try!(self.request(peer, &msg));
+            if let Some(record) = reply.record {
+                match self.validator.validate(key, &record.value) {
+                    Ok(()) => values.push(record.value),
+                    Err(e) => debug!("Invalid record from {}: {}", peer, e),
+                }
+            }
+            Ok((reply.closer_peers, false))
+        });
+        if values.is_empty() {
+            try!(lookup);
+            return Err(Error::NotFound(format!("record {}", record::format_key(key))));
+        }
+
+        let best = self.validator.select(key, &values);
+        Ok(values.swap_remove(best))
+    }
+
+    // Runs an iterative lookup for `target`. `query` is called on the
+    // closest peer not yet queried, and returns the closer peers it knows of,
+    // and whether to stop. Returns the K closest peers that answered.
+    fn lookup<F>(&self, target: &[u8], mut query: F) -> Result<Vec<PeerId>, Error>
+        where F: FnMut(&PeerId) -> Result<(Vec<PeerInfo>, bool), Error>
+    {
+        let target_key = kbucket::key_for(target);
+        let local_peer = self.swarm.local_peer().clone();
+
+        // candidates by distance from the target
+        let mut candidates = BTreeMap::new();
+        for peer in self.routing_table.lock().unwrap().closest(&target_key, K) {
+            candidates.insert(kbucket::distance(&kbucket::peer_key(&peer), &target_key), peer);
+        }
+        if candidates.is_empty() {
+            return Err(Error::Network("No peers in the DHT routing table".to_string()));
+        }
+
+        let mut queried = HashSet::new();
+        let mut answered = HashSet::new();
+        loop {
+            let next = candidates.iter()
+                                 .take(K)
+                                 .find(|&(_, peer)| !queried.contains(peer))
+                                 .map(|(distance, peer)| (distance.clone(), peer.clone()));
+            let (distance, peer) = match next {
+                None => break,
+                Some(next) => next,
+            };
+            queried.insert(peer.clone());
+
+            let (closer, done) = match query(&peer) {
+                Err(e) => {
+                    debug!("DHT query to {} failed: {}", peer, e);
+                    candidates.remove(&distance);
+                    continue;
+                }
+                Ok(reply) => reply,
+            };
+            answered.insert(peer);
+            for info in closer {
+                if info.id == local_peer {
+                    continue;
+                }
+                self.swarm.add_peer_addrs(&info.id, &info.addrs);
+                let distance = kbucket::distance(&kbucket::peer_key(&info.id), &target_key);
+                candidates.insert(distance, info.id);
+            }
+            if done {
+                break;
+            }
+        }
+
+        Ok(candidates.into_iter()
+                     .map(|(_, peer)| peer)
+                     .filter(|peer| answered.contains(peer))
+                     .take(K)
+                     .collect())
+    }
+
+    // Connects to `peer` at any of its known addresses
+    pub fn connect(&self, peer: &PeerId) -> Result<(), Error> {
+        if self.swarm.is_connected(peer) {
+            return Ok(());
+        }
+        let mut last_err = Error::NotFound(format!("addresses for peer {}", peer));
+        for mut addr in self.swarm.peer_addrs(peer) {
+            addr.push(Protocol::P2p(peer.clone()));
+            match Swarm::dial(&self.swarm, &addr) {
+                Ok(_) => return Ok(()),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    // Sends a request and waits for the response
+    fn request(&self, peer: &PeerId, msg: &Message) -> Result<Message, Error> {
+        let mut stream = try!(self.open_stream(peer, msg));
+        let reply = try!(codec::read_length_prefixed(&mut stream).map_err(|e| {
+            Error::Io(format!("Error reading DHT response from {}", peer), e)
+        }));
+        let reply = try!(Message::decode(&reply));
+        if reply.msg_type != msg.msg_type {
+            return Err(Error::Network(format!("{} answered a DHT {:?} request with {:?}",
+                                              peer,
+                                              msg.msg_type,
+                                              reply.msg_type)));
+        }
+        self.routing_table.lock().unwrap().update(peer);
+        Ok(reply)
+    }
+
+    // Sends a message that has no response
+    fn send(&self, peer: &PeerId, msg: &Message) -> Result<(), Error> {
+        try!(self.open_stream(peer, msg));
+        Ok(())
+    }
+
+    fn open_stream(&self, peer: &PeerId, msg: &Message) -> Result<Stream, Error> {
+        try!(self.connect(peer));
+        let mut stream = try!(self.swarm.new_stream(peer, PROTOCOL_ID));
+        try!(codec::write_length_prefixed(&mut stream, &msg.encode())
+                 .and_then(|_| stream.close_write())
+                 .map_err(|e| Error::Io(format!("Error sending DHT message to {}", peer), e)));
+        Ok(stream)
+    }
+
+    fn handle_stream(&self, peer: &PeerId, mut stream: Stream) {
+        loop {
+            let frame = match codec::read_length_prefixed(&mut stream) {
+                Err(_) => return, // the peer closed the stream
+                Ok(frame) => frame,
+            };
+            let msg = match Message::decode(&frame) {
+                Err(e) => {
+                    warn!("Invalid DHT message from {}: {}", peer, e);
+                    stream.reset();
+                    return;
+                }
+                Ok(msg) => msg,
+            };
+            self.routing_table.lock().unwrap().update(peer);
+
+            let reply = match self.handle_message(peer, msg) {
+                Err(e) => {
+                    debug!("Error handling DHT message from {}: {}", peer, e);
+                    stream.reset();
+                    return;
+                }
+                Ok(reply) => reply,
+            };
+            if let Some(reply) = reply {
+                if let Err(e) = codec::write_length_prefixed(&mut stream, &reply.encode()) {
+                    debug!("Error sending DHT response to {}: {}", peer, e);
+                    return;
+                }
+            }
+        }
+    }
+
+    // Answers a request from `peer`
+    fn handle_message(&self, peer: &PeerId, msg: Message) -> Result<Option<Message>, Error> {
+        let mut reply = Message::new(msg.msg_type, msg.key.clone());
+        match msg.msg_type {
+            MessageType::Ping => {}
+            MessageType::FindNode => reply.closer_peers = self.closer_peers(&msg.key, peer),
+            MessageType::GetProviders => {
+                for provider in try!(self.providers.get_providers(&msg.key)) {
+                    let addrs = if provider == *self.swarm.local_peer() {
+                        self.swarm.listen_addrs()
+                    } else {
+                        self.swarm.peer_addrs(&provider)
+                    };
+                    reply.provider_peers.push(PeerInfo {
+                        connected: self.swarm.is_connected(&provider),
+                        id: provider,
+                        addrs: addrs,
+                    });
+                }
+                reply.closer_peers = self.closer_peers(&msg.key, peer);
+            }
+            MessageType::AddProvider => {
+                // peers may only add themselves as providers
+                for info in msg.provider_peers.into_iter().filter(|info| info.id == *peer) {
+                    let addrs = self.swarm.resolve_unspecified(peer, &info.addrs);
+                    self.swarm.add_peer_addrs(peer, &addrs);
+                    try!(self.providers.add_provider(&msg.key, peer));
+                }
+                return Ok(None);
+            }
+            MessageType::GetValue => {
+                reply.record = try!(self.get_local_record(&msg.key));
+                reply.closer_peers = self.closer_peers(&msg.key, peer);
+            }
+            MessageType::PutValue => {
+                let record = match msg.record {
+                    None => {
+                        return Err(Error::InvalidArgument("PUT_VALUE without a record".to_string()))
+                    }
+                    Some(record) => record,
+                };
+                if record.key != msg.key {
+                    return Err(Error::InvalidArgument("PUT_VALUE record key does not match"
+                                                          .to_string()));
+                }
+                try!(self.validator.validate(&record.key, &record.value));
+                try!(self.store_record(record.clone()));
+                reply.record = Some(record);
+            }
+        }
+        Ok(Some(reply))
+    }
+
+    // The peers in our routing table closest to `key`, other than `requester`
+    fn closer_peers(&self, key: &[u8], requester: &PeerId) -> Vec<PeerInfo> {
+        let peers = self.routing_table.lock().unwrap().closest(&kbucket::key_for(key), K + 1);
+        peers.into_iter()
+             .filter(|peer| peer != requester)
+             .take(K)
+             .map(|peer| {
+                 PeerInfo {
+                     addrs: self.swarm.peer_addrs(&peer),
+                     connected: self.swarm.is_connected(&peer),
+                     id: peer,
+                 }
+             })
+             .collect()
+    }
+
+    fn store_record(&self, mut record: Record) -> Result<(), Error> {
+        // keep the best of the new and existing records
+        if let Some(existing) = try!(self.get_local_record(&record.key)) {
+            let values = vec![existing.value, record.value.clone()];
+            if self.validator.select(&record.key, &values) == 0 {
+                return Ok(());
+            }
+        }
+        record.time_received = util::unix_time().to_string();
+        self.datastore.put(&record_key(&record.key), &record.encode())
+    }
+
+    // Our record for `key`, if we have an unexpired valid one
+    fn get_local_record(&self, key: &[u8]) -> Result<Option<Record>, Error> {
+        let ds_key = record_key(key);
+        let record = match self.datastore.get(&ds_key) {
+            Err(Error::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+            Ok(buf) => try!(Record::decode(&buf)),
+        };
+
+        let received = record.time_received.parse::<u64>().unwrap_or(0);
+        if received + RECORD_MAX_AGE_SECS < util::unix_time() ||
+           self.validator.validate(key, &record.value).is_err() {
+            try!(self.datastore.delete(&ds_key));
+            return Ok(None);
+        }
+        Ok(Some(record))
+    }
+}
+
+fn record_key(key: &[u8]) -> String {
+    format!("{}/{}", RECORDS_PREFIX, key.to_hex())
+}
+
+impl Notifiee for Dht {
+    fn connected(&self, peer: &PeerId) {
+        self.routing_table.lock().unwrap().update(peer);
+    }
+
+    fn disconnected(&self, peer: &PeerId) {
+        self.routing_table.lock().unwrap().remove(peer);
+    }
+}
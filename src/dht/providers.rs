@@ -0,0 +1,59 @@
+// Provider records: which peers have told us they can provide a key. They're
+// stored in the datastore as /providers/<hex key>/<peer id>, with the time
+// they were added (seconds since the epoch) as the value, and expire after
+// PROVIDER_VALIDITY_SECS.
+
+use datastore::Datastore;
+use error::Error;
+use peer::{self, PeerId};
+use util;
+
+use rustc_serialize::hex::ToHex;
+use std::str;
+use std::sync::Arc;
+
+pub const PROVIDER_VALIDITY_SECS: u64 = 24 * 60 * 60;
+
+const PROVIDERS_PREFIX: &'static str = "/providers";
+
+pub struct ProviderStore {
+    datastore: Arc<Datastore>,
+}
+
+impl ProviderStore {
+    pub fn new(datastore: Arc<Datastore>) -> Self {
+        ProviderStore { datastore: datastore }
+    }
+
+    pub fn add_provider(&self, key: &[u8], provider: &PeerId) -> Result<(), Error> {
+        let ds_key = format!("{}/{}", providers_key(key), provider);
+        self.datastore.put(&ds_key, util::unix_time().to_string().as_bytes())
+    }
+
+    // The providers of `key` that haven't expired. Expired ones are removed.
+    pub fn get_providers(&self, key: &[u8]) -> Result<Vec<PeerId>, Error> {
+        let now = util::unix_time();
+        let mut providers = Vec::new();
+        for (ds_key, value) in try!(self.datastore.query(&providers_key(key))) {
+            let added = str::from_utf8(&value).ok().and_then(|s| s.parse::<u64>().ok());
+            match added {
+                Some(added) if added + PROVIDER_VALIDITY_SECS > now => {}
+                _ => {
+                    try!(self.datastore.delete(&ds_key));
+                    continue;
+                }
+            }
+
+            let id = ds_key.rsplit('/').next().unwrap();
+            match peer::parse_peer_id(id) {
+                Ok(provider) => providers.push(provider),
+                Err(e) => warn!("Invalid provider record {}: {}", ds_key, e),
+            }
+        }
+        Ok(providers)
+    }
+}
+
+fn providers_key(key: &[u8]) -> String {
+    format!("{}/{}", PROVIDERS_PREFIX, key.to_hex())
+}
@@ -0,0 +1,132 @@
+// Record validation. DHT record keys are namespaced, /<namespace>/<rest>,
+// and each namespace has a validator deciding which values are valid and
+// which of several valid values is best. Records in unknown namespaces are
+// rejected.
+
+use error::Error;
+use peer::{self, PeerId};
+use util;
+
+use rust_multihash::Multihash;
+use std::collections::HashMap;
+use std::str;
+use std::sync::{Arc, RwLock};
+
+pub trait Validator: Send + Sync {
+    fn validate(&self, key: &[u8], value: &[u8]) -> Result<(), Error>;
+    // The index of the best of `values`, which have all been validated
+    fn select(&self, key: &[u8], values: &[Vec<u8>]) -> usize;
+}
+
+// Splits a key into its namespace and the rest
+pub fn split_key(key: &[u8]) -> Option<(&str, &[u8])> {
+    if key.first() != Some(&b'/') {
+        return None;
+    }
+    let rest = &key[1..];
+    let end = match rest.iter().position(|&b| b == b'/') {
+        None => return None,
+        Some(end) => end,
+    };
+    match str::from_utf8(&rest[..end]) {
+        Ok(namespace) if !namespace.is_empty() => Some((namespace, &rest[end + 1..])),
+        _ => None,
+    }
+}
+
+// Keys in the pk and ipns namespaces are written /<namespace>/<peer id>, but
+// contain the binary peer ID
+pub fn parse_key(s: &str) -> Result<Vec<u8>, Error> {
+    let (namespace, rest) = match split_key(s.as_bytes()) {
+        None => return Err(Error::InvalidArgument(format!("Invalid record key {}: expected \
+                                                           /<namespace>/<key>",
+                                                          s))),
+        Some(parts) => parts,
+    };
+    let mut key = format!("/{}/", namespace).into_bytes();
+    match namespace {
+        "pk" | "ipns" => {
+            // `rest` was split from a str at an ASCII character
+            let peer = try!(peer::parse_peer_id(str::from_utf8(rest).unwrap()));
+            key.extend_from_slice(&peer.into_bytes());
+        }
+        _ => key.extend_from_slice(rest),
+    }
+    Ok(key)
+}
+
+// The inverse of parse_key
+pub fn format_key(key: &[u8]) -> String {
+    match split_key(key) {
+        Some((namespace, rest)) if namespace == "pk" || namespace == "ipns" => {
+            format!("/{}/{}", namespace, Multihash::from_vec(rest.to_vec()))
+        }
+        _ => String::from_utf8_lossy(key).into_owned(),
+    }
+}
+
+// Validates records according to their namespace
+pub struct NamespacedValidator {
+    validators: RwLock<HashMap<String, Arc<Validator>>>,
+}
+
+impl NamespacedValidator {
+    pub fn new() -> Self {
+        let v = NamespacedValidator { validators: RwLock::new(HashMap::new()) };
+        v.add("pk", Arc::new(PublicKeyValidator));
+        v
+    }
+
+    pub fn add(&self, namespace: &str, validator: Arc<Validator>) {
+        self.validators.write().unwrap().insert(namespace.to_string(), validator);
+    }
+
+    fn validator(&self, key: &[u8]) -> Result<Arc<Validator>, Error> {
+        let namespace = match split_key(key) {
+            None => return Err(Error::InvalidArgument("Record key has no namespace".to_string())),
+            Some((namespace, _)) => namespace,
+        };
+        match self.validators.read().unwrap().get(namespace) {
+            None => {
+                Err(Error::InvalidArgument(format!("Unknown record namespace {}", namespace)))
+            }
+            Some(validator) => Ok(validator.clone()),
+        }
+    }
+}
+
+impl Validator for NamespacedValidator {
+    fn validate(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        try!(self.validator(key)).validate(key, value)
+    }
+
+    fn select(&self, key: &[u8], values: &[Vec<u8>]) -> usize {
+        match self.validator(key) {
+            Err(_) => 0,
+            Ok(validator) => validator.select(key, values),
+        }
+    }
+}
+
+// /pk/<peer id> records hold the public key of a peer: the DER encoding of
+// the key whose hash is the peer ID
+pub struct PublicKeyValidator;
+
+impl Validator for PublicKeyValidator {
+    fn validate(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let peer: PeerId = match split_key(key) {
+            Some((_, rest)) => Multihash::from_vec(rest.to_vec()),
+            None => return Err(Error::InvalidArgument("Invalid public key record key".to_string())),
+        };
+        if util::hash(value) != peer {
+            return Err(Error::InvalidArgument(format!("Public key does not match peer ID {}",
+                                                      peer)));
+        }
+        Ok(())
+    }
+
+    // all valid values are the same key
+    fn select(&self, _key: &[u8], _values: &[Vec<u8>]) -> usize {
+        0
+    }
+}
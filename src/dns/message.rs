@@ -0,0 +1,266 @@
+// Just enough of the DNS message format (RFC 1035) for mDNS discovery and
+// DNSLink lookups: questions, and PTR and TXT records. Names in received
+// messages may be compressed; names we send aren't.
+
+use error::Error;
+
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_TXT: u16 = 16;
+pub const CLASS_IN: u16 = 1;
+
+pub const RCODE_NAME_ERROR: u8 = 3; // the name doesn't exist
+
+const FLAGS_RESPONSE: u16 = 0x8400; // QR and AA
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const MAX_POINTERS: usize = 16;
+
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+}
+
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+pub enum RecordData {
+    Ptr(String),
+    Txt(Vec<String>),
+    Other,
+}
+
+pub struct Message {
+    pub id: u16,
+    pub response: bool,
+    // asks a unicast server to resolve the question on our behalf
+    pub recursion_desired: bool,
+    pub rcode: u8,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>, // additional records are included
+}
+
+impl Message {
+    // An mDNS query
+    pub fn query(name: &str, qtype: u16) -> Self {
+        Message {
+            id: 0, // mDNS messages have an ID of 0
+            response: false,
+            recursion_desired: false,
+            rcode: 0,
+            questions: vec![Question {
+                                name: name.to_string(),
+                                qtype: qtype,
+                            }],
+            answers: vec![],
+        }
+    }
+
+    // A query to a recursive DNS server
+    pub fn lookup(id: u16, name: &str, qtype: u16) -> Self {
+        Message {
+            id: id,
+            recursion_desired: true,
+            ..Message::query(name, qtype)
+        }
+    }
+
+    pub fn response(answers: Vec<Record>) -> Self {
+        Message {
+            id: 0,
+            response: true,
+            recursion_desired: false,
+            rcode: 0,
+            questions: vec![],
+            answers: answers,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        put_u16(&mut buf, self.id);
+        let mut flags = self.rcode as u16 & 0xf;
+        if self.response {
+            flags |= FLAGS_RESPONSE;
+        }
+        if self.recursion_desired {
+            flags |= FLAG_RECURSION_DESIRED;
+        }
+        put_u16(&mut buf, flags);
+        put_u16(&mut buf, self.questions.len() as u16);
+        put_u16(&mut buf, self.answers.len() as u16);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, 0);
+
+        for question in &self.questions {
+            try!(put_name(&mut buf, &question.name));
+            put_u16(&mut buf, question.qtype);
+            put_u16(&mut buf, CLASS_IN);
+        }
+        for record in &self.answers {
+            try!(put_name(&mut buf, &record.name));
+            put_u16(&mut buf, record.rtype);
+            put_u16(&mut buf, CLASS_IN);
+            put_u16(&mut buf, (record.ttl >> 16) as u16);
+            put_u16(&mut buf, record.ttl as u16);
+
+            let mut data = Vec::new();
+            match record.data {
+                RecordData::Ptr(ref name) => try!(put_name(&mut data, name)),
+                RecordData::Txt(ref strings) => {
+                    for s in strings {
+                        if s.len() > 255 {
+                            return Err(Error::InvalidArgument(format!("TXT string too long: {}",
+                                                                      s)));
+                        }
+                        data.push(s.len() as u8);
+                        data.extend_from_slice(s.as_bytes());
+                    }
+                }
+                RecordData::Other => {}
+            }
+            put_u16(&mut buf, data.len() as u16);
+            buf.extend(data);
+        }
+        Ok(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut pos = 0;
+        let id = try!(get_u16(buf, &mut pos));
+        let flags = try!(get_u16(buf, &mut pos));
+        let num_questions = try!(get_u16(buf, &mut pos));
+        let mut num_records = 0;
+        for _ in 0..3 {
+            num_records += try!(get_u16(buf, &mut pos)) as usize;
+        }
+
+        let mut msg = Message {
+            id: id,
+            response: flags & 0x8000 != 0,
+            recursion_desired: flags & FLAG_RECURSION_DESIRED != 0,
+            rcode: (flags & 0xf) as u8,
+            questions: Vec::new(),
+            answers: Vec::new(),
+        };
+        for _ in 0..num_questions {
+            let name = try!(get_name(buf, &mut pos));
+            let qtype = try!(get_u16(buf, &mut pos));
+            let _class = try!(get_u16(buf, &mut pos));
+            msg.questions.push(Question {
+                name: name,
+                qtype: qtype,
+            });
+        }
+        for _ in 0..num_records {
+            let name = try!(get_name(buf, &mut pos));
+            let rtype = try!(get_u16(buf, &mut pos));
+            let _class = try!(get_u16(buf, &mut pos));
+            let ttl = (try!(get_u16(buf, &mut pos)) as u32) << 16 |
+                      try!(get_u16(buf, &mut pos)) as u32;
+            let len = try!(get_u16(buf, &mut pos)) as usize;
+            if pos + len > buf.len() {
+                return Err(truncated());
+            }
+
+            let data = match rtype {
+                TYPE_PTR => {
+                    let mut data_pos = pos;
+                    RecordData::Ptr(try!(get_name(buf, &mut data_pos)))
+                }
+                TYPE_TXT => {
+                    let mut strings = Vec::new();
+                    let mut rest = &buf[pos..pos + len];
+                    while !rest.is_empty() {
+                        let n = rest[0] as usize;
+                        if 1 + n > rest.len() {
+                            return Err(truncated());
+                        }
+                        strings.push(String::from_utf8_lossy(&rest[1..1 + n]).into_owned());
+                        rest = &rest[1 + n..];
+                    }
+                    RecordData::Txt(strings)
+                }
+                _ => RecordData::Other,
+            };
+            pos += len;
+            msg.answers.push(Record {
+                name: name,
+                rtype: rtype,
+                ttl: ttl,
+                data: data,
+            });
+        }
+        Ok(msg)
+    }
+}
+
+fn truncated() -> Error {
+    Error::Decode("Truncated DNS message".to_string(), None)
+}
+
+fn put_u16(buf: &mut Vec<u8>, n: u16) {
+    buf.push((n >> 8) as u8);
+    buf.push(n as u8);
+}
+
+fn get_u16(buf: &[u8], pos: &mut usize) -> Result<u16, Error> {
+    if *pos + 2 > buf.len() {
+        return Err(truncated());
+    }
+    let n = (buf[*pos] as u16) << 8 | buf[*pos + 1] as u16;
+    *pos += 2;
+    Ok(n)
+}
+
+// Writes a dotted name as a sequence of labels
+fn put_name(buf: &mut Vec<u8>, name: &str) -> Result<(), Error> {
+    for label in name.trim_right_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(Error::InvalidArgument(format!("Invalid DNS name {}", name)));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    Ok(())
+}
+
+// Reads a name, following compression pointers
+fn get_name(buf: &[u8], pos: &mut usize) -> Result<String, Error> {
+    let mut labels = Vec::new();
+    let mut cur = *pos;
+    let mut pointers = 0;
+    loop {
+        if cur >= buf.len() {
+            return Err(truncated());
+        }
+        let len = buf[cur] as usize;
+        if len & 0xc0 == 0xc0 {
+            if cur + 2 > buf.len() {
+                return Err(truncated());
+            }
+            if pointers == 0 {
+                *pos = cur + 2;
+            }
+            pointers += 1;
+            if pointers > MAX_POINTERS {
+                return Err(Error::Decode("Too many DNS name pointers".to_string(), None));
+            }
+            cur = (len & 0x3f) << 8 | buf[cur + 1] as usize;
+        } else if len == 0 {
+            if pointers == 0 {
+                *pos = cur + 1;
+            }
+            return Ok(labels.join("."));
+        } else {
+            if cur + 1 + len > buf.len() {
+                return Err(truncated());
+            }
+            labels.push(String::from_utf8_lossy(&buf[cur + 1..cur + 1 + len]).into_owned());
+            cur += 1 + len;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// Looking up DNS TXT records, for DNSLink. Lookups go through the
+// `Resolver` trait: `SystemResolver` asks the name servers of
+// /etc/resolv.conf (or given ones, such as a local test server), and
+// `StaticResolver` answers from a fixed map without touching the network.
+
+use crypto;
+use error::Error;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+pub use self::message::{Message, Question, Record, RecordData};
+pub use self::message::{CLASS_IN, RCODE_NAME_ERROR, TYPE_PTR, TYPE_TXT};
+
+pub mod message;
+
+const RESOLV_CONF: &'static str = "/etc/resolv.conf";
+const DNS_PORT: u16 = 53;
+const TIMEOUT_SECS: u64 = 5;
+const MAX_PACKET_SIZE: usize = 4096;
+
+pub trait Resolver: Send + Sync {
+    // The TXT records of `name`, each as the concatenation of its strings.
+    // Fails with `Error::NotFound` if the name doesn't exist.
+    fn lookup_txt(&self, name: &str) -> Result<Vec<String>, Error>;
+}
+
+pub struct SystemResolver {
+    servers: Vec<SocketAddr>,
+    timeout: Duration,
+}
+
+impl SystemResolver {
+    // Uses the name servers in /etc/resolv.conf, or one on localhost if
+    // there are none
+    pub fn new() -> Self {
+        let mut servers = read_resolv_conf().unwrap_or(vec![]);
+        if servers.is_empty() {
+            servers.push(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), DNS_PORT));
+        }
+        SystemResolver::with_servers(servers)
+    }
+
+    pub fn with_servers(servers: Vec<SocketAddr>) -> Self {
+        SystemResolver {
+            servers: servers,
+            timeout: Duration::from_secs(TIMEOUT_SECS),
+        }
+    }
+
+    fn query(&self, server: &SocketAddr, name: &str) -> Result<Message, Error> {
+        let io_error = |e: io::Error| {
+            Error::Io(format!("Error querying name server {}", server), e)
+        };
+        let local = match *server {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let socket = try!(UdpSocket::bind(local).map_err(&io_error));
+        try!(socket.set_read_timeout(Some(self.timeout)).map_err(&io_error));
+
+        let rand = crypto::random_bytes(2);
+        let id = (rand[0] as u16) << 8 | rand[1] as u16;
+        try!(socket.send_to(&try!(Message::lookup(id, name, TYPE_TXT).encode()), server)
+                   .map_err(&io_error));
+
+        let mut buf = vec![0; MAX_PACKET_SIZE];
+        loop {
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock ||
+                              e.kind() == io::ErrorKind::TimedOut => {
+                    return Err(Error::Timeout(format!("looking up {} with {}", name, server)))
+                }
+                Err(e) => return Err(io_error(e)),
+                Ok(received) => received,
+            };
+            // ignore stray packets, including answers to earlier queries
+            if from != *server {
+                continue;
+            }
+            match Message::decode(&buf[..len]) {
+                Ok(msg) => {
+                    if msg.response && msg.id == id {
+                        return Ok(msg);
+                    }
+                }
+                Err(e) => debug!("Invalid DNS response from {}: {}", server, e),
+            }
+        }
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn lookup_txt(&self, name: &str) -> Result<Vec<String>, Error> {
+        let mut last_error = Error::Network(format!("No name server to look up {}", name));
+        for server in &self.servers {
+            let msg = match self.query(server, name) {
+                Err(e) => {
+                    debug!("Error looking up {} with {}: {}", name, server, e);
+                    last_error = e;
+                    continue;
+                }
+                Ok(msg) => msg,
+            };
+            return match msg.rcode {
+                0 => Ok(txt_records(&msg)),
+                RCODE_NAME_ERROR => Err(Error::NotFound(format!("DNS name {}", name))),
+                rcode => {
+                    Err(Error::Network(format!("Name server {} failed to look up {} (error {})",
+                                               server,
+                                               name,
+                                               rcode)))
+                }
+            };
+        }
+        Err(last_error)
+    }
+}
+
+// Answers from a map of names to their TXT records
+pub struct StaticResolver {
+    records: HashMap<String, Vec<String>>,
+}
+
+impl StaticResolver {
+    pub fn new(records: HashMap<String, Vec<String>>) -> Self {
+        StaticResolver { records: records }
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn lookup_txt(&self, name: &str) -> Result<Vec<String>, Error> {
+        match self.records.get(name.trim_right_matches('.')) {
+            None => Err(Error::NotFound(format!("DNS name {}", name))),
+            Some(records) => Ok(records.clone()),
+        }
+    }
+}
+
+fn txt_records(msg: &Message) -> Vec<String> {
+    msg.answers
+       .iter()
+       .filter_map(|record| {
+           match record.data {
+               RecordData::Txt(ref strings) => Some(strings.concat()),
+               _ => None,
+           }
+       })
+       .collect()
+}
+
+// The name servers of the "nameserver <address>" lines
+fn read_resolv_conf() -> io::Result<Vec<SocketAddr>> {
+    let mut conf = String::new();
+    try!(File::open(RESOLV_CONF).and_then(|mut f| f.read_to_string(&mut conf)));
+    Ok(conf.lines()
+           .filter_map(|line| {
+               let mut words = line.split_whitespace();
+               match (words.next(), words.next()) {
+                   (Some("nameserver"), Some(addr)) => addr.parse::<IpAddr>().ok(),
+                   _ => None,
+               }
+           })
+           .map(|ip| SocketAddr::new(ip, DNS_PORT))
+           .collect())
+}
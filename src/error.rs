@@ -0,0 +1,183 @@
+use commands::cli::ParseError;
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Cause = Box<error::Error + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Error {
+    // A block, object or file that doesn't exist
+    NotFound(String),
+    // Failure reading or writing the filesystem. The string describes what
+    // was being attempted
+    Io(String, io::Error),
+    // Stored data that is inconsistent, e.g. a block whose contents don't
+    // hash to its key
+    Corrupt(String),
+    // Data that couldn't be decoded (protobuf, JSON, base58, ...)
+    Decode(String, Option<Cause>),
+    // Data that couldn't be encoded
+    Encode(String, Option<Cause>),
+    // The repo at the path is locked by another process
+    Locked(PathBuf),
+    NotInitialized(PathBuf),
+    AlreadyInitialized(PathBuf),
+    InvalidPath(String),
+    // A well-formed request with a bad value, e.g. an unsupported shell
+    InvalidArgument(String),
+    // A request that isn't allowed from where it came, e.g. an API request
+    // from a web page
+    Forbidden(String),
+    Parse(ParseError),
+    // A peer misbehaved, or no peer could serve a request
+    Network(String),
+    Timeout(String),
+    // An error returned by the daemon through the API: its message and exit
+    // code
+    Remote(String, i32),
+}
+
+impl Error {
+    pub fn decode<E>(msg: String, cause: E) -> Self
+        where E: Into<Cause>
+    {
+        Error::Decode(msg, Some(cause.into()))
+    }
+
+    pub fn encode<E>(msg: String, cause: E) -> Self
+        where E: Into<Cause>
+    {
+        Error::Encode(msg, Some(cause.into()))
+    }
+
+    // Process exit codes, following the conventions of sysexits.h
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Parse(_) | Error::InvalidArgument(_) => 64, // EX_USAGE
+            Error::InvalidPath(_) | Error::Decode(..) | Error::Corrupt(_) => 65, // EX_DATAERR
+            Error::NotFound(_) => 66, // EX_NOINPUT
+            Error::Network(_) => 69, // EX_UNAVAILABLE
+            Error::Encode(..) => 70, // EX_SOFTWARE
+            Error::AlreadyInitialized(_) => 73, // EX_CANTCREAT
+            Error::Io(..) => 74, // EX_IOERR
+            Error::Locked(_) | Error::Timeout(_) => 75, // EX_TEMPFAIL
+            Error::Forbidden(_) => 77, // EX_NOPERM
+            Error::NotInitialized(_) => 78, // EX_CONFIG
+            Error::Remote(_, code) => code,
+        }
+    }
+
+    // HTTP status codes for when the error is returned through an API
+    pub fn http_status(&self) -> u16 {
+        match *self {
+            Error::Parse(_) |
+            Error::InvalidArgument(_) |
+            Error::InvalidPath(_) |
+            Error::Decode(..) => 400,
+            Error::Forbidden(_) => 403,
+            Error::NotFound(_) => 404,
+            Error::AlreadyInitialized(_) => 409,
+            Error::Locked(_) | Error::NotInitialized(_) => 503,
+            Error::Network(_) => 502,
+            Error::Timeout(_) => 504,
+            Error::Corrupt(_) | Error::Encode(..) | Error::Io(..) | Error::Remote(..) => 500,
+        }
+    }
+
+    // Whether retrying the same operation later could succeed
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::Locked(_) | Error::Network(_) | Error::Timeout(_) => true,
+            Error::Io(_, ref e) => {
+                match e.kind() {
+                    io::ErrorKind::Interrupted |
+                    io::ErrorKind::WouldBlock |
+                    io::ErrorKind::TimedOut |
+                    io::ErrorKind::ConnectionRefused |
+                    io::ErrorKind::ConnectionReset |
+                    io::ErrorKind::ConnectionAborted => true,
+                    _ => false,
+                }
+            }
+            // exit codes carry the error category across the API
+            Error::Remote(_, code) => code == 69 || code == 75,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotFound(ref what) => write!(f, "Not found: {}", what),
+            Error::Io(ref msg, ref e) => write!(f, "{}: {}", msg, e),
+            Error::Corrupt(ref msg) => write!(f, "Corrupt data: {}", msg),
+            Error::Decode(ref msg, Some(ref e)) |
+            Error::Encode(ref msg, Some(ref e)) => write!(f, "{}: {}", msg, e),
+            Error::Decode(ref msg, None) |
+            Error::Encode(ref msg, None) => write!(f, "{}", msg),
+            Error::Locked(ref path) => {
+                write!(f,
+                       "Another process has locked the repo at {:?}. Unable to continue.",
+                       path)
+            }
+            Error::NotInitialized(ref path) => {
+                write!(f, "No ipfs repo found at {:?}. Please run `ipfs init`", path)
+            }
+            Error::AlreadyInitialized(ref path) => {
+                write!(f,
+                       "IPFS repo already exists at {:?}.\nReinitializing would overwrite \
+                        your keys.\n(Use -f to force reinitialization.)",
+                       path)
+            }
+            Error::InvalidPath(ref msg) => write!(f, "Invalid path: {}", msg),
+            Error::InvalidArgument(ref msg) => write!(f, "{}", msg),
+            Error::Forbidden(ref msg) => write!(f, "Forbidden: {}", msg),
+            Error::Parse(ref e) => write!(f, "{}", e),
+            Error::Network(ref msg) => write!(f, "Network error: {}", msg),
+            Error::Timeout(ref msg) => write!(f, "Timed out: {}", msg),
+            Error::Remote(ref msg, _) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::NotFound(_) => "not found",
+            Error::Io(..) => "I/O error",
+            Error::Corrupt(_) => "corrupt data",
+            Error::Decode(..) => "decoding error",
+            Error::Encode(..) => "encoding error",
+            Error::Locked(_) => "repo is locked",
+            Error::NotInitialized(_) => "repo is not initialized",
+            Error::AlreadyInitialized(_) => "repo is already initialized",
+            Error::InvalidPath(_) => "invalid path",
+            Error::InvalidArgument(_) => "invalid argument",
+            Error::Forbidden(_) => "forbidden",
+            Error::Parse(_) => "command line parse error",
+            Error::Network(_) => "network error",
+            Error::Timeout(_) => "timed out",
+            Error::Remote(..) => "error from daemon",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Io(_, ref e) => Some(e),
+            Error::Decode(_, Some(ref e)) |
+            Error::Encode(_, Some(ref e)) => Some(&**e),
+            Error::Parse(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
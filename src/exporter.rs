@@ -0,0 +1,272 @@
+// Writes unixfs DAGs out to the filesystem: files with their contents,
+// directories with their entries, and symlinks with their target.
+//
+// The mode and modification time kept in nodes added with them are given to
+// what's written; symlinks only get their modification time. The setuid,
+// setgid and sticky bits are only kept if asked for, since whoever added the
+// DAG could otherwise have us write setuid programs.
+//
+// With `dereference`, a symlink is replaced by what its target names in the
+// DAG, resolved from the directory holding the symlink as the filesystem
+// would. Only relative targets within the DAG being exported can be
+// dereferenced.
+
+use error::Error;
+use merkledag::{DagService, Node};
+use unixfs::{self, directory, pb, Mtime};
+use unixfs::io::DagReader;
+
+use libc;
+use rust_multihash::Multihash;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::unix;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+
+pub const MAX_SYMLINK_HOPS: usize = 40; // same as Linux
+
+/// How `export` writes a DAG out
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// Write what symlinks point to instead of the symlinks
+    pub dereference: bool,
+    /// Keep the setuid, setgid and sticky bits of modes
+    pub special_bits: bool,
+}
+
+/// Writes the file, directory or symlink `node` to `path`, which must not
+/// exist
+pub fn export(ds: &Arc<DagService>,
+              node: Arc<Node>,
+              path: &Path,
+              options: &ExportOptions)
+              -> Result<(), Error> {
+    let mut exporter = Exporter {
+        ds: ds,
+        options: options,
+        exporting: Vec::new(),
+    };
+    exporter.export(node, &mut Vec::new(), path)
+}
+
+struct Exporter<'a> {
+    ds: &'a Arc<DagService>,
+    options: &'a ExportOptions,
+    // the directories being written, to catch symlinks that would make us
+    // write a directory inside itself forever
+    exporting: Vec<Multihash>,
+}
+
+impl<'a> Exporter<'a> {
+    // Writes `node` to `path`. `dirs` are the directories holding `node`,
+    // from the root down.
+    fn export(&mut self,
+              node: Arc<Node>,
+              dirs: &mut Vec<Arc<Node>>,
+              path: &Path)
+              -> Result<(), Error> {
+        if let Some(target) = try!(unixfs::symlink_target(&node)) {
+            if !self.options.dereference {
+                try!(unix::fs::symlink(&target, path).map_err(|e| {
+                    Error::Io(format!("Error creating symlink {:?}", path), e)
+                }));
+                return self.apply_attributes(&node, path, true);
+            }
+            let (target_node, mut target_dirs) = try!(self.resolve_symlink(&target,
+                                                                           dirs.clone(),
+                                                                           0));
+            return self.export(target_node, &mut target_dirs, path);
+        }
+
+        match try!(node_type(&node)) {
+            pb::Data_DataType::File | pb::Data_DataType::Raw => self.write_file(node, path),
+            pb::Data_DataType::Directory | pb::Data_DataType::HAMTShard => {
+                self.write_directory(node, dirs, path)
+            }
+            pb::Data_DataType::Metadata => {
+                let file = try!(unixfs::unwrap_metadata(self.ds, node));
+                self.export(file, dirs, path)
+            }
+            ty => {
+                Err(Error::InvalidArgument(format!("Cannot write unixfs node {} of type {:?}",
+                                                   node.multihash(),
+                                                   ty)))
+            }
+        }
+    }
+
+    fn write_file(&self, node: Arc<Node>, path: &Path) -> Result<(), Error> {
+        let mut reader = try!(DagReader::new(node.clone(), self.ds.clone()));
+        // never through something already there, such as a symlink written
+        // for an earlier entry of the same name
+        let mut file = try!(OpenOptions::new()
+                                .write(true)
+                                .create_new(true)
+                                .open(path)
+                                .map_err(|e| {
+                                    Error::Io(format!("Error creating file {:?}", path), e)
+                                }));
+        try!(io::copy(&mut reader, &mut file)
+                 .map_err(|e| Error::Io(format!("Error writing file {:?}", path), e)));
+        self.apply_attributes(&node, path, false)
+    }
+
+    fn write_directory(&mut self,
+                       node: Arc<Node>,
+                       dirs: &mut Vec<Arc<Node>>,
+                       path: &Path)
+                       -> Result<(), Error> {
+        let hash = node.multihash();
+        if self.exporting.contains(&hash) {
+            return Err(Error::InvalidArgument(format!("Symlink cycle: {:?} would hold \
+                                                       directory {} inside itself",
+                                                      path,
+                                                      hash)));
+        }
+        // checked before anything is written, so that a crafted directory
+        // can't write outside of `path` or over what it wrote itself
+        let entries = try!(directory::entries(self.ds, &node));
+        {
+            let mut names = HashSet::new();
+            for link in &entries {
+                let name = link.get_name();
+                if name.is_empty() || name == "." || name == ".." || name.contains('/') ||
+                   name.contains('\0') {
+                    return Err(Error::Corrupt(format!("Invalid entry name {:?} in directory {}",
+                                                      name,
+                                                      hash)));
+                }
+                if !names.insert(name) {
+                    return Err(Error::Corrupt(format!("Duplicate entry name {:?} in \
+                                                       directory {}",
+                                                      name,
+                                                      hash)));
+                }
+            }
+        }
+        try!(fs::create_dir(path).map_err(|e| {
+            Error::Io(format!("Error creating directory {:?}", path), e)
+        }));
+
+        self.exporting.push(hash);
+        dirs.push(node.clone());
+        for link in entries {
+            let name = link.get_name();
+            let child = try!(link.get_node(self.ds));
+            try!(self.export(child, dirs, &path.join(name)));
+        }
+        dirs.pop();
+        self.exporting.pop();
+        // after the entries, which would change the modification time, and
+        // which a read-only mode would keep from being written
+        self.apply_attributes(&node, path, false)
+    }
+
+    // Gives what's been written at `path` the mode and modification time
+    // kept in `node`, if any. The mode of a symlink can't be changed, so
+    // it's left.
+    fn apply_attributes(&self, node: &Node, path: &Path, symlink: bool) -> Result<(), Error> {
+        let attrs = unixfs::attributes(&try!(unixfs::node_data(node)));
+        if let (Some(mode), false) = (attrs.mode, symlink) {
+            let mode = if self.options.special_bits {
+                mode
+            } else {
+                mode & 0o777
+            };
+            try!(fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| {
+                Error::Io(format!("Error setting the mode of {:?}", path), e)
+            }));
+        }
+        match attrs.mtime {
+            // one that's out of range is left out, as it is from listings
+            Some(mtime) if mtime.to_system_time().is_some() => set_mtime(path, mtime, symlink),
+            _ => Ok(()),
+        }
+    }
+
+    // Finds what the symlink `target` names, from the directory holding the
+    // symlink (the last of `dirs`). Returns it along with the directories
+    // holding it.
+    fn resolve_symlink(&self,
+                       target: &str,
+                       mut dirs: Vec<Arc<Node>>,
+                       hops: usize)
+                       -> Result<(Arc<Node>, Vec<Arc<Node>>), Error> {
+        if hops >= MAX_SYMLINK_HOPS {
+            return Err(Error::InvalidArgument(format!("Too many levels of symlinks resolving \
+                                                       {}",
+                                                      target)));
+        }
+        if target.starts_with('/') {
+            return Err(Error::InvalidArgument(format!("Cannot dereference symlink to \
+                                                       absolute path {}",
+                                                      target)));
+        }
+        let outside = || {
+            Error::InvalidArgument(format!("Cannot dereference symlink to {}, which is \
+                                            outside of what's being written",
+                                           target))
+        };
+        let not_found = || Error::NotFound(format!("symlink target {}", target));
+
+        let mut node = try!(dirs.pop().ok_or_else(&outside));
+        for component in target.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => node = try!(dirs.pop().ok_or_else(&outside)),
+                name => {
+                    if !directory::is_directory(try!(node_type(&node))) {
+                        return Err(not_found());
+                    }
+                    let child = match try!(directory::find_link(self.ds, &node, name)) {
+                        None => return Err(not_found()),
+                        Some(link) => try!(link.get_node(self.ds)),
+                    };
+                    dirs.push(node);
+                    node = child;
+                    if let Some(next) = try!(unixfs::symlink_target(&node)) {
+                        let (next_node, next_dirs) = try!(self.resolve_symlink(&next,
+                                                                               dirs,
+                                                                               hops + 1));
+                        node = next_node;
+                        dirs = next_dirs;
+                    }
+                }
+            }
+        }
+        Ok((node, dirs))
+    }
+}
+
+fn node_type(node: &Node) -> Result<pb::Data_DataType, Error> {
+    Ok(try!(unixfs::node_data(node)).get_Type())
+}
+
+fn set_mtime(path: &Path, mtime: Mtime, symlink: bool) -> Result<(), Error> {
+    let c_path = try!(CString::new(path.as_os_str().as_bytes())
+                          .map_err(|_| Error::InvalidPath(format!("{:?}", path))));
+    let times = [// the access time is left as it is
+                 libc::timespec {
+                     tv_sec: 0,
+                     tv_nsec: libc::UTIME_OMIT,
+                 },
+                 libc::timespec {
+                     tv_sec: mtime.seconds as libc::time_t,
+                     tv_nsec: mtime.nanoseconds as libc::c_long,
+                 }];
+    let flags = if symlink {
+        libc::AT_SYMLINK_NOFOLLOW
+    } else {
+        0
+    };
+    if unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), flags) } != 0 {
+        return Err(Error::Io(format!("Error setting the modification time of {:?}", path),
+                             io::Error::last_os_error()));
+    }
+    Ok(())
+}
@@ -1,35 +1,40 @@
 use config;
+use error::Error;
+use swarm::pnet::Psk;
 use util;
 
 use libc;
 
-use atomicwrites::{AtomicFile, DisallowOverwrite};
+use atomicwrites::{AtomicFile, AllowOverwrite, DisallowOverwrite, OverwriteBehavior};
 use std::env;
 use std::fs::{self, File};
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 
-const LOCK_FILE: &'static str = "repo.lock";
+pub const LOCK_FILE: &'static str = "repo.lock";
+// The pre-shared key of the private network the node belongs to, if any
+pub const SWARM_KEY_FILE: &'static str = "swarm.key";
 const DATASTORE_DIR: &'static str = "datastore";
 const BLOCKSTORE_DIR: &'static str = "blocks";
 const LOGS_DIR: &'static str = "logs";
 
 // TODO: make this work across multiple threads?
-pub fn is_locked(mut repo_path: PathBuf) -> Result<bool, String> {
-    repo_path.push(LOCK_FILE);
+pub fn is_locked(repo_path: PathBuf) -> Result<bool, Error> {
+    let mut lock_path = repo_path.clone();
+    lock_path.push(LOCK_FILE);
 
-    match util::file_exists(&repo_path) {
+    match util::file_exists(&lock_path) {
         Ok(false) => return Ok(false),
         _ => {}
     }
 
-    match lock(&repo_path) {
+    match lock(&lock_path) {
         Err(e) => {
             match e.kind() {
                 // TODO: is WouldBlock correct here?
                 io::ErrorKind::WouldBlock => Ok(true),
-                _ => Err(format!("{}", e)),
+                _ => Err(Error::Io(format!("Error locking repo at {:?}", repo_path), e)),
             }
         }
         Ok(_) => Ok(false),
@@ -44,28 +49,26 @@ pub fn lock(repo_path: &PathBuf) -> Result<File, io::Error> {
 }
 
 fn fcntl(file: &File, cmd: libc::c_int) -> Result<(), io::Error> {
-    // this only works on 64-bit linux probably
-    #[derive(Debug)]
-    struct flock64 {
-        ty: libc::c_int,
-        whence: libc::c_int,
-        start: libc::off_t,
-        len: libc::off_t,
-        pid: libc::pid_t,
+    // struct flock as Linux lays it out
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    struct flock {
+        l_type: libc::c_short,
+        l_whence: libc::c_short,
+        l_start: libc::off_t,
+        l_len: libc::off_t,
+        l_pid: libc::pid_t,
     }
 
-    let flock = flock64 {
-        ty: 0x1, // there's no F_RDLCK / F_WRLCK / F_UNLCK in libc :(
-                 // this is F_WRLCK, btw, I think
-        whence: libc::SEEK_SET,
-        start: 0,
-        len: 0,
-        pid: unsafe { libc::getpid() },
+    let lock = flock {
+        l_type: 0x1, // there's no F_RDLCK / F_WRLCK / F_UNLCK in libc; this is F_WRLCK
+        l_whence: libc::SEEK_SET as libc::c_short,
+        l_start: 0,
+        l_len: 0,
+        l_pid: unsafe { libc::getpid() },
     };
 
-    println!("{:?}", flock);
-
-    let ret = unsafe { libc::fcntl(file.as_raw_fd(), cmd, &flock) };
+    let ret = unsafe { libc::fcntl(file.as_raw_fd(), cmd, &lock) };
     if ret < 0 {
         Err(io::Error::last_os_error())
     } else {
@@ -75,7 +78,7 @@ fn fcntl(file: &File, cmd: libc::c_int) -> Result<(), io::Error> {
 
 // Use the environment variable to determine repo path if it exists,
 // else use the default path.
-pub fn best_known_path() -> Result<PathBuf, String> {
+pub fn best_known_path() -> Result<PathBuf, Error> {
     // TODO: this should probably use var_os, but having to handle OsString
     // complicates things
     let unexpanded_path = match env::var(config::ENV_NAME_REPO_DIR) {
@@ -83,8 +86,9 @@ pub fn best_known_path() -> Result<PathBuf, String> {
         Ok(s) => s,
     };
 
-    expand_tilde(unexpanded_path)
-        .map_err(|e| format!("Could not expand repo path"))
+    expand_tilde(unexpanded_path.clone()).map_err(|_| {
+        Error::InvalidPath(format!("Could not expand repo path {}", unexpanded_path))
+    })
 }
 
 fn expand_tilde(s: String) -> Result<PathBuf, ()> {
@@ -105,12 +109,11 @@ fn expand_tilde(s: String) -> Result<PathBuf, ()> {
 // TODO: ensure this process can't modify the repo while this check is in progress
 // assumes that we have sufficient permission to the repo directory,
 // so doesn't worry about any permissions errors from checking existence
-pub fn is_initialized(mut repo_path: PathBuf) -> Result<bool, String> {
+pub fn is_initialized(mut repo_path: PathBuf) -> Result<bool, Error> {
     let config_path = config::repo_path_to_config_file(repo_path.clone());
     let config_exists = try!(util::file_exists(&config_path).map_err(|e| {
-        format!("Error checking existence of config file {:?}: {}",
-                config_path,
-                e)
+        Error::Io(format!("Error checking existence of config file {:?}", config_path),
+                  e)
     }));
     if !config_exists {
         return Ok(false);
@@ -120,9 +123,9 @@ pub fn is_initialized(mut repo_path: PathBuf) -> Result<bool, String> {
     // datastore directory? what about blocks and log directories
     repo_path.push(DATASTORE_DIR);
     let ds_exists = try!(util::file_exists(&config_path).map_err(|e| {
-        format!("Error checking existence of datastore directory {:?}: {}",
-                repo_path,
-                e)
+        Error::Io(format!("Error checking existence of datastore directory {:?}",
+                          repo_path),
+                  e)
     }));
     if !ds_exists {
         return Ok(false);
@@ -131,11 +134,13 @@ pub fn is_initialized(mut repo_path: PathBuf) -> Result<bool, String> {
     Ok(true)
 }
 
-pub fn remove<P: AsRef<Path>>(repo_path: P) -> Result<(), String> {
-    fs::remove_dir_all(repo_path).map_err(|e| format!("Error removing repo: {}", e))
+pub fn remove<P: AsRef<Path>>(repo_path: P) -> Result<(), Error> {
+    fs::remove_dir_all(&repo_path).map_err(|e| {
+        Error::Io(format!("Error removing repo {:?}", repo_path.as_ref()), e)
+    })
 }
 
-pub fn init(mut repo_path: PathBuf, cfg: &config::Config) -> Result<(), String> {
+pub fn init(mut repo_path: PathBuf, cfg: &config::Config) -> Result<(), Error> {
     // Don't initialize if already initialized.
     if try!(is_initialized(repo_path.clone())) {
         return Ok(());
@@ -146,19 +151,21 @@ pub fn init(mut repo_path: PathBuf, cfg: &config::Config) -> Result<(), String>
 
     let mut datastore_path = repo_path.clone();
     datastore_path.push(DATASTORE_DIR);
-    try!(util::ensure_dir_writable(datastore_path)
-             .map_err(|e| format!("Error checking writability of datastore dir: {}", e)));
+    try!(util::ensure_dir_writable(datastore_path).map_err(|e| {
+        Error::Io("Error checking writability of datastore dir".to_string(), e)
+    }));
 
     let mut blockstore_path = repo_path.clone();
     blockstore_path.push(BLOCKSTORE_DIR);
     try!(util::ensure_dir_writable(blockstore_path).map_err(|e| {
-        format!("Error checking writability of blockstore dir: {}", e)
+        Error::Io("Error checking writability of blockstore dir".to_string(), e)
     }));
 
     let mut logs_path = repo_path.clone();
     logs_path.push(LOGS_DIR);
-    try!(util::ensure_dir_writable(logs_path)
-             .map_err(|e| format!("Error checking writability of logs dir: {}", e)));
+    try!(util::ensure_dir_writable(logs_path).map_err(|e| {
+        Error::Io("Error checking writability of logs dir".to_string(), e)
+    }));
 
     Ok(())
 }
@@ -166,20 +173,56 @@ pub fn init(mut repo_path: PathBuf, cfg: &config::Config) -> Result<(), String>
 // Caller should ensure the directory exists before calling
 fn write_config_file<P: AsRef<Path>>(file_path: P,
                                      cfg: &config::Config)
-                                     -> Result<(), String> {
-    let s = match cfg.to_json_string() {
-        Err(e) => return Err(format!("Error encoding config as Json: {}", e)),
-        Ok(s) => s,
-    };
+                                     -> Result<(), Error> {
+    write_config(file_path, cfg, DisallowOverwrite)
+}
 
-    let file = AtomicFile::new(file_path, DisallowOverwrite);
-    file.write(|f| f.write_all(s.as_bytes()))
-        .map_err(|e| format!("Error writing config file: {}", e))
+// Replaces the config file of an initialized repo
+pub fn update_config_file<P: AsRef<Path>>(file_path: P,
+                                          cfg: &config::Config)
+                                          -> Result<(), Error> {
+    write_config(file_path, cfg, AllowOverwrite)
 }
 
-pub fn read_config_file<P: AsRef<Path>>(file_path: P) -> Result<config::Config, String> {
+fn write_config<P: AsRef<Path>>(file_path: P,
+                                cfg: &config::Config,
+                                overwrite: OverwriteBehavior)
+                                -> Result<(), Error> {
+    let s = try!(cfg.to_json_string());
+
+    let file = AtomicFile::new(file_path, overwrite);
+    file.write(|f| f.write_all(s.as_bytes())).map_err(|e| {
+        Error::Io("Error writing config file".to_string(),
+                  util::atomic_write_error(e))
+    })
+}
+
+pub fn read_config_file<P: AsRef<Path>>(file_path: P) -> Result<config::Config, Error> {
     let mut config_file = try!(File::open(file_path).map_err(|e| {
-        format!("Error opening config file: {}", e)
+        Error::Io("Error opening config file".to_string(), e)
     }));
     config::Config::from_reader(&mut config_file)
 }
+
+// The repo's swarm key, or None if the node isn't part of a private network
+pub fn read_swarm_key(repo_path: &Path) -> Result<Option<Psk>, Error> {
+    let key_path = repo_path.join(SWARM_KEY_FILE);
+    let mut key_file = match File::open(&key_path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Io("Error opening swarm key file".to_string(), e)),
+        Ok(f) => f,
+    };
+    let mut data = Vec::new();
+    try!(key_file.read_to_end(&mut data)
+                 .map_err(|e| Error::Io("Error reading swarm key file".to_string(), e)));
+    Psk::parse(&data).map(Some)
+}
+
+// Writes the repo's swarm key. An existing key is not replaced.
+pub fn write_swarm_key(repo_path: &Path, psk: &Psk) -> Result<(), Error> {
+    let file = AtomicFile::new(repo_path.join(SWARM_KEY_FILE), DisallowOverwrite);
+    file.write(|f| f.write_all(psk.to_string().as_bytes())).map_err(|e| {
+        Error::Io("Error writing swarm key file".to_string(),
+                  util::atomic_write_error(e))
+    })
+}
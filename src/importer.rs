@@ -1,3 +1,371 @@
-use std::io::Read;
+// Imports files into the DAG as unixfs objects.
+//
+// Files are split into fixed-size chunks stored as leaf nodes, and the leaves
+// are arranged in a balanced tree: each internal node links to up to
+// DEFAULT_LINKS_PER_BLOCK children and records their sizes in `blocksizes`.
+// A file that fits in a single chunk is just a leaf.
+//
+// Directories are added with their contents, each entry linked by its file
+// name (see unixfs::directory for when they're sharded). Symlinks met by
+// add_directory are added as symlink nodes holding their target, not
+// followed.
+//
+// Files can be given a MIME type, in which case they're wrapped in a
+// Metadata node holding the type and linking to the file, as in go-ipfs.
+// Their permissions and modification time can be kept too, in the root node
+// of each file, directory or symlink (under any Metadata node).
+//
+// With raw leaves, file data is stored in raw blocks linked by CIDv1 rather
+// than in unixfs leaf nodes. With an inline limit, any node that encodes to
+// no more than the limit is put in its CID with the identity hash instead of
+// being stored (see cid), as with go-ipfs's --inline.
 
-pub fn build_dag_from_reader<R: Read>(reader: &mut R, ds: 
+use cid::{self, Cid};
+use error::Error;
+use merkledag::{DagService, Link, Node};
+use mime;
+use unixfs::{self, directory, pb, Attributes, FSNode, Mtime};
+
+use rust_multihash::Multihash;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+pub const DEFAULT_LINKS_PER_BLOCK: usize = 174; // same as go-ipfs
+
+/// How files are added
+#[derive(Clone, Debug, Default)]
+pub struct AddOptions {
+    /// The MIME type files are wrapped in a Metadata node with, if any
+    pub mime_type: Option<MimeType>,
+    /// Keep the permissions of what's added
+    pub preserve_mode: bool,
+    /// Keep the modification time of what's added
+    pub preserve_mtime: bool,
+    /// Store file data in raw blocks
+    pub raw_leaves: bool,
+    /// Inline nodes of up to this many bytes in their CIDs
+    pub inline_limit: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub enum MimeType {
+    /// Detected from the contents or extension of each file. Files of an
+    /// unknown type aren't wrapped.
+    Detect,
+    Given(String),
+}
+
+/// Adds the file at `path` to the DAG, returning the CID of its root node
+pub fn add_file<P: AsRef<Path>>(path: P,
+                                ds: &DagService,
+                                options: &AddOptions)
+                                -> Result<Cid, Error> {
+    let (cid, _) = try!(import_file(path.as_ref(), ds, options));
+    Ok(cid)
+}
+
+/// Adds the directory at `path` and everything under it to the DAG.
+/// Returns the paths added with their CIDs, each directory after its
+/// contents, so the root directory comes last.
+pub fn add_directory<P: AsRef<Path>>(path: P,
+                                     ds: &DagService,
+                                     shard_threshold: u64,
+                                     options: &AddOptions)
+                                     -> Result<Vec<(PathBuf, Cid)>, Error> {
+    let mut added = Vec::new();
+    try!(add_path(path.as_ref(), ds, shard_threshold, options, &mut added));
+    Ok(added)
+}
+
+// Adds the file or directory at `path`, returning a link to it named after
+// its file name
+fn add_path(path: &Path,
+            ds: &DagService,
+            shard_threshold: u64,
+            options: &AddOptions,
+            added: &mut Vec<(PathBuf, Cid)>)
+            -> Result<Link, Error> {
+    let metadata = try!(fs::symlink_metadata(path)
+                            .map_err(|e| Error::Io(format!("Error reading {:?}", path), e)));
+    let (cid, cumulative_size) = if metadata.file_type().is_symlink() {
+        let target = try!(fs::read_link(path).map_err(|e| {
+            Error::Io(format!("Error reading symlink {:?}", path), e)
+        }));
+        let target = match target.to_str() {
+            Some(target) => target.to_string(),
+            None => {
+                return Err(Error::InvalidPath(format!("{:?} (symlink targets must be valid \
+                                                       UTF-8)",
+                                                      path)))
+            }
+        };
+        let mut node = Node::from_data(try!(unixfs::symlink_data(&target)));
+        let attrs = preserved_attributes(&metadata, options);
+        if !attrs.is_empty() {
+            node = try!(unixfs::with_attributes(&node, &attrs));
+        }
+        (try!(add_node(ds, &node, options)), try!(node.cumulative_size()))
+    } else if metadata.is_dir() {
+        let mut entries = Vec::new();
+        for entry in try!(read_dir_sorted(path)) {
+            entries.push(try!(add_path(&entry, ds, shard_threshold, options, added)));
+        }
+        let dir = try!(directory::build(ds, entries, shard_threshold));
+        let cid = match try!(inline_cid(&dir, options)) {
+            Some(cid) => cid,
+            None => dir.cid(),
+        };
+        try!(add_attributes(ds,
+                            cid,
+                            try!(dir.cumulative_size()),
+                            &preserved_attributes(&metadata, options),
+                            options))
+    } else {
+        try!(import_file(path, ds, options))
+    };
+
+    added.push((path.to_path_buf(), cid.clone()));
+    let name = match path.file_name().map(|name| name.to_str()) {
+        Some(Some(name)) => name.to_string(),
+        Some(None) => {
+            return Err(Error::InvalidPath(format!("{:?} (names must be valid UTF-8)", path)))
+        }
+        None => String::new(), // the root, e.g. "."
+    };
+    Ok(Link::from_cid(name, cid, cumulative_size))
+}
+
+// Adds the file at `path`, wrapped in a Metadata node if it's given a MIME
+// type. Returns the CID and cumulative size of the node added last.
+fn import_file(path: &Path,
+               ds: &DagService,
+               options: &AddOptions)
+               -> Result<(Cid, u64), Error> {
+    let read_error = |e| Error::Io(format!("Error reading file {:?}", path), e);
+    let mut file = try!(File::open(path).map_err(&read_error));
+    let attrs = preserved_attributes(&try!(file.metadata().map_err(&read_error)), options);
+    let mut head = Vec::new();
+    try!(file.by_ref().take(mime::SNIFF_LEN as u64).read_to_end(&mut head).map_err(&read_error));
+
+    let mime_type = match options.mime_type {
+        None => None,
+        Some(MimeType::Given(ref ty)) => Some(ty.clone()),
+        Some(MimeType::Detect) => mime::detect(path, &head).map(|ty| ty.to_string()),
+    };
+    let root = try!(build_balanced(&mut (&head[..]).chain(file),
+                                   ds,
+                                   DEFAULT_CHUNK_SIZE,
+                                   DEFAULT_LINKS_PER_BLOCK,
+                                   options));
+    let (cid, cumulative_size) = try!(add_attributes(ds,
+                                                     root.cid,
+                                                     root.cumulative_size,
+                                                     &attrs,
+                                                     options));
+    let mime_type = match mime_type {
+        None => return Ok((cid, cumulative_size)),
+        Some(ty) => ty,
+    };
+
+    let link = Link::from_cid(String::new(), cid, cumulative_size);
+    let node = Node::new(try!(unixfs::metadata_data(&mime_type)), vec![link]);
+    Ok((try!(add_node(ds, &node, options)), try!(node.cumulative_size())))
+}
+
+// The attributes of what `metadata` describes that `options` ask to keep
+fn preserved_attributes(metadata: &fs::Metadata, options: &AddOptions) -> Attributes {
+    Attributes {
+        mode: if options.preserve_mode {
+            Some(metadata.permissions().mode() & 0o7777)
+        } else {
+            None
+        },
+        mtime: if options.preserve_mtime {
+            Some(Mtime {
+                seconds: metadata.mtime(),
+                nanoseconds: metadata.mtime_nsec() as u32,
+            })
+        } else {
+            None
+        },
+    }
+}
+
+// Adds the node `cid` again with `attrs` set, unless there are none. A raw
+// block can't hold them, so it gets a File root linking to it instead, as
+// in go-ipfs. Returns the CID and cumulative size of the node to link to.
+pub fn add_attributes(ds: &DagService,
+                      cid: Cid,
+                      cumulative_size: u64,
+                      attrs: &Attributes,
+                      options: &AddOptions)
+                      -> Result<(Cid, u64), Error> {
+    if attrs.is_empty() {
+        return Ok((cid, cumulative_size));
+    }
+    let node = try!(ds.get_cid(&cid));
+    if node.codec() == cid::Codec::Raw {
+        let size = node.get_data().len() as u64;
+        let mut fs_node = FSNode::new(pb::Data_DataType::File);
+        fs_node.add_block_size(size);
+        fs_node.set_attributes(*attrs);
+        let link = Link::from_cid(String::new(), cid, cumulative_size);
+        let root = try!(add_fsnode(fs_node, vec![link], ds, options));
+        return Ok((root.cid, root.cumulative_size));
+    }
+    let node = try!(unixfs::with_attributes(&node, attrs));
+    Ok((try!(add_node(ds, &node, options)), try!(node.cumulative_size())))
+}
+
+// Adds `node` to the DAG, unless it's inlined in its CID
+fn add_node(ds: &DagService, node: &Node, options: &AddOptions) -> Result<Cid, Error> {
+    match try!(inline_cid(node, options)) {
+        Some(cid) => Ok(cid),
+        None => Ok(Cid::new(node.codec(), try!(ds.add(node)))),
+    }
+}
+
+// The CID holding `node` itself, if there's an inline limit it's within
+fn inline_cid(node: &Node, options: &AddOptions) -> Result<Option<Cid>, Error> {
+    let limit = match options.inline_limit {
+        Some(limit) => limit,
+        None => return Ok(None),
+    };
+    let mut data = Vec::new();
+    try!(node.encode_to_writer(&mut data));
+    if data.len() > limit {
+        return Ok(None);
+    }
+    Ok(Some(Cid::new(node.codec(), cid::identity_hash(&data))))
+}
+
+// The paths of the entries of the directory at `path`, by name
+fn read_dir_sorted(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let read_error = |e| Error::Io(format!("Error reading directory {:?}", path), e);
+    let mut entries = Vec::new();
+    for entry in try!(fs::read_dir(path).map_err(&read_error)) {
+        entries.push(try!(entry.map_err(&read_error)).path());
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Adds everything read from `reader` to the DAG as a unixfs file, returning
+/// the hash of its root node
+pub fn build_dag_from_reader<R: Read>(reader: &mut R, ds: &DagService) -> Result<Multihash, Error> {
+    let root = try!(build_balanced(reader,
+                                   ds,
+                                   DEFAULT_CHUNK_SIZE,
+                                   DEFAULT_LINKS_PER_BLOCK,
+                                   &AddOptions::default()));
+    Ok(root.cid.hash)
+}
+
+/// A node that has been added to the DAG. Only what's needed to link to it
+/// is kept, so the file data isn't held in memory.
+pub struct Imported {
+    pub cid: Cid,
+    pub cumulative_size: u64,
+    pub file_size: u64, // size of the file data under the node
+}
+
+impl Imported {
+    pub fn link(&self, name: String) -> Link {
+        Link::from_cid(name, self.cid.clone(), self.cumulative_size)
+    }
+}
+
+fn build_balanced<R: Read>(reader: &mut R,
+                           ds: &DagService,
+                           chunk_size: usize,
+                           links_per_block: usize,
+                           options: &AddOptions)
+                           -> Result<Imported, Error> {
+    let leaves = try!(add_leaves(reader, ds, chunk_size, options, Vec::new()));
+    build_tree(leaves, ds, links_per_block, options)
+}
+
+// Splits everything read from `reader` into leaves, added after `leaves`
+pub fn add_leaves<R: Read>(reader: &mut R,
+                           ds: &DagService,
+                           chunk_size: usize,
+                           options: &AddOptions,
+                           mut leaves: Vec<Imported>)
+                           -> Result<Vec<Imported>, Error> {
+    loop {
+        let chunk = try!(read_chunk(reader, chunk_size)
+                             .map_err(|e| Error::Io("Error reading file data".to_string(), e)));
+        let len = chunk.len();
+
+        // an empty file still gets a (single, empty) leaf
+        if len == 0 && !leaves.is_empty() {
+            break;
+        }
+        if options.raw_leaves {
+            let node = Node::raw(chunk);
+            leaves.push(Imported {
+                cid: try!(add_node(ds, &node, options)),
+                cumulative_size: len as u64,
+                file_size: len as u64,
+            });
+        } else {
+            leaves.push(try!(add_fsnode(FSNode::file_from_bytes(chunk), vec![], ds, options)));
+        }
+        if len < chunk_size {
+            break;
+        }
+    }
+    Ok(leaves)
+}
+
+// Arranges `leaves` in a balanced tree, returning its root. There must be at
+// least one leaf.
+pub fn build_tree(leaves: Vec<Imported>,
+                  ds: &DagService,
+                  links_per_block: usize,
+                  options: &AddOptions)
+                  -> Result<Imported, Error> {
+    let mut level = leaves;
+    while level.len() > 1 {
+        let mut parents = Vec::with_capacity(level.len() / links_per_block + 1);
+        for children in level.chunks(links_per_block) {
+            let mut fs_node = FSNode::new(pb::Data_DataType::File);
+            let mut links = Vec::with_capacity(children.len());
+            for child in children {
+                fs_node.add_block_size(child.file_size);
+                links.push(child.link(String::new()));
+            }
+            parents.push(try!(add_fsnode(fs_node, links, ds, options)));
+        }
+        level = parents;
+    }
+
+    Ok(level.pop().unwrap())
+}
+
+pub fn add_fsnode(fs_node: FSNode,
+                  links: Vec<Link>,
+                  ds: &DagService,
+                  options: &AddOptions)
+                  -> Result<Imported, Error> {
+    let file_size = fs_node.file_size();
+    let mut data = Vec::new();
+    try!(fs_node.encode_to_writer(&mut data));
+
+    let node = Node::new(data, links);
+    Ok(Imported {
+        cid: try!(add_node(ds, &node, options)),
+        cumulative_size: try!(node.cumulative_size()),
+        file_size: file_size,
+    })
+}
+
+// Reads until `size` bytes have been read or the reader is exhausted
+pub fn read_chunk<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(size);
+    try!(reader.by_ref().take(size as u64).read_to_end(&mut chunk));
+    Ok(chunk)
+}
@@ -1,49 +1,93 @@
 use commands::{self, HelpText, Command};
 use commands::request;
-use merkledag::{DagService, Node};
-use unixfs::FSNode;
+use error::Error;
+use importer::{AddOptions, MimeType};
 
-use rust_multihash::Multihash;
-use std::fs::File;
-use std::io::Read;
 use std::path::Path;
-use std::sync::Arc;
+
+const DEFAULT_INLINE_LIMIT: usize = 32; // same as go-ipfs
 
 const HELP_TEXT: HelpText = HelpText {
     tagline: "Add an object to ipfs.",
     synopsis: "",
-    short_desc: "Adds contents of <path> to ipfs.",
+    short_desc: r#"
+Adds contents of <path> to ipfs, and pins it.
+
+With -r, directories are added with everything under them. Directories with
+too many entries for one object are sharded as in go-ipfs.
+
+With --mime-type, files are wrapped in a metadata object giving their MIME
+type. 'auto' detects the type of each file from its contents or extension.
+
+--preserve-mode and --preserve-mtime keep the permissions and modification
+time of what's added, which 'ipfs get' restores. Without them, the same
+content always gets the same hash.
+
+With --raw-leaves, file data is stored in raw blocks, linked by version 1
+CIDs (shown in base32, starting with 'b').
+
+With --inline, objects of up to --inline-limit bytes (32 by default) are put
+in their CIDs with the identity hash rather than stored.
+
+When the daemon is running, the new content is announced to the DHT
+according to the reprovider strategy in the config (all, pinned or roots),
+and announced again every reprovider interval.
+"#,
 };
 
-fn run(req: &request::Request) -> Result<(), String> {
+fn run(req: &request::Request) -> Result<(), Error> {
     let node = try!(req.context.get_node());
 
+    let recursive = req.bool_option("recursive");
+    let options = AddOptions {
+        mime_type: match req.option("mime-type") {
+            Some(&request::Opt::String(ref s)) if s == "auto" => Some(MimeType::Detect),
+            Some(&request::Opt::String(ref s)) => Some(MimeType::Given(s.clone())),
+            _ => None,
+        },
+        preserve_mode: req.bool_option("preserve-mode"),
+        preserve_mtime: req.bool_option("preserve-mtime"),
+        raw_leaves: req.bool_option("raw-leaves"),
+        inline_limit: if req.bool_option("inline") {
+            Some(try!(inline_limit(req)))
+        } else {
+            None
+        },
+    };
+
     for path in req.file_arg("path").unwrap() {
-        let hash = try!(add_file(path, node.dagservice.clone()));
-        println!("added {} {:?}", hash, path);
+        let is_dir = {
+            let path: &Path = path.as_ref();
+            path.is_dir()
+        };
+        if !is_dir {
+            let cid = try!(node.add_file(path, &options));
+            try!(writeln!(req, "added {} {:?}", cid, path));
+        } else if recursive {
+            for (added_path, cid) in try!(node.add_directory(path, &options)) {
+                try!(writeln!(req, "added {} {:?}", cid, added_path));
+            }
+        } else {
+            return Err(Error::InvalidArgument(format!("{:?} is a directory, use -r to add \
+                                                       directories",
+                                                      path)));
+        }
     }
     Ok(())
 }
 
-ipfs_command!(AddCommand, run);
-
-
-fn add_file<P: AsRef<Path>>(path: P, ds: Arc<DagService>) -> Result<Multihash, String> {
-    let mut file = try!(File::open(path).map_err(|e| {
-        format!("Error opening file: {}", e)
-    }));
-    let mut file_data = Vec::new();
-    file.read_to_end(&mut file_data);
-
-    let fs_node = FSNode::file_from_bytes(file_data);
-
-    let mut buf = Vec::new();
-    try!(fs_node.encode_to_writer(&mut buf));
-
-    let dag_node = Node::from_data(buf);
-    ds.add(&dag_node)
+fn inline_limit(req: &request::Request) -> Result<usize, Error> {
+    match req.option("inline-limit") {
+        Some(&request::Opt::Int(n)) if n < 0 => {
+            Err(Error::InvalidArgument(format!("Invalid inline limit: {}", n)))
+        }
+        Some(&request::Opt::Int(n)) => Ok(n as usize),
+        _ => Ok(DEFAULT_INLINE_LIMIT),
+    }
 }
 
+ipfs_command!(AddCommand, run);
+
 pub fn make_command() -> Box<Command> {
     let arg_path = commands::Argument::new_file(
         "path",
@@ -51,9 +95,34 @@ pub fn make_command() -> Box<Command> {
         true,
         "The path(s) to a file to be added to IPFS"
     );
+    let recursive = commands::Opt::new_bool(vec!["recursive", "r"],
+                                            "Add directory paths recursively");
+    let mime_type = commands::Opt::new_string(vec!["mime-type", "m"],
+                                              "Wrap files in metadata giving this MIME \
+                                               type, or 'auto' to detect it");
+    let preserve_mode = commands::Opt::new_bool(vec!["preserve-mode"],
+                                                "Keep the permissions of files and \
+                                                 directories");
+    let preserve_mtime = commands::Opt::new_bool(vec!["preserve-mtime"],
+                                                 "Keep the modification time of files and \
+                                                  directories");
+    let raw_leaves = commands::Opt::new_bool(vec!["raw-leaves"],
+                                             "Store file data in raw blocks");
+    let inline = commands::Opt::new_bool(vec!["inline"],
+                                         "Inline small objects in their CIDs");
+    let inline_limit = commands::Opt::new_int(vec!["inline-limit"],
+                                              "Maximum size of inlined objects in bytes \
+                                               (default 32)");
 
-
-    Box::new(AddCommand::new("add", vec![], vec![arg_path], HELP_TEXT, vec![]))
+    Box::new(AddCommand::new("add",
+                             vec![recursive,
+                                  mime_type,
+                                  preserve_mode,
+                                  preserve_mtime,
+                                  raw_leaves,
+                                  inline,
+                                  inline_limit],
+                             vec![arg_path],
+                             HELP_TEXT,
+                             vec![]))
 }
-
-// fn add_file<P: AsRef<Path>>(path: P)
@@ -0,0 +1,132 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use peer;
+
+use std::io::Write;
+
+const BitswapHelpText: HelpText = HelpText {
+    tagline: "Interact with the bitswap agent",
+    synopsis: "ipfs bitswap <command>",
+    short_desc: r#"
+Bitswap exchanges blocks with connected peers: it asks them for the blocks
+we want, and sends them those they want that we have. These commands
+require the daemon to be running.
+
+    ipfs bitswap stat                  Show what was exchanged with peers
+    ipfs bitswap wantlist [-p <peer>]  Show the blocks we (or a peer) want
+    ipfs bitswap ledger <peer>         Show what was exchanged with a peer
+"#,
+};
+
+const StatHelpText: HelpText = HelpText {
+    tagline: "Show some diagnostic information on the bitswap agent",
+    synopsis: "ipfs bitswap stat",
+    short_desc: r#"
+'ipfs bitswap stat' shows the blocks and bytes exchanged with the peers we
+are connected to, our wantlist, and those peers.
+"#,
+};
+
+const WantlistHelpText: HelpText = HelpText {
+    tagline: "Show blocks currently on the wantlist",
+    synopsis: "ipfs bitswap wantlist [-p <peer>]",
+    short_desc: r#"
+'ipfs bitswap wantlist' lists the blocks we're waiting for, or with
+--peer, those a connected peer has asked us for and we don't have yet.
+"#,
+};
+
+const LedgerHelpText: HelpText = HelpText {
+    tagline: "Show the current ledger for a peer",
+    synopsis: "ipfs bitswap ledger <peer>",
+    short_desc: r#"
+'ipfs bitswap ledger' shows the bytes sent to and received from a peer
+since it connected. The debt ratio is the bytes sent divided by the bytes
+received (plus one).
+"#,
+};
+
+fn run_bitswap(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(BitswapCommand, run_bitswap);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(BitswapCommand::new("bitswap",
+                                 vec![],
+                                 vec![],
+                                 BitswapHelpText,
+                                 vec![make_stat_command(),
+                                      make_wantlist_command(),
+                                      make_ledger_command()]))
+}
+
+fn run_stat(req: &request::Request) -> Result<(), Error> {
+    let stat = try!(try!(req.context.get_node()).get_bitswap()).stat();
+
+    try!(writeln!(req, "bitswap status"));
+    try!(writeln!(req, "\tblocks received: {}", stat.blocks_received));
+    try!(writeln!(req, "\tblocks sent: {}", stat.blocks_sent));
+    try!(writeln!(req, "\tdata received: {}", stat.bytes_received));
+    try!(writeln!(req, "\tdata sent: {}", stat.bytes_sent));
+    try!(writeln!(req, "\twantlist [{} keys]", stat.wantlist.len()));
+    for hash in &stat.wantlist {
+        try!(writeln!(req, "\t\t{}", hash));
+    }
+    try!(writeln!(req, "\tpartners [{}]", stat.peers.len()));
+    for peer in &stat.peers {
+        try!(writeln!(req, "\t\t{}", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(StatCommand, run_stat);
+
+fn make_stat_command() -> Box<Command> {
+    Box::new(StatCommand::new("stat", vec![], vec![], StatHelpText, vec![]))
+}
+
+fn run_wantlist(req: &request::Request) -> Result<(), Error> {
+    let bitswap = try!(try!(req.context.get_node()).get_bitswap());
+    let wantlist = match req.string_option("peer") {
+        None => bitswap.wantlist(),
+        Some(s) => bitswap.peer_wantlist(&try!(peer::parse_peer_id(s))),
+    };
+
+    for hash in wantlist {
+        try!(writeln!(req, "{}", hash));
+    }
+    Ok(())
+}
+
+ipfs_command!(WantlistCommand, run_wantlist);
+
+fn make_wantlist_command() -> Box<Command> {
+    let peer = commands::Opt::new_string(vec!["peer", "p"],
+                                         "Show the wantlist of this peer instead of ours");
+
+    Box::new(WantlistCommand::new("wantlist", vec![peer], vec![], WantlistHelpText, vec![]))
+}
+
+fn run_ledger(req: &request::Request) -> Result<(), Error> {
+    let bitswap = try!(try!(req.context.get_node()).get_bitswap());
+    let peer = try!(peer::parse_peer_id(&req.string_arg("peer").unwrap()[0]));
+
+    let receipt = bitswap.ledger(&peer);
+    try!(writeln!(req, "Ledger for {}", receipt.peer));
+    try!(writeln!(req, "Debt ratio:\t{:.6}", receipt.debt_ratio));
+    try!(writeln!(req, "Exchanges:\t{}", receipt.blocks_exchanged));
+    try!(writeln!(req, "Bytes sent:\t{}", receipt.bytes_sent));
+    writeln!(req, "Bytes received:\t{}", receipt.bytes_received)
+}
+
+ipfs_command!(LedgerCommand, run_ledger);
+
+fn make_ledger_command() -> Box<Command> {
+    let arg_peer = Argument::new_string("peer", true, false, "The peer ID to show the ledger of");
+
+    Box::new(LedgerCommand::new("ledger", vec![], vec![arg_peer], LedgerHelpText, vec![]))
+}
@@ -0,0 +1,197 @@
+use bootstrap;
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use config::{self, BootstrapConfig};
+use error::Error;
+use fsrepo;
+use multiaddr::Multiaddr;
+
+const BootstrapHelpText: HelpText = HelpText {
+    tagline: "Show or edit the list of bootstrap peers",
+    synopsis: "ipfs bootstrap <command>",
+    short_desc: r#"
+Running 'ipfs bootstrap' with no arguments lists the bootstrap peers: the
+peers the daemon connects to when it starts, to find other peers through
+the DHT. Each is a multiaddr ending with the peer's ID. Changes are saved
+to the config (bootstrap.peers), and take effect the next time the daemon
+starts.
+
+    ipfs bootstrap list         Show the bootstrap peers
+    ipfs bootstrap add          Add peers to the bootstrap list
+    ipfs bootstrap rm           Remove peers from the bootstrap list
+
+SECURITY WARNING: The bootstrap command manipulates the list of peers the
+node trusts to learn about the rest of the network. Only add peers you
+trust.
+"#,
+};
+
+const ListHelpText: HelpText = HelpText {
+    tagline: "Show peers in the bootstrap list",
+    synopsis: "ipfs bootstrap list",
+    short_desc: r#"
+'ipfs bootstrap list' outputs the addresses of the bootstrap peers.
+"#,
+};
+
+const AddHelpText: HelpText = HelpText {
+    tagline: "Add peers to the bootstrap list",
+    synopsis: "ipfs bootstrap add [--default] [<peer>...]",
+    short_desc: r#"
+'ipfs bootstrap add' adds peers to the bootstrap list, and outputs those
+that weren't already on it. Addresses must end with /p2p/<peer id>:
+
+    ipfs bootstrap add /ip4/10.0.0.5/tcp/4001/p2p/QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ
+
+With --default, the default bootstrap peers are added.
+"#,
+};
+
+const RmHelpText: HelpText = HelpText {
+    tagline: "Remove peers from the bootstrap list",
+    synopsis: "ipfs bootstrap rm [--all] [<peer>...]",
+    short_desc: r#"
+'ipfs bootstrap rm' removes peers from the bootstrap list, and outputs
+those that were on it. With --all, every peer is removed, which is how a
+private network starts:
+
+    ipfs bootstrap rm --all
+    ipfs bootstrap add /ip4/10.0.0.5/tcp/4001/p2p/<seed peer id>
+"#,
+};
+
+fn run_bootstrap(req: &request::Request) -> Result<(), Error> {
+    run_list(req)
+}
+
+ipfs_command!(BootstrapCommand, run_bootstrap);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(BootstrapCommand::new("bootstrap",
+                                   vec![],
+                                   vec![],
+                                   BootstrapHelpText,
+                                   vec![make_list_command(),
+                                        make_add_command(),
+                                        make_rm_command()]))
+}
+
+fn run_list(req: &request::Request) -> Result<(), Error> {
+    let config_path = config::repo_path_to_config_file(req.context.repo_dir.clone());
+    let mut cfg = try!(fsrepo::read_config_file(&config_path));
+    for peer in &cfg.bootstrap_mut().peers {
+        try!(writeln!(req, "{}", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(ListCommand, run_list);
+
+fn make_list_command() -> Box<Command> {
+    Box::new(ListCommand::new("list", vec![], vec![], ListHelpText, vec![]))
+}
+
+fn run_add(req: &request::Request) -> Result<(), Error> {
+    let mut peers = try!(parse_peers(req));
+    if req.bool_option("default") {
+        for s in bootstrap::DEFAULT_PEERS {
+            peers.push(try!(bootstrap::parse_peer_addr(s)));
+        }
+    }
+    if peers.is_empty() {
+        return Err(Error::InvalidArgument("No bootstrap peers to add".to_string()));
+    }
+
+    let mut added = Vec::new();
+    try!(update_config_bootstrap(req, |cfg| {
+        for peer in peers {
+            if !try!(contains(&cfg.peers, &peer)) {
+                cfg.peers.push(peer.to_string());
+                added.push(peer);
+            }
+        }
+        Ok(())
+    }));
+
+    for peer in added {
+        try!(writeln!(req, "added {}", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(AddCommand, run_add);
+
+fn make_add_command() -> Box<Command> {
+    let default = commands::Opt::new_bool(vec!["default"], "Add the default bootstrap peers");
+    let arg_peer = Argument::new_string("peer", false, true, "A peer to add to the bootstrap list")
+                       .enable_stdin();
+
+    Box::new(AddCommand::new("add", vec![default], vec![arg_peer], AddHelpText, vec![]))
+}
+
+fn run_rm(req: &request::Request) -> Result<(), Error> {
+    let peers = try!(parse_peers(req));
+    let all = req.bool_option("all");
+    if peers.is_empty() && !all {
+        return Err(Error::InvalidArgument("No bootstrap peers to remove".to_string()));
+    }
+
+    let mut removed = Vec::new();
+    try!(update_config_bootstrap(req, |cfg| {
+        let mut kept = Vec::new();
+        for s in cfg.peers.drain(..) {
+            // compare parsed addresses, since the config may be hand-edited
+            if all || peers.contains(&try!(bootstrap::parse_peer_addr(&s))) {
+                removed.push(s);
+            } else {
+                kept.push(s);
+            }
+        }
+        cfg.peers = kept;
+        Ok(())
+    }));
+
+    for peer in removed {
+        try!(writeln!(req, "removed {}", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(RmCommand, run_rm);
+
+fn make_rm_command() -> Box<Command> {
+    let all = commands::Opt::new_bool(vec!["all"], "Remove all bootstrap peers");
+    let arg_peer = Argument::new_string("peer",
+                                        false,
+                                        true,
+                                        "A peer to remove from the bootstrap list")
+                       .enable_stdin();
+
+    Box::new(RmCommand::new("rm", vec![all], vec![arg_peer], RmHelpText, vec![]))
+}
+
+fn parse_peers(req: &request::Request) -> Result<Vec<Multiaddr>, Error> {
+    match req.string_arg("peer") {
+        None => Ok(vec![]),
+        Some(args) => args.iter().map(|s| bootstrap::parse_peer_addr(s)).collect(),
+    }
+}
+
+fn contains(peers: &[String], addr: &Multiaddr) -> Result<bool, Error> {
+    for s in peers {
+        if try!(bootstrap::parse_peer_addr(s)) == *addr {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Applies `f` to the bootstrap section of the repo's config file
+fn update_config_bootstrap<F>(req: &request::Request, f: F) -> Result<(), Error>
+    where F: FnOnce(&mut BootstrapConfig) -> Result<(), Error>
+{
+    let config_path = config::repo_path_to_config_file(req.context.repo_dir.clone());
+    let mut cfg = try!(fsrepo::read_config_file(&config_path));
+    try!(f(cfg.bootstrap_mut()));
+    fsrepo::update_config_file(&config_path, &cfg)
+}
@@ -0,0 +1,38 @@
+use commands::{HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+
+use std::io;
+
+const HELP_TEXT: HelpText = HelpText {
+    tagline: "Show IPFS object data",
+    synopsis: "",
+    short_desc: r#"
+Retrieves the object named by <ipfs-path> and outputs the data it
+contains. When the daemon is running, blocks missing locally are fetched
+from connected peers.
+"#,
+};
+
+fn run(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+
+    for path in req.string_arg("ipfs-path").unwrap() {
+        let mut reader = try!(node.cat(path));
+        try!(io::copy(&mut reader, &mut *req.output())
+                 .map_err(|e| Error::Io(format!("Error writing contents of {}", path), e)));
+    }
+    Ok(())
+}
+
+ipfs_command!(CatCommand, run);
+
+pub fn make_command() -> Box<Command> {
+    let arg_path = Argument::new_string("ipfs-path",
+                                        true,
+                                        true,
+                                        "The path(s) to the IPFS object(s) to be outputted")
+                       .enable_stdin();
+
+    Box::new(CatCommand::new("cat", vec![], vec![arg_path], HELP_TEXT, vec![]))
+}
@@ -0,0 +1,78 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::completion::{self, Shell};
+use commands::request;
+use error::Error;
+
+const CommandsHelpText: HelpText = HelpText {
+    tagline: "List all available commands.",
+    synopsis: "",
+    short_desc: r#"
+Lists all available commands (and subcommands) and exits.
+"#,
+};
+
+const CompletionHelpText: HelpText = HelpText {
+    tagline: "Generate shell completion scripts.",
+    synopsis: "ipfs commands completion <shell>",
+    short_desc: r#"
+Prints a completion script for <shell> (one of bash, zsh or fish) covering
+every ipfs subcommand and option. To enable it for the current session:
+
+    bash:  source <(ipfs commands completion bash)
+    zsh:   source <(ipfs commands completion zsh)
+    fish:  ipfs commands completion fish | source
+"#,
+};
+
+fn run_commands(req: &request::Request) -> Result<(), Error> {
+    let root = super::root::make_command();
+    for (path, _) in commands::all_commands(&*root) {
+        let mut line = "ipfs".to_string();
+        for name in path {
+            line.push(' ');
+            line.push_str(name);
+        }
+        try!(writeln!(req, "{}", line));
+    }
+    Ok(())
+}
+
+ipfs_command!(CommandsCommand, run_commands);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(CommandsCommand::new("commands",
+                                  vec![],
+                                  vec![],
+                                  CommandsHelpText,
+                                  vec![make_completion_command()]))
+}
+
+fn run_completion(req: &request::Request) -> Result<(), Error> {
+    let shell_name = &req.string_arg("shell").unwrap()[0];
+    let shell = try!(Shell::from_name(shell_name).ok_or(Error::InvalidArgument(format!(
+        "Unsupported shell: {}. Supported shells are: {}",
+        shell_name,
+        completion::SUPPORTED_SHELLS.join(", ")))));
+
+    // The tree is rebuilt here since a request only knows about the command
+    // being run, not the root
+    let root = super::root::make_command();
+    try!(write!(req, "{}", completion::generate(&*root, "ipfs", shell)));
+    Ok(())
+}
+
+ipfs_command!(CompletionCommand, run_completion);
+
+fn make_completion_command() -> Box<Command> {
+    let arg_shell = Argument::new_string("shell",
+                                         true,
+                                         false,
+                                         "The shell to generate completions for: bash, zsh \
+                                          or fish");
+
+    Box::new(CompletionCommand::new("completion",
+                                    vec![],
+                                    vec![arg_shell],
+                                    CompletionHelpText,
+                                    vec![]))
+}
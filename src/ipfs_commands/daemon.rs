@@ -0,0 +1,135 @@
+use api;
+use commands::{self, HelpText, Command};
+use commands::request;
+use core::IpfsNode;
+use error::Error;
+use fsrepo;
+use multiaddr::Multiaddr;
+
+use std::io::Write;
+use std::net::TcpListener;
+use std::sync::Arc;
+
+const HELP_TEXT: HelpText = HelpText {
+    tagline: "Run a network-connected IPFS node",
+    synopsis: "",
+    short_desc: r#"
+'ipfs daemon' runs a persistent ipfs node that exchanges blocks with the
+peers it's connected to. While it runs, other ipfs commands are sent to it
+through its API instead of opening the repo themselves.
+
+The addresses listened on are set in the config, and can be overridden
+with --swarm-addr and --api-addr. Peers are connected to with --connect,
+given a comma-separated list of multiaddrs. For example, to run a second
+node on the same machine and connect it to the first:
+
+    IPFS_PATH=~/.rust-ipfs2 ipfs daemon \
+        --swarm-addr /ip4/127.0.0.1/tcp/4002 \
+        --api-addr /ip4/127.0.0.1/tcp/5002 \
+        --connect /ip4/127.0.0.1/tcp/4001
+
+The daemon also connects to the bootstrap peers in the config (see
+'ipfs bootstrap'), until it has bootstrap.min_connections connections.
+Peers on the local network, including other daemons on the same machine,
+are found with mDNS unless discovery.mdns.enabled is false.
+
+If the repo contains a swarm.key file (see 'ipfs init --swarm-key'), the
+node is part of a private network: only peers with the same key can
+connect to it, or be connected to. Set LIBP2P_FORCE_PNET=1 to refuse to
+start without a swarm key.
+"#,
+};
+
+fn run(req: &request::Request) -> Result<(), Error> {
+    let repo_dir = req.context.repo_dir.clone();
+    if try!(fsrepo::is_locked(repo_dir.clone())) {
+        return Err(Error::Locked(repo_dir));
+    }
+    let mut lock_path = repo_dir.clone();
+    lock_path.push(fsrepo::LOCK_FILE);
+    // held until the daemon exits
+    let _lock = try!(fsrepo::lock(&lock_path).map_err(|e| {
+        Error::Io(format!("Error locking repo at {:?}", repo_dir), e)
+    }));
+
+    let mut node = try!(IpfsNode::open(repo_dir.clone()));
+    let swarm_addrs = match req.string_option("swarm-addr") {
+        None => try!(node.config().swarm_addrs()),
+        Some(addrs) => try!(parse_multiaddrs(addrs)),
+    };
+    let api_addr = match req.string_option("api-addr") {
+        None => try!(node.config().api_addr()),
+        Some(addr) => try!(Multiaddr::parse(addr)),
+    };
+
+    for addr in try!(node.go_online(&swarm_addrs)) {
+        try!(writeln!(req, "Swarm listening on {}", addr));
+    }
+    try!(writeln!(req, "Peer ID: {}", node.peer_id()));
+    if let Some(psk) = node.swarm_key() {
+        try!(writeln!(req, "Swarm is limited to private network of peers with the swarm key"));
+        try!(writeln!(req, "Swarm key fingerprint: {}", psk.fingerprint()));
+    }
+
+    if let Some(addrs) = req.string_option("connect") {
+        for addr in try!(parse_multiaddrs(addrs)) {
+            match node.connect(&addr) {
+                Ok(peer) => try!(writeln!(req, "Connected to {} at {}", peer, addr)),
+                Err(e) => try!(writeln!(req, "Error connecting to {}: {}", addr, e)),
+            }
+        }
+    }
+
+    try!(node.bootstrap());
+
+    let socket_addrs = try!(api_addr.to_socket_addrs());
+    let listener = try!(TcpListener::bind(&socket_addrs[..]).map_err(|e| {
+        Error::Io(format!("Error binding API listener to {}", api_addr), e)
+    }));
+    let api_addr = match listener.local_addr() {
+        Ok(addr) => Multiaddr::from_socket_addr(&addr),
+        Err(e) => return Err(Error::Io("Error getting API listener address".to_string(), e)),
+    };
+    let token = try!(api::write_token_file(&repo_dir));
+    try!(api::write_api_file(&repo_dir, &api_addr));
+    try!(writeln!(req, "API server listening on {}", api_addr));
+    try!(writeln!(req, "Daemon is ready"));
+    try!(req.output()
+            .flush()
+            .map_err(|e| Error::Io("Error writing command output".to_string(), e)));
+
+    api::serve(Arc::new(node), repo_dir, listener, token);
+    Ok(())
+}
+
+// Parses a comma-separated list of multiaddrs
+fn parse_multiaddrs(s: &str) -> Result<Vec<Multiaddr>, Error> {
+    s.split(',')
+     .map(|addr| addr.trim())
+     .filter(|addr| !addr.is_empty())
+     .map(|addr| Multiaddr::parse(addr))
+     .collect()
+}
+
+ipfs_command!(DaemonCommand, run);
+
+pub fn make_command() -> Box<Command> {
+    let swarm_addr = commands::Opt::new_string(
+        vec!["swarm-addr"],
+        "Comma-separated multiaddrs to listen on for peers, instead of those in the config"
+    );
+    let api_addr = commands::Opt::new_string(
+        vec!["api-addr"],
+        "Multiaddr to serve the API on, instead of the one in the config"
+    );
+    let connect = commands::Opt::new_string(
+        vec!["connect"],
+        "Comma-separated multiaddrs of peers to connect to"
+    );
+
+    Box::new(DaemonCommand::new("daemon",
+                                vec![swarm_addr, api_addr, connect],
+                                vec![],
+                                HELP_TEXT,
+                                vec![]))
+}
@@ -0,0 +1,242 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use dht::record;
+use error::Error;
+use peer;
+
+use rust_multihash::Multihash;
+use std::io::Write;
+
+const DEFAULT_NUM_PROVIDERS: usize = 20;
+
+const DhtHelpText: HelpText = HelpText {
+    tagline: "Issue commands directly through the DHT",
+    synopsis: "ipfs dht <command>",
+    short_desc: r#"
+'ipfs dht' queries the Kademlia DHT the daemon takes part in. These
+commands require the daemon to be running.
+
+    ipfs dht findprovs <key>     Find peers that can provide a key
+    ipfs dht findpeer <peer>     Find the addresses of a peer
+    ipfs dht provide <key>...    Announce that we can provide keys
+    ipfs dht get <key>           Get the value of a record
+    ipfs dht put <key> <value>   Store a record
+    ipfs dht query <peer>        Find the peers closest to a peer ID
+"#,
+};
+
+const QueryHelpText: HelpText = HelpText {
+    tagline: "Find the closest peer IDs to a given peer ID by querying the DHT",
+    synopsis: "ipfs dht query <peerID>",
+    short_desc: r#"
+'ipfs dht query' runs a DHT lookup for <peerID>, and outputs the IDs of
+the closest peers that answered, closest first.
+"#,
+};
+
+const FindProvsHelpText: HelpText = HelpText {
+    tagline: "Find peers that can provide a specific value, given a key",
+    synopsis: "ipfs dht findprovs [--num-providers <n>] <key>",
+    short_desc: r#"
+'ipfs dht findprovs' outputs the IDs of peers that have announced they can
+provide <key>, a base58-encoded multihash.
+"#,
+};
+
+const FindPeerHelpText: HelpText = HelpText {
+    tagline: "Find the multiaddresses associated with a peer ID",
+    synopsis: "ipfs dht findpeer <peerID>",
+    short_desc: r#"
+'ipfs dht findpeer' outputs the addresses <peerID> can be reached at,
+looking it up in the DHT if we're not connected to it.
+"#,
+};
+
+const ProvideHelpText: HelpText = HelpText {
+    tagline: "Announce to the network that you are providing given values",
+    synopsis: "ipfs dht provide <key>...",
+    short_desc: r#"
+'ipfs dht provide' tells the peers closest to each <key> that we can
+provide it. The blocks must be in the local blockstore.
+"#,
+};
+
+const GetHelpText: HelpText = HelpText {
+    tagline: "Given a key, query the routing system for its best value",
+    synopsis: "ipfs dht get <key>",
+    short_desc: r#"
+'ipfs dht get' outputs the best value stored under <key>, which is
+namespaced: /<namespace>/<rest>. Values are validated according to their
+namespace. For example, /pk/<peer id> holds the public key of a peer.
+"#,
+};
+
+const PutHelpText: HelpText = HelpText {
+    tagline: "Write a key/value pair to the routing system",
+    synopsis: "ipfs dht put <key> <value>",
+    short_desc: r#"
+'ipfs dht put' stores <value> under <key> with the peers closest to the
+key. The value must be valid for the namespace of the key (see
+'ipfs dht get'), and records in unknown namespaces are refused.
+"#,
+};
+
+fn run_dht(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(DhtCommand, run_dht);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(DhtCommand::new("dht",
+                             vec![],
+                             vec![],
+                             DhtHelpText,
+                             vec![make_query_command(),
+                                  make_findprovs_command(),
+                                  make_findpeer_command(),
+                                  make_provide_command(),
+                                  make_get_command(),
+                                  make_put_command()]))
+}
+
+fn run_query(req: &request::Request) -> Result<(), Error> {
+    let dht = try!(try!(req.context.get_node()).get_dht());
+    let target = try!(peer::parse_peer_id(&req.string_arg("peerID").unwrap()[0]));
+
+    for peer in try!(dht.get_closest_peers(&target.into_bytes())) {
+        try!(writeln!(req, "{}", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(QueryCommand, run_query);
+
+fn make_query_command() -> Box<Command> {
+    let arg_peer = Argument::new_string("peerID", true, false, "The peer ID to run the query for");
+
+    Box::new(QueryCommand::new("query", vec![], vec![arg_peer], QueryHelpText, vec![]))
+}
+
+fn run_findprovs(req: &request::Request) -> Result<(), Error> {
+    let dht = try!(try!(req.context.get_node()).get_dht());
+    let key = try!(parse_key(&req.string_arg("key").unwrap()[0]));
+    let count = match req.option("num-providers") {
+        Some(&request::Opt::Int(n)) if n > 0 => n as usize,
+        Some(&request::Opt::Int(n)) => {
+            return Err(Error::InvalidArgument(format!("Invalid number of providers: {}", n)))
+        }
+        _ => DEFAULT_NUM_PROVIDERS,
+    };
+
+    let providers = try!(dht.find_providers(&key, count));
+    if providers.is_empty() {
+        return Err(Error::NotFound(format!("providers for {}", key)));
+    }
+    for provider in providers {
+        try!(writeln!(req, "{}", provider));
+    }
+    Ok(())
+}
+
+ipfs_command!(FindProvsCommand, run_findprovs);
+
+fn make_findprovs_command() -> Box<Command> {
+    let num_providers = commands::Opt::new_int(vec!["num-providers", "n"],
+                                               "The number of providers to find");
+    let arg_key = Argument::new_string("key", true, false, "The key to find providers for");
+
+    Box::new(FindProvsCommand::new("findprovs",
+                                   vec![num_providers],
+                                   vec![arg_key],
+                                   FindProvsHelpText,
+                                   vec![]))
+}
+
+fn run_findpeer(req: &request::Request) -> Result<(), Error> {
+    let dht = try!(try!(req.context.get_node()).get_dht());
+    let peer = try!(peer::parse_peer_id(&req.string_arg("peerID").unwrap()[0]));
+
+    for addr in try!(dht.find_peer(&peer)) {
+        try!(writeln!(req, "{}", addr));
+    }
+    Ok(())
+}
+
+ipfs_command!(FindPeerCommand, run_findpeer);
+
+fn make_findpeer_command() -> Box<Command> {
+    let arg_peer = Argument::new_string("peerID", true, false, "The ID of the peer to search for");
+
+    Box::new(FindPeerCommand::new("findpeer", vec![], vec![arg_peer], FindPeerHelpText, vec![]))
+}
+
+fn run_provide(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let dht = try!(node.get_dht());
+
+    for s in req.string_arg("key").unwrap() {
+        let key = try!(parse_key(s));
+        if !try!(node.blockstore().has(&key)) {
+            return Err(Error::NotFound(format!("block {} in the local blockstore", key)));
+        }
+        let told = try!(dht.provide(&key));
+        try!(writeln!(req, "provided {} to {} peers", key, told));
+    }
+    Ok(())
+}
+
+ipfs_command!(ProvideCommand, run_provide);
+
+fn make_provide_command() -> Box<Command> {
+    let arg_key = Argument::new_string("key",
+                                       true,
+                                       true,
+                                       "The key(s) to send provide records for")
+                      .enable_stdin();
+
+    Box::new(ProvideCommand::new("provide", vec![], vec![arg_key], ProvideHelpText, vec![]))
+}
+
+fn run_get(req: &request::Request) -> Result<(), Error> {
+    let dht = try!(try!(req.context.get_node()).get_dht());
+    let key = try!(record::parse_key(&req.string_arg("key").unwrap()[0]));
+
+    let value = try!(dht.get_value(&key));
+    req.output()
+       .write_all(&value)
+       .map_err(|e| Error::Io("Error writing command output".to_string(), e))
+}
+
+ipfs_command!(GetCommand, run_get);
+
+fn make_get_command() -> Box<Command> {
+    let arg_key = Argument::new_string("key", true, false, "The key to find a value for");
+
+    Box::new(GetCommand::new("get", vec![], vec![arg_key], GetHelpText, vec![]))
+}
+
+fn run_put(req: &request::Request) -> Result<(), Error> {
+    let dht = try!(try!(req.context.get_node()).get_dht());
+    let key = try!(record::parse_key(&req.string_arg("key").unwrap()[0]));
+    let value = &req.string_arg("value").unwrap()[0];
+
+    let stored = try!(dht.put_value(&key, value.as_bytes()));
+    writeln!(req, "stored {} with {} peers", record::format_key(&key), stored)
+}
+
+ipfs_command!(PutCommand, run_put);
+
+fn make_put_command() -> Box<Command> {
+    let arg_key = Argument::new_string("key", true, false, "The key to store the value at");
+    let arg_value = Argument::new_string("value", true, false, "The value to store");
+
+    Box::new(PutCommand::new("put", vec![], vec![arg_key, arg_value], PutHelpText, vec![]))
+}
+
+fn parse_key(s: &str) -> Result<Multihash, Error> {
+    Multihash::from_base58_str(s).map_err(|e| {
+        Error::InvalidArgument(format!("Invalid key {}: {}", s, e))
+    })
+}
@@ -0,0 +1,38 @@
+use commands::{HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use ipns::dnslink;
+
+const DnsHelpText: HelpText = HelpText {
+    tagline: "Resolve DNS links",
+    synopsis: "ipfs dns <domain>",
+    short_desc: r#"
+'ipfs dns' outputs the path the DNSLink record of <domain> points to.
+The record is a TXT record on _dnslink.<domain> (or on <domain> itself)
+of the form:
+
+    dnslink=/ipfs/QmatmE9msSfkKxoffpHwNLNKgwZG8eT9Bud6YoPab52vpy
+
+Links to other domains or IPNS names are followed, up to 32 of them, so
+the output is always an /ipfs/ path. /ipns/<domain> paths are resolved the
+same way by the other commands.
+"#,
+};
+
+fn run_dns(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let domain = &req.string_arg("domain").unwrap()[0];
+    if !dnslink::is_domain(domain) {
+        return Err(Error::InvalidArgument(format!("Invalid domain name {}", domain)));
+    }
+    let path = try!(node.resolve_name(domain));
+    writeln!(req, "{}", path)
+}
+
+ipfs_command!(DnsCommand, run_dns);
+
+pub fn make_command() -> Box<Command> {
+    let arg_domain = Argument::new_string("domain", true, false, "The domain name to resolve");
+
+    Box::new(DnsCommand::new("dns", vec![], vec![arg_domain], DnsHelpText, vec![]))
+}
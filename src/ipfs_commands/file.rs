@@ -1,10 +1,11 @@
-use commands::{HelpText, Command, Argument};
+use cid::Cid;
+use commands::{self, HelpText, Command, Argument};
 use commands::request;
-use unixfs;
+use error::Error;
+use unixfs::{self, directory};
 
-use rust_multihash::Multihash;
-use std::collections::HashMap;
-use std::sync::Arc;
+use rustc_serialize::json;
+use std::collections::BTreeMap;
 
 const FileHelpText: HelpText = HelpText {
     tagline: "Interact with ipfs objects representing Unix filesystems",
@@ -18,18 +19,34 @@ objects (e.g. fanout and chunking).
 
 const LsHelpText: HelpText = HelpText {
     tagline: "List directory contents for Unix-filesystem objects",
-    synopsis: "",
+    synopsis: "ipfs file ls [-l] <ipfs-path>...",
     short_desc: r#"
-Retrieves the object named by <ipfs-or-ipns-path> and displays the
-contents.
+Retrieves the objects named by <ipfs-path> and displays their contents, in
+the order given. Each object is shown under its hash, followed by its
+entries if it's a directory, or else by itself. Entries are listed one per
+line as <size> <type> <name>, separated by tabs; with -l, their mode and
+modification time come first.
+
+For files, the size is the total size of the file contents. For
+directories, it is the IPFS link size.
+
+With --enc=json, the output is an object as in go-ipfs: "Arguments" maps
+each <ipfs-path> to its hash, and "Objects" maps each hash to the object's
+"Hash", "Size", "Type" and "Links" (each with a "Name", "Hash", "Size" and
+"Type").
+"#,
+};
 
-The JSON output contains size information.  For files, the child size
-is the total size of the file contents.  For directories, the child
-size is the IPFS link size.
+const StatHelpText: HelpText = HelpText {
+    tagline: "Display the status of Unix-filesystem objects",
+    synopsis: "",
+    short_desc: r#"
+Shows the hash, size and type of the objects named by <ipfs-path>, along
+with the MIME type of files added with one.
 "#,
 };
 
-fn run_file(req: &request::Request) -> Result<(), String> {
+fn run_file(req: &request::Request) -> Result<(), Error> {
     unimplemented!()
 }
 
@@ -40,95 +57,219 @@ pub fn make_command() -> Box<Command> {
                               vec![],
                               vec![],
                               FileHelpText,
-                              vec![make_ls_command()]))
+                              vec![make_ls_command(), make_stat_command()]))
 }
 
 #[derive(Debug)]
 struct LsLink {
     pub name: String,
-    hash: Multihash,
+    hash: Cid,
     size: u64,
     ty: unixfs::pb::Data_DataType,
+    target: Option<String>,
+    mime_type: Option<String>,
+    attributes: unixfs::Attributes,
 }
 
 #[derive(Debug)]
 struct LsObject {
-    hash: Multihash,
+    path: String, // the first argument naming the object
+    hash: Cid,
     size: u64,
     ty: unixfs::pb::Data_DataType,
+    mime_type: Option<String>,
+    attributes: unixfs::Attributes,
     pub links: Vec<LsLink>,
 }
 
-// TODO: this is only going to accept hashes for now. Need to implement
-// path resolver so it can do paths.
-fn run_ls(req: &request::Request) -> Result<(), String> {
+// JSON output, with field names as in go-ipfs
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct LsLinkOutput {
+    Name: String,
+    Hash: String,
+    Size: u64,
+    Type: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct LsObjectOutput {
+    Hash: String,
+    Size: u64,
+    Type: String,
+    Links: Vec<LsLinkOutput>,
+}
+
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct LsOutput {
+    Arguments: BTreeMap<String, String>, // the hash of each path
+    Objects: BTreeMap<String, LsObjectOutput>,
+}
+
+fn run_ls(req: &request::Request) -> Result<(), Error> {
     let node = try!(req.context.get_node());
 
-    let mut objects: HashMap<Multihash, LsObject> = HashMap::new();
+    let long = req.bool_option("long");
+    let json = match req.option("encoding") {
+        Some(&request::Opt::String(ref enc)) if enc == "json" => true,
+        Some(&request::Opt::String(ref enc)) if enc != "text" => {
+            return Err(Error::InvalidArgument(format!("Unknown encoding {}: expected text or \
+                                                       json",
+                                                      enc)))
+        }
+        _ => false,
+    };
+    let mut arguments = Vec::new();
+    let mut objects: Vec<LsObject> = Vec::new();
 
     for path in req.string_arg("ipfs-path").unwrap() {
-        let mh = try!(Multihash::from_base58_str(&path));
-        // retrieve merkledag node for the path (multihash, at this point)
-        let mut dag_node = try!(node.dagservice.get(&mh));
-        let unixfs_data = try!(unixfs::from_reader(&mut dag_node.get_data()));
+        let dag_node = try!(node.resolve_path(path));
+        let cid = dag_node.cid();
+        arguments.push((path.clone(), cid.clone()));
+        if objects.iter().any(|obj| obj.hash == cid) {
+            continue;
+        }
+        let mime_type = try!(unixfs::mime_type(&dag_node));
+        let dag_node = try!(unixfs::unwrap_metadata(node.dagservice(), dag_node));
+        let unixfs_data = try!(unixfs::node_data(&dag_node));
 
         let file_type = unixfs_data.get_Type();
 
         let links = match file_type {
-            unixfs::pb::Data_DataType::File => vec![],
-            unixfs::pb::Data_DataType::Directory => {
-                let links = Arc::get_mut(&mut dag_node)
-                                .unwrap()
-                                .get_mut_links();
+            unixfs::pb::Data_DataType::Directory | unixfs::pb::Data_DataType::HAMTShard => {
+                let links = try!(directory::entries(node.dagservice(), &dag_node));
                 let mut v = Vec::with_capacity(links.len());
 
-                for link in links.iter_mut() {
-                    let link_node = try!(link.get_node(&node.dagservice));
-                    link.set_node(link_node.clone()); // TODO: needed?
+                for link in links.iter() {
+                    let link_node = try!(link.get_node(node.dagservice()));
+                    let mime_type = try!(unixfs::mime_type(&link_node));
+                    let link_node = try!(unixfs::unwrap_metadata(node.dagservice(), link_node));
 
-                    let link_node_data =
-                        try!(unixfs::from_reader(&mut link_node.get_data()));
+                    let link_node_data = try!(unixfs::node_data(&link_node));
 
                     let ty = link_node_data.get_Type();
 
                     let size = match ty {
-                        unixfs::pb::Data_DataType::File => link_node_data.get_filesize(),
+                        unixfs::pb::Data_DataType::File |
+                        unixfs::pb::Data_DataType::Raw => link_node_data.get_filesize(),
                         _ => link.get_target_size(),
                     };
 
                     v.push(LsLink {
                         name: link.clone_name(),
-                        hash: link.clone_hash(),
+                        hash: link.cid(),
                         size: size,
                         ty: ty,
+                        target: try!(unixfs::symlink_target(&link_node)),
+                        mime_type: mime_type,
+                        attributes: unixfs::attributes(&link_node_data),
                     });
                 }
 
                 v
             }
-            _ => unimplemented!(),
+            // files, symlinks and the like have no entries
+            _ => vec![],
         };
 
-        let ls_obj = LsObject {
-            hash: mh,
+        objects.push(LsObject {
+            path: path.clone(),
+            hash: cid,
             size: unixfs_data.get_filesize(),
             ty: file_type,
+            mime_type: mime_type,
+            attributes: unixfs::attributes(&unixfs_data),
             links: links,
-        };
+        });
+    }
 
-        objects.insert(dag_node.multihash(), ls_obj);
+    if json {
+        let output = LsOutput {
+            Arguments: arguments.into_iter().map(|(path, cid)| (path, cid.to_string())).collect(),
+            Objects: objects.iter().map(|obj| (obj.hash.to_string(), object_output(obj))).collect(),
+        };
+        return writeln!(req, "{}", json::as_pretty_json(&output));
     }
 
-    for (hash, obj) in &objects {
-        println!("{}:", hash);
+    for (i, obj) in objects.iter().enumerate() {
+        if i > 0 {
+            try!(writeln!(req, ""));
+        }
+        match obj.mime_type {
+            Some(ref mime_type) => try!(writeln!(req, "{} ({}):", obj.hash, mime_type)),
+            None => try!(writeln!(req, "{}:", obj.hash)),
+        }
+        // an object without entries is listed as itself
+        if !directory::is_directory(obj.ty) {
+            try!(write_entry(req, long, &obj.attributes, obj.size, obj.ty, &obj.path));
+        }
         for link in obj.links.iter() {
-            println!("{}", link.name);
+            let name = match (&link.target, &link.mime_type) {
+                (&Some(ref target), _) => format!("{} -> {}", link.name, target),
+                (_, &Some(ref mime_type)) => format!("{} ({})", link.name, mime_type),
+                _ => link.name.clone(),
+            };
+            try!(write_entry(req, long, &link.attributes, link.size, link.ty, &name));
         }
-        println!("");
     }
     Ok(())
 }
 
+// Writes a line of `file ls` output: the size, type and name of an entry,
+// after its mode and modification time with -l
+fn write_entry(req: &request::Request,
+               long: bool,
+               attributes: &unixfs::Attributes,
+               size: u64,
+               ty: unixfs::pb::Data_DataType,
+               name: &str)
+               -> Result<(), Error> {
+    if long {
+        writeln!(req,
+                 "{}\t{}\t{}\t{}\t{}",
+                 attributes.mode_string(),
+                 attributes.mtime_string(),
+                 size,
+                 unixfs::type_name(ty),
+                 name)
+    } else {
+        writeln!(req, "{}\t{}\t{}", size, unixfs::type_name(ty), name)
+    }
+}
+
+fn object_output(obj: &LsObject) -> LsObjectOutput {
+    LsObjectOutput {
+        Hash: obj.hash.to_string(),
+        Size: obj.size,
+        Type: json_type_name(obj.ty).to_string(),
+        Links: obj.links
+                  .iter()
+                  .map(|link| {
+                      LsLinkOutput {
+                          Name: link.name.clone(),
+                          Hash: link.hash.to_string(),
+                          Size: link.size,
+                          Type: json_type_name(link.ty).to_string(),
+                      }
+                  })
+                  .collect(),
+    }
+}
+
+// The type names go-ipfs uses in JSON output
+fn json_type_name(ty: unixfs::pb::Data_DataType) -> &'static str {
+    match ty {
+        unixfs::pb::Data_DataType::Raw | unixfs::pb::Data_DataType::File => "File",
+        unixfs::pb::Data_DataType::Directory | unixfs::pb::Data_DataType::HAMTShard => {
+            "Directory"
+        }
+        unixfs::pb::Data_DataType::Metadata => "Metadata",
+        unixfs::pb::Data_DataType::Symlink => "Symlink",
+    }
+}
+
 ipfs_command!(LsCommand, run_ls);
 
 fn make_ls_command() -> Box<Command> {
@@ -136,8 +277,52 @@ fn make_ls_command() -> Box<Command> {
                                         true,
                                         true,
                                         "The path(s) to the IPFS object(s) to list \
-                                         links from");
+                                         links from")
+                       .enable_stdin();
+    let long = commands::Opt::new_bool(vec!["long", "l"],
+                                       "Show the mode and modification time of entries");
+    let encoding = commands::Opt::new_string(vec!["encoding", "enc"],
+                                             "Output encoding: text (default) or json");
+
+    Box::new(LsCommand::new("ls", vec![long, encoding], vec![arg_path], LsHelpText, vec![]))
+}
+
+fn run_stat(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+
+    for path in req.string_arg("ipfs-path").unwrap() {
+        let dag_node = try!(node.resolve_path(path));
+        let mime_type = try!(unixfs::mime_type(&dag_node));
+        let file = try!(unixfs::unwrap_metadata(node.dagservice(), dag_node.clone()));
+        let data = try!(unixfs::node_data(&file));
+
+        try!(writeln!(req, "{}", dag_node.cid()));
+        try!(writeln!(req, "Size: {}", data.get_filesize()));
+        try!(writeln!(req, "CumulativeSize: {}", try!(dag_node.cumulative_size())));
+        try!(writeln!(req, "Type: {}", unixfs::type_name(data.get_Type())));
+        if let Some(mime_type) = mime_type {
+            try!(writeln!(req, "MimeType: {}", mime_type));
+        }
+        let attributes = unixfs::attributes(&data);
+        if attributes.mode.is_some() {
+            try!(writeln!(req, "Mode: {}", attributes.mode_string()));
+        }
+        if attributes.mtime.is_some() {
+            try!(writeln!(req, "Mtime: {}", attributes.mtime_string()));
+        }
+    }
+    Ok(())
+}
+
+ipfs_command!(StatCommand, run_stat);
 
+fn make_stat_command() -> Box<Command> {
+    let arg_path = Argument::new_string("ipfs-path",
+                                        true,
+                                        true,
+                                        "The path(s) to the IPFS object(s) to show the status \
+                                         of")
+                       .enable_stdin();
 
-    Box::new(LsCommand::new("ls", vec![], vec![arg_path], FileHelpText, vec![]))
+    Box::new(StatCommand::new("stat", vec![], vec![arg_path], StatHelpText, vec![]))
 }
@@ -0,0 +1,358 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use mfs::WriteOptions;
+use unixfs;
+
+use std::fs::File;
+use std::io::{self, Read};
+
+const FilesHelpText: HelpText = HelpText {
+    tagline: "Interact with unixfs files in MFS",
+    synopsis: "ipfs files <command>",
+    short_desc: r#"
+'ipfs files' edits the mutable file system (MFS), a tree of unixfs files
+and directories that can be changed like a local filesystem. Each change
+adds new objects for what changed, up to a new root directory, so unchanged
+parts of a file or tree are shared with earlier versions. MFS paths start
+with /, the root directory.
+
+    ipfs files ls [<path>]              List a directory
+    ipfs files mkdir <path>             Make a directory
+    ipfs files write <path> <file>      Write to a file
+    ipfs files read <path>              Output a file
+    ipfs files cp <src> <dst>           Copy an MFS or /ipfs/ path into MFS
+    ipfs files mv <src> <dst>           Move a file or directory
+    ipfs files rm <path>...             Remove files or directories
+    ipfs files stat <path>              Show the hash and sizes of a path
+    ipfs files flush [<path>]           Output the hash of a path
+"#,
+};
+
+const LsHelpText: HelpText = HelpText {
+    tagline: "List directories in the local mutable namespace",
+    synopsis: "ipfs files ls [-l] [<path>]",
+    short_desc: r#"
+'ipfs files ls' outputs the names of the entries of the directory at
+<path>, or / if no path is given. With -l, each name is followed by the
+hash of the entry and its size in bytes.
+"#,
+};
+
+const MkdirHelpText: HelpText = HelpText {
+    tagline: "Make directories",
+    synopsis: "ipfs files mkdir [-p] <path>",
+    short_desc: r#"
+'ipfs files mkdir' makes an empty directory at <path>. With -p, missing
+parent directories are made too, and it's not an error if the directory
+exists already.
+"#,
+};
+
+const WriteHelpText: HelpText = HelpText {
+    tagline: "Write to a mutable file in a given filesystem",
+    synopsis: "ipfs files write [--offset <n>] [-e] [-t] [-p] <path> <file>",
+    short_desc: r#"
+'ipfs files write' writes the contents of the local file <file> to the MFS
+file at <path>, starting at --offset (0 by default). Only the blocks of the
+file that are written to are replaced. Writing past the end of the file
+extends it, filling any gap with zeros.
+
+With -e the file is created if it doesn't exist, and with -t it's emptied
+before writing.
+
+    ipfs files write -e /notes.txt ./notes.txt
+    ipfs files write --offset 100 /notes.txt ./correction.txt
+"#,
+};
+
+const ReadHelpText: HelpText = HelpText {
+    tagline: "Read a file in a given MFS",
+    synopsis: "ipfs files read [--offset <n>] [--count <n>] <path>",
+    short_desc: r#"
+'ipfs files read' outputs the contents of the file at <path>, from byte
+--offset on, and at most --count bytes of them if given.
+"#,
+};
+
+const CpHelpText: HelpText = HelpText {
+    tagline: "Copy files into mfs",
+    synopsis: "ipfs files cp <src> <dst>",
+    short_desc: r#"
+'ipfs files cp' links the file or directory at <src> at the MFS path
+<dst>, which must not exist. <src> is either an MFS path or an /ipfs/ or
+/ipns/ path, so content added with 'ipfs add' can be copied into MFS
+without copying its data.
+
+    ipfs files cp /ipfs/<hash> /docs/report.pdf
+"#,
+};
+
+const MvHelpText: HelpText = HelpText {
+    tagline: "Move files",
+    synopsis: "ipfs files mv <src> <dst>",
+    short_desc: r#"
+'ipfs files mv' moves the file or directory at <src> to <dst>, which must
+not exist, or into <dst> if it's a directory.
+"#,
+};
+
+const RmHelpText: HelpText = HelpText {
+    tagline: "Remove a file",
+    synopsis: "ipfs files rm [-r] <path>...",
+    short_desc: r#"
+'ipfs files rm' removes the files at the paths given, and the directories
+with -r.
+"#,
+};
+
+const StatHelpText: HelpText = HelpText {
+    tagline: "Display file status",
+    synopsis: "ipfs files stat <path>",
+    short_desc: r#"
+'ipfs files stat' outputs the hash of the file or directory at <path>,
+followed by the size of its data (0 for directories), its size with all the
+objects it links to, the number of links it has and its type.
+"#,
+};
+
+const FlushHelpText: HelpText = HelpText {
+    tagline: "Flush a given path's data to disk",
+    synopsis: "ipfs files flush [<path>]",
+    short_desc: r#"
+'ipfs files flush' outputs the hash of <path>, or / if no path is given.
+Changes are saved to the repo as they are made, so there's nothing left to
+write; this is kept for scripts written against go-ipfs.
+"#,
+};
+
+fn run_files(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(FilesCommand, run_files);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(FilesCommand::new("files",
+                               vec![],
+                               vec![],
+                               FilesHelpText,
+                               vec![make_ls_command(),
+                                    make_mkdir_command(),
+                                    make_write_command(),
+                                    make_read_command(),
+                                    make_cp_command(),
+                                    make_mv_command(),
+                                    make_rm_command(),
+                                    make_stat_command(),
+                                    make_flush_command()]))
+}
+
+fn run_ls(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let path = optional_path(req);
+    let long = req.bool_option("long");
+
+    for entry in try!(node.mfs().ls(path)) {
+        if long {
+            try!(writeln!(req, "{}\t{}\t{}", entry.name, entry.hash, entry.size));
+        } else {
+            try!(writeln!(req, "{}", entry.name));
+        }
+    }
+    Ok(())
+}
+
+ipfs_command!(LsCommand, run_ls);
+
+fn make_ls_command() -> Box<Command> {
+    let long = commands::Opt::new_bool(vec!["long", "l"], "Show the hashes and sizes of entries");
+    let arg_path = Argument::new_string("path", false, false, "Directory to list (default /)");
+
+    Box::new(LsCommand::new("ls", vec![long], vec![arg_path], LsHelpText, vec![]))
+}
+
+fn run_mkdir(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let path = &req.string_arg("path").unwrap()[0];
+    node.mfs().mkdir(path, req.bool_option("parents"))
+}
+
+ipfs_command!(MkdirCommand, run_mkdir);
+
+fn make_mkdir_command() -> Box<Command> {
+    let parents = commands::Opt::new_bool(vec!["parents", "p"],
+                                          "Make parent directories as needed");
+    let arg_path = Argument::new_string("path", true, false, "Directory to make");
+
+    Box::new(MkdirCommand::new("mkdir", vec![parents], vec![arg_path], MkdirHelpText, vec![]))
+}
+
+fn run_write(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let path = &req.string_arg("path").unwrap()[0];
+    let data_path = &req.file_arg("data").unwrap()[0];
+
+    let options = WriteOptions {
+        offset: try!(req.int_option("offset")).unwrap_or(0),
+        create: req.bool_option("create"),
+        truncate: req.bool_option("truncate"),
+        parents: req.bool_option("parents"),
+    };
+    let mut file = try!(File::open(data_path)
+                            .map_err(|e| Error::Io(format!("Error opening {:?}", data_path), e)));
+    node.mfs().write(path, &mut file, &options)
+}
+
+ipfs_command!(WriteCommand, run_write);
+
+fn make_write_command() -> Box<Command> {
+    let offset = commands::Opt::new_int(vec!["offset", "o"], "Byte offset to start writing at");
+    let create = commands::Opt::new_bool(vec!["create", "e"],
+                                         "Create the file if it doesn't exist");
+    let truncate = commands::Opt::new_bool(vec!["truncate", "t"],
+                                           "Empty the file before writing");
+    let parents = commands::Opt::new_bool(vec!["parents", "p"],
+                                          "Make parent directories as needed");
+    let arg_path = Argument::new_string("path", true, false, "File to write to");
+    let arg_data = Argument::new_file("data", true, false, "Local file holding the data to write");
+
+    Box::new(WriteCommand::new("write",
+                               vec![offset, create, truncate, parents],
+                               vec![arg_path, arg_data],
+                               WriteHelpText,
+                               vec![]))
+}
+
+fn run_read(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let path = &req.string_arg("path").unwrap()[0];
+    let offset = try!(req.int_option("offset")).unwrap_or(0);
+
+    let mut reader = try!(node.mfs().read(path, offset));
+    let result = match try!(req.int_option("count")) {
+        Some(count) => io::copy(&mut reader.by_ref().take(count), &mut *req.output()),
+        None => io::copy(&mut reader, &mut *req.output()),
+    };
+    try!(result.map_err(|e| Error::Io(format!("Error writing contents of {}", path), e)));
+    Ok(())
+}
+
+ipfs_command!(ReadCommand, run_read);
+
+fn make_read_command() -> Box<Command> {
+    let offset = commands::Opt::new_int(vec!["offset", "o"], "Byte offset to start reading at");
+    let count = commands::Opt::new_int(vec!["count", "n"], "Maximum number of bytes to read");
+    let arg_path = Argument::new_string("path", true, false, "File to read");
+
+    Box::new(ReadCommand::new("read", vec![offset, count], vec![arg_path], ReadHelpText, vec![]))
+}
+
+fn run_cp(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let src = &req.string_arg("source").unwrap()[0];
+    let dst = &req.string_arg("dest").unwrap()[0];
+
+    let src_node = if src.starts_with("/ipfs/") || src.starts_with("/ipns/") {
+        try!(node.resolve_path(src))
+    } else {
+        try!(node.mfs().lookup(src))
+    };
+    node.mfs().put(dst, src_node)
+}
+
+ipfs_command!(CpCommand, run_cp);
+
+fn make_cp_command() -> Box<Command> {
+    let arg_src = Argument::new_string("source", true, false, "MFS, /ipfs/ or /ipns/ path to copy");
+    let arg_dst = Argument::new_string("dest", true, false, "MFS path to copy to");
+
+    Box::new(CpCommand::new("cp", vec![], vec![arg_src, arg_dst], CpHelpText, vec![]))
+}
+
+fn run_mv(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let src = &req.string_arg("source").unwrap()[0];
+    let dst = &req.string_arg("dest").unwrap()[0];
+    node.mfs().mv(src, dst)
+}
+
+ipfs_command!(MvCommand, run_mv);
+
+fn make_mv_command() -> Box<Command> {
+    let arg_src = Argument::new_string("source", true, false, "Path to move");
+    let arg_dst = Argument::new_string("dest", true, false, "Path to move to");
+
+    Box::new(MvCommand::new("mv", vec![], vec![arg_src, arg_dst], MvHelpText, vec![]))
+}
+
+fn run_rm(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let recursive = req.bool_option("recursive");
+    for path in req.string_arg("path").unwrap() {
+        try!(node.mfs().rm(path, recursive));
+    }
+    Ok(())
+}
+
+ipfs_command!(RmCommand, run_rm);
+
+fn make_rm_command() -> Box<Command> {
+    let recursive = commands::Opt::new_bool(vec!["recursive", "r"], "Remove directories too");
+    let arg_path = Argument::new_string("path", true, true, "Files or directories to remove");
+
+    Box::new(RmCommand::new("rm", vec![recursive], vec![arg_path], RmHelpText, vec![]))
+}
+
+fn run_stat(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let path = &req.string_arg("path").unwrap()[0];
+
+    let stat = try!(node.mfs().stat(path));
+    try!(writeln!(req, "{}", stat.hash));
+    try!(writeln!(req, "Size: {}", stat.size));
+    try!(writeln!(req, "CumulativeSize: {}", stat.cumulative_size));
+    try!(writeln!(req, "ChildBlocks: {}", stat.blocks));
+    try!(writeln!(req, "Type: {}", unixfs::type_name(stat.ty)));
+    if let Some(mime_type) = stat.mime_type {
+        try!(writeln!(req, "MimeType: {}", mime_type));
+    }
+    if stat.attributes.mode.is_some() {
+        try!(writeln!(req, "Mode: {}", stat.attributes.mode_string()));
+    }
+    if stat.attributes.mtime.is_some() {
+        try!(writeln!(req, "Mtime: {}", stat.attributes.mtime_string()));
+    }
+    Ok(())
+}
+
+ipfs_command!(StatCommand, run_stat);
+
+fn make_stat_command() -> Box<Command> {
+    let arg_path = Argument::new_string("path", true, false, "Path to show the status of");
+
+    Box::new(StatCommand::new("stat", vec![], vec![arg_path], StatHelpText, vec![]))
+}
+
+fn run_flush(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let hash = try!(node.mfs().flush(optional_path(req)));
+    writeln!(req, "{}", hash)
+}
+
+ipfs_command!(FlushCommand, run_flush);
+
+fn make_flush_command() -> Box<Command> {
+    let arg_path = Argument::new_string("path", false, false, "Path to flush (default /)");
+
+    Box::new(FlushCommand::new("flush", vec![], vec![arg_path], FlushHelpText, vec![]))
+}
+
+// The "path" argument, or / if it wasn't given
+fn optional_path(req: &request::Request) -> &str {
+    match req.string_arg("path") {
+        Some(paths) if !paths.is_empty() => &paths[0],
+        _ => "/",
+    }
+}
@@ -0,0 +1,64 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use exporter::ExportOptions;
+
+const HELP_TEXT: HelpText = HelpText {
+    tagline: "Download IPFS objects",
+    synopsis: "",
+    short_desc: r#"
+Writes the file or directory named by <ipfs-path> to the filesystem, at
+--output or under its own name in the current directory. Symlinks are
+written as symlinks, unless -L is given: they're then replaced by what they
+point to, which must be within the object being written. Permissions and
+modification times kept by 'ipfs add --preserve-mode --preserve-mtime' are
+restored. The setuid, setgid and sticky bits are left out unless
+--special-bits is given.
+"#,
+};
+
+fn run(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+
+    let path = &req.string_arg("ipfs-path").unwrap()[0];
+    let output = match req.option("output") {
+        Some(&request::Opt::String(ref s)) => s.clone(),
+        _ => default_output(path),
+    };
+    let options = ExportOptions {
+        dereference: req.bool_option("dereference"),
+        special_bits: req.bool_option("special-bits"),
+    };
+
+    try!(writeln!(req, "Saving file(s) to {}", output));
+    node.get(path, &output, &options)
+}
+
+// The last component of `path`, e.g. the hash of /ipfs/<hash>
+fn default_output(path: &str) -> String {
+    path.trim_right_matches('/').rsplit('/').next().unwrap().to_string()
+}
+
+ipfs_command!(GetCommand, run);
+
+pub fn make_command() -> Box<Command> {
+    let arg_path = Argument::new_string("ipfs-path",
+                                        true,
+                                        false,
+                                        "The path to the IPFS object(s) to be outputted")
+                       .enable_stdin();
+    let output = commands::Opt::new_string(vec!["output", "o"],
+                                           "The path where the output should be stored");
+    let dereference = commands::Opt::new_bool(vec!["dereference", "L"],
+                                              "Write what symlinks point to instead of the \
+                                               symlinks");
+    let special_bits = commands::Opt::new_bool(vec!["special-bits"],
+                                               "Keep the setuid, setgid and sticky bits of \
+                                                modes");
+
+    Box::new(GetCommand::new("get",
+                             vec![output, dereference, special_bits],
+                             vec![arg_path],
+                             HELP_TEXT,
+                             vec![]))
+}
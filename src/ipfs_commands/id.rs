@@ -0,0 +1,100 @@
+use commands::{HelpText, Command, Argument};
+use commands::request;
+use crypto;
+use error::Error;
+use multiaddr::{Multiaddr, Protocol};
+use peer::{self, PeerId};
+use swarm::identify::{self, IdentifyInfo};
+
+use rustc_serialize::base64::{self, ToBase64};
+use rustc_serialize::json;
+
+const IdHelpText: HelpText = HelpText {
+    tagline: "Show ipfs node id info",
+    synopsis: "ipfs id [<peerID>]",
+    short_desc: r#"
+'ipfs id' outputs information about a peer as JSON: its ID, public key,
+the addresses it listens on, its agent and protocol versions and the
+protocols it supports. Without <peerID>, it describes this node; the
+addresses and protocols are only known when the daemon is running.
+
+Other peers are asked through the identify protocol, which requires the
+daemon to be running. They're looked up in the DHT if we're not
+connected to them.
+"#,
+};
+
+// Field names as in go-ipfs
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct IdOutput {
+    ID: String,
+    PublicKey: String,
+    Addresses: Vec<String>,
+    AgentVersion: String,
+    ProtocolVersion: String,
+    Protocols: Vec<String>,
+}
+
+impl IdOutput {
+    fn new(peer: &PeerId, info: IdentifyInfo) -> Self {
+        IdOutput {
+            ID: peer.to_string(),
+            PublicKey: info.public_key.to_base64(base64::STANDARD),
+            Addresses: info.listen_addrs.into_iter().map(|addr| with_peer_id(addr, peer)).collect(),
+            AgentVersion: info.agent_version,
+            ProtocolVersion: info.protocol_version,
+            Protocols: info.protocols,
+        }
+    }
+}
+
+fn run_id(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let peer = match req.string_arg("peerID") {
+        Some(args) => Some(try!(peer::parse_peer_id(&args[0]))),
+        None => None,
+    };
+
+    let output = match peer {
+        Some(ref peer) if peer != node.peer_id() => {
+            try!(node.connect_peer(peer));
+            let info = try!(identify::identify(try!(node.get_swarm()), peer));
+            IdOutput::new(peer, info)
+        }
+        _ => {
+            let info = match node.get_swarm() {
+                Ok(swarm) => IdentifyInfo::local(swarm, None),
+                Err(_) => {
+                    let key = crypto::load_private_key(&try!(node.config().private_key()));
+                    IdentifyInfo {
+                        public_key: key.save_pub(),
+                        listen_addrs: vec![],
+                        protocols: vec![],
+                        observed_addr: None,
+                        protocol_version: identify::PROTOCOL_VERSION.to_string(),
+                        agent_version: identify::AGENT_VERSION.to_string(),
+                    }
+                }
+            };
+            IdOutput::new(node.peer_id(), info)
+        }
+    };
+    writeln!(req, "{}", json::as_pretty_json(&output))
+}
+
+ipfs_command!(IdCommand, run_id);
+
+pub fn make_command() -> Box<Command> {
+    let arg_peer = Argument::new_string("peerID",
+                                        false,
+                                        false,
+                                        "Peer ID of the node to look up");
+
+    Box::new(IdCommand::new("id", vec![], vec![arg_peer], IdHelpText, vec![]))
+}
+
+fn with_peer_id(mut addr: Multiaddr, peer: &PeerId) -> String {
+    addr.push(Protocol::P2p(peer.clone()));
+    addr.to_string()
+}
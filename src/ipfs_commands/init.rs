@@ -1,7 +1,9 @@
 use commands::{self, HelpText, Command};
 use commands::request;
 use config;
+use error::Error;
 use fsrepo;
+use swarm::pnet::Psk;
 use util;
 
 use std::fs::{self, File};
@@ -11,14 +13,19 @@ use std::path;
 const InitHelpText: HelpText = HelpText {
     tagline: "Initializes IPFS config file",
     synopsis: "",
-    short_desc: "Initializes IPFS configuration files and generates a new keypair.",
+    short_desc: r#"
+Initializes IPFS configuration files and generates a new keypair.
+
+With --swarm-key, a pre-shared key for a new private network is also
+generated, in the repo's swarm.key file. Copy it to the repos of the other
+nodes of the network, which then only connect to each other.
+"#,
 };
 
-fn run(req: &request::Request) -> Result<(), String> {
+fn run(req: &request::Request) -> Result<(), Error> {
     let repo_dir = req.context.repo_dir.clone();
     if try!(fsrepo::is_locked(repo_dir.clone())) {
-        return Err("Another process has locked the repo. Unable to continue."
-                       .to_string());
+        return Err(Error::Locked(repo_dir));
     }
 
     try!(check_and_prepare_repo_dir(repo_dir.clone()));
@@ -27,20 +34,28 @@ fn run(req: &request::Request) -> Result<(), String> {
         if req.option("f").is_some() {
             try!(fsrepo::remove(&repo_dir));
             try!(util::ensure_dir_writable(&repo_dir).map_err(|e| {
-                format!("Error ensuring repo directory is writable after forced \
-                         removal: {}",
-                        e)
+                Error::Io("Error ensuring repo directory is writable after forced removal"
+                              .to_string(),
+                          e)
             }));
         } else {
-            return Err("IPFS repo already exists.\nReinitializing would overwrite your \
-                        keys.\n(Use -f to force reinitialization.)"
-                           .to_string());
+            return Err(Error::AlreadyInitialized(repo_dir));
         }
     }
 
-    let config = config::init(config::DEFAULT_KEYPAIR_NUM_BITS);
+    let mut config = config::init(config::DEFAULT_KEYPAIR_NUM_BITS);
+    // the public bootstrap peers aren't part of a new private network
+    if req.option("swarm-key").is_some() {
+        config.bootstrap_mut().peers.clear();
+    }
+    try!(fsrepo::init(repo_dir.clone(), &config));
 
-    fsrepo::init(repo_dir, &config)
+    if req.option("swarm-key").is_some() {
+        let psk = Psk::generate();
+        try!(fsrepo::write_swarm_key(&repo_dir, &psk));
+        try!(writeln!(req, "Generated swarm key {}", psk.fingerprint()));
+    }
+    Ok(())
 }
 
 ipfs_command!(InitCommand, run);
@@ -50,34 +65,37 @@ pub fn make_command() -> Box<Command> {
         vec!["f", "force"],
         "Overwrite existing configuration (if it exists)"
     );
+    let swarm_key = commands::Opt::new_bool(
+        vec!["swarm-key"],
+        "Generate a swarm key for a new private network"
+    );
 
-
-    Box::new(InitCommand::new("init", vec![force], vec![], InitHelpText, vec![]))
+    Box::new(InitCommand::new("init", vec![force, swarm_key], vec![], InitHelpText, vec![]))
 }
 
 
 // if the directory exists, try creating a file in it.
 // if the directory doesnt exist, try to create it
 // if either of these fail, return an error
-fn check_and_prepare_repo_dir(mut repo_path: path::PathBuf) -> Result<(), String> {
+fn check_and_prepare_repo_dir(mut repo_path: path::PathBuf) -> Result<(), Error> {
     match fs::metadata(&repo_path) {
         Err(e) => {
             match e.kind() {
                 io::ErrorKind::NotFound => {
                     fs::create_dir_all(repo_path).map_err(|e| {
-                        format!("Error creating repo directory: {}", e)
+                        Error::Io("Error creating repo directory".to_string(), e)
                     })
                 }
-                _ => Err(format!("Error checking repo directory: {}", e)),
+                _ => Err(Error::Io("Error checking repo directory".to_string(), e)),
             }
         }
         Ok(_) => {
             repo_path.push("test");
             // discard the File as we don't need it.
             try!(File::create(&repo_path)
-                     .map_err(|e| format!("Error creating test file: {}", e)));
+                     .map_err(|e| Error::Io("Error creating test file".to_string(), e)));
             fs::remove_file(&repo_path)
-                .map_err(|e| format!("Error removing test file: {}", e))
+                .map_err(|e| Error::Io("Error removing test file".to_string(), e))
         }
     }
 }
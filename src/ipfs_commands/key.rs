@@ -0,0 +1,273 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use crypto::{KeyType, PrivateKey};
+use error::Error;
+use keystore;
+
+use std::fs::File;
+use std::io::{Read, Write};
+
+const DEFAULT_RSA_BITS: usize = 2048;
+const MIN_RSA_BITS: i32 = 2048;
+const FORMAT_PEM: &'static str = "pem-pkcs8-cleartext";
+const FORMAT_PROTOBUF: &'static str = "libp2p-protobuf-cleartext";
+
+const KeyHelpText: HelpText = HelpText {
+    tagline: "Create and list IPNS name keypairs",
+    synopsis: "ipfs key <command>",
+    short_desc: r#"
+'ipfs key' manages the keys IPNS names can be published with, besides the
+node's own key ("self"). Keys are kept in the keystore directory of the
+repo. The name of a key, as published with 'ipfs name publish --key', is
+its ID.
+
+    ipfs key gen <name>             Generate a new key
+    ipfs key list                   List the keys
+    ipfs key rm <name>...           Remove keys
+    ipfs key rename <old> <new>     Rename a key
+    ipfs key export <name>          Output a key
+    ipfs key import <name> <file>   Import a key
+"#,
+};
+
+const GenHelpText: HelpText = HelpText {
+    tagline: "Create a new keypair",
+    synopsis: "ipfs key gen [--type rsa|ed25519] [--size <bits>] <name>",
+    short_desc: r#"
+'ipfs key gen' generates a key, stores it under <name> and outputs its ID.
+RSA keys are 2048 bits unless --size is given; --size doesn't apply to
+Ed25519 keys.
+"#,
+};
+
+const ListHelpText: HelpText = HelpText {
+    tagline: "List all local keypairs",
+    synopsis: "ipfs key list [-l]",
+    short_desc: r#"
+'ipfs key list' outputs the names of the keys, starting with "self". With
+-l, each name is preceded by the key's ID.
+"#,
+};
+
+const RmHelpText: HelpText = HelpText {
+    tagline: "Remove a keypair",
+    synopsis: "ipfs key rm <name>...",
+    short_desc: r#"
+'ipfs key rm' removes the keys named <name> from the keystore, outputting
+the ID and name of each. Records already published with them stay valid
+until they expire.
+"#,
+};
+
+const RenameHelpText: HelpText = HelpText {
+    tagline: "Rename a keypair",
+    synopsis: "ipfs key rename <old> <new>",
+    short_desc: r#"
+'ipfs key rename' stores the key named <old> under <new>, which must not
+be taken. The key's ID doesn't change.
+"#,
+};
+
+const ExportHelpText: HelpText = HelpText {
+    tagline: "Export a keypair",
+    synopsis: "ipfs key export [--format <format>] <name>",
+    short_desc: r#"
+'ipfs key export' outputs the private key named <name>, unencrypted, in
+the libp2p protobuf encoding (libp2p-protobuf-cleartext, the default) or
+as PEM (pem-pkcs8-cleartext). Anyone with the exported key can publish
+under its name, so keep it safe.
+
+    ipfs key export mykey > mykey.key
+"#,
+};
+
+const ImportHelpText: HelpText = HelpText {
+    tagline: "Import a key and print its ID",
+    synopsis: "ipfs key import <name> <file>",
+    short_desc: r#"
+'ipfs key import' stores the private key in <file> under <name>. The file
+holds a key as output by 'ipfs key export', in either format.
+"#,
+};
+
+fn run_key(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(KeyCommand, run_key);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(KeyCommand::new("key",
+                             vec![],
+                             vec![],
+                             KeyHelpText,
+                             vec![make_gen_command(),
+                                  make_list_command(),
+                                  make_rm_command(),
+                                  make_rename_command(),
+                                  make_export_command(),
+                                  make_import_command()]))
+}
+
+fn run_gen(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let name = &req.string_arg("name").unwrap()[0];
+    try!(keystore::check_name(name));
+    if try!(node.keystore().has(name)) {
+        return Err(Error::InvalidArgument(format!("A key named {} already exists", name)));
+    }
+
+    let key_type = try!(KeyType::parse(req.string_option("type").unwrap_or("rsa")));
+    let num_bits = match (key_type, req.option("size")) {
+        (_, None) => DEFAULT_RSA_BITS,
+        (KeyType::Rsa, Some(&request::Opt::Int(n))) if n >= MIN_RSA_BITS => n as usize,
+        (KeyType::Rsa, Some(_)) => {
+            return Err(Error::InvalidArgument(format!("RSA keys must be at least {} bits",
+                                                      MIN_RSA_BITS)))
+        }
+        (_, Some(_)) => {
+            return Err(Error::InvalidArgument("--size only applies to RSA keys".to_string()))
+        }
+    };
+
+    let key = PrivateKey::generate(key_type, num_bits);
+    try!(node.keystore().put(name, &key));
+    writeln!(req, "{}", key.public_key().id())
+}
+
+ipfs_command!(GenCommand, run_gen);
+
+fn make_gen_command() -> Box<Command> {
+    let key_type = commands::Opt::new_string(vec!["type", "t"],
+                                             "Type of the key: rsa (default) or ed25519");
+    let size = commands::Opt::new_int(vec!["size", "s"], "Size of the key in bits (RSA only)");
+    let arg_name = Argument::new_string("name", true, false, "Name of the key to create");
+
+    Box::new(GenCommand::new("gen", vec![key_type, size], vec![arg_name], GenHelpText, vec![]))
+}
+
+fn run_list(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let long = req.bool_option("l");
+
+    let mut names = vec![keystore::SELF_KEY_NAME.to_string()];
+    names.extend(try!(node.keystore().list()));
+    for name in names {
+        if long {
+            let id = try!(node.get_key(&name)).public_key().id();
+            try!(writeln!(req, "{} {}", id, name));
+        } else {
+            try!(writeln!(req, "{}", name));
+        }
+    }
+    Ok(())
+}
+
+ipfs_command!(ListCommand, run_list);
+
+fn make_list_command() -> Box<Command> {
+    let long = commands::Opt::new_bool(vec!["l"], "Show the IDs of the keys");
+
+    Box::new(ListCommand::new("list", vec![long], vec![], ListHelpText, vec![]))
+}
+
+fn run_rm(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    for name in req.string_arg("name").unwrap() {
+        let id = try!(node.keystore().get(name)).public_key().id();
+        try!(node.keystore().delete(name));
+        try!(writeln!(req, "{} {}", id, name));
+    }
+    Ok(())
+}
+
+ipfs_command!(RmCommand, run_rm);
+
+fn make_rm_command() -> Box<Command> {
+    let arg_name = Argument::new_string("name", true, true, "Names of the keys to remove");
+
+    Box::new(RmCommand::new("rm", vec![], vec![arg_name], RmHelpText, vec![]))
+}
+
+fn run_rename(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let old = &req.string_arg("old").unwrap()[0];
+    let new = &req.string_arg("new").unwrap()[0];
+
+    let id = try!(node.keystore().get(old)).public_key().id();
+    try!(node.keystore().rename(old, new));
+    writeln!(req, "Key {} renamed to {}", id, new)
+}
+
+ipfs_command!(RenameCommand, run_rename);
+
+fn make_rename_command() -> Box<Command> {
+    let arg_old = Argument::new_string("old", true, false, "Name of the key to rename");
+    let arg_new = Argument::new_string("new", true, false, "New name of the key");
+
+    Box::new(RenameCommand::new("rename",
+                                vec![],
+                                vec![arg_old, arg_new],
+                                RenameHelpText,
+                                vec![]))
+}
+
+fn run_export(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let name = &req.string_arg("name").unwrap()[0];
+    let key = try!(node.get_key(name));
+
+    let data = match req.string_option("format").unwrap_or(FORMAT_PROTOBUF) {
+        FORMAT_PROTOBUF => key.encode(),
+        FORMAT_PEM => try!(key.to_pem()).into_bytes(),
+        format => return Err(Error::InvalidArgument(format!("Unknown key format {}", format))),
+    };
+    let mut output = req.output();
+    output.write_all(&data)
+          .and_then(|_| output.flush())
+          .map_err(|e| Error::Io(format!("Error writing key {}", name), e))
+}
+
+ipfs_command!(ExportCommand, run_export);
+
+fn make_export_command() -> Box<Command> {
+    let format = commands::Opt::new_string(vec!["format", "f"],
+                                           "libp2p-protobuf-cleartext (default) or \
+                                            pem-pkcs8-cleartext");
+    let arg_name = Argument::new_string("name", true, false, "Name of the key to export");
+
+    Box::new(ExportCommand::new("export", vec![format], vec![arg_name], ExportHelpText, vec![]))
+}
+
+fn run_import(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let name = &req.string_arg("name").unwrap()[0];
+    let path = &req.file_arg("file").unwrap()[0];
+
+    let mut data = Vec::new();
+    try!(File::open(path)
+             .and_then(|mut f| f.read_to_end(&mut data))
+             .map_err(|e| Error::Io(format!("Error reading key file {:?}", path), e)));
+    let key = if data.starts_with(b"-----BEGIN") {
+        try!(PrivateKey::from_pem(&data))
+    } else {
+        try!(PrivateKey::decode(&data))
+    };
+
+    try!(node.keystore().put(name, &key));
+    writeln!(req, "{}", key.public_key().id())
+}
+
+ipfs_command!(ImportCommand, run_import);
+
+fn make_import_command() -> Box<Command> {
+    let arg_name = Argument::new_string("name", true, false, "Name to store the key under");
+    let arg_file = Argument::new_file("file", true, false, "File holding the key");
+
+    Box::new(ImportCommand::new("import",
+                                vec![],
+                                vec![arg_name, arg_file],
+                                ImportHelpText,
+                                vec![]))
+}
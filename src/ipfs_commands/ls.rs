@@ -0,0 +1,215 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use merkledag::{DagService, Node};
+use unixfs::{self, directory, pb};
+
+use rustc_serialize::json;
+
+const HELP_TEXT: HelpText = HelpText {
+    tagline: "List directory contents for Unix filesystem objects",
+    synopsis: "ipfs ls [-v] [--resolve-type=false] [--size=false] [-r] <ipfs-path>...",
+    short_desc: r#"
+Displays the links of the objects named by <ipfs-path>, one per line as
+<cid> <size> <name>, separated by tabs. The entries of directories are
+listed by name (sharded directories included), and the links of any other
+object as they are. Directories are shown with a trailing slash and no
+size, and symlinks along with what they point to. With -r, the contents of
+directories are listed too, named by their path under <ipfs-path>.
+
+Each link is fetched to find its type and size. --resolve-type=false and
+--size=false leave those out, so that links needn't be fetched. -v prints a
+header line.
+
+With --enc=json, the output is an object as in go-ipfs: {"Objects": [{"Hash":
+<ipfs-path>, "Links": [{"Name", "Hash", "Size", "Type", "Target"}]}]}, where
+Type is the unixfs type (1 for directories, 2 for files, 4 for symlinks), or
+-1 if it wasn't resolved.
+"#,
+};
+
+// The Type of links whose type wasn't resolved
+const UNKNOWN_TYPE: i32 = -1;
+
+// Field names as in go-ipfs
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct LsLink {
+    Name: String,
+    Hash: String,
+    Size: u64, // the size of files; 0 for anything else
+    Type: i32,
+    Target: String, // what a symlink points to
+}
+
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct LsObject {
+    Hash: String,
+    Links: Vec<LsLink>,
+}
+
+#[allow(non_snake_case)]
+#[derive(RustcEncodable)]
+struct LsOutput {
+    Objects: Vec<LsObject>,
+}
+
+struct ListOptions {
+    resolve_type: bool,
+    size: bool,
+    recursive: bool,
+}
+
+fn run(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let options = ListOptions {
+        resolve_type: req.option("resolve-type").is_none() || req.bool_option("resolve-type"),
+        size: req.option("size").is_none() || req.bool_option("size"),
+        recursive: req.bool_option("recursive"),
+    };
+    let json = try!(json_output(req));
+
+    let mut objects = Vec::new();
+    for path in req.string_arg("ipfs-path").unwrap() {
+        let object = try!(node.resolve_path(path));
+        let mut links = Vec::new();
+        try!(list(node.dagservice(), &object, "", &options, &mut links));
+        objects.push(LsObject {
+            Hash: path.clone(),
+            Links: links,
+        });
+    }
+    if json {
+        return writeln!(req, "{}", json::as_pretty_json(&LsOutput { Objects: objects }));
+    }
+
+    let headers = req.bool_option("headers");
+    for (i, object) in objects.iter().enumerate() {
+        if objects.len() > 1 {
+            if i > 0 {
+                try!(writeln!(req, ""));
+            }
+            try!(writeln!(req, "{}:", object.Hash));
+        }
+        if headers && options.size {
+            try!(writeln!(req, "Hash\tSize\tName"));
+        } else if headers {
+            try!(writeln!(req, "Hash\tName"));
+        }
+        for link in &object.Links {
+            let is_dir = link.Type == pb::Data_DataType::Directory as i32;
+            let name = if is_dir {
+                format!("{}/", link.Name)
+            } else if !link.Target.is_empty() {
+                format!("{} -> {}", link.Name, link.Target)
+            } else {
+                link.Name.clone()
+            };
+            if !options.size {
+                try!(writeln!(req, "{}\t{}", link.Hash, name));
+            } else if link.Type == pb::Data_DataType::File as i32 || link.Type == UNKNOWN_TYPE {
+                try!(writeln!(req, "{}\t{}\t{}", link.Hash, link.Size, name));
+            } else {
+                try!(writeln!(req, "{}\t-\t{}", link.Hash, name));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Adds the links of `object` to `links`, their names prefixed with `prefix`.
+// With -r, the links of each directory among them follow it.
+fn list(ds: &DagService,
+        object: &Node,
+        prefix: &str,
+        options: &ListOptions,
+        links: &mut Vec<LsLink>)
+        -> Result<(), Error> {
+    let is_dir = match unixfs::node_data(object) {
+        Ok(data) => directory::is_directory(data.get_Type()),
+        Err(_) => false, // not a unixfs object
+    };
+    let entries = if is_dir {
+        try!(directory::entries(ds, object))
+    } else {
+        object.get_links().to_vec()
+    };
+
+    for link in entries {
+        let name = format!("{}{}", prefix, link.get_name());
+        let mut ls_link = LsLink {
+            Name: name.clone(),
+            Hash: link.cid().to_string(),
+            Size: 0,
+            Type: UNKNOWN_TYPE,
+            Target: String::new(),
+        };
+        if !options.resolve_type && !options.size && !options.recursive {
+            links.push(ls_link);
+            continue;
+        }
+
+        let child = try!(link.get_node(ds));
+        if unixfs::node_data(&child).is_err() {
+            links.push(ls_link);
+            continue;
+        }
+        let child = try!(unixfs::unwrap_metadata(ds, child));
+        let data = try!(unixfs::node_data(&child));
+        let ty = match data.get_Type() {
+            pb::Data_DataType::Raw | pb::Data_DataType::File => pb::Data_DataType::File,
+            pb::Data_DataType::HAMTShard => pb::Data_DataType::Directory,
+            ty => ty,
+        };
+        if options.size && ty == pb::Data_DataType::File {
+            ls_link.Size = data.get_filesize();
+        }
+        if options.resolve_type {
+            ls_link.Type = ty as i32;
+            ls_link.Target = try!(unixfs::symlink_target(&child)).unwrap_or(String::new());
+        }
+        links.push(ls_link);
+
+        if options.recursive && ty == pb::Data_DataType::Directory {
+            try!(list(ds, &child, &format!("{}/", name), options, links));
+        }
+    }
+    Ok(())
+}
+
+fn json_output(req: &request::Request) -> Result<bool, Error> {
+    match req.option("encoding") {
+        Some(&request::Opt::String(ref enc)) if enc == "json" => Ok(true),
+        Some(&request::Opt::String(ref enc)) if enc != "text" => {
+            Err(Error::InvalidArgument(format!("Unknown encoding {}: expected text or json", enc)))
+        }
+        _ => Ok(false),
+    }
+}
+
+ipfs_command!(LsCommand, run);
+
+pub fn make_command() -> Box<Command> {
+    let arg_path = Argument::new_string("ipfs-path",
+                                        true,
+                                        true,
+                                        "The path(s) to the IPFS object(s) to list links from")
+                       .enable_stdin();
+    let headers = commands::Opt::new_bool(vec!["headers", "v"], "Print table headers");
+    let resolve_type = commands::Opt::new_bool(vec!["resolve-type"],
+                                               "Fetch links to find their types (default \
+                                                true)");
+    let size = commands::Opt::new_bool(vec!["size"],
+                                       "Fetch links to find the sizes of files (default true)");
+    let recursive = commands::Opt::new_bool(vec!["recursive", "r"],
+                                            "List the contents of directories recursively");
+    let encoding = commands::Opt::new_string(vec!["encoding", "enc"],
+                                             "Output encoding: text (default) or json");
+
+    Box::new(LsCommand::new("ls",
+                            vec![headers, resolve_type, size, recursive, encoding],
+                            vec![arg_path],
+                            HELP_TEXT,
+                            vec![]))
+}
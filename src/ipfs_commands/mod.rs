@@ -1,6 +1,14 @@
+use api;
 use commands::{CommandName, Opt, Argument, HelpText, Command, CommandOptions};
+use commands::cli::{self, ParseError};
+use commands::request;
+use core::IpfsNode;
+use error::Error;
+use fsrepo;
 
 use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
 use std::slice;
 
 pub struct CommandInfo {
@@ -46,6 +54,12 @@ impl CommandInfo {
     fn get_subcommand(&self, subcmd: &str) -> Option<&Command> {
         self.subcommands.get(subcmd).map(|cmd| &**cmd)
     }
+
+    fn get_subcommands(&self) -> Vec<&Command> {
+        let mut cmds: Vec<&Command> = self.subcommands.values().map(|cmd| &**cmd).collect();
+        cmds.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        cmds
+    }
 }
 
 // Defines a type that satisfies the `commands::Command` trait
@@ -92,8 +106,12 @@ macro_rules! ipfs_command {
                 self.info.get_subcommand(subcmd)
             }
 
+            fn get_subcommands(&self) -> Vec<&::commands::Command> {
+                self.info.get_subcommands()
+            }
+
 
-            fn run(&self, req: &::commands::request::Request) -> Result<(), String> {
+            fn run(&self, req: &::commands::request::Request) -> Result<(), ::error::Error> {
                 $f(req)
             }
         }
@@ -101,7 +119,97 @@ macro_rules! ipfs_command {
 }
 
 pub mod add;
+pub mod bitswap;
+pub mod bootstrap;
+pub mod cat;
+pub mod commands;
+pub mod daemon;
+pub mod dht;
+pub mod dns;
 pub mod file;
+pub mod files;
+pub mod get;
+pub mod id;
 pub mod init;
+pub mod key;
+pub mod ls;
+pub mod name;
 pub mod object;
+pub mod ping;
+pub mod pubsub;
 pub mod root;
+pub mod swarm;
+
+// Commands that always run in this process, even when a daemon is running
+const LOCAL_COMMANDS: &'static [CommandName] = &["init", "daemon", "commands"];
+
+struct CommandInvocation<'a, 'b> {
+    pub request: request::Request<'a, 'b>,
+    pub command: &'a Command,
+    pub path: Vec<CommandName>,
+}
+
+impl<'a, 'b> CommandInvocation<'a, 'b> {
+    fn from_cli_parse<I>(args: I,
+                         root: &'a Command,
+                         context: request::Context<'b>)
+                         -> Result<Self, ParseError>
+        where I: Iterator<Item = String>
+    {
+        let (path, cmd, args, opts) = try!(cli::parse(args, root));
+        let req = request::Request::new(cmd, args, opts, context);
+        Ok(CommandInvocation {
+            request: req,
+            command: cmd,
+            path: path,
+        })
+    }
+
+    fn runs_locally(&self) -> bool {
+        match self.path.first() {
+            None => true,
+            Some(name) => LOCAL_COMMANDS.contains(name),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), Error> {
+        if !self.runs_locally() {
+            let repo_dir = self.request.context.repo_dir.clone();
+            if let Some(addr) = try!(api::daemon_address(&repo_dir)) {
+                let token = api::read_token_file(&repo_dir);
+                let token = token.as_ref().map(|t| &t[..]);
+                match api::run_remote(&addr, token, &self.path, &self.request, &mut io::stdout()) {
+                    // left behind by a daemon that's no longer running
+                    Err(Error::Io(_, ref e)) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+                    result => return result,
+                }
+            }
+        }
+        self.command.run(&mut self.request)
+    }
+}
+
+/// Runs the `ipfs` command line with `args` (not including the program
+/// name), as the `ipfs` binary does. Commands other than `init`, `daemon`
+/// and `commands` are sent to the daemon when one is running.
+pub fn run_cli<I>(args: I) -> Result<(), Error>
+    where I: Iterator<Item = String>
+{
+    let root = root::make_command();
+
+    let path = try!(fsrepo::best_known_path());
+    let node = try!(construct_node(path.clone()));
+    let context = request::Context::new(path, node.as_ref());
+
+    let mut invoc = try!(CommandInvocation::from_cli_parse(args, &*root, context));
+    invoc.run()
+}
+
+// Commands like `init` run without a repo, so a missing one isn't an error here
+fn construct_node(repo_path: PathBuf) -> Result<Option<IpfsNode>, Error> {
+    match IpfsNode::open(repo_path) {
+        Err(Error::NotInitialized(_)) => Ok(None),
+        Err(e) => Err(e),
+        Ok(node) => Ok(Some(node)),
+    }
+}
@@ -0,0 +1,141 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use ipns;
+
+use std::time::Duration;
+
+const NameHelpText: HelpText = HelpText {
+    tagline: "Publish and resolve IPNS names",
+    synopsis: "ipfs name <command>",
+    short_desc: r#"
+IPNS is a naming system for mutable content. A name is the ID of a key
+(the peer's own, or one from 'ipfs key'), and points to a path through a
+record signed with the key. Records are published to the DHT when the
+daemon is running, and kept locally either way.
+
+    ipfs name publish <path>     Point our name to <path>
+    ipfs name resolve [<name>]   Show the path a name points to
+"#,
+};
+
+const PublishHelpText: HelpText = HelpText {
+    tagline: "Publish an IPNS name",
+    synopsis: "ipfs name publish [--key <name>] [--lifetime <time>] [--ttl <time>] <path>",
+    short_desc: r#"
+'ipfs name publish' points the name of a key to <path>, which is an
+/ipfs/ or /ipns/ path, or a bare hash. The key is the node's own ("self")
+by default, or one made with 'ipfs key gen' or 'ipfs key import'. The
+record is valid for --lifetime (24h by default); durations are written
+like 24h, 30m or 10s.
+
+    > ipfs name publish --lifetime 1h30m QmatmE9msSfkKxoffpHwNLNKgwZG8eT9Bud6YoPab52vpy
+    Published to QmbCMUZw6JFeZ7Wp9jkzbye3Fzp2GGcPgC3nmeUjfVF87n: /ipfs/QmatmE9msSf...
+"#,
+};
+
+const ResolveHelpText: HelpText = HelpText {
+    tagline: "Resolve an IPNS name",
+    synopsis: "ipfs name resolve [<name>]",
+    short_desc: r#"
+'ipfs name resolve' outputs the path <name> points to, following names
+that point to other names. <name> defaults to our own peer ID. Names are
+looked up in the DHT when the daemon is running, and among the records we
+published otherwise. Domain names are resolved through DNSLink (see
+'ipfs dns').
+"#,
+};
+
+fn run_name(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(NameCommand, run_name);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(NameCommand::new("name",
+                              vec![],
+                              vec![],
+                              NameHelpText,
+                              vec![make_publish_command(), make_resolve_command()]))
+}
+
+fn run_publish(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let key = req.string_option("key").unwrap_or("self");
+    let lifetime = match req.string_option("lifetime") {
+        None => Duration::from_secs(ipns::DEFAULT_LIFETIME_SECS),
+        Some(s) => try!(parse_duration(s)),
+    };
+    let ttl = match req.string_option("ttl") {
+        None => Duration::from_secs(ipns::DEFAULT_TTL_SECS),
+        Some(s) => try!(parse_duration(s)),
+    };
+
+    let path = &req.string_arg("path").unwrap()[0];
+    let (name, entry) = try!(node.publish_name(key, path, lifetime, ttl));
+    writeln!(req, "Published to {}: {}", name, try!(entry.path()))
+}
+
+ipfs_command!(PublishCommand, run_publish);
+
+fn make_publish_command() -> Box<Command> {
+    let key = commands::Opt::new_string(vec!["key", "k"],
+                                        "Name of the key to publish with (default: self)");
+    let lifetime = commands::Opt::new_string(vec!["lifetime", "t"],
+                                             "How long the record is valid (default: 24h)");
+    let ttl = commands::Opt::new_string(vec!["ttl"],
+                                        "How long the record may be cached (default: 1h)");
+    let arg_path = Argument::new_string("path", true, false, "The path to publish");
+
+    Box::new(PublishCommand::new("publish",
+                                 vec![key, lifetime, ttl],
+                                 vec![arg_path],
+                                 PublishHelpText,
+                                 vec![]))
+}
+
+fn run_resolve(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let name = match req.string_arg("name") {
+        Some(args) => args[0].clone(),
+        None => node.peer_id().to_string(),
+    };
+    let path = try!(node.resolve_name(&name));
+    writeln!(req, "{}", path)
+}
+
+ipfs_command!(ResolveCommand, run_resolve);
+
+fn make_resolve_command() -> Box<Command> {
+    let arg_name = Argument::new_string("name", false, false, "The IPNS name to resolve");
+
+    Box::new(ResolveCommand::new("resolve", vec![], vec![arg_name], ResolveHelpText, vec![]))
+}
+
+// Parses durations like 24h, 1h30m or 10s
+fn parse_duration(s: &str) -> Result<Duration, Error> {
+    let invalid = || Error::InvalidArgument(format!("Invalid duration {}", s));
+    let mut secs = 0;
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c >= '0' && c <= '9' {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let n: u64 = try!(digits.parse().map_err(|_| invalid()));
+        secs += n * unit;
+        digits.clear();
+    }
+    if !digits.is_empty() || secs == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(secs))
+}
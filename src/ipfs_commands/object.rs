@@ -1,7 +1,7 @@
+use cid::Cid;
 use commands::{self, HelpText, Command};
 use commands::request;
-
-use rust_multihash::Multihash;
+use error::Error;
 
 const ObjectHelpText: HelpText = HelpText {
     tagline: "Interact with ipfs objects",
@@ -22,7 +22,7 @@ is a base58 encoded multihash.
 "#,
 };
 
-fn run_object(req: &request::Request) -> Result<(), String> {
+fn run_object(req: &request::Request) -> Result<(), Error> {
     unimplemented!()
 }
 
@@ -39,7 +39,7 @@ pub fn make_command() -> Box<Command> {
 #[derive(Debug)]
 struct Link {
     name: String,
-    hash: Multihash,
+    hash: Cid,
     size: u64,
 }
 
@@ -49,19 +49,18 @@ struct Node<'a> {
     data: &'a [u8],
 }
 
-fn run_get(req: &request::Request) -> Result<(), String> {
+fn run_get(req: &request::Request) -> Result<(), Error> {
     let node = try!(req.context.get_node());
 
     let path = &req.string_arg("key").unwrap()[0];
-    let mh = try!(Multihash::from_base58_str(path));
-    let mut dag_node = try!(node.dagservice.get(&mh));
+    let dag_node = try!(node.resolve_path(path));
 
     let mut links = Vec::new();
     for link in dag_node.get_links() {
         // TODO: no cloning?
         links.push(Link {
             name: link.clone_name(),
-            hash: link.clone_hash(),
+            hash: link.cid(),
             size: link.get_target_size(),
         });
     }
@@ -71,7 +70,7 @@ fn run_get(req: &request::Request) -> Result<(), String> {
         data: dag_node.get_data(),
     };
 
-    println!("{:?}", view_node);
+    try!(writeln!(req, "{:?}", view_node));
 
     Ok(())
 }
@@ -84,7 +83,7 @@ fn make_get_command() -> Box<Command> {
         true,
         false,
         "Key of the object to retrieve (in base58-encoded multihash format)"
-    );
+    ).enable_stdin();
 
     Box::new(GetCommand::new("get", vec![], vec![arg_key], GetHelpText, vec![]))
 }
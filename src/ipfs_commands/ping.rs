@@ -0,0 +1,83 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use multiaddr::Multiaddr;
+use peer;
+use swarm::ping::Pinger;
+
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_COUNT: i32 = 10;
+
+const PingHelpText: HelpText = HelpText {
+    tagline: "Send echo request packets to IPFS hosts",
+    synopsis: "ipfs ping [--count <n>] <peer>",
+    short_desc: r#"
+'ipfs ping' measures the round trip time to a peer over the ping
+protocol, sending one ping a second. <peer> is either a peer ID, looked up
+in the DHT if we're not connected to it, or a multiaddr ending with
+/p2p/<peer id>. This command requires the daemon to be running.
+
+    ipfs ping -n 3 QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ
+"#,
+};
+
+fn run_ping(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let count = match req.option("count") {
+        Some(&request::Opt::Int(n)) if n > 0 => n,
+        Some(&request::Opt::Int(n)) => {
+            return Err(Error::InvalidArgument(format!("Invalid number of pings: {}", n)))
+        }
+        _ => DEFAULT_COUNT,
+    };
+
+    let arg = &req.string_arg("peer").unwrap()[0];
+    let peer = if arg.starts_with('/') {
+        let addr = try!(Multiaddr::parse(arg));
+        if addr.peer_id().is_none() {
+            return Err(Error::InvalidArgument(format!("{} does not end with /p2p/<peer id>",
+                                                      addr)));
+        }
+        try!(node.connect(&addr))
+    } else {
+        let peer = try!(peer::parse_peer_id(arg));
+        if peer == *node.peer_id() {
+            return Err(Error::InvalidArgument("Cannot ping self".to_string()));
+        }
+        try!(node.connect_peer(&peer));
+        peer
+    };
+
+    try!(writeln!(req, "PING {}.", peer));
+    let mut pinger = try!(Pinger::new(try!(node.get_swarm()), &peer));
+    let mut total = Duration::new(0, 0);
+    for i in 0..count {
+        if i > 0 {
+            thread::sleep(Duration::from_secs(1));
+        }
+        let rtt = try!(pinger.ping());
+        total = total + rtt;
+        try!(writeln!(req, "Pong received: time={}", format_millis(&rtt)));
+    }
+    writeln!(req, "Average latency: {}", format_millis(&(total / count as u32)))
+}
+
+ipfs_command!(PingCommand, run_ping);
+
+pub fn make_command() -> Box<Command> {
+    let count = commands::Opt::new_int(vec!["count", "n"], "Number of ping messages to send");
+    let arg_peer = Argument::new_string("peer",
+                                        true,
+                                        false,
+                                        "ID or multiaddr of the peer to be pinged");
+
+    Box::new(PingCommand::new("ping", vec![count], vec![arg_peer], PingHelpText, vec![]))
+}
+
+// e.g. 0.123 ms
+fn format_millis(d: &Duration) -> String {
+    let millis = d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0;
+    format!("{:.3} ms", millis)
+}
@@ -0,0 +1,173 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use error::Error;
+use pubsub::PubSub;
+
+use std::io::{self, Write};
+
+const PubsubHelpText: HelpText = HelpText {
+    tagline: "Send and receive messages on pubsub topics",
+    synopsis: "ipfs pubsub <command>",
+    short_desc: r#"
+Peers publish messages to topics, and every peer subscribed to a topic
+receives them. Messages are routed with gossipsub (or floodsub, if
+pubsub.router is "floodsub" in the config), and signed with the
+publishing node's key. These commands require the daemon to be running.
+
+    ipfs pubsub pub <topic> <data>   Publish a message to <topic>
+    ipfs pubsub sub <topic>          Print the messages published to <topic>
+    ipfs pubsub ls                   List the topics we're subscribed to
+    ipfs pubsub peers [<topic>]      List the peers we exchange messages with
+"#,
+};
+
+const PubHelpText: HelpText = HelpText {
+    tagline: "Publish a message to a topic",
+    synopsis: "ipfs pubsub pub <topic> <data>",
+    short_desc: r#"
+'ipfs pubsub pub' publishes <data> to <topic>, sending it to the peers
+subscribed to it. Nothing tells whether anyone received it.
+"#,
+};
+
+const SubHelpText: HelpText = HelpText {
+    tagline: "Subscribe to a topic",
+    synopsis: "ipfs pubsub sub [--count <n>] <topic>",
+    short_desc: r#"
+'ipfs pubsub sub' subscribes to <topic>, and prints the data of each
+message published to it on a line, including those we publish ourselves.
+It runs until interrupted, or until --count messages were received.
+
+    > ipfs pubsub sub weather
+    sunny
+"#,
+};
+
+const LsHelpText: HelpText = HelpText {
+    tagline: "List subscribed topics",
+    synopsis: "ipfs pubsub ls",
+    short_desc: r#"
+'ipfs pubsub ls' lists the topics the node is subscribed to, through a
+running 'ipfs pubsub sub'.
+"#,
+};
+
+const PeersHelpText: HelpText = HelpText {
+    tagline: "List pubsub peers",
+    synopsis: "ipfs pubsub peers [<topic>]",
+    short_desc: r#"
+'ipfs pubsub peers' lists the connected peers taking part in pubsub, or
+only those subscribed to <topic>.
+"#,
+};
+
+fn run_pubsub(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(PubsubCommand, run_pubsub);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(PubsubCommand::new("pubsub",
+                                vec![],
+                                vec![],
+                                PubsubHelpText,
+                                vec![make_pub_command(),
+                                     make_sub_command(),
+                                     make_ls_command(),
+                                     make_peers_command()]))
+}
+
+fn run_pub(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let topic = &req.string_arg("topic").unwrap()[0];
+    let data = &req.string_arg("data").unwrap()[0];
+    try!(node.get_pubsub()).publish(topic, data.as_bytes().to_vec())
+}
+
+ipfs_command!(PubCommand, run_pub);
+
+fn make_pub_command() -> Box<Command> {
+    let arg_topic = Argument::new_string("topic", true, false, "The topic to publish to");
+    let arg_data = Argument::new_string("data", true, false, "The message to publish");
+
+    Box::new(PubCommand::new("pub", vec![], vec![arg_topic, arg_data], PubHelpText, vec![]))
+}
+
+fn run_sub(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let count = match req.option("count") {
+        Some(&request::Opt::Int(n)) if n > 0 => Some(n as usize),
+        Some(&request::Opt::Int(n)) => {
+            return Err(Error::InvalidArgument(format!("Invalid number of messages: {}", n)))
+        }
+        _ => None,
+    };
+
+    let topic = &req.string_arg("topic").unwrap()[0];
+    let subscription = PubSub::subscribe(try!(node.get_pubsub()), topic);
+    for (i, msg) in subscription.enumerate() {
+        try!(write_message(req, &msg.data)
+                 .map_err(|e| Error::Io("Error writing command output".to_string(), e)));
+        if count == Some(i + 1) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Writes a message's data on a line, flushing it so that it isn't held back
+fn write_message(req: &request::Request, data: &[u8]) -> io::Result<()> {
+    let mut output = req.output();
+    try!(output.write_all(data));
+    try!(output.write_all(b"\n"));
+    output.flush()
+}
+
+ipfs_command!(SubCommand, run_sub);
+
+fn make_sub_command() -> Box<Command> {
+    let count = commands::Opt::new_int(vec!["count", "n"],
+                                       "Exit after receiving this many messages");
+    let arg_topic = Argument::new_string("topic", true, false, "The topic to subscribe to");
+
+    Box::new(SubCommand::new("sub", vec![count], vec![arg_topic], SubHelpText, vec![]))
+}
+
+fn run_ls(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    for topic in try!(node.get_pubsub()).topics() {
+        try!(writeln!(req, "{}", topic));
+    }
+    Ok(())
+}
+
+ipfs_command!(LsCommand, run_ls);
+
+fn make_ls_command() -> Box<Command> {
+    Box::new(LsCommand::new("ls", vec![], vec![], LsHelpText, vec![]))
+}
+
+fn run_peers(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+    let topic = req.string_arg("topic").map(|args| &args[0][..]);
+    let mut peers: Vec<String> = try!(node.get_pubsub())
+                                     .peers(topic)
+                                     .iter()
+                                     .map(|peer| peer.to_string())
+                                     .collect();
+    peers.sort();
+    for peer in peers {
+        try!(writeln!(req, "{}", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(PeersCommand, run_peers);
+
+fn make_peers_command() -> Box<Command> {
+    let arg_topic = Argument::new_string("topic", false, false, "Only list peers of this topic");
+
+    Box::new(PeersCommand::new("peers", vec![], vec![arg_topic], PeersHelpText, vec![]))
+}
@@ -1,6 +1,7 @@
 use super::*;
 use commands::{self, HelpText, Command};
 use commands::request;
+use error::Error;
 
 const HELP_TEXT: HelpText = HelpText {
     tagline: "global p2p merkle-dag filesystem",
@@ -22,6 +23,7 @@ DATA STRUCTURE COMMANDS
     block         Interact with raw blocks in the datastore
     object        Interact with raw dag nodes
     file          Interact with Unix filesystem objects
+    files         Interact with files in the mutable file system
 
 ADVANCED COMMANDS
 
@@ -29,6 +31,7 @@ ADVANCED COMMANDS
     mount         Mount an ipfs read-only mountpoint
     resolve       Resolve any type of name
     name          Publish or resolve IPNS names
+    key           Create and list IPNS name keypairs
     dns           Resolve DNS links
     pin           Pin objects to local storage
     repo gc       Garbage collect unpinned objects
@@ -37,9 +40,11 @@ NETWORK COMMANDS
 
     id            Show info about ipfs peers
     bootstrap     Add or remove bootstrap peers
+    bitswap       Inspect bitswap state
     swarm         Manage connections to the p2p network
     dht           Query the dht for values or peers
     ping          Measure the latency of a connection
+    pubsub        Send and receive messages on pubsub topics
     diag          Print diagnostics
 
 TOOL COMMANDS
@@ -53,13 +58,13 @@ Use 'ipfs <command> --help' to learn more about each command.
 "#,
 };
 
-fn run(req: &request::Request) -> Result<(), String> {
+fn run(req: &request::Request) -> Result<(), Error> {
     let help_text = req.command.get_help_text();
-    println!("{}\n{}\n{}",
+    writeln!(req,
+             "{}\n{}\n{}",
              help_text.tagline,
              help_text.short_desc,
-             help_text.synopsis);
-    Ok(())
+             help_text.synopsis)
 }
 
 ipfs_command!(RootCommand, run);
@@ -85,8 +90,24 @@ pub fn make_command() -> Box<commands::Command> {
         vec![
             init::make_command(),
             add::make_command(),
+            bitswap::make_command(),
+            bootstrap::make_command(),
+            cat::make_command(),
+            daemon::make_command(),
+            dht::make_command(),
+            dns::make_command(),
             file::make_command(),
+            files::make_command(),
+            get::make_command(),
+            id::make_command(),
+            key::make_command(),
+            ls::make_command(),
+            name::make_command(),
             object::make_command(),
+            ping::make_command(),
+            pubsub::make_command(),
+            swarm::make_command(),
+            super::commands::make_command(),
         ]
     ))
 }
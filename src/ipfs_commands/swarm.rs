@@ -0,0 +1,433 @@
+use commands::{self, HelpText, Command, Argument};
+use commands::request;
+use config;
+use error::Error;
+use fsrepo;
+use multiaddr::{Multiaddr, Protocol};
+use swarm::filter::IpFilter;
+
+use std::time::Duration;
+
+const SwarmHelpText: HelpText = HelpText {
+    tagline: "Interact with the swarm",
+    synopsis: "ipfs swarm <command>",
+    short_desc: r#"
+'ipfs swarm' is a tool to manipulate the network swarm. The swarm is the
+component that opens, listens for, and maintains connections to other
+ipfs peers on the internet. These commands require the daemon to be
+running.
+
+    ipfs swarm peers          List the peers we're connected to
+    ipfs swarm addrs          List the addresses of known peers
+    ipfs swarm addrs local    List the addresses we listen on
+    ipfs swarm connect        Open a connection to a peer
+    ipfs swarm disconnect     Close the connection to a peer
+    ipfs swarm filters        Manage the address filters
+    ipfs swarm reachability   Check whether peers can dial us directly
+"#,
+};
+
+const PeersHelpText: HelpText = HelpText {
+    tagline: "List peers with open connections",
+    synopsis: "ipfs swarm peers [--verbose] [--latency]",
+    short_desc: r#"
+'ipfs swarm peers' lists the set of peers this node is connected to, as
+the address of each connection followed by the peer's ID. --latency adds
+the round trip time last measured to the peer, and --verbose adds the
+latency, the direction of the connection, the number of open streams and
+how long it's been idle.
+"#,
+};
+
+const AddrsHelpText: HelpText = HelpText {
+    tagline: "List known addresses. Useful for debugging",
+    synopsis: "ipfs swarm addrs",
+    short_desc: r#"
+'ipfs swarm addrs' lists the addresses of each peer we know of: the
+addresses we've connected to them at.
+"#,
+};
+
+const AddrsLocalHelpText: HelpText = HelpText {
+    tagline: "List local addresses",
+    synopsis: "ipfs swarm addrs local [--id]",
+    short_desc: r#"
+'ipfs swarm addrs local' lists the addresses this node listens on. With
+--id, each address ends with our peer ID.
+"#,
+};
+
+const ConnectHelpText: HelpText = HelpText {
+    tagline: "Open connection to a given address",
+    synopsis: "ipfs swarm connect <address>...",
+    short_desc: r#"
+'ipfs swarm connect' opens a new direct connection to a peer address. The
+address format is a multiaddr, optionally ending with the peer's ID:
+
+    ipfs swarm connect /ip4/104.131.131.82/tcp/4001/p2p/QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ
+
+Peers can also be connected to through a relay they have a reservation
+on, with an address of the form <relay address>/p2p/<relay
+id>/p2p-circuit/p2p/<peer id>.
+"#,
+};
+
+const DisconnectHelpText: HelpText = HelpText {
+    tagline: "Close connection to a given address",
+    synopsis: "ipfs swarm disconnect <address>...",
+    short_desc: r#"
+'ipfs swarm disconnect' closes the connection to a peer. The address must
+end with the peer's ID, as printed by 'ipfs swarm peers':
+
+    ipfs swarm disconnect /ip4/104.131.131.82/tcp/4001/p2p/QmaCpDMGvV2BGHeYERUEnRQAwe3N8SzbUtfsmvsqQLuvuJ
+
+The peer may reconnect, or be reconnected to, later.
+"#,
+};
+
+const FiltersHelpText: HelpText = HelpText {
+    tagline: "Manipulate address filters",
+    synopsis: "ipfs swarm filters",
+    short_desc: r#"
+'ipfs swarm filters' lists the address filters. Connections to and from
+addresses matching a filter are refused. Filters are CIDR ranges written
+as multiaddrs:
+
+    /ip4/192.168.0.0/ipcidr/16
+    /ip6/fd00::/ipcidr/8
+
+Filters are kept in the config (swarm.addr_filters), so they also apply
+the next time the daemon starts.
+"#,
+};
+
+const FiltersAddHelpText: HelpText = HelpText {
+    tagline: "Add an address filter",
+    synopsis: "ipfs swarm filters add <address>...",
+    short_desc: r#"
+'ipfs swarm filters add' adds filters for the given CIDR ranges, and saves
+them to the config. Existing connections are not closed.
+
+    ipfs swarm filters add /ip4/192.168.0.0/ipcidr/16
+"#,
+};
+
+const FiltersRmHelpText: HelpText = HelpText {
+    tagline: "Remove an address filter",
+    synopsis: "ipfs swarm filters rm <address>...",
+    short_desc: r#"
+'ipfs swarm filters rm' removes the filters for the given CIDR ranges,
+including from the config.
+
+    ipfs swarm filters rm /ip4/192.168.0.0/ipcidr/16
+"#,
+};
+
+const ReachabilityHelpText: HelpText = HelpText {
+    tagline: "Check whether the node can be dialed directly",
+    synopsis: "ipfs swarm reachability",
+    short_desc: r#"
+'ipfs swarm reachability' asks connected peers to dial us back at the
+addresses we listen on (AutoNAT). It prints 'public' and the address a
+peer reached us at, 'private' if peers failed to reach us (we're behind a
+NAT or firewall, and can only be reached through relays), or 'unknown' if
+no peer answered.
+"#,
+};
+
+fn run_swarm(req: &request::Request) -> Result<(), Error> {
+    let help_text = req.command.get_help_text();
+    writeln!(req, "{}\n{}", help_text.tagline, help_text.short_desc)
+}
+
+ipfs_command!(SwarmCommand, run_swarm);
+
+pub fn make_command() -> Box<Command> {
+    Box::new(SwarmCommand::new("swarm",
+                               vec![],
+                               vec![],
+                               SwarmHelpText,
+                               vec![make_peers_command(),
+                                    make_addrs_command(),
+                                    make_connect_command(),
+                                    make_disconnect_command(),
+                                    make_filters_command(),
+                                    make_reachability_command()]))
+}
+
+fn run_peers(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+    let verbose = req.bool_option("verbose");
+    let latency = verbose || req.bool_option("latency");
+
+    let mut conns = swarm.connections();
+    conns.sort_by(|a, b| a.peer.to_string().cmp(&b.peer.to_string()));
+    for conn in conns {
+        let mut addr = conn.remote_addr.clone();
+        addr.push(Protocol::P2p(conn.peer.clone()));
+        let mut line = addr.to_string();
+        if latency {
+            line.push_str(&format!(" {}",
+                                   conn.latency()
+                                       .map(|d| format_duration(&d))
+                                       .unwrap_or("n/a".to_string())));
+        }
+        if verbose {
+            line.push_str(&format!(" {} streams={} idle={}",
+                                   if conn.outbound { "outbound" } else { "inbound" },
+                                   conn.num_streams(),
+                                   format_duration(&conn.idle_time())));
+        }
+        try!(writeln!(req, "{}", line));
+    }
+    Ok(())
+}
+
+ipfs_command!(PeersCommand, run_peers);
+
+fn make_peers_command() -> Box<Command> {
+    let verbose = commands::Opt::new_bool(vec!["verbose", "v"],
+                                          "Display all extra information");
+    let latency = commands::Opt::new_bool(vec!["latency"],
+                                          "Also list information about latency to each peer");
+
+    Box::new(PeersCommand::new("peers", vec![verbose, latency], vec![], PeersHelpText, vec![]))
+}
+
+fn run_addrs(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+
+    let mut peers = swarm.known_peers();
+    peers.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    for peer in peers {
+        let mut addrs: Vec<String> = swarm.peer_addrs(&peer)
+                                          .iter()
+                                          .map(|a| a.to_string())
+                                          .collect();
+        addrs.sort();
+        try!(writeln!(req, "{} ({})", peer, addrs.len()));
+        for addr in addrs {
+            try!(writeln!(req, "\t{}", addr));
+        }
+    }
+    Ok(())
+}
+
+ipfs_command!(AddrsCommand, run_addrs);
+
+fn make_addrs_command() -> Box<Command> {
+    Box::new(AddrsCommand::new("addrs",
+                               vec![],
+                               vec![],
+                               AddrsHelpText,
+                               vec![make_addrs_local_command()]))
+}
+
+fn run_addrs_local(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+    let show_id = req.bool_option("id");
+
+    for mut addr in swarm.listen_addrs() {
+        if show_id {
+            addr.push(Protocol::P2p(swarm.local_peer().clone()));
+        }
+        try!(writeln!(req, "{}", addr));
+    }
+    Ok(())
+}
+
+ipfs_command!(AddrsLocalCommand, run_addrs_local);
+
+fn make_addrs_local_command() -> Box<Command> {
+    let id = commands::Opt::new_bool(vec!["id"], "Show peer ID in addresses");
+
+    Box::new(AddrsLocalCommand::new("local", vec![id], vec![], AddrsLocalHelpText, vec![]))
+}
+
+fn run_connect(req: &request::Request) -> Result<(), Error> {
+    let node = try!(req.context.get_node());
+
+    for s in req.string_arg("address").unwrap() {
+        let addr = try!(Multiaddr::parse(s));
+        let peer = try!(node.connect(&addr));
+        try!(writeln!(req, "connect {} success", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(ConnectCommand, run_connect);
+
+fn make_connect_command() -> Box<Command> {
+    let arg_address = Argument::new_string("address",
+                                           true,
+                                           true,
+                                           "Address of peer to connect to")
+                          .enable_stdin();
+
+    Box::new(ConnectCommand::new("connect", vec![], vec![arg_address], ConnectHelpText, vec![]))
+}
+
+fn run_disconnect(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+
+    for s in req.string_arg("address").unwrap() {
+        let addr = try!(Multiaddr::parse(s));
+        let peer = match addr.peer_id() {
+            None => {
+                return Err(Error::InvalidArgument(format!("{} does not end with /p2p/<peer id>",
+                                                          addr)))
+            }
+            Some(peer) => peer.clone(),
+        };
+        try!(swarm.disconnect(&peer));
+        try!(writeln!(req, "disconnect {} success", peer));
+    }
+    Ok(())
+}
+
+ipfs_command!(DisconnectCommand, run_disconnect);
+
+fn make_disconnect_command() -> Box<Command> {
+    let arg_address = Argument::new_string("address",
+                                           true,
+                                           true,
+                                           "Address of peer to disconnect from")
+                          .enable_stdin();
+
+    Box::new(DisconnectCommand::new("disconnect",
+                                    vec![],
+                                    vec![arg_address],
+                                    DisconnectHelpText,
+                                    vec![]))
+}
+
+fn run_filters(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+
+    for filter in swarm.filters.list() {
+        try!(writeln!(req, "{}", filter));
+    }
+    Ok(())
+}
+
+ipfs_command!(FiltersCommand, run_filters);
+
+fn make_filters_command() -> Box<Command> {
+    Box::new(FiltersCommand::new("filters",
+                                 vec![],
+                                 vec![],
+                                 FiltersHelpText,
+                                 vec![make_filters_add_command(), make_filters_rm_command()]))
+}
+
+fn run_filters_add(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+    let filters = try!(parse_filters(req));
+
+    for filter in &filters {
+        swarm.filters.add(filter.clone());
+    }
+    try!(update_config_filters(req, |config_filters| {
+        for filter in &filters {
+            let s = filter.to_string();
+            if !config_filters.contains(&s) {
+                config_filters.push(s);
+            }
+        }
+    }));
+
+    for filter in filters {
+        try!(writeln!(req, "add {} success", filter));
+    }
+    Ok(())
+}
+
+ipfs_command!(FiltersAddCommand, run_filters_add);
+
+fn make_filters_add_command() -> Box<Command> {
+    let arg_address = Argument::new_string("address",
+                                           true,
+                                           true,
+                                           "Multiaddr to filter, such as /ip4/10.0.0.0/ipcidr/8");
+
+    Box::new(FiltersAddCommand::new("add",
+                                    vec![],
+                                    vec![arg_address],
+                                    FiltersAddHelpText,
+                                    vec![]))
+}
+
+fn run_filters_rm(req: &request::Request) -> Result<(), Error> {
+    let swarm = try!(try!(req.context.get_node()).get_swarm());
+    let filters = try!(parse_filters(req));
+
+    for filter in &filters {
+        if !swarm.filters.remove(filter) {
+            return Err(Error::NotFound(format!("address filter {}", filter)));
+        }
+    }
+    try!(update_config_filters(req, |config_filters| {
+        // compare parsed filters, since the config may be hand-edited
+        config_filters.retain(|s| {
+            match IpFilter::parse(s) {
+                Ok(f) => !filters.contains(&f),
+                Err(_) => true,
+            }
+        });
+    }));
+
+    for filter in filters {
+        try!(writeln!(req, "remove {} success", filter));
+    }
+    Ok(())
+}
+
+ipfs_command!(FiltersRmCommand, run_filters_rm);
+
+fn make_filters_rm_command() -> Box<Command> {
+    let arg_address = Argument::new_string("address",
+                                           true,
+                                           true,
+                                           "Multiaddr filter to remove");
+
+    Box::new(FiltersRmCommand::new("rm",
+                                   vec![],
+                                   vec![arg_address],
+                                   FiltersRmHelpText,
+                                   vec![]))
+}
+
+fn parse_filters(req: &request::Request) -> Result<Vec<IpFilter>, Error> {
+    req.string_arg("address").unwrap().iter().map(|s| IpFilter::parse(s)).collect()
+}
+
+// Applies `f` to the address filters in the repo's config file
+fn update_config_filters<F>(req: &request::Request, f: F) -> Result<(), Error>
+    where F: FnOnce(&mut Vec<String>)
+{
+    let config_path = config::repo_path_to_config_file(req.context.repo_dir.clone());
+    let mut cfg = try!(fsrepo::read_config_file(&config_path));
+    f(&mut cfg.swarm_mut().addr_filters);
+    fsrepo::update_config_file(&config_path, &cfg)
+}
+
+// Formats a duration in milliseconds, e.g. 12.345ms
+fn format_duration(d: &Duration) -> String {
+    let millis = d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0;
+    format!("{:.3}ms", millis)
+}
+
+fn run_reachability(req: &request::Request) -> Result<(), Error> {
+    let reachability = try!(try!(req.context.get_node()).reachability());
+    writeln!(req, "{}", reachability)
+}
+
+ipfs_command!(ReachabilityCommand, run_reachability);
+
+fn make_reachability_command() -> Box<Command> {
+    Box::new(ReachabilityCommand::new("reachability",
+                                      vec![],
+                                      vec![],
+                                      ReachabilityHelpText,
+                                      vec![]))
+}
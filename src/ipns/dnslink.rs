@@ -0,0 +1,40 @@
+// DNSLink: a domain points to a path with a TXT record of the form
+// dnslink=/ipfs/<hash> (or /ipns/<name>) on _dnslink.<domain>, or on the
+// domain itself.
+
+use dns::Resolver;
+use error::Error;
+
+const DNSLINK_PREFIX: &'static str = "dnslink=";
+const DNSLINK_SUBDOMAIN: &'static str = "_dnslink.";
+
+// Whether `name` looks like a domain name rather than a peer ID
+pub fn is_domain(name: &str) -> bool {
+    name.contains('.') &&
+    name.trim_right_matches('.').split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63 &&
+        label.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+// The path the DNSLink record of `domain` points to
+pub fn resolve(resolver: &Resolver, domain: &str) -> Result<String, Error> {
+    match lookup(resolver, &format!("{}{}", DNSLINK_SUBDOMAIN, domain)) {
+        Ok(path) => return Ok(path),
+        Err(e) => debug!("No DNSLink record on {}{}: {}", DNSLINK_SUBDOMAIN, domain, e),
+    }
+    lookup(resolver, domain)
+}
+
+fn lookup(resolver: &Resolver, name: &str) -> Result<String, Error> {
+    for record in try!(resolver.lookup_txt(name)) {
+        let record = record.trim();
+        if record.starts_with(DNSLINK_PREFIX) {
+            let path = record[DNSLINK_PREFIX.len()..].trim();
+            if path.starts_with('/') {
+                return Ok(path.to_string());
+            }
+        }
+    }
+    Err(Error::NotFound(format!("DNSLink record for {}", name)))
+}
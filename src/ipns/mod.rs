@@ -0,0 +1,287 @@
+// IPNS: mutable names. A name is the ID of a peer (or of a keystore key), and
+// points to a path through a record signed with the name's key. Records
+// have a sequence number, so newer ones win, and an end of life after which
+// they're invalid. Records are stored in the DHT under /ipns/<binary peer
+// id>; the encoding is described in ipns.proto. Domain names with DNSLink
+// records can be resolved like names too (see dnslink).
+
+use codec::{PbReader, PbWriter};
+use crypto::{PrivateKey, PublicKey};
+use dht::record::{self, Validator};
+use error::Error;
+use peer::PeerId;
+use util;
+
+use rust_multihash::Multihash;
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub use self::namesys::NameSystem;
+
+pub mod dnslink;
+pub mod namesys;
+
+pub const NAMESPACE: &'static str = "ipns";
+pub const DEFAULT_LIFETIME_SECS: u64 = 24 * 60 * 60;
+pub const DEFAULT_TTL_SECS: u64 = 60 * 60;
+
+const VALIDITY_EOL: i32 = 0;
+const VALIDITY_EOL_NAME: &'static [u8] = b"EOL";
+
+#[derive(Clone, Debug)]
+pub struct IpnsEntry {
+    pub value: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub validity: Vec<u8>,
+    pub sequence: u64,
+    pub ttl: u64, // nanoseconds
+    pub public_key: Vec<u8>,
+}
+
+impl IpnsEntry {
+    // A record pointing to `value`, valid until `eol`
+    pub fn new(key: &PrivateKey,
+               value: &str,
+               sequence: u64,
+               eol: SystemTime,
+               ttl: Duration)
+               -> Self {
+        let validity = format_rfc3339(&eol).into_bytes();
+        let signature = key.sign(&signed_data(value.as_bytes(), &validity));
+        IpnsEntry {
+            value: value.as_bytes().to_vec(),
+            signature: signature,
+            validity: validity,
+            sequence: sequence,
+            ttl: ttl.as_secs() * 1_000_000_000 + ttl.subsec_nanos() as u64,
+            public_key: key.public_key().to_bytes(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut msg = PbWriter::new();
+        msg.bytes(1, &self.value)
+           .bytes(2, &self.signature)
+           .int32(3, VALIDITY_EOL)
+           .bytes(4, &self.validity)
+           .uint64(5, self.sequence)
+           .uint64(6, self.ttl)
+           .bytes(7, &self.public_key);
+        msg.into_bytes()
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut entry = IpnsEntry {
+            value: Vec::new(),
+            signature: Vec::new(),
+            validity: Vec::new(),
+            sequence: 0,
+            ttl: 0,
+            public_key: Vec::new(),
+        };
+        let mut validity_type = VALIDITY_EOL;
+
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => entry.value = try!(value.as_bytes()).to_vec(),
+                2 => entry.signature = try!(value.as_bytes()).to_vec(),
+                3 => validity_type = try!(value.as_i32()),
+                4 => entry.validity = try!(value.as_bytes()).to_vec(),
+                5 => entry.sequence = try!(value.as_u64()),
+                6 => entry.ttl = try!(value.as_u64()),
+                7 => entry.public_key = try!(value.as_bytes()).to_vec(),
+                _ => {}
+            }
+        }
+
+        if validity_type != VALIDITY_EOL {
+            return Err(Error::Decode(format!("Unknown IPNS validity type {}", validity_type),
+                                     None));
+        }
+        Ok(entry)
+    }
+
+    // The path the record points to
+    pub fn path(&self) -> Result<String, Error> {
+        String::from_utf8(self.value.clone())
+            .map_err(|e| Error::decode("Invalid UTF-8 in IPNS record value".to_string(), e))
+    }
+
+    pub fn eol(&self) -> Result<SystemTime, Error> {
+        parse_rfc3339(&String::from_utf8_lossy(&self.validity))
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::new(self.ttl / 1_000_000_000, (self.ttl % 1_000_000_000) as u32)
+    }
+
+    // Checks that the record was signed by the key of `name`, and hasn't
+    // expired
+    pub fn verify(&self, name: &PeerId) -> Result<(), Error> {
+        if self.public_key.is_empty() {
+            return Err(Error::InvalidArgument("IPNS record has no public key".to_string()));
+        }
+        if util::hash(&self.public_key) != *name {
+            return Err(Error::InvalidArgument(format!("IPNS record for {} was signed by \
+                                                       another key",
+                                                      name)));
+        }
+        let key = try!(PublicKey::from_bytes(&self.public_key));
+        if !key.verify(&signed_data(&self.value, &self.validity), &self.signature) {
+            return Err(Error::InvalidArgument(format!("Invalid signature on IPNS record for {}",
+                                                      name)));
+        }
+        if try!(self.eol()) < SystemTime::now() {
+            return Err(Error::InvalidArgument(format!("IPNS record for {} has expired", name)));
+        }
+        Ok(())
+    }
+}
+
+// The DHT key of the records for `name`
+pub fn record_key(name: &PeerId) -> Vec<u8> {
+    let mut key = format!("/{}/", NAMESPACE).into_bytes();
+    key.extend_from_slice(&name.clone().into_bytes());
+    key
+}
+
+fn signed_data(value: &[u8], validity: &[u8]) -> Vec<u8> {
+    let mut data = value.to_vec();
+    data.extend_from_slice(validity);
+    data.extend_from_slice(VALIDITY_EOL_NAME);
+    data
+}
+
+// Validates /ipns/<peer id> records: the best record has the highest
+// sequence number, then the latest end of life
+pub struct IpnsValidator;
+
+impl Validator for IpnsValidator {
+    fn validate(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        let name: PeerId = match record::split_key(key) {
+            Some((_, rest)) => Multihash::from_vec(rest.to_vec()),
+            None => return Err(Error::InvalidArgument("Invalid IPNS record key".to_string())),
+        };
+        try!(IpnsEntry::decode(value)).verify(&name)
+    }
+
+    fn select(&self, _key: &[u8], values: &[Vec<u8>]) -> usize {
+        let mut best: Option<(usize, IpnsEntry)> = None;
+        for (i, value) in values.iter().enumerate() {
+            let entry = match IpnsEntry::decode(value) {
+                Err(_) => continue,
+                Ok(entry) => entry,
+            };
+            let better = match best {
+                None => true,
+                Some((_, ref current)) => {
+                    match entry.sequence.cmp(&current.sequence) {
+                        Ordering::Equal => entry.eol().ok() > current.eol().ok(),
+                        ordering => ordering == Ordering::Greater,
+                    }
+                }
+            };
+            if better {
+                best = Some((i, entry));
+            }
+        }
+        best.map(|(i, _)| i).unwrap_or(0)
+    }
+}
+
+// Formats a time as in RFC 3339, in UTC with nanoseconds:
+// 2016-01-02T15:04:05.000000000Z
+pub fn format_rfc3339(time: &SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::new(0, 0));
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day / 60 % 60,
+            secs_of_day % 60,
+            since_epoch.subsec_nanos())
+}
+
+// Parses an RFC 3339 time, with or without fractional seconds, in UTC (Z)
+// or with an offset (+01:00)
+pub fn parse_rfc3339(s: &str) -> Result<SystemTime, Error> {
+    let invalid = || Error::InvalidArgument(format!("Invalid RFC 3339 time {}", s));
+    let b = s.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || (b[10] != b'T' && b[10] != b't') ||
+       b[13] != b':' || b[16] != b':' {
+        return Err(invalid());
+    }
+    let num = |start: usize, end: usize| -> Result<i64, Error> {
+        s[start..end].parse::<u32>().map(|n| n as i64).map_err(|_| invalid())
+    };
+    let days = days_from_civil(try!(num(0, 4)), try!(num(5, 7)), try!(num(8, 10)));
+    let mut secs = days * 86400 + try!(num(11, 13)) * 3600 + try!(num(14, 16)) * 60 +
+                   try!(num(17, 19));
+
+    // fractional seconds
+    let mut pos = 19;
+    let mut nanos = 0u32;
+    if b[pos] == b'.' {
+        pos += 1;
+        let start = pos;
+        while pos < b.len() && b[pos] >= b'0' && b[pos] <= b'9' {
+            pos += 1;
+        }
+        if pos == start {
+            return Err(invalid());
+        }
+        let digits = &s[start..if pos - start > 9 { start + 9 } else { pos }];
+        nanos = try!(digits.parse::<u32>().map_err(|_| invalid())) *
+                10u32.pow(9 - digits.len() as u32);
+    }
+
+    // time zone
+    match &s[pos..] {
+        "Z" | "z" => {}
+        offset if offset.len() == 6 && (offset.starts_with('+') || offset.starts_with('-')) &&
+                  offset.as_bytes()[3] == b':' => {
+            let minutes = try!(num(pos + 1, pos + 3)) * 60 + try!(num(pos + 4, pos + 6));
+            if offset.starts_with('+') {
+                secs -= minutes * 60;
+            } else {
+                secs += minutes * 60;
+            }
+        }
+        _ => return Err(invalid()),
+    }
+
+    if secs < 0 {
+        return Err(invalid());
+    }
+    Ok(UNIX_EPOCH + Duration::new(secs as u64, nanos))
+}
+
+// Days since the Unix epoch of a date in the proleptic Gregorian calendar
+// (Howard Hinnant's algorithm)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// The inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (if month <= 2 { 1 } else { 0 });
+    (year, month, day)
+}
@@ -0,0 +1,129 @@
+// Publishing and resolving IPNS names. The records we publish are kept in
+// the datastore as /ipns/<peer id>, and put to the DHT when the node is
+// online. Names are resolved through the DHT when online, and from the
+// datastore otherwise. Domain names are resolved through DNSLink.
+
+use crypto::PrivateKey;
+use datastore::Datastore;
+use dht::Dht;
+use dns::Resolver;
+use error::Error;
+use path::{IPFS_PREFIX, IPNS_PREFIX};
+use peer::{self, PeerId};
+
+use super::{IpnsEntry, dnslink, record_key};
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+// How many names a path may go through before reaching an /ipfs/ path
+const MAX_RESOLVE_DEPTH: usize = 32;
+const LOCAL_PREFIX: &'static str = "/ipns";
+
+pub struct NameSystem {
+    datastore: Arc<Datastore>,
+    dht: Option<Arc<Dht>>,
+    resolver: Arc<Resolver>,
+}
+
+impl NameSystem {
+    pub fn new(datastore: Arc<Datastore>, dht: Option<Arc<Dht>>, resolver: Arc<Resolver>) -> Self {
+        NameSystem {
+            datastore: datastore,
+            dht: dht,
+            resolver: resolver,
+        }
+    }
+
+    // Points the name of `key` to `value` for `lifetime`, returning the
+    // record. The sequence number follows that of the last record we
+    // published for the name.
+    pub fn publish(&self,
+                   key: &PrivateKey,
+                   value: &str,
+                   lifetime: Duration,
+                   ttl: Duration)
+                   -> Result<IpnsEntry, Error> {
+        let name = key.public_key().id();
+        let sequence = match try!(self.local_entry(&name)) {
+            None => 0,
+            Some(previous) => previous.sequence + 1,
+        };
+        let entry = IpnsEntry::new(key, value, sequence, SystemTime::now() + lifetime, ttl);
+        let encoded = entry.encode();
+        try!(self.datastore.put(&local_key(&name), &encoded));
+
+        if let Some(ref dht) = self.dht {
+            let stored = try!(dht.put_value(&record_key(&name), &encoded));
+            debug!("Stored IPNS record for {} with {} peers", name, stored);
+        }
+        Ok(entry)
+    }
+
+    // The path `name` points to
+    pub fn resolve_once(&self, name: &PeerId) -> Result<String, Error> {
+        if let Some(ref dht) = self.dht {
+            match dht.get_value(&record_key(name)) {
+                // the DHT validated the record
+                Ok(value) => return try!(IpnsEntry::decode(&value)).path(),
+                Err(e) => debug!("Error resolving {} through the DHT: {}", name, e),
+            }
+        }
+
+        match try!(self.local_entry(name)) {
+            None => Err(Error::NotFound(format!("IPNS record for {}", name))),
+            Some(entry) => {
+                try!(entry.verify(name));
+                entry.path()
+            }
+        }
+    }
+
+    // Resolves the names in `path` (/ipns/<name>/...) until it's an /ipfs/
+    // path. Names are peer IDs or domains. Other paths are returned as they
+    // are.
+    pub fn resolve(&self, path: &str) -> Result<String, Error> {
+        let mut path = path.to_string();
+        for _ in 0..MAX_RESOLVE_DEPTH {
+            if !path.starts_with(IPNS_PREFIX) {
+                return Ok(path);
+            }
+            let (name, rest) = {
+                let trimmed = &path[IPNS_PREFIX.len()..];
+                match trimmed.find('/') {
+                    None => (trimmed.to_string(), String::new()),
+                    Some(i) => (trimmed[..i].to_string(), trimmed[i..].to_string()),
+                }
+            };
+            let value = if dnslink::is_domain(&name) {
+                try!(dnslink::resolve(&*self.resolver, &name))
+            } else {
+                let name = try!(peer::parse_peer_id(&name).map_err(|_| {
+                    Error::InvalidPath(format!("{} (invalid IPNS name {})", path, name))
+                }));
+                try!(self.resolve_once(&name))
+            };
+            path = if value.starts_with('/') {
+                format!("{}{}", value, rest)
+            } else {
+                format!("{}{}{}", IPFS_PREFIX, value, rest)
+            };
+        }
+        Err(Error::InvalidPath(format!("{} (more than {} names to resolve)",
+                                       path,
+                                       MAX_RESOLVE_DEPTH)))
+    }
+
+    // The last record we published for `name`
+    fn local_entry(&self, name: &PeerId) -> Result<Option<IpnsEntry>, Error> {
+        match self.datastore.get(&local_key(name)) {
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+            Ok(value) => IpnsEntry::decode(&value).map(Some),
+        }
+    }
+}
+
+fn local_key(name: &PeerId) -> String {
+    format!("{}/{}", LOCAL_PREFIX, name)
+}
@@ -0,0 +1,206 @@
+use crypto::PrivateKey;
+use error::Error;
+
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+pub const KEYSTORE_DIR: &'static str = "keystore";
+/// The name of the node's own key, which isn't kept in the keystore
+pub const SELF_KEY_NAME: &'static str = "self";
+
+/// Named private keys, besides the node's own, used to publish IPNS names.
+/// Keys are kept either in a repo directory, one file per name holding the
+/// protobuf-encoded key and readable only by its owner, or in memory.
+pub struct Keystore {
+    storage: Storage,
+}
+
+enum Storage {
+    Dir(PathBuf),
+    Memory(RwLock<BTreeMap<String, Vec<u8>>>),
+}
+
+impl Keystore {
+    pub fn new(path: PathBuf) -> Self {
+        Keystore { storage: Storage::Dir(path) }
+    }
+
+    /// A Keystore that keeps keys in memory and never touches the disk
+    pub fn in_memory() -> Self {
+        Keystore { storage: Storage::Memory(RwLock::new(BTreeMap::new())) }
+    }
+
+    /// The names of the stored keys, in order
+    pub fn list(&self) -> Result<Vec<String>, Error> {
+        let path = match self.storage {
+            Storage::Dir(ref path) => path,
+            Storage::Memory(ref keys) => return Ok(keys.read().unwrap().keys().cloned().collect()),
+        };
+        let entries = match fs::read_dir(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Error::Io("Error reading keystore directory".to_string(), e)),
+            Ok(entries) => entries,
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = try!(entry.map_err(|e| {
+                Error::Io("Error reading keystore directory".to_string(), e)
+            }));
+            // skip anything that couldn't have been stored under a name
+            if let Ok(name) = entry.file_name().into_string() {
+                if check_name(&name).is_ok() {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn has(&self, name: &str) -> Result<bool, Error> {
+        try!(check_name(name));
+        match self.storage {
+            Storage::Dir(ref path) => Ok(path.join(name).is_file()),
+            Storage::Memory(ref keys) => Ok(keys.read().unwrap().contains_key(name)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<PrivateKey, Error> {
+        try!(check_name(name));
+        let data = match self.storage {
+            Storage::Dir(ref path) => {
+                let mut file = match File::open(path.join(name)) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                        return Err(not_found(name))
+                    }
+                    Err(e) => return Err(Error::Io(format!("Error opening key {}", name), e)),
+                    Ok(file) => file,
+                };
+                let mut data = Vec::new();
+                try!(file.read_to_end(&mut data)
+                         .map_err(|e| Error::Io(format!("Error reading key {}", name), e)));
+                data
+            }
+            Storage::Memory(ref keys) => {
+                match keys.read().unwrap().get(name) {
+                    None => return Err(not_found(name)),
+                    Some(data) => data.clone(),
+                }
+            }
+        };
+        PrivateKey::decode(&data)
+    }
+
+    /// Stores `key` under `name`, which must not be taken
+    pub fn put(&self, name: &str, key: &PrivateKey) -> Result<(), Error> {
+        try!(check_name(name));
+        let path = match self.storage {
+            Storage::Dir(ref path) => path,
+            Storage::Memory(ref keys) => {
+                let mut keys = keys.write().unwrap();
+                if keys.contains_key(name) {
+                    return Err(already_exists(name));
+                }
+                keys.insert(name.to_string(), key.encode());
+                return Ok(());
+            }
+        };
+
+        try!(DirBuilder::new()
+                 .recursive(true)
+                 .mode(0o700)
+                 .create(path)
+                 .map_err(|e| Error::Io("Error making keystore directory".to_string(), e)));
+        let mut file = match OpenOptions::new()
+                                 .write(true)
+                                 .create_new(true)
+                                 .mode(0o600)
+                                 .open(path.join(name)) {
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(already_exists(name))
+            }
+            Err(e) => return Err(Error::Io(format!("Error creating key file for {}", name), e)),
+            Ok(file) => file,
+        };
+        file.write_all(&key.encode())
+            .and_then(|_| file.sync_all())
+            .map_err(|e| Error::Io(format!("Error writing key {}", name), e))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), Error> {
+        try!(check_name(name));
+        match self.storage {
+            Storage::Dir(ref path) => {
+                match fs::remove_file(path.join(name)) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => Err(not_found(name)),
+                    Err(e) => Err(Error::Io(format!("Error removing key {}", name), e)),
+                    Ok(()) => Ok(()),
+                }
+            }
+            Storage::Memory(ref keys) => {
+                match keys.write().unwrap().remove(name) {
+                    None => Err(not_found(name)),
+                    Some(_) => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Moves the key stored under `old` to `new`, which must not be taken
+    pub fn rename(&self, old: &str, new: &str) -> Result<(), Error> {
+        try!(check_name(old));
+        try!(check_name(new));
+        let path = match self.storage {
+            Storage::Dir(ref path) => path,
+            Storage::Memory(ref keys) => {
+                let mut keys = keys.write().unwrap();
+                if keys.contains_key(new) {
+                    return Err(already_exists(new));
+                }
+                let data = match keys.remove(old) {
+                    None => return Err(not_found(old)),
+                    Some(data) => data,
+                };
+                keys.insert(new.to_string(), data);
+                return Ok(());
+            }
+        };
+
+        // linking, unlike renaming, fails rather than replacing another key
+        match fs::hard_link(path.join(old), path.join(new)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Err(not_found(old)),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(already_exists(new))
+            }
+            Err(e) => return Err(Error::Io(format!("Error renaming key {}", old), e)),
+            Ok(()) => {}
+        }
+        fs::remove_file(path.join(old))
+            .map_err(|e| Error::Io(format!("Error renaming key {}", old), e))
+    }
+}
+
+/// Key names are used as file names, so they can't contain `/` or start
+/// with `.`. "self" is reserved for the node's own key.
+pub fn check_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err(Error::InvalidArgument(format!("Invalid key name {:?}", name)));
+    }
+    if name == SELF_KEY_NAME {
+        return Err(Error::InvalidArgument(format!("Key name {:?} is reserved", name)));
+    }
+    Ok(())
+}
+
+fn not_found(name: &str) -> Error {
+    Error::NotFound(format!("key {}", name))
+}
+
+fn already_exists(name: &str) -> Error {
+    Error::InvalidArgument(format!("A key named {} already exists", name))
+}
@@ -0,0 +1,102 @@
+//! A (partial) implementation of IPFS.
+//!
+//! The easiest way to embed a node is through `IpfsNode`:
+//!
+//! ```no_run
+//! use ipfs::IpfsNode;
+//! use std::io::Read;
+//!
+//! let node = IpfsNode::in_memory();
+//! let hash = node.add_reader(&mut &b"hello world"[..]).unwrap();
+//!
+//! let mut contents = String::new();
+//! node.cat(&format!("/ipfs/{}", hash)).unwrap().read_to_string(&mut contents).unwrap();
+//! ```
+//!
+//! `IpfsNode::open` opens an initialized repo on disk instead, and
+//! `IpfsNode::init` creates one. The `Blockstore` and `DagService` of a node
+//! are exposed for working with blocks and DAG nodes directly, and
+//! `IpfsNode::with_datastore` keeps the rest of its state (pins, DHT
+//! records, ...) in a given `Datastore`. Failures are reported as `Error`s.
+//!
+//! `run_cli` runs the `ipfs` command line, as the `ipfs` binary does.
+//!
+//! A node exchanges blocks with peers once brought online with
+//! `IpfsNode::go_online`, and finds peers and content through a Kademlia
+//! DHT. Several in-memory nodes listening on loopback addresses make up a
+//! network of their own:
+//!
+//! ```no_run
+//! use ipfs::IpfsNode;
+//! use ipfs::multiaddr::Multiaddr;
+//!
+//! let addr = Multiaddr::parse("/ip4/127.0.0.1/tcp/0").unwrap();
+//! let mut nodes: Vec<IpfsNode> = (0..12).map(|_| IpfsNode::in_memory()).collect();
+//! let mut first_addr = None;
+//! for node in &mut nodes {
+//!     let listening = node.go_online(&[addr.clone()]).unwrap();
+//!     match first_addr {
+//!         None => first_addr = Some(format!("{}/p2p/{}", listening[0], node.peer_id())),
+//!         Some(ref first) => {
+//!             node.connect(&Multiaddr::parse(first).unwrap()).unwrap();
+//!         }
+//!     }
+//! }
+//!
+//! let hash = nodes[3].add_reader(&mut &b"hello"[..]).unwrap();
+//! nodes[3].get_dht().unwrap().provide(&hash).unwrap();
+//! let providers = nodes[9].get_dht().unwrap().find_providers(&hash, 1).unwrap();
+//! assert_eq!(providers[0], *nodes[3].peer_id());
+//! ```
+
+extern crate atomicwrites;
+extern crate crypto as rust_crypto;
+extern crate libc;
+#[macro_use] extern crate log;
+extern crate openssl;
+extern crate protobuf;
+extern crate rust_multihash;
+extern crate rustc_serialize;
+
+mod api;
+pub mod bitswap;
+mod block;
+mod blockservice;
+mod blockstore;
+mod bootstrap;
+pub mod cid;
+mod codec;
+mod commands;
+pub mod config;
+mod core;
+pub mod crypto;
+mod datastore;
+pub mod dht;
+pub mod dns;
+mod error;
+pub mod exporter;
+mod fsrepo;
+pub mod importer;
+mod ipfs_commands;
+pub mod ipns;
+pub mod keystore;
+mod mdns;
+pub mod merkledag;
+pub mod mfs;
+mod mime;
+pub mod multiaddr;
+mod path;
+pub mod peer;
+mod pin;
+mod provider;
+pub mod pubsub;
+pub mod swarm;
+pub mod unixfs;
+mod util;
+
+pub use blockstore::Blockstore;
+pub use datastore::Datastore;
+pub use core::IpfsNode;
+pub use error::Error;
+pub use ipfs_commands::run_cli;
+pub use merkledag::DagService;
@@ -0,0 +1,258 @@
+// Finding peers on the local network with multicast DNS, following the
+// libp2p mDNS spec. Every interval we ask for _p2p._udp.local PTR records.
+// Nodes answer with a PTR record pointing at a random name of their own,
+// and a TXT record under that name with a dnsaddr=<multiaddr>/p2p/<peer id>
+// string for each address they listen on. Peers discovered this way are
+// dialed.
+//
+// The mDNS socket is shared with other responders on the host, so several
+// daemons on one machine find each other over the loopback multicast.
+
+use crypto;
+use dns::{self, Message, Question, Record, RecordData};
+use error::Error;
+use multiaddr::{Multiaddr, Protocol};
+use peer::PeerId;
+use swarm::Swarm;
+
+use libc;
+use rustc_serialize::hex::ToHex;
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 10;
+
+const SERVICE_NAME: &'static str = "_p2p._udp.local";
+const MDNS_PORT: u16 = 5353;
+const DNSADDR_PREFIX: &'static str = "dnsaddr=";
+const RECORD_TTL_SECS: u32 = 120;
+const MAX_PACKET_SIZE: usize = 9000;
+
+fn mdns_addr() -> Ipv4Addr {
+    Ipv4Addr::new(224, 0, 0, 251)
+}
+
+pub struct MdnsService {
+    swarm: Weak<Swarm>,
+    socket: UdpSocket,
+    peer_name: String, // our random name under the service name
+    interval: Duration,
+}
+
+impl MdnsService {
+    // Starts answering queries and querying every `interval`, for as long as
+    // the swarm exists
+    pub fn start(swarm: &Arc<Swarm>, interval: Duration) -> Result<(), Error> {
+        let socket = try!(bind_multicast());
+        let peer_name = format!("{}.{}", crypto::random_bytes(16).to_hex(), SERVICE_NAME);
+        let service = Arc::new(MdnsService {
+            swarm: Arc::downgrade(swarm),
+            socket: socket,
+            peer_name: peer_name,
+            interval: interval,
+        });
+
+        let receiver = service.clone();
+        thread::spawn(move || receiver.receive_loop());
+        thread::spawn(move || {
+            while service.swarm.upgrade().is_some() {
+                if let Err(e) = service.query() {
+                    debug!("Error sending mDNS query: {}", e);
+                }
+                thread::sleep(service.interval);
+            }
+        });
+        Ok(())
+    }
+
+    fn query(&self) -> Result<(), Error> {
+        let query = try!(Message::query(SERVICE_NAME, dns::TYPE_PTR).encode());
+        self.send(&query)
+    }
+
+    fn send(&self, packet: &[u8]) -> Result<(), Error> {
+        let dest = SocketAddr::V4(SocketAddrV4::new(mdns_addr(), MDNS_PORT));
+        try!(self.socket
+                 .send_to(packet, dest)
+                 .map_err(|e| Error::Io("Error sending mDNS packet".to_string(), e)));
+        Ok(())
+    }
+
+    fn receive_loop(&self) {
+        let mut buf = vec![0; MAX_PACKET_SIZE];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Err(e) => {
+                    warn!("Error receiving mDNS packet: {}", e);
+                    return;
+                }
+                Ok(received) => received,
+            };
+            let swarm = match self.swarm.upgrade() {
+                None => return,
+                Some(swarm) => swarm,
+            };
+            let msg = match Message::decode(&buf[..len]) {
+                Err(e) => {
+                    trace!("Invalid mDNS packet from {}: {}", from, e);
+                    continue;
+                }
+                Ok(msg) => msg,
+            };
+
+            if msg.response {
+                self.handle_response(&swarm, &msg, from.ip());
+            } else if msg.questions.iter().any(is_service_question) {
+                if let Err(e) = self.respond(&swarm) {
+                    debug!("Error answering mDNS query: {}", e);
+                }
+            }
+        }
+    }
+
+    // Advertises our listen addresses. Peers on the local network don't need
+    // relays to reach us.
+    fn respond(&self, swarm: &Swarm) -> Result<(), Error> {
+        let addrs: Vec<String> = swarm.listen_addrs()
+                                      .into_iter()
+                                      .filter(|addr| !addr.is_circuit())
+                                      .map(|mut addr| {
+                                          addr.push(Protocol::P2p(swarm.local_peer().clone()));
+                                          format!("{}{}", DNSADDR_PREFIX, addr)
+                                      })
+                                      .collect();
+        if addrs.is_empty() {
+            return Ok(());
+        }
+        let response = Message::response(vec![Record {
+                                                  name: SERVICE_NAME.to_string(),
+                                                  rtype: dns::TYPE_PTR,
+                                                  ttl: RECORD_TTL_SECS,
+                                                  data: RecordData::Ptr(self.peer_name.clone()),
+                                              },
+                                              Record {
+                                                  name: self.peer_name.clone(),
+                                                  rtype: dns::TYPE_TXT,
+                                                  ttl: RECORD_TTL_SECS,
+                                                  data: RecordData::Txt(addrs),
+                                              }]);
+        self.send(&try!(response.encode()))
+    }
+
+    // Dials the peers in a response that we aren't connected to
+    fn handle_response(&self, swarm: &Arc<Swarm>, msg: &Message, from: IpAddr) {
+        for record in &msg.answers {
+            let strings = match record.data {
+                RecordData::Txt(ref strings) if record.name.ends_with(SERVICE_NAME) => strings,
+                _ => continue,
+            };
+            let addrs: Vec<Multiaddr> = strings.iter()
+                                               .filter(|s| s.starts_with(DNSADDR_PREFIX))
+                                               .filter_map(|s| {
+                                                   Multiaddr::parse(&s[DNSADDR_PREFIX.len()..])
+                                                       .ok()
+                                               })
+                                               .map(|addr| resolve_unspecified(addr, from))
+                                               .collect();
+            let peer = match addrs.first().and_then(|addr| addr.peer_id()) {
+                None => continue,
+                Some(peer) => peer.clone(),
+            };
+            if peer == *swarm.local_peer() || swarm.is_connected(&peer) {
+                continue;
+            }
+
+            debug!("Discovered {} through mDNS", peer);
+            swarm.add_peer_addrs(&peer, &addrs);
+            dial(swarm, peer, addrs);
+        }
+    }
+}
+
+fn is_service_question(question: &Question) -> bool {
+    question.qtype == dns::TYPE_PTR && question.name == SERVICE_NAME
+}
+
+// Peers listening on all interfaces advertise unspecified addresses, for
+// which we use the address the response came from
+fn resolve_unspecified(addr: Multiaddr, from: IpAddr) -> Multiaddr {
+    let unspecified = match addr.protocols().first() {
+        Some(&Protocol::Ip4(ip)) => ip.is_unspecified(),
+        Some(&Protocol::Ip6(ip)) => ip.is_unspecified(),
+        _ => false,
+    };
+    if !unspecified {
+        return addr;
+    }
+    let mut protocols = addr.protocols().to_vec();
+    protocols[0] = match from {
+        IpAddr::V4(ip) => Protocol::Ip4(ip),
+        IpAddr::V6(ip) => Protocol::Ip6(ip),
+    };
+    Multiaddr::new(protocols)
+}
+
+// Dials a discovered peer in the background, trying each of its addresses
+fn dial(swarm: &Arc<Swarm>, peer: PeerId, addrs: Vec<Multiaddr>) {
+    let swarm = swarm.clone();
+    thread::spawn(move || {
+        for addr in addrs.iter().filter(|addr| addr.peer_id() == Some(&peer)) {
+            match Swarm::dial(&swarm, addr) {
+                Ok(_) => return,
+                Err(e) => debug!("Error dialing {} discovered through mDNS: {}", addr, e),
+            }
+        }
+    });
+}
+
+// Binds the mDNS port, allowing other responders on the host to bind it too,
+// and joins the mDNS multicast group
+fn bind_multicast() -> Result<UdpSocket, Error> {
+    let socket_error = |msg: &str| Error::Io(msg.to_string(), io::Error::last_os_error());
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(socket_error("Error creating mDNS socket"));
+    }
+    // closes the socket if we return early
+    let socket = unsafe { UdpSocket::from_raw_fd(fd) };
+
+    let one: libc::c_int = 1;
+    for &option in &[libc::SO_REUSEADDR, libc::SO_REUSEPORT] {
+        let ret = unsafe {
+            libc::setsockopt(socket.as_raw_fd(),
+                             libc::SOL_SOCKET,
+                             option,
+                             &one as *const libc::c_int as *const libc::c_void,
+                             mem::size_of::<libc::c_int>() as libc::socklen_t)
+        };
+        if ret < 0 {
+            return Err(socket_error("Error setting mDNS socket options"));
+        }
+    }
+
+    let addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: MDNS_PORT.to_be(),
+        sin_addr: libc::in_addr { s_addr: 0 }, // INADDR_ANY
+        sin_zero: [0; 8],
+    };
+    let ret = unsafe {
+        libc::bind(socket.as_raw_fd(),
+                   &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+                   mem::size_of::<libc::sockaddr_in>() as libc::socklen_t)
+    };
+    if ret < 0 {
+        return Err(socket_error("Error binding mDNS socket"));
+    }
+
+    try!(socket.join_multicast_v4(&mdns_addr(), &Ipv4Addr::new(0, 0, 0, 0))
+               .and_then(|_| socket.set_multicast_loop_v4(true))
+               .map_err(|e| Error::Io("Error joining the mDNS multicast group".to_string(), e)));
+    Ok(socket)
+}
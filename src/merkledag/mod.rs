@@ -1,7 +1,9 @@
 mod pb;
 
 use block::Block;
-use blockstore::Blockstore;
+use blockservice::BlockService;
+use cid::{Cid, Codec};
+use error::Error;
 use util;
 
 use protobuf::{self, Message, RepeatedField};
@@ -9,20 +11,51 @@ use rust_multihash::Multihash;
 use std::io::{Read, Write};
 use std::sync::{Arc, RwLock};
 
+#[derive(Clone)]
 pub struct Link {
     name: String,
     hash: Multihash,
+    codec: Codec,
     target_size: u64,
     node: Option<Arc<Node>>,
 }
 
+// A dag-pb node, or a raw block (which has no links, and whose data is the
+// whole block)
 pub struct Node {
     data: Vec<u8>,
     links: Vec<Link>,
+    codec: Codec,
     multihash: RwLock<Option<Multihash>>, // caches the multihash so it isn't recomputed
 }
 
 impl Link {
+    pub fn new(name: String, hash: Multihash, target_size: u64) -> Self {
+        Link::from_cid(name, Cid::new(Codec::DagProtobuf, hash), target_size)
+    }
+
+    pub fn from_cid(name: String, cid: Cid, target_size: u64) -> Self {
+        Link {
+            name: name,
+            hash: cid.hash,
+            codec: cid.codec,
+            target_size: target_size,
+            node: None,
+        }
+    }
+
+    // creates a Link pointing to `node`, caching the node in the link
+    pub fn from_node(name: String, node: Arc<Node>) -> Result<Self, Error> {
+        let size = try!(node.cumulative_size());
+        Ok(Link {
+            name: name,
+            hash: node.multihash(),
+            codec: node.codec,
+            target_size: size,
+            node: Some(node),
+        })
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -32,14 +65,23 @@ impl Link {
     pub fn clone_hash(&self) -> Multihash {
         self.hash.clone()
     }
+    pub fn get_hash(&self) -> &Multihash {
+        &self.hash
+    }
+    pub fn get_codec(&self) -> Codec {
+        self.codec
+    }
+    pub fn cid(&self) -> Cid {
+        Cid::new(self.codec, self.hash.clone())
+    }
     pub fn get_target_size(&self) -> u64 {
         self.target_size
     }
 
-    pub fn get_node(&self, dagservice: &DagService) -> Result<Arc<Node>, String> {
+    pub fn get_node(&self, dagservice: &DagService) -> Result<Arc<Node>, Error> {
         match self.node {
             Some(ref node) => Ok(node.clone()),
-            None => dagservice.get(&self.hash),
+            None => dagservice.get_cid(&self.cid()),
         }
     }
 
@@ -47,19 +89,21 @@ impl Link {
         self.node = Some(node)
     }
 
+    // Links hold CIDs, but anything that isn't one is taken as the multihash
+    // of a dag-pb node, as links used to be
     pub fn from_pblink(mut link: pb::PBLink) -> Self {
-        Link {
-            name: link.take_Name(),
-            hash: Multihash::from_vec(link.take_Hash()),
-            target_size: link.get_Tsize(),
-            node: None,
-        }
+        let bytes = link.take_Hash();
+        let cid = match Cid::from_bytes(&bytes) {
+            Ok(cid) => cid,
+            Err(_) => Cid::new(Codec::DagProtobuf, Multihash::from_vec(bytes)),
+        };
+        Link::from_cid(link.take_Name(), cid, link.get_Tsize())
     }
 
     pub fn clone_to_pblink(&self) -> pb::PBLink {
         let mut pblink = pb::PBLink::new();
         pblink.set_Name(self.name.clone());
-        pblink.set_Hash(self.hash.clone().into_bytes());
+        pblink.set_Hash(self.cid().to_bytes());
         pblink.set_Tsize(self.target_size);
         pblink
     }
@@ -75,6 +119,12 @@ impl Node {
     pub fn get_mut_links(&mut self) -> &mut [Link] {
         &mut self.links[..]
     }
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+    pub fn cid(&self) -> Cid {
+        Cid::new(self.codec, self.multihash())
+    }
 
     pub fn multihash(&self) -> Multihash {
         match self.multihash.try_read() {
@@ -99,18 +149,46 @@ impl Node {
 
     // creates a Node with no Links, only data
     pub fn from_data(data: Vec<u8>) -> Self {
+        Self::new(data, vec![])
+    }
+
+    pub fn new(data: Vec<u8>, links: Vec<Link>) -> Self {
+        Node {
+            data: data,
+            links: links,
+            codec: Codec::DagProtobuf,
+            multihash: RwLock::new(None),
+        }
+    }
+
+    // creates a raw block holding `data`
+    pub fn raw(data: Vec<u8>) -> Self {
         Node {
             data: data,
             links: vec![],
+            codec: Codec::Raw,
             multihash: RwLock::new(None),
         }
     }
 
+    pub fn add_link(&mut self, link: Link) {
+        self.links.push(link);
+        self.multihash = RwLock::new(None);
+    }
+
+    // size of the encoded node plus the cumulative sizes of everything it
+    // links to
+    pub fn cumulative_size(&self) -> Result<u64, Error> {
+        let mut buf = Vec::new();
+        try!(self.encode_to_writer(&mut buf));
+        Ok(self.links.iter().fold(buf.len() as u64, |sum, link| sum + link.target_size))
+    }
+
     // parses a protobuf-encoded PBNode from the reader
-    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, String> {
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let mut pbnode = try!(protobuf::parse_from_reader::<pb::PBNode>(reader)
                                   .map_err(|e| {
-                                      format!("Error parsing encoded Node: {}", e)
+                                      Error::decode("Error parsing encoded Node".to_string(), e)
                                   }));
 
         let mut links = Vec::new();
@@ -122,12 +200,17 @@ impl Node {
         Ok(Node {
             data: pbnode.take_Data(),
             links: links,
+            codec: Codec::DagProtobuf,
             multihash: RwLock::new(None),
         })
     }
 
-    // encodes, using protobuf, as a PBNode
-    pub fn encode_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+    // encodes, using protobuf, as a PBNode. Raw blocks are just their data.
+    pub fn encode_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.codec == Codec::Raw {
+            return writer.write_all(&self.data)
+                         .map_err(|e| Error::Io("Error writing raw block".to_string(), e));
+        }
         let mut pbnode = pb::PBNode::new();
         pbnode.set_Data(self.data.clone());
 
@@ -139,31 +222,46 @@ impl Node {
         pbnode.set_Links(RepeatedField::from_vec(pblinks));
 
         pbnode.write_to_writer(writer)
-              .map_err(|e| format!("Error cloning node to writer: {}", e))
+              .map_err(|e| Error::encode("Error cloning node to writer".to_string(), e))
     }
 }
 
-// TODO: wrap a Blockservice instead
 pub struct DagService {
-    blockstore: Arc<Blockstore>,
+    blockservice: Arc<BlockService>,
 }
 
 impl DagService {
-    pub fn new(blockstore: Arc<Blockstore>) -> Self {
-        DagService { blockstore: blockstore }
+    pub fn new(blockservice: Arc<BlockService>) -> Self {
+        DagService { blockservice: blockservice }
     }
 
-    pub fn add<'a>(&self, node: &'a Node) -> Result<Multihash, String> {
+    pub fn add<'a>(&self, node: &'a Node) -> Result<Multihash, Error> {
         let hash = node.multihash();
         let mut data = Vec::new();
         try!(node.encode_to_writer(&mut data));
-        try!(self.blockstore.put(&hash, &data[..]));
+        try!(self.blockservice.put(&hash, &data[..]));
         Ok(hash)
     }
 
-    pub fn get(&self, hash: &Multihash) -> Result<Arc<Node>, String> {
-        let block = try!(self.blockstore.get(hash));
-        let data = block.take_data();
-        Node::from_reader(&mut &data[..]).map(|node| Arc::new(node))
+    // Gets the dag-pb node `hash`
+    pub fn get(&self, hash: &Multihash) -> Result<Arc<Node>, Error> {
+        self.get_cid(&Cid::new(Codec::DagProtobuf, hash.clone()))
+    }
+
+    // Gets the node or raw block `cid`. Blocks inlined in their CID aren't
+    // looked for in the block service.
+    pub fn get_cid(&self, cid: &Cid) -> Result<Arc<Node>, Error> {
+        let data = match cid.inline_data() {
+            Some(data) => data,
+            None => try!(self.blockservice.get(&cid.hash)).take_data(),
+        };
+        let node = match cid.codec {
+            Codec::DagProtobuf => try!(Node::from_reader(&mut &data[..])),
+            Codec::Raw => Node::raw(data),
+        };
+        // so that an inlined node is known by its CID, not by the hash of
+        // its data
+        *node.multihash.write().unwrap() = Some(cid.hash.clone());
+        Ok(Arc::new(node))
     }
 }
@@ -0,0 +1,431 @@
+// The mutable file system (MFS): a tree of unixfs directories and files that
+// can be edited like a POSIX filesystem. DAG nodes can't change, so each
+// edit adds new nodes for what changed and for every directory above it, up
+// to a new root directory. The hash of the root is kept in the datastore as
+// /local/filesroot, and updated after each edit. Directories are sharded
+// once they grow over the sharding threshold (see unixfs::directory).
+//
+// MFS paths start with / (the root directory), e.g. /docs/notes.txt.
+
+use cid::Cid;
+use datastore::Datastore;
+use error::Error;
+use importer::Imported;
+use merkledag::{DagService, Link, Node};
+use path;
+use unixfs::{self, directory, pb};
+use unixfs::io::DagReader;
+use unixfs::modifier;
+
+use rust_multihash::Multihash;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+pub const ROOT_KEY: &'static str = "/local/filesroot";
+
+/// An entry of a directory listing
+pub struct Entry {
+    pub name: String,
+    pub hash: Cid,
+    pub ty: pb::Data_DataType,
+    pub size: u64, // the size of the file data; 0 for directories
+    pub mime_type: Option<String>, // for files wrapped in a Metadata node
+    pub attributes: unixfs::Attributes,
+}
+
+pub struct Stat {
+    pub hash: Cid,
+    pub ty: pb::Data_DataType,
+    pub size: u64, // the size of the file data; 0 for directories
+    pub cumulative_size: u64,
+    pub blocks: usize, // the number of links
+    pub mime_type: Option<String>,
+    pub attributes: unixfs::Attributes,
+}
+
+/// How `Mfs::write` treats the file written to
+#[derive(Default)]
+pub struct WriteOptions {
+    pub offset: u64,
+    pub create: bool, // create the file if it doesn't exist
+    pub truncate: bool, // empty the file before writing
+    pub parents: bool, // create missing parent directories
+}
+
+pub struct Mfs {
+    dagservice: Arc<DagService>,
+    datastore: Arc<Datastore>,
+    root: Mutex<Option<Multihash>>, // loaded on first use
+    shard_threshold: u64,
+}
+
+impl Mfs {
+    pub fn new(dagservice: Arc<DagService>,
+               datastore: Arc<Datastore>,
+               shard_threshold: u64)
+               -> Self {
+        Mfs {
+            dagservice: dagservice,
+            datastore: datastore,
+            root: Mutex::new(None),
+            shard_threshold: shard_threshold,
+        }
+    }
+
+    // The node at `path`
+    pub fn lookup(&self, path: &str) -> Result<Arc<Node>, Error> {
+        let components = try!(parse_path(path));
+        let root = {
+            let mut cached = self.root.lock().unwrap();
+            try!(self.load_root(&mut cached))
+        };
+        path::resolve_links(&self.dagservice, root, &components)
+    }
+
+    // The entries of the directory at `path`, or the file at `path`
+    pub fn ls(&self, path: &str) -> Result<Vec<Entry>, Error> {
+        let node = try!(self.lookup(path));
+        if !directory::is_directory(try!(node_type(&node))) {
+            let name = path.rsplit('/').find(|s| !s.is_empty()).unwrap_or("");
+            return Ok(vec![try!(entry(&self.dagservice, name, node))]);
+        }
+
+        let links = try!(directory::entries(&self.dagservice, &node));
+        let mut entries = Vec::with_capacity(links.len());
+        for link in links {
+            let child = try!(link.get_node(&self.dagservice));
+            entries.push(try!(entry(&self.dagservice, link.get_name(), child)));
+        }
+        Ok(entries)
+    }
+
+    pub fn stat(&self, path: &str) -> Result<Stat, Error> {
+        let node = try!(self.lookup(path));
+        let entry = try!(entry(&self.dagservice, "", node.clone()));
+        Ok(Stat {
+            hash: entry.hash,
+            ty: entry.ty,
+            size: entry.size,
+            cumulative_size: try!(node.cumulative_size()),
+            blocks: node.get_links().len(),
+            mime_type: entry.mime_type,
+            attributes: entry.attributes,
+        })
+    }
+
+    // Makes a directory at `path`. With `parents`, missing parent
+    // directories are made too, and it isn't an error for the directory to
+    // exist already.
+    pub fn mkdir(&self, path: &str, parents: bool) -> Result<(), Error> {
+        let components = try!(parse_path(path));
+        let (name, dir) = match components.split_last() {
+            None if parents => return Ok(()),
+            None => return Err(Error::InvalidArgument("/ already exists".to_string())),
+            Some(split) => split,
+        };
+
+        self.update(|root| {
+            self.edit_dir(root, dir, parents, |d| {
+                if let Some(link) = try!(self.find_link(&d, name)) {
+                    let existing = try!(link.get_node(&self.dagservice));
+                    if parents && directory::is_directory(try!(node_type(&existing))) {
+                        return Ok(d);
+                    }
+                    return Err(Error::InvalidArgument(format!("{} already exists", path)));
+                }
+                let new_dir = Arc::new(try!(directory::empty()));
+                try!(self.dagservice.add(&new_dir));
+                self.set_link(&d, name, Some(try!(Link::from_node(name.clone(), new_dir))))
+            })
+        })
+    }
+
+    // Writes everything read from `reader` to the file at `path`
+    pub fn write<R: Read>(&self,
+                          path: &str,
+                          reader: &mut R,
+                          options: &WriteOptions)
+                          -> Result<(), Error> {
+        let components = try!(parse_path(path));
+        let (name, dir) = match components.split_last() {
+            None => return Err(Error::InvalidArgument("/ is a directory".to_string())),
+            Some(split) => split,
+        };
+
+        self.update(|root| {
+            self.edit_dir(root, dir, options.parents, |d| {
+                // a file wrapped in a Metadata node is written to under it,
+                // and the result wrapped again with the same MIME type
+                let (file, mime_type) = match try!(self.find_link(&d, name)) {
+                    Some(link) => {
+                        let file = try!(link.get_node(&self.dagservice));
+                        if directory::is_directory(try!(node_type(&file))) {
+                            return Err(Error::InvalidArgument(format!("{} is a directory",
+                                                                      path)));
+                        }
+                        let mime_type = try!(unixfs::mime_type(&file));
+                        let file = try!(unixfs::unwrap_metadata(&self.dagservice, file));
+                        if options.truncate {
+                            let attributes = unixfs::attributes(&try!(unixfs::node_data(&file)));
+                            (try!(modifier::empty_file(attributes)), mime_type)
+                        } else {
+                            (file, mime_type)
+                        }
+                    }
+                    None if options.create => {
+                        (try!(modifier::empty_file(unixfs::Attributes::default())), None)
+                    }
+                    None => return Err(Error::NotFound(format!("file {}", path))),
+                };
+                let written = try!(modifier::write_at(&self.dagservice,
+                                                      file,
+                                                      options.offset,
+                                                      reader));
+                let link = match mime_type {
+                    None => imported_link(name, written),
+                    Some(ty) => try!(metadata_link(&self.dagservice, name, &ty, written)),
+                };
+                self.set_link(&d, name, Some(link))
+            })
+        })
+    }
+
+    // A reader over the file at `path`, from `offset` on
+    pub fn read(&self, path: &str, offset: u64) -> Result<DagReader, Error> {
+        let node = try!(self.lookup(path));
+        if directory::is_directory(try!(node_type(&node))) {
+            return Err(Error::InvalidArgument(format!("{} is a directory", path)));
+        }
+        let mut reader = try!(DagReader::new(node, self.dagservice.clone()));
+        try!(io::copy(&mut reader.by_ref().take(offset), &mut io::sink())
+                 .map_err(|e| Error::Io(format!("Error reading {}", path), e)));
+        Ok(reader)
+    }
+
+    // Links `node` at `path`, which must not exist yet. This is how
+    // /ipfs/ paths and other MFS paths are copied into MFS.
+    pub fn put(&self, path: &str, node: Arc<Node>) -> Result<(), Error> {
+        let components = try!(parse_path(path));
+        self.update(|root| self.insert(root, path, &components, node))
+    }
+
+    // Moves the file or directory at `src` to `dst`, which must not exist
+    // yet, or into `dst` if it's a directory
+    pub fn mv(&self, src: &str, dst: &str) -> Result<(), Error> {
+        let src_components = try!(parse_path(src));
+        let mut dst_components = try!(parse_path(dst));
+        let (src_name, src_dir) = match src_components.split_last() {
+            None => return Err(Error::InvalidArgument("Cannot move /".to_string())),
+            Some(split) => split,
+        };
+
+        self.update(|root| {
+            let mut dst = dst.to_string();
+            let dst_dir = path::resolve_links(&self.dagservice, root.clone(), &dst_components);
+            if let Ok(dst_dir) = dst_dir {
+                if directory::is_directory(try!(node_type(&dst_dir))) {
+                    dst = format!("{}/{}", dst.trim_right_matches('/'), src_name);
+                    dst_components.push(src_name.clone());
+                }
+            }
+            if dst_components.starts_with(&src_components) {
+                return Err(Error::InvalidArgument(format!("Cannot move {} into itself", src)));
+            }
+
+            let node = try!(path::resolve_links(&self.dagservice, root.clone(), &src_components));
+            let root = try!(self.edit_dir(root, src_dir, false, |d| {
+                self.set_link(&d, src_name, None)
+            }));
+            self.insert(root, &dst, &dst_components, node)
+        })
+    }
+
+    // Removes the file or directory at `path`. Directories are only removed
+    // if `recursive` is true.
+    pub fn rm(&self, path: &str, recursive: bool) -> Result<(), Error> {
+        let components = try!(parse_path(path));
+        let (name, dir) = match components.split_last() {
+            None => return Err(Error::InvalidArgument("Cannot remove /".to_string())),
+            Some(split) => split,
+        };
+
+        self.update(|root| {
+            self.edit_dir(root, dir, false, |d| {
+                let node = match try!(self.find_link(&d, name)) {
+                    None => return Err(Error::NotFound(format!("file or directory {}", path))),
+                    Some(link) => try!(link.get_node(&self.dagservice)),
+                };
+                if !recursive && directory::is_directory(try!(node_type(&node))) {
+                    return Err(Error::InvalidArgument(format!("{} is a directory, use -r to \
+                                                               remove directories",
+                                                              path)));
+                }
+                self.set_link(&d, name, None)
+            })
+        })
+    }
+
+    // Makes sure the root is saved, returning the CID of the node at
+    // `path`. Edits are saved as they are made, so this only matters for a
+    // root that was never edited.
+    pub fn flush(&self, path: &str) -> Result<Cid, Error> {
+        let node = try!(self.lookup(path));
+        Ok(node.cid())
+    }
+
+    // Loads the root directory, creating an empty one if there's none yet
+    fn load_root(&self, cached: &mut Option<Multihash>) -> Result<Arc<Node>, Error> {
+        if let Some(ref hash) = *cached {
+            return self.dagservice.get(hash);
+        }
+        if try!(self.datastore.has(ROOT_KEY)) {
+            let value = try!(self.datastore.get(ROOT_KEY));
+            let hash = try!(String::from_utf8(value)
+                                .ok()
+                                .and_then(|s| Multihash::from_base58_str(&s).ok())
+                                .ok_or(Error::Corrupt(format!("Invalid MFS root in {}",
+                                                              ROOT_KEY))));
+            let root = try!(self.dagservice.get(&hash));
+            *cached = Some(hash);
+            return Ok(root);
+        }
+
+        let root = Arc::new(try!(directory::empty()));
+        try!(self.dagservice.add(&root));
+        try!(self.save_root(cached, root.multihash()));
+        Ok(root)
+    }
+
+    fn save_root(&self, cached: &mut Option<Multihash>, hash: Multihash) -> Result<(), Error> {
+        try!(self.datastore.put(ROOT_KEY, hash.to_string().as_bytes()));
+        *cached = Some(hash);
+        Ok(())
+    }
+
+    // Replaces the root with `f(root)`. Edits are made one at a time.
+    fn update<F>(&self, f: F) -> Result<(), Error>
+        where F: FnOnce(Arc<Node>) -> Result<Arc<Node>, Error>
+    {
+        let mut cached = self.root.lock().unwrap();
+        let root = try!(self.load_root(&mut cached));
+        let new_root = try!(f(root));
+        self.save_root(&mut cached, new_root.multihash())
+    }
+
+    // Replaces the directory at `components` under `dir` with `f(directory)`,
+    // which adds it to the DAG, then adds the new directories above it up to
+    // `dir`. Missing directories are made empty if `parents` is true.
+    fn edit_dir<F>(&self,
+                   dir: Arc<Node>,
+                   components: &[String],
+                   parents: bool,
+                   f: F)
+                   -> Result<Arc<Node>, Error>
+        where F: FnOnce(Arc<Node>) -> Result<Arc<Node>, Error>
+    {
+        let (name, rest) = match components.split_first() {
+            None => return f(dir),
+            Some(split) => split,
+        };
+        let child = match try!(self.find_link(&dir, name)) {
+            Some(link) => {
+                let child = try!(link.get_node(&self.dagservice));
+                if !directory::is_directory(try!(node_type(&child))) {
+                    return Err(Error::InvalidArgument(format!("{} is not a directory", name)));
+                }
+                child
+            }
+            None if parents => Arc::new(try!(directory::empty())),
+            None => return Err(Error::NotFound(format!("directory {}", name))),
+        };
+        let new_child = try!(self.edit_dir(child, rest, parents, f));
+        self.set_link(&dir, name, Some(try!(Link::from_node(name.clone(), new_child))))
+    }
+
+    fn find_link(&self, dir: &Node, name: &str) -> Result<Option<Link>, Error> {
+        directory::find_link(&self.dagservice, dir, name)
+    }
+
+    // A copy of the directory `dir` with the entry `name` replaced by
+    // `entry`, or removed if `entry` is None, added to the DAG
+    fn set_link(&self, dir: &Node, name: &str, entry: Option<Link>) -> Result<Arc<Node>, Error> {
+        directory::set_link(&self.dagservice, dir, name, entry, self.shard_threshold)
+    }
+
+    // Links `node` at `components` (`path`) under `root`
+    fn insert(&self,
+              root: Arc<Node>,
+              path: &str,
+              components: &[String],
+              node: Arc<Node>)
+              -> Result<Arc<Node>, Error> {
+        let (name, dir) = match components.split_last() {
+            None => return Err(Error::InvalidArgument("/ already exists".to_string())),
+            Some(split) => split,
+        };
+        self.edit_dir(root, dir, false, |d| {
+            if try!(self.find_link(&d, name)).is_some() {
+                return Err(Error::InvalidArgument(format!("{} already exists", path)));
+            }
+            self.set_link(&d, name, Some(try!(Link::from_node(name.clone(), node))))
+        })
+    }
+}
+
+// Splits an MFS path into the names leading to it from the root
+fn parse_path(path: &str) -> Result<Vec<String>, Error> {
+    if !path.starts_with('/') {
+        return Err(Error::InvalidPath(format!("{} (MFS paths start with /)", path)));
+    }
+    let components: Vec<String> = path.split('/')
+                                      .filter(|s| !s.is_empty())
+                                      .map(|s| s.to_string())
+                                      .collect();
+    if components.iter().any(|c| c == "." || c == "..") {
+        return Err(Error::InvalidPath(format!("{} (. and .. are not supported)", path)));
+    }
+    Ok(components)
+}
+
+fn node_type(node: &Node) -> Result<pb::Data_DataType, Error> {
+    Ok(try!(unixfs::node_data(node)).get_Type())
+}
+
+// A file wrapped in a Metadata node is described as the file, with the MIME
+// type and the CID of the Metadata node
+fn entry(ds: &DagService, name: &str, node: Arc<Node>) -> Result<Entry, Error> {
+    let hash = node.cid();
+    let mime_type = try!(unixfs::mime_type(&node));
+    let node = try!(unixfs::unwrap_metadata(ds, node));
+    let data = try!(unixfs::node_data(&node));
+    let size = if directory::is_directory(data.get_Type()) {
+        0
+    } else {
+        data.get_filesize()
+    };
+    Ok(Entry {
+        name: name.to_string(),
+        hash: hash,
+        ty: data.get_Type(),
+        size: size,
+        mime_type: mime_type,
+        attributes: unixfs::attributes(&data),
+    })
+}
+
+fn imported_link(name: &str, imported: Imported) -> Link {
+    imported.link(name.to_string())
+}
+
+// A link named `name` to a new Metadata node giving `file` the MIME type
+// `mime_type`
+fn metadata_link(ds: &DagService,
+                 name: &str,
+                 mime_type: &str,
+                 file: Imported)
+                 -> Result<Link, Error> {
+    let node = Node::new(try!(unixfs::metadata_data(mime_type)),
+                         vec![file.link(String::new())]);
+    try!(ds.add(&node));
+    Link::from_node(name.to_string(), Arc::new(node))
+}
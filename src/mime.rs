@@ -0,0 +1,77 @@
+// Guesses the MIME type of files being added, first from the magic bytes at
+// the start of their contents, then from their extension. Only common types
+// are known.
+
+use std::path::Path;
+
+// How much of the start of a file is needed to detect its type
+pub const SNIFF_LEN: usize = 512;
+
+const MAGIC: &'static [(&'static [u8], &'static str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b\x08", "application/gzip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\xfd7zXZ\x00", "application/x-xz"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"OggS", "application/ogg"),
+    (b"ID3", "audio/mpeg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+    (b"\x00asm", "application/wasm"),
+    (b"\x7fELF", "application/x-executable"),
+];
+
+const EXTENSIONS: &'static [(&'static str, &'static str)] = &[
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "application/javascript"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+// The MIME type of the file at `path`, whose contents start with `head`
+// (the first SNIFF_LEN bytes, or all of a shorter file). None if it's not
+// one we know.
+pub fn detect(path: &Path, head: &[u8]) -> Option<&'static str> {
+    if let Some(&(_, ty)) = MAGIC.iter().find(|&&(magic, _)| head.starts_with(magic)) {
+        return Some(ty);
+    }
+    // RIFF containers say what they hold after the size
+    if head.len() >= 12 && &head[..4] == b"RIFF" {
+        if &head[8..12] == b"WEBP" {
+            return Some("image/webp");
+        }
+        if &head[8..12] == b"WAVE" {
+            return Some("audio/wav");
+        }
+    }
+
+    let extension = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.to_lowercase(),
+        None => return None,
+    };
+    EXTENSIONS.iter().find(|&&(ext, _)| ext == extension).map(|&(_, ty)| ty)
+}
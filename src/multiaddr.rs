@@ -0,0 +1,299 @@
+// Multiaddrs: self-describing network addresses such as
+// /ip4/127.0.0.1/tcp/4001/p2p/<peer id>. Only the protocols needed for TCP
+// connections are supported, along with /p2p-circuit for connections relayed
+// through another peer: <relay address>/p2p/<relay id>/p2p-circuit/p2p/<peer id>.
+
+use codec;
+use error::Error;
+use peer::{self, PeerId};
+
+use rust_multihash::Multihash;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::str;
+
+// protocol codes used in the binary encoding
+const CODE_IP4: u64 = 4;
+const CODE_TCP: u64 = 6;
+const CODE_IP6: u64 = 41;
+const CODE_DNS: u64 = 53;
+const CODE_DNS4: u64 = 54;
+const CODE_DNS6: u64 = 55;
+const CODE_P2P_CIRCUIT: u64 = 290;
+const CODE_P2P: u64 = 421;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Ip4(Ipv4Addr),
+    Ip6(Ipv6Addr),
+    Dns(String),
+    Dns4(String), // resolves to IPv4 addresses only
+    Dns6(String), // resolves to IPv6 addresses only
+    Tcp(u16),
+    P2p(PeerId),
+    P2pCircuit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Multiaddr {
+    protocols: Vec<Protocol>,
+}
+
+impl Multiaddr {
+    pub fn new(protocols: Vec<Protocol>) -> Self {
+        Multiaddr { protocols: protocols }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidArgument(format!("Invalid multiaddr {}: {}", s, msg));
+
+        let mut parts = s.split('/');
+        if parts.next() != Some("") {
+            return Err(invalid("must start with /"));
+        }
+
+        let mut protocols = Vec::new();
+        while let Some(name) = parts.next() {
+            if name.is_empty() && parts.clone().next().is_none() {
+                break; // trailing slash
+            }
+            if name == "p2p-circuit" {
+                protocols.push(Protocol::P2pCircuit);
+                continue;
+            }
+            let value = match parts.next() {
+                None => return Err(invalid(&format!("missing value for {}", name))),
+                Some(value) => value,
+            };
+
+            let protocol = match name {
+                "ip4" => Protocol::Ip4(try!(value.parse().map_err(|_| invalid("bad IPv4 address")))),
+                "ip6" => Protocol::Ip6(try!(value.parse().map_err(|_| invalid("bad IPv6 address")))),
+                "dns" => Protocol::Dns(value.to_string()),
+                "dns4" => Protocol::Dns4(value.to_string()),
+                "dns6" => Protocol::Dns6(value.to_string()),
+                "tcp" => Protocol::Tcp(try!(value.parse().map_err(|_| invalid("bad TCP port")))),
+                "p2p" | "ipfs" => Protocol::P2p(try!(peer::parse_peer_id(value))),
+                _ => return Err(invalid(&format!("unsupported protocol {}", name))),
+            };
+            protocols.push(protocol);
+        }
+
+        if protocols.is_empty() {
+            return Err(invalid("no protocols"));
+        }
+        Ok(Multiaddr::new(protocols))
+    }
+
+    pub fn from_socket_addr(addr: &SocketAddr) -> Self {
+        let ip = match addr.ip() {
+            IpAddr::V4(ip) => Protocol::Ip4(ip),
+            IpAddr::V6(ip) => Protocol::Ip6(ip),
+        };
+        Multiaddr::new(vec![ip, Protocol::Tcp(addr.port())])
+    }
+
+    pub fn protocols(&self) -> &[Protocol] {
+        &self.protocols
+    }
+
+    pub fn push(&mut self, protocol: Protocol) {
+        self.protocols.push(protocol);
+    }
+
+    // The peer ID at the end of the address, if any
+    pub fn peer_id(&self) -> Option<&PeerId> {
+        match self.protocols.last() {
+            Some(&Protocol::P2p(ref peer)) => Some(peer),
+            _ => None,
+        }
+    }
+
+    pub fn without_peer_id(&self) -> Self {
+        let mut addr = self.clone();
+        if self.peer_id().is_some() {
+            addr.protocols.pop();
+        }
+        addr
+    }
+
+    // Whether the address goes through a relay
+    pub fn is_circuit(&self) -> bool {
+        self.protocols.contains(&Protocol::P2pCircuit)
+    }
+
+    // The address of the relay of a /p2p-circuit address, ending with the
+    // relay's /p2p/<peer id>
+    pub fn relay_addr(&self) -> Option<Multiaddr> {
+        self.protocols
+            .iter()
+            .position(|p| *p == Protocol::P2pCircuit)
+            .map(|i| Multiaddr::new(self.protocols[..i].to_vec()))
+    }
+
+    // Resolves an address of the form /<ip4|ip6|dns|dns4|dns6>/<host>/tcp/<port>
+    // (optionally followed by /p2p/<peer id>) to the socket addresses to
+    // connect to
+    pub fn to_socket_addrs(&self) -> Result<Vec<SocketAddr>, Error> {
+        let addr = self.without_peer_id();
+        let (host, port) = match (addr.protocols.len(), addr.protocols.last()) {
+            (2, Some(&Protocol::Tcp(port))) => (&addr.protocols[0], port),
+            _ => {
+                return Err(Error::InvalidArgument(format!("{} is not a TCP address", self)))
+            }
+        };
+
+        let (name, want_v4, want_v6) = match *host {
+            Protocol::Ip4(ip) => return Ok(vec![SocketAddr::new(IpAddr::V4(ip), port)]),
+            Protocol::Ip6(ip) => return Ok(vec![SocketAddr::new(IpAddr::V6(ip), port)]),
+            Protocol::Dns(ref name) => (name, true, true),
+            Protocol::Dns4(ref name) => (name, true, false),
+            Protocol::Dns6(ref name) => (name, false, true),
+            _ => return Err(Error::InvalidArgument(format!("{} has no host", self))),
+        };
+
+        let resolved = try!((&name[..], port).to_socket_addrs().map_err(|e| {
+            Error::Io(format!("Error resolving {}", name), e)
+        }));
+        let addrs: Vec<_> = resolved.filter(|a| {
+                                        match *a {
+                                            SocketAddr::V4(_) => want_v4,
+                                            SocketAddr::V6(_) => want_v6,
+                                        }
+                                    })
+                                    .collect();
+        if addrs.is_empty() {
+            return Err(Error::NotFound(format!("addresses for {}", name)));
+        }
+        Ok(addrs)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for protocol in &self.protocols {
+            match *protocol {
+                Protocol::Ip4(ip) => {
+                    codec::encode_varint(CODE_IP4, &mut buf);
+                    buf.extend_from_slice(&ip.octets());
+                }
+                Protocol::Ip6(ip) => {
+                    codec::encode_varint(CODE_IP6, &mut buf);
+                    for segment in &ip.segments() {
+                        buf.push((segment >> 8) as u8);
+                        buf.push(*segment as u8);
+                    }
+                }
+                Protocol::Dns(ref name) => encode_length_prefixed(CODE_DNS, name.as_bytes(), &mut buf),
+                Protocol::Dns4(ref name) => encode_length_prefixed(CODE_DNS4, name.as_bytes(), &mut buf),
+                Protocol::Dns6(ref name) => encode_length_prefixed(CODE_DNS6, name.as_bytes(), &mut buf),
+                Protocol::Tcp(port) => {
+                    codec::encode_varint(CODE_TCP, &mut buf);
+                    buf.push((port >> 8) as u8);
+                    buf.push(port as u8);
+                }
+                Protocol::P2p(ref peer) => {
+                    encode_length_prefixed(CODE_P2P, &peer.clone().into_bytes(), &mut buf)
+                }
+                Protocol::P2pCircuit => codec::encode_varint(CODE_P2P_CIRCUIT, &mut buf),
+            }
+        }
+        buf
+    }
+
+    pub fn from_bytes(mut buf: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::Decode("Invalid binary multiaddr".to_string(), None);
+
+        let mut protocols = Vec::new();
+        while !buf.is_empty() {
+            let (code, n) = try!(codec::decode_varint(buf));
+            buf = &buf[n..];
+
+            let size = match code {
+                CODE_IP4 => 4,
+                CODE_IP6 => 16,
+                CODE_TCP => 2,
+                CODE_P2P_CIRCUIT => 0,
+                CODE_DNS | CODE_DNS4 | CODE_DNS6 | CODE_P2P => {
+                    let (size, n) = try!(codec::decode_varint(buf));
+                    buf = &buf[n..];
+                    size as usize
+                }
+                _ => {
+                    return Err(Error::Decode(format!("Unsupported multiaddr protocol code {}",
+                                                     code),
+                                             None))
+                }
+            };
+            if buf.len() < size {
+                return Err(invalid());
+            }
+            let (value, rest) = buf.split_at(size);
+            buf = rest;
+
+            let protocol = match code {
+                CODE_IP4 => Protocol::Ip4(Ipv4Addr::new(value[0], value[1], value[2], value[3])),
+                CODE_IP6 => {
+                    let mut segments = [0u16; 8];
+                    for (i, segment) in segments.iter_mut().enumerate() {
+                        *segment = (value[2 * i] as u16) << 8 | value[2 * i + 1] as u16;
+                    }
+                    Protocol::Ip6(Ipv6Addr::new(segments[0],
+                                                segments[1],
+                                                segments[2],
+                                                segments[3],
+                                                segments[4],
+                                                segments[5],
+                                                segments[6],
+                                                segments[7]))
+                }
+                CODE_TCP => Protocol::Tcp((value[0] as u16) << 8 | value[1] as u16),
+                CODE_P2P => Protocol::P2p(Multihash::from_vec(value.to_vec())),
+                CODE_P2P_CIRCUIT => Protocol::P2pCircuit,
+                _ => {
+                    let name = try!(str::from_utf8(value).map_err(|_| invalid())).to_string();
+                    match code {
+                        CODE_DNS => Protocol::Dns(name),
+                        CODE_DNS4 => Protocol::Dns4(name),
+                        _ => Protocol::Dns6(name),
+                    }
+                }
+            };
+            protocols.push(protocol);
+        }
+
+        if protocols.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Multiaddr::new(protocols))
+    }
+}
+
+fn encode_length_prefixed(code: u64, value: &[u8], buf: &mut Vec<u8>) {
+    codec::encode_varint(code, buf);
+    codec::encode_varint(value.len() as u64, buf);
+    buf.extend_from_slice(value);
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Protocol::Ip4(ip) => write!(f, "/ip4/{}", ip),
+            Protocol::Ip6(ip) => write!(f, "/ip6/{}", ip),
+            Protocol::Dns(ref name) => write!(f, "/dns/{}", name),
+            Protocol::Dns4(ref name) => write!(f, "/dns4/{}", name),
+            Protocol::Dns6(ref name) => write!(f, "/dns6/{}", name),
+            Protocol::Tcp(port) => write!(f, "/tcp/{}", port),
+            Protocol::P2p(ref peer) => write!(f, "/p2p/{}", peer),
+            Protocol::P2pCircuit => write!(f, "/p2p-circuit"),
+        }
+    }
+}
+
+impl fmt::Display for Multiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for protocol in &self.protocols {
+            try!(write!(f, "{}", protocol));
+        }
+        Ok(())
+    }
+}
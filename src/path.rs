@@ -0,0 +1,92 @@
+// Resolves ipfs paths to DAG nodes.
+//
+// Accepted forms are `/ipfs/<cid>/<name>/...`, and the same without the
+// `/ipfs/` prefix (`<cid>/<name>/...`), where the CID is a base58 hash or a
+// base32 version 1 CID (see cid). Each name after the CID is looked up
+// among the links of the node reached so far, or in its shards if it's a
+// sharded directory. `/ipns/<name>/...` paths are first resolved to `/ipfs/`
+// paths by the name system (see ipns::namesys).
+//
+// Symlinks are never followed: their targets are paths on the filesystem
+// they were added from, so a path naming anything under a symlink is
+// refused. A path ending at a symlink resolves to the symlink itself.
+
+use cid::Cid;
+use error::Error;
+use merkledag::{DagService, Node};
+use unixfs::{self, directory};
+
+use std::sync::Arc;
+
+pub const IPFS_PREFIX: &'static str = "/ipfs/";
+pub const IPNS_PREFIX: &'static str = "/ipns/";
+
+/// A parsed ipfs path: the CID of the root object, and the link names to
+/// follow from it
+#[derive(Debug)]
+pub struct IpfsPath {
+    pub root: Cid,
+    pub components: Vec<String>,
+}
+
+impl IpfsPath {
+    pub fn parse(path: &str) -> Result<Self, Error> {
+        let trimmed = if path.starts_with(IPFS_PREFIX) {
+            &path[IPFS_PREFIX.len()..]
+        } else if path.starts_with("/") {
+            return Err(Error::InvalidPath(format!("{} (unknown namespace)", path)));
+        } else {
+            path
+        };
+
+        let mut parts = trimmed.split('/').filter(|s| !s.is_empty());
+        let root = match parts.next() {
+            None => return Err(Error::InvalidPath(format!("{} (no hash)", path))),
+            Some(cid) => {
+                try!(Cid::parse(cid).map_err(|e| Error::InvalidPath(format!("{}: {}", path, e))))
+            }
+        };
+
+        Ok(IpfsPath {
+            root: root,
+            components: parts.map(|s| s.to_string()).collect(),
+        })
+    }
+}
+
+/// Resolves `path` to the node it names
+pub fn resolve(ds: &DagService, path: &str) -> Result<Arc<Node>, Error> {
+    let parsed = try!(IpfsPath::parse(path));
+    let root = try!(ds.get_cid(&parsed.root));
+    resolve_links(ds, root, &parsed.components)
+}
+
+/// Follows the links named by `components`, starting at `node`
+pub fn resolve_links(ds: &DagService,
+                     node: Arc<Node>,
+                     components: &[String])
+                     -> Result<Arc<Node>, Error> {
+    let mut node = node;
+    for (i, name) in components.iter().enumerate() {
+        if let Ok(Some(target)) = unixfs::symlink_target(&node) {
+            let symlink = match i {
+                0 => node.cid().to_string(),
+                _ => components[..i].join("/"),
+            };
+            return Err(Error::InvalidPath(format!("{} is a symlink to {} (symlinks are not \
+                                                   followed)",
+                                                  symlink,
+                                                  target)));
+        }
+        let next = match try!(directory::find_link(ds, &node, name)) {
+            None => {
+                return Err(Error::NotFound(format!("no link named {} under {}",
+                                                   components[..i + 1].join("/"),
+                                                   node.cid())))
+            }
+            Some(link) => try!(link.get_node(ds)),
+        };
+        node = next;
+    }
+    Ok(node)
+}
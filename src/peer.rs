@@ -0,0 +1,12 @@
+use error::Error;
+
+use rust_multihash::Multihash;
+
+// A peer is identified by the multihash of its public key (see config::init)
+pub type PeerId = Multihash;
+
+pub fn parse_peer_id(s: &str) -> Result<PeerId, Error> {
+    Multihash::from_base58_str(s).map_err(|e| {
+        Error::InvalidArgument(format!("Invalid peer ID {}: {}", s, e))
+    })
+}
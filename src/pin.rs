@@ -0,0 +1,52 @@
+// The pin set: the roots of DAGs kept in local storage, along with
+// everything they link to. Pins are stored in the datastore as
+// /pins/<cid>. Content added to a node is pinned.
+
+use cid::Cid;
+use datastore::Datastore;
+use error::Error;
+
+use std::sync::Arc;
+
+const PINS_PREFIX: &'static str = "/pins";
+
+pub struct PinSet {
+    datastore: Arc<Datastore>,
+}
+
+impl PinSet {
+    pub fn new(datastore: Arc<Datastore>) -> Self {
+        PinSet { datastore: datastore }
+    }
+
+    pub fn pin(&self, root: &Cid) -> Result<(), Error> {
+        self.datastore.put(&pin_key(root), &[])
+    }
+
+    pub fn unpin(&self, root: &Cid) -> Result<(), Error> {
+        if !try!(self.is_pinned(root)) {
+            return Err(Error::NotFound(format!("pin for {}", root)));
+        }
+        self.datastore.delete(&pin_key(root))
+    }
+
+    pub fn is_pinned(&self, root: &Cid) -> Result<bool, Error> {
+        self.datastore.has(&pin_key(root))
+    }
+
+    pub fn roots(&self) -> Result<Vec<Cid>, Error> {
+        let mut roots = Vec::new();
+        for (key, _) in try!(self.datastore.query(PINS_PREFIX)) {
+            let cid = key.rsplit('/').next().unwrap();
+            match Cid::parse(cid) {
+                Ok(root) => roots.push(root),
+                Err(e) => warn!("Invalid pin {}: {}", key, e),
+            }
+        }
+        Ok(roots)
+    }
+}
+
+fn pin_key(root: &Cid) -> String {
+    format!("{}/{}", PINS_PREFIX, root)
+}
@@ -0,0 +1,215 @@
+// Announcing the content we have to the DHT. Content is announced when it's
+// added, and the reprovider announces it again every interval, since
+// provider records expire. Which blocks are announced depends on the
+// strategy:
+//
+//  - all: every block in the blockstore
+//  - pinned: every block of the pinned DAGs
+//  - roots: only the roots of the pinned DAGs
+//
+// When each block was last announced is kept in the datastore as
+// /reprovider/provided/<hash>, so a restarted node only announces the
+// blocks that are due.
+
+use blockstore::Blockstore;
+use cid::{Cid, Codec};
+use datastore::Datastore;
+use dht::Dht;
+use error::Error;
+use merkledag::DagService;
+use pin::PinSet;
+use util;
+
+use rust_multihash::Multihash;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_STRATEGY: &'static str = "all";
+pub const DEFAULT_INTERVAL_SECS: u64 = 12 * 60 * 60;
+
+// Give the node time to connect to peers before the first run
+const INITIAL_DELAY_SECS: u64 = 60;
+// How often to check for blocks that are due
+const CHECK_INTERVAL_SECS: u64 = 10 * 60;
+const PROVIDED_PREFIX: &'static str = "/reprovider/provided";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Strategy {
+    All,
+    Pinned,
+    Roots,
+}
+
+impl Strategy {
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        match s {
+            "all" => Ok(Strategy::All),
+            "pinned" => Ok(Strategy::Pinned),
+            "roots" => Ok(Strategy::Roots),
+            _ => {
+                Err(Error::InvalidArgument(format!("Unknown reprovider strategy {}: expected \
+                                                    all, pinned or roots",
+                                                   s)))
+            }
+        }
+    }
+}
+
+pub struct Reprovider {
+    dht: Arc<Dht>,
+    blockstore: Arc<Blockstore>,
+    dagservice: Arc<DagService>,
+    pins: Arc<PinSet>,
+    datastore: Arc<Datastore>,
+    strategy: Strategy,
+    interval: Duration,
+}
+
+impl Reprovider {
+    pub fn new(dht: Arc<Dht>,
+               blockstore: Arc<Blockstore>,
+               dagservice: Arc<DagService>,
+               pins: Arc<PinSet>,
+               datastore: Arc<Datastore>,
+               strategy: Strategy,
+               interval: Duration)
+               -> Self {
+        Reprovider {
+            dht: dht,
+            blockstore: blockstore,
+            dagservice: dagservice,
+            pins: pins,
+            datastore: datastore,
+            strategy: strategy,
+            interval: interval,
+        }
+    }
+
+    // Announces the blocks of a newly added or pinned DAG in the background,
+    // according to the strategy
+    pub fn provide_dag(reprovider: &Arc<Self>, root: &Cid) {
+        let reprovider = reprovider.clone();
+        let root = root.clone();
+        thread::spawn(move || {
+            let keys = match reprovider.strategy {
+                Strategy::Roots => Ok(block_key(&root).into_iter().collect()),
+                Strategy::All | Strategy::Pinned => reprovider.dag_keys(&root),
+            };
+            let result = keys.and_then(|keys| {
+                for key in keys {
+                    try!(reprovider.provide(&key));
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                warn!("Error announcing {}: {}", root, e);
+            }
+        });
+    }
+
+    // Announces a block now, and records when
+    pub fn provide(&self, key: &Multihash) -> Result<(), Error> {
+        try!(self.dht.provide(key));
+        self.datastore.put(&provided_key(key), util::unix_time().to_string().as_bytes())
+    }
+
+    // Announces the blocks that haven't been announced within the interval,
+    // returning how many were
+    pub fn reprovide(&self) -> Result<usize, Error> {
+        let now = util::unix_time();
+        let mut provided = 0;
+        for key in try!(self.keys()) {
+            let last_provided = match self.datastore.get(&provided_key(&key)) {
+                Err(Error::NotFound(_)) => 0,
+                Err(e) => return Err(e),
+                Ok(value) => String::from_utf8_lossy(&value).parse::<u64>().unwrap_or(0),
+            };
+            if last_provided + self.interval.as_secs() > now {
+                continue;
+            }
+            try!(self.provide(&key));
+            provided += 1;
+        }
+        Ok(provided)
+    }
+
+    // Reprovides in the background for as long as the node runs
+    pub fn start(reprovider: &Arc<Self>) {
+        let reprovider = reprovider.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(INITIAL_DELAY_SECS));
+            loop {
+                match reprovider.reprovide() {
+                    Ok(0) => {}
+                    Ok(n) => info!("Reprovided {} blocks", n),
+                    Err(e) => warn!("Error reproviding: {}", e),
+                }
+                let check = Duration::from_secs(CHECK_INTERVAL_SECS);
+                thread::sleep(if reprovider.interval < check {
+                    reprovider.interval
+                } else {
+                    check
+                });
+            }
+        });
+    }
+
+    // The blocks to announce
+    fn keys(&self) -> Result<Vec<Multihash>, Error> {
+        match self.strategy {
+            Strategy::All => self.blockstore.keys(),
+            Strategy::Roots => {
+                Ok(try!(self.pins.roots()).iter().filter_map(block_key).collect())
+            }
+            Strategy::Pinned => {
+                let mut keys = Vec::new();
+                let mut seen = HashSet::new();
+                for root in try!(self.pins.roots()) {
+                    for key in try!(self.dag_keys(&root)) {
+                        if seen.insert(key.clone()) {
+                            keys.push(key);
+                        }
+                    }
+                }
+                Ok(keys)
+            }
+        }
+    }
+
+    // The hashes of `root` and every node under it. Only local blocks are
+    // included, and raw blocks, having no links, aren't read.
+    fn dag_keys(&self, root: &Cid) -> Result<Vec<Multihash>, Error> {
+        let mut keys = Vec::new();
+        let mut to_visit = vec![root.clone()];
+        while let Some(cid) = to_visit.pop() {
+            let key = block_key(&cid);
+            if let Some(ref key) = key {
+                if !try!(self.blockstore.has(key)) {
+                    continue;
+                }
+            }
+            if cid.codec == Codec::DagProtobuf {
+                let node = try!(self.dagservice.get_cid(&cid));
+                for link in node.get_links() {
+                    to_visit.push(link.cid());
+                }
+            }
+            keys.extend(key);
+        }
+        Ok(keys)
+    }
+}
+
+// The block to announce for `cid`: none if it's inlined in the CID
+fn block_key(cid: &Cid) -> Option<Multihash> {
+    match cid.inline_data() {
+        Some(_) => None,
+        None => Some(cid.hash.clone()),
+    }
+}
+
+fn provided_key(key: &Multihash) -> String {
+    format!("{}/{}", PROVIDED_PREFIX, key)
+}
@@ -0,0 +1,108 @@
+// The IDs of messages seen recently, so that each message is handled once,
+// and the messages of the last few heartbeats, which gossipsub peers can ask
+// for after hearing about them.
+
+use super::message::Message;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+pub struct SeenCache {
+    ttl: Duration,
+    ids: HashSet<String>,
+    added: VecDeque<(Instant, String)>, // oldest first
+}
+
+impl SeenCache {
+    pub fn new(ttl: Duration) -> Self {
+        SeenCache {
+            ttl: ttl,
+            ids: HashSet::new(),
+            added: VecDeque::new(),
+        }
+    }
+
+    // Returns whether `id` is new
+    pub fn add(&mut self, id: &str) -> bool {
+        self.sweep();
+        if self.ids.contains(id) {
+            return false;
+        }
+        self.ids.insert(id.to_string());
+        self.added.push_back((Instant::now(), id.to_string()));
+        true
+    }
+
+    pub fn has(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    fn sweep(&mut self) {
+        loop {
+            match self.added.front() {
+                Some(&(added, _)) if added.elapsed() > self.ttl => {}
+                _ => return,
+            }
+            if let Some((_, id)) = self.added.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+pub struct MessageCache {
+    msgs: HashMap<String, Message>,
+    // the IDs and topics of the messages added during each of the last
+    // heartbeats, newest first
+    windows: VecDeque<Vec<(String, Vec<String>)>>,
+    history: usize,
+    gossip: usize,
+}
+
+impl MessageCache {
+    // Keeps messages for `history` heartbeats, and gossips about those of the
+    // last `gossip` ones
+    pub fn new(history: usize, gossip: usize) -> Self {
+        let mut windows = VecDeque::new();
+        windows.push_front(Vec::new());
+        MessageCache {
+            msgs: HashMap::new(),
+            windows: windows,
+            history: history,
+            gossip: gossip,
+        }
+    }
+
+    pub fn put(&mut self, msg: Message) {
+        let id = msg.id();
+        self.windows[0].push((id.clone(), msg.topics.clone()));
+        self.msgs.insert(id, msg);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Message> {
+        self.msgs.get(id)
+    }
+
+    // The IDs of recent messages on `topic`
+    pub fn gossip_ids(&self, topic: &str) -> Vec<String> {
+        self.windows
+            .iter()
+            .take(self.gossip)
+            .flat_map(|window| window.iter())
+            .filter(|&&(_, ref topics)| topics.iter().any(|t| t == topic))
+            .map(|&(ref id, _)| id.clone())
+            .collect()
+    }
+
+    // Starts a new heartbeat, forgetting the messages of the oldest one
+    pub fn shift(&mut self) {
+        if self.windows.len() >= self.history {
+            if let Some(window) = self.windows.pop_back() {
+                for (id, _) in window {
+                    self.msgs.remove(&id);
+                }
+            }
+        }
+        self.windows.push_front(Vec::new());
+    }
+}
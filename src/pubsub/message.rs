@@ -0,0 +1,264 @@
+// Pubsub RPCs, encoded as described in pubsub.proto
+
+use codec::{PbReader, PbWriter};
+use crypto::{PrivateKey, PublicKey};
+use error::Error;
+use peer::PeerId;
+
+use rust_multihash::Multihash;
+use rustc_serialize::hex::ToHex;
+
+const SIGNATURE_PREFIX: &'static [u8] = b"libp2p-pubsub:";
+
+#[derive(Clone, Debug)]
+pub struct SubOpts {
+    pub subscribe: bool, // false to unsubscribe
+    pub topic: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub from: PeerId,
+    pub data: Vec<u8>,
+    pub seqno: Vec<u8>,
+    pub topics: Vec<String>,
+    pub signature: Vec<u8>,
+    pub key: Vec<u8>,
+}
+
+impl Message {
+    // A message from `key`'s owner, signed with it
+    pub fn new(key: &PrivateKey, seqno: u64, topics: Vec<String>, data: Vec<u8>) -> Self {
+        let public_key = key.public_key();
+        let mut seqno_bytes = Vec::with_capacity(8);
+        for i in 0..8 {
+            seqno_bytes.push((seqno >> (56 - 8 * i)) as u8);
+        }
+        let mut msg = Message {
+            from: public_key.id(),
+            data: data,
+            seqno: seqno_bytes,
+            topics: topics,
+            signature: Vec::new(),
+            key: public_key.to_bytes(),
+        };
+        msg.signature = key.sign(&msg.signed_data());
+        msg
+    }
+
+    // Identifies the message for deduplication and gossip
+    pub fn id(&self) -> String {
+        format!("{}{}", self.from, self.seqno.to_hex())
+    }
+
+    // Checks that the message was signed by its author
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.signature.is_empty() || self.key.is_empty() {
+            return Err(Error::InvalidArgument(format!("Message {} is not signed", self.id())));
+        }
+        let key = try!(PublicKey::from_bytes(&self.key));
+        if key.id() != self.from {
+            return Err(Error::InvalidArgument(format!("Message {} was signed by another key",
+                                                      self.id())));
+        }
+        if !key.verify(&self.signed_data(), &self.signature) {
+            return Err(Error::InvalidArgument(format!("Invalid signature on message {}",
+                                                      self.id())));
+        }
+        Ok(())
+    }
+
+    fn signed_data(&self) -> Vec<u8> {
+        let mut data = SIGNATURE_PREFIX.to_vec();
+        data.extend(self.encode_unsigned().into_bytes());
+        data
+    }
+
+    fn encode_unsigned(&self) -> PbWriter {
+        let mut msg = PbWriter::new();
+        msg.bytes(1, &self.from.clone().into_bytes())
+           .bytes(2, &self.data)
+           .bytes(3, &self.seqno);
+        for topic in &self.topics {
+            msg.string(4, topic);
+        }
+        msg
+    }
+
+    fn encode(&self) -> PbWriter {
+        let mut msg = self.encode_unsigned();
+        msg.bytes(5, &self.signature).bytes(6, &self.key);
+        msg
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut msg = Message {
+            from: Multihash::from_vec(vec![]),
+            data: Vec::new(),
+            seqno: Vec::new(),
+            topics: Vec::new(),
+            signature: Vec::new(),
+            key: Vec::new(),
+        };
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => msg.from = Multihash::from_vec(try!(value.as_bytes()).to_vec()),
+                2 => msg.data = try!(value.as_bytes()).to_vec(),
+                3 => msg.seqno = try!(value.as_bytes()).to_vec(),
+                4 => msg.topics.push(try!(value.as_string())),
+                5 => msg.signature = try!(value.as_bytes()).to_vec(),
+                6 => msg.key = try!(value.as_bytes()).to_vec(),
+                _ => {}
+            }
+        }
+        Ok(msg)
+    }
+}
+
+// Gossipsub control messages
+#[derive(Clone, Debug)]
+pub struct Control {
+    pub ihave: Vec<(String, Vec<String>)>, // topic and message IDs
+    pub iwant: Vec<String>,
+    pub graft: Vec<String>,
+    pub prune: Vec<String>,
+}
+
+impl Control {
+    pub fn new() -> Self {
+        Control {
+            ihave: Vec::new(),
+            iwant: Vec::new(),
+            graft: Vec::new(),
+            prune: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ihave.is_empty() && self.iwant.is_empty() && self.graft.is_empty() &&
+        self.prune.is_empty()
+    }
+
+    fn encode(&self) -> PbWriter {
+        let mut msg = PbWriter::new();
+        for &(ref topic, ref ids) in &self.ihave {
+            let mut ihave = PbWriter::new();
+            ihave.string(1, topic);
+            for id in ids {
+                ihave.string(2, id);
+            }
+            msg.message(1, &ihave);
+        }
+        if !self.iwant.is_empty() {
+            let mut iwant = PbWriter::new();
+            for id in &self.iwant {
+                iwant.string(1, id);
+            }
+            msg.message(2, &iwant);
+        }
+        for topic in &self.graft {
+            let mut graft = PbWriter::new();
+            graft.string(1, topic);
+            msg.message(3, &graft);
+        }
+        for topic in &self.prune {
+            let mut prune = PbWriter::new();
+            prune.string(1, topic);
+            msg.message(4, &prune);
+        }
+        msg
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut control = Control::new();
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            let mut topic = String::new();
+            let mut ids = Vec::new();
+            let mut inner = PbReader::new(try!(value.as_bytes()));
+            while let Some((inner_field, inner_value)) = try!(inner.next_field()) {
+                match (field, inner_field) {
+                    (1, 1) | (3, 1) | (4, 1) => topic = try!(inner_value.as_string()),
+                    (1, 2) | (2, 1) => ids.push(try!(inner_value.as_string())),
+                    _ => {}
+                }
+            }
+            match field {
+                1 => control.ihave.push((topic, ids)),
+                2 => control.iwant.extend(ids),
+                3 => control.graft.push(topic),
+                4 => control.prune.push(topic),
+                _ => {}
+            }
+        }
+        Ok(control)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rpc {
+    pub subscriptions: Vec<SubOpts>,
+    pub messages: Vec<Message>,
+    pub control: Control,
+}
+
+impl Rpc {
+    pub fn new() -> Self {
+        Rpc {
+            subscriptions: Vec::new(),
+            messages: Vec::new(),
+            control: Control::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty() && self.messages.is_empty() && self.control.is_empty()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut rpc = PbWriter::new();
+        for sub in &self.subscriptions {
+            let mut opts = PbWriter::new();
+            opts.bool(1, sub.subscribe).string(2, &sub.topic);
+            rpc.message(1, &opts);
+        }
+        for msg in &self.messages {
+            rpc.message(2, &msg.encode());
+        }
+        if !self.control.is_empty() {
+            rpc.message(3, &self.control.encode());
+        }
+        rpc.into_bytes()
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut rpc = Rpc::new();
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => rpc.subscriptions.push(try!(decode_sub_opts(try!(value.as_bytes())))),
+                2 => rpc.messages.push(try!(Message::decode(try!(value.as_bytes())))),
+                3 => rpc.control = try!(Control::decode(try!(value.as_bytes()))),
+                _ => {}
+            }
+        }
+        Ok(rpc)
+    }
+}
+
+fn decode_sub_opts(buf: &[u8]) -> Result<SubOpts, Error> {
+    let mut opts = SubOpts {
+        subscribe: false,
+        topic: String::new(),
+    };
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => opts.subscribe = try!(value.as_bool()),
+            2 => opts.topic = try!(value.as_string()),
+            _ => {}
+        }
+    }
+    Ok(opts)
+}
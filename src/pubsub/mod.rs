@@ -0,0 +1,651 @@
+// Publish/subscribe messaging between peers, with the floodsub and gossipsub
+// protocols of libp2p. Messages are published to topics, and delivered to
+// every peer subscribed to them.
+//
+// Peers tell each other the topics they subscribe to. With floodsub, a
+// message is forwarded to every peer subscribed to one of its topics. With
+// gossipsub, it's only forwarded to the peers of the topic's mesh: a random
+// subset of about D of the subscribed peers, kept up to date every
+// heartbeat with GRAFT and PRUNE messages. Every heartbeat, we also tell a
+// few peers outside the mesh (IHAVE) the IDs of recent messages, which they
+// can ask for (IWANT) if they missed them. Gossipsub nodes speak floodsub
+// to peers that only know floodsub.
+//
+// Every message is signed by its author, and messages with invalid
+// signatures are dropped. Each message is handled once: the IDs of the
+// messages seen in the last two minutes are remembered.
+
+use codec;
+use crypto::{self, PrivateKey};
+use error::Error;
+use peer::PeerId;
+use swarm::{Notifiee, Stream, Swarm};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use self::cache::{MessageCache, SeenCache};
+pub use self::message::{Control, Message, Rpc, SubOpts};
+
+pub mod cache;
+pub mod message;
+
+pub const FLOODSUB_ID: &'static str = "/floodsub/1.0.0";
+pub const GOSSIPSUB_ID: &'static str = "/meshsub/1.0.0";
+pub const DEFAULT_ROUTER: &'static str = "gossipsub";
+
+// Gossipsub parameters, as in go-libp2p-pubsub
+const D: usize = 6; // the number of peers in a mesh
+const D_LOW: usize = 4;
+const D_HIGH: usize = 12;
+const HEARTBEAT_INTERVAL_SECS: u64 = 1;
+const FANOUT_TTL_SECS: u64 = 60;
+const HISTORY_LENGTH: usize = 5;
+const HISTORY_GOSSIP: usize = 3;
+
+const SEEN_TTL_SECS: u64 = 120;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Router {
+    Floodsub,
+    Gossipsub,
+}
+
+impl Router {
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        match s {
+            "floodsub" => Ok(Router::Floodsub),
+            "gossipsub" => Ok(Router::Gossipsub),
+            _ => {
+                Err(Error::InvalidArgument(format!("Unknown pubsub router {}: expected \
+                                                    floodsub or gossipsub",
+                                                   s)))
+            }
+        }
+    }
+}
+
+// Messages published to a topic we subscribed to. Iterating blocks until
+// the next message arrives. Dropping the subscription unsubscribes.
+pub struct Subscription {
+    pub topic: String,
+    id: u64,
+    receiver: Receiver<Message>,
+    pubsub: Arc<PubSub>,
+}
+
+impl Iterator for Subscription {
+    type Item = Message;
+
+    fn next(&mut self) -> Option<Message> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.pubsub.unsubscribe(&self.topic, self.id);
+    }
+}
+
+struct PeerInfo {
+    protocol: Router, // the protocol the peer speaks
+    topics: HashSet<String>,
+}
+
+struct State {
+    // local subscribers to each topic
+    subscriptions: HashMap<String, Vec<(u64, Sender<Message>)>>,
+    next_subscription_id: u64,
+    peers: HashMap<PeerId, PeerInfo>,
+    // the gossipsub peers we forward each subscribed topic's messages to
+    mesh: HashMap<String, HashSet<PeerId>>,
+    // the gossipsub peers we publish to on topics we're not subscribed to,
+    // and when we last did
+    fanout: HashMap<String, (HashSet<PeerId>, Instant)>,
+    seen: SeenCache,
+    mcache: MessageCache,
+    seqno: u64,
+}
+
+impl State {
+    // The peers speaking `protocol` subscribed to `topic`
+    fn topic_peers(&self, topic: &str, protocol: Router) -> Vec<PeerId> {
+        self.peers
+            .iter()
+            .filter(|&(_, info)| info.protocol == protocol && info.topics.contains(topic))
+            .map(|(peer, _)| peer.clone())
+            .collect()
+    }
+
+    // Up to `n` random gossipsub peers subscribed to `topic`, other than
+    // those in `exclude`
+    fn random_peers(&self, topic: &str, n: usize, exclude: &HashSet<PeerId>) -> Vec<PeerId> {
+        let mut peers: Vec<PeerId> = self.topic_peers(topic, Router::Gossipsub)
+                                         .into_iter()
+                                         .filter(|peer| !exclude.contains(peer))
+                                         .collect();
+        shuffle(&mut peers);
+        peers.truncate(n);
+        peers
+    }
+}
+
+pub struct PubSub {
+    swarm: Arc<Swarm>,
+    key: PrivateKey,
+    router: Router,
+    state: Mutex<State>,
+    // the streams we send RPCs to each peer on
+    streams: Mutex<HashMap<PeerId, Arc<Mutex<Stream>>>>,
+}
+
+impl PubSub {
+    // Takes part in pubsub on the swarm with `router`, signing messages with
+    // `key`, the node's key
+    pub fn new(swarm: Arc<Swarm>, key: PrivateKey, router: Router) -> Arc<Self> {
+        // sequence numbers must not repeat across restarts
+        let since_epoch = SystemTime::now()
+                              .duration_since(UNIX_EPOCH)
+                              .unwrap_or(Duration::new(0, 0));
+        let pubsub = Arc::new(PubSub {
+            swarm: swarm.clone(),
+            key: key,
+            router: router,
+            state: Mutex::new(State {
+                subscriptions: HashMap::new(),
+                next_subscription_id: 0,
+                peers: HashMap::new(),
+                mesh: HashMap::new(),
+                fanout: HashMap::new(),
+                seen: SeenCache::new(Duration::from_secs(SEEN_TTL_SECS)),
+                mcache: MessageCache::new(HISTORY_LENGTH, HISTORY_GOSSIP),
+                seqno: since_epoch.as_secs() * 1_000_000_000 + since_epoch.subsec_nanos() as u64,
+            }),
+            streams: Mutex::new(HashMap::new()),
+        });
+
+        let mut protocols = vec![(FLOODSUB_ID, Router::Floodsub)];
+        if router == Router::Gossipsub {
+            protocols.push((GOSSIPSUB_ID, Router::Gossipsub));
+        }
+        for (protocol_id, protocol) in protocols {
+            let handler_pubsub = pubsub.clone();
+            swarm.set_stream_handler(protocol_id,
+                                     Arc::new(move |peer: &PeerId, stream: Stream| {
+                                         handler_pubsub.read_rpcs(peer, protocol, stream)
+                                     }));
+        }
+        swarm.add_notifiee(Arc::new(PubSubOnConnect { pubsub: Arc::downgrade(&pubsub) }));
+        for peer in swarm.peers() {
+            let peer_pubsub = pubsub.clone();
+            thread::spawn(move || peer_pubsub.add_peer(&peer));
+        }
+
+        if router == Router::Gossipsub {
+            let heartbeat_pubsub = pubsub.clone();
+            thread::spawn(move || {
+                loop {
+                    thread::sleep(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+                    heartbeat_pubsub.heartbeat();
+                }
+            });
+        }
+        pubsub
+    }
+
+    // Publishes `data` to `topic`
+    pub fn publish(&self, topic: &str, data: Vec<u8>) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        state.seqno += 1;
+        let msg = Message::new(&self.key, state.seqno, vec![topic.to_string()], data);
+        state.seen.add(&msg.id());
+        state.mcache.put(msg.clone());
+        deliver(&mut state, &msg);
+
+        let mut targets: HashSet<PeerId> = state.topic_peers(topic, Router::Floodsub)
+                                                .into_iter()
+                                                .collect();
+        if self.router == Router::Gossipsub {
+            let mesh = state.mesh.get(topic).cloned();
+            if let Some(mesh) = mesh {
+                targets.extend(mesh);
+            } else {
+                let fanout = state.fanout.remove(topic);
+                let peers = match fanout {
+                    Some((peers, _)) => peers,
+                    None => state.random_peers(topic, D, &HashSet::new()).into_iter().collect(),
+                };
+                targets.extend(peers.iter().cloned());
+                state.fanout.insert(topic.to_string(), (peers, Instant::now()));
+            }
+        }
+        drop(state);
+
+        let mut rpc = Rpc::new();
+        rpc.messages.push(msg);
+        for peer in targets {
+            self.send(&peer, &rpc);
+        }
+        Ok(())
+    }
+
+    // Subscribes to `topic`, telling our peers
+    pub fn subscribe(pubsub: &Arc<Self>, topic: &str) -> Subscription {
+        let (sender, receiver) = mpsc::channel();
+        let mut state = pubsub.state.lock().unwrap();
+        let id = state.next_subscription_id;
+        state.next_subscription_id += 1;
+
+        let joined = !state.subscriptions.contains_key(topic);
+        state.subscriptions.entry(topic.to_string()).or_insert(vec![]).push((id, sender));
+        let mut grafts = Vec::new();
+        if joined && pubsub.router == Router::Gossipsub {
+            // join the mesh, starting with the peers we published to
+            let fanout = state.fanout.remove(topic);
+            let mut mesh = match fanout {
+                Some((peers, _)) => peers,
+                None => HashSet::new(),
+            };
+            if mesh.len() < D {
+                let more = state.random_peers(topic, D - mesh.len(), &mesh);
+                mesh.extend(more);
+            }
+            grafts.extend(mesh.iter().cloned());
+            state.mesh.insert(topic.to_string(), mesh);
+        }
+        let peers: Vec<PeerId> = state.peers.keys().cloned().collect();
+        drop(state);
+
+        if joined {
+            let mut rpc = Rpc::new();
+            rpc.subscriptions.push(SubOpts {
+                subscribe: true,
+                topic: topic.to_string(),
+            });
+            for peer in peers {
+                let mut peer_rpc = rpc.clone();
+                if grafts.contains(&peer) {
+                    peer_rpc.control.graft.push(topic.to_string());
+                }
+                pubsub.send(&peer, &peer_rpc);
+            }
+        }
+
+        Subscription {
+            topic: topic.to_string(),
+            id: id,
+            receiver: receiver,
+            pubsub: pubsub.clone(),
+        }
+    }
+
+    // Removes a subscription, leaving the topic if it was the last one
+    fn unsubscribe(&self, topic: &str, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        let left = match state.subscriptions.get_mut(topic) {
+            None => return,
+            Some(subscribers) => {
+                subscribers.retain(|&(sub_id, _)| sub_id != id);
+                subscribers.is_empty()
+            }
+        };
+        if !left {
+            return;
+        }
+        state.subscriptions.remove(topic);
+        let mesh = state.mesh.remove(topic).unwrap_or(HashSet::new());
+        let peers: Vec<PeerId> = state.peers.keys().cloned().collect();
+        drop(state);
+
+        let mut rpc = Rpc::new();
+        rpc.subscriptions.push(SubOpts {
+            subscribe: false,
+            topic: topic.to_string(),
+        });
+        for peer in peers {
+            let mut peer_rpc = rpc.clone();
+            if mesh.contains(&peer) {
+                peer_rpc.control.prune.push(topic.to_string());
+            }
+            self.send(&peer, &peer_rpc);
+        }
+    }
+
+    // The topics we're subscribed to, in order
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self.state
+                                          .lock()
+                                          .unwrap()
+                                          .subscriptions
+                                          .keys()
+                                          .cloned()
+                                          .collect();
+        topics.sort();
+        topics
+    }
+
+    // The pubsub peers subscribed to `topic`, or all of them
+    pub fn peers(&self, topic: Option<&str>) -> Vec<PeerId> {
+        let state = self.state.lock().unwrap();
+        state.peers
+             .iter()
+             .filter(|&(_, info)| topic.map(|t| info.topics.contains(t)).unwrap_or(true))
+             .map(|(peer, _)| peer.clone())
+             .collect()
+    }
+
+    // Opens a stream to a newly connected peer, with the best protocol it
+    // supports, and tells it our subscriptions
+    fn add_peer(&self, peer: &PeerId) {
+        let mut protocols = vec![];
+        if self.router == Router::Gossipsub {
+            protocols.push((GOSSIPSUB_ID, Router::Gossipsub));
+        }
+        protocols.push((FLOODSUB_ID, Router::Floodsub));
+
+        for (protocol_id, protocol) in protocols {
+            let stream = match self.swarm.new_stream(peer, protocol_id) {
+                Err(e) => {
+                    debug!("Error opening {} stream to {}: {}", protocol_id, peer, e);
+                    continue;
+                }
+                Ok(stream) => stream,
+            };
+            self.streams.lock().unwrap().insert(peer.clone(), Arc::new(Mutex::new(stream)));
+
+            let mut rpc = Rpc::new();
+            {
+                let mut state = self.state.lock().unwrap();
+                state.peers
+                     .entry(peer.clone())
+                     .or_insert(PeerInfo {
+                         protocol: protocol,
+                         topics: HashSet::new(),
+                     })
+                     .protocol = protocol;
+                for topic in state.subscriptions.keys() {
+                    rpc.subscriptions.push(SubOpts {
+                        subscribe: true,
+                        topic: topic.clone(),
+                    });
+                }
+            }
+            if !rpc.is_empty() {
+                self.send(peer, &rpc);
+            }
+            return;
+        }
+    }
+
+    fn remove_peer(&self, peer: &PeerId) {
+        self.streams.lock().unwrap().remove(peer);
+        let mut state = self.state.lock().unwrap();
+        state.peers.remove(peer);
+        for mesh in state.mesh.values_mut() {
+            mesh.remove(peer);
+        }
+        for &mut (ref mut peers, _) in state.fanout.values_mut() {
+            peers.remove(peer);
+        }
+    }
+
+    fn send(&self, peer: &PeerId, rpc: &Rpc) {
+        let stream = match self.streams.lock().unwrap().get(peer) {
+            None => return,
+            Some(stream) => stream.clone(),
+        };
+        let mut stream = stream.lock().unwrap();
+        if let Err(e) = codec::write_length_prefixed(&mut *stream, &rpc.encode()) {
+            debug!("Error sending pubsub RPC to {}: {}", peer, e);
+        }
+    }
+
+    fn read_rpcs(&self, peer: &PeerId, protocol: Router, mut stream: Stream) {
+        loop {
+            let frame = match codec::read_length_prefixed(&mut stream) {
+                Err(_) => return, // the peer closed the stream
+                Ok(frame) => frame,
+            };
+            match Rpc::decode(&frame) {
+                Err(e) => {
+                    warn!("Invalid pubsub RPC from {}: {}", peer, e);
+                    stream.reset();
+                    return;
+                }
+                Ok(rpc) => self.handle_rpc(peer, protocol, rpc),
+            }
+        }
+    }
+
+    fn handle_rpc(&self, peer: &PeerId, protocol: Router, rpc: Rpc) {
+        let mut forwards = Vec::new();
+        let mut reply = Rpc::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            {
+                let info = state.peers.entry(peer.clone()).or_insert(PeerInfo {
+                    protocol: protocol,
+                    topics: HashSet::new(),
+                });
+                for sub in &rpc.subscriptions {
+                    if sub.subscribe {
+                        info.topics.insert(sub.topic.clone());
+                    } else {
+                        info.topics.remove(&sub.topic);
+                    }
+                }
+            }
+            for sub in rpc.subscriptions.iter().filter(|sub| !sub.subscribe) {
+                if let Some(mesh) = state.mesh.get_mut(&sub.topic) {
+                    mesh.remove(peer);
+                }
+            }
+
+            for msg in rpc.messages {
+                let id = msg.id();
+                if state.seen.has(&id) {
+                    continue;
+                }
+                // only marked as seen once verified, so that a forged copy
+                // can't keep the genuine message from being delivered
+                if let Err(e) = msg.verify() {
+                    debug!("Dropping message from {}: {}", peer, e);
+                    continue;
+                }
+                state.seen.add(&id);
+                deliver(&mut state, &msg);
+                let targets = self.forward_targets(&state, &msg, peer);
+                state.mcache.put(msg.clone());
+                forwards.push((msg, targets));
+            }
+
+            if self.router == Router::Gossipsub {
+                self.handle_control(&mut state, peer, &rpc.control, &mut reply);
+            }
+        }
+
+        for (msg, targets) in forwards {
+            let mut rpc = Rpc::new();
+            rpc.messages.push(msg);
+            for target in targets {
+                self.send(&target, &rpc);
+            }
+        }
+        if !reply.is_empty() {
+            self.send(peer, &reply);
+        }
+    }
+
+    // The peers a message received from `source` is passed on to
+    fn forward_targets(&self, state: &State, msg: &Message, source: &PeerId) -> Vec<PeerId> {
+        let mut targets = HashSet::new();
+        for topic in &msg.topics {
+            targets.extend(state.topic_peers(topic, Router::Floodsub));
+            if let Some(mesh) = state.mesh.get(topic) {
+                targets.extend(mesh.iter().cloned());
+            }
+        }
+        targets.remove(source);
+        targets.remove(&msg.from);
+        targets.into_iter().collect()
+    }
+
+    fn handle_control(&self,
+                      state: &mut State,
+                      peer: &PeerId,
+                      control: &Control,
+                      reply: &mut Rpc) {
+        for &(ref topic, ref ids) in &control.ihave {
+            if state.mesh.contains_key(topic) {
+                reply.control.iwant.extend(ids.iter().filter(|id| !state.seen.has(id)).cloned());
+            }
+        }
+        for id in &control.iwant {
+            if let Some(msg) = state.mcache.get(id) {
+                reply.messages.push(msg.clone());
+            }
+        }
+        for topic in &control.graft {
+            match state.mesh.get_mut(topic) {
+                Some(mesh) => {
+                    mesh.insert(peer.clone());
+                }
+                None => reply.control.prune.push(topic.clone()),
+            }
+        }
+        for topic in &control.prune {
+            if let Some(mesh) = state.mesh.get_mut(topic) {
+                mesh.remove(peer);
+            }
+        }
+    }
+
+    // Keeps each mesh between D_LOW and D_HIGH peers, forgets fanout topics
+    // we haven't published to for a while, and gossips about recent messages
+    fn heartbeat(&self) {
+        let mut rpcs: HashMap<PeerId, Rpc> = HashMap::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let topics: Vec<String> = state.mesh.keys().cloned().collect();
+            for topic in &topics {
+                let mut mesh = state.mesh.remove(topic).unwrap_or(HashSet::new());
+                let subscribed: HashSet<PeerId> = state.topic_peers(topic, Router::Gossipsub)
+                                                       .into_iter()
+                                                       .collect();
+                mesh = mesh.intersection(&subscribed).cloned().collect();
+
+                if mesh.len() < D_LOW {
+                    for peer in state.random_peers(topic, D - mesh.len(), &mesh) {
+                        control_rpc(&mut rpcs, &peer).graft.push(topic.clone());
+                        mesh.insert(peer);
+                    }
+                } else if mesh.len() > D_HIGH {
+                    let mut peers: Vec<PeerId> = mesh.iter().cloned().collect();
+                    shuffle(&mut peers);
+                    for peer in peers.into_iter().take(mesh.len() - D) {
+                        control_rpc(&mut rpcs, &peer).prune.push(topic.clone());
+                        mesh.remove(&peer);
+                    }
+                }
+                state.mesh.insert(topic.clone(), mesh);
+            }
+
+            let fanout_ttl = Duration::from_secs(FANOUT_TTL_SECS);
+            let topics: Vec<String> = state.fanout.keys().cloned().collect();
+            for topic in &topics {
+                let (mut peers, last_published) = state.fanout.remove(topic).unwrap();
+                if last_published.elapsed() > fanout_ttl {
+                    continue;
+                }
+                let subscribed: HashSet<PeerId> = state.topic_peers(topic, Router::Gossipsub)
+                                                       .into_iter()
+                                                       .collect();
+                peers = peers.intersection(&subscribed).cloned().collect();
+                if peers.len() < D {
+                    let more = state.random_peers(topic, D - peers.len(), &peers);
+                    peers.extend(more);
+                }
+                state.fanout.insert(topic.clone(), (peers, last_published));
+            }
+
+            let mut gossip_topics: Vec<(String, HashSet<PeerId>)> = Vec::new();
+            for (topic, mesh) in &state.mesh {
+                gossip_topics.push((topic.clone(), mesh.clone()));
+            }
+            for (topic, &(ref peers, _)) in &state.fanout {
+                gossip_topics.push((topic.clone(), peers.clone()));
+            }
+            for (topic, exclude) in gossip_topics {
+                let ids = state.mcache.gossip_ids(&topic);
+                if ids.is_empty() {
+                    continue;
+                }
+                for peer in state.random_peers(&topic, D, &exclude) {
+                    control_rpc(&mut rpcs, &peer).ihave.push((topic.clone(), ids.clone()));
+                }
+            }
+            state.mcache.shift();
+        }
+
+        for (peer, rpc) in rpcs {
+            self.send(&peer, &rpc);
+        }
+    }
+}
+
+struct PubSubOnConnect {
+    pubsub: Weak<PubSub>,
+}
+
+impl Notifiee for PubSubOnConnect {
+    fn connected(&self, peer: &PeerId) {
+        // opening a stream waits for the peer, so it's done in another thread
+        let pubsub = self.pubsub.clone();
+        let peer = peer.clone();
+        thread::spawn(move || {
+            if let Some(pubsub) = pubsub.upgrade() {
+                pubsub.add_peer(&peer);
+            }
+        });
+    }
+
+    fn disconnected(&self, peer: &PeerId) {
+        if let Some(pubsub) = self.pubsub.upgrade() {
+            pubsub.remove_peer(peer);
+        }
+    }
+}
+
+// Passes `msg` to the local subscribers of its topics
+fn deliver(state: &mut State, msg: &Message) {
+    for topic in &msg.topics {
+        if let Some(subscribers) = state.subscriptions.get_mut(topic) {
+            // subscribers whose receiver is gone are dropped when they
+            // unsubscribe
+            for &(_, ref sender) in subscribers.iter() {
+                let _ = sender.send(msg.clone());
+            }
+        }
+    }
+}
+
+fn control_rpc<'a>(rpcs: &'a mut HashMap<PeerId, Rpc>, peer: &PeerId) -> &'a mut Control {
+    &mut rpcs.entry(peer.clone()).or_insert(Rpc::new()).control
+}
+
+// Shuffles `items` in place (Fisher-Yates)
+fn shuffle<T>(items: &mut Vec<T>) {
+    if items.len() < 2 {
+        return;
+    }
+    let random = crypto::random_bytes(4 * items.len());
+    for i in (1..items.len()).rev() {
+        let r = (random[4 * i] as usize) << 24 | (random[4 * i + 1] as usize) << 16 |
+                (random[4 * i + 2] as usize) << 8 | random[4 * i + 3] as usize;
+        items.swap(i, r % (i + 1));
+    }
+}
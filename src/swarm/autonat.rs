@@ -0,0 +1,270 @@
+// AutoNAT: a peer that wants to know whether it can be dialed from outside
+// its network (rather than being behind a NAT or firewall) asks connected
+// peers to dial it back at its listen addresses. Peers only dial back
+// addresses with the IP address they see the connection come from, so that
+// they can't be used to dial arbitrary hosts.
+
+use super::{Stream, Swarm};
+use codec::{self, PbReader, PbWriter};
+use error::Error;
+use multiaddr::{Multiaddr, Protocol};
+use peer::PeerId;
+
+use rust_multihash::Multihash;
+use std::fmt;
+use std::sync::Arc;
+
+pub const PROTOCOL_ID: &'static str = "/libp2p/autonat/1.0.0";
+
+// The number of peers asked by a probe
+const MAX_SERVERS: usize = 4;
+
+const TYPE_DIAL: u64 = 0;
+const TYPE_DIAL_RESPONSE: u64 = 1;
+
+const STATUS_OK: u64 = 0;
+const STATUS_DIAL_ERROR: u64 = 100;
+const STATUS_DIAL_REFUSED: u64 = 101;
+const STATUS_BAD_REQUEST: u64 = 200;
+
+pub enum Reachability {
+    Public(Multiaddr), // a peer dialed us back at this address
+    Private, // peers failed to dial us back
+    Unknown, // no peer could tell
+}
+
+impl fmt::Display for Reachability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Reachability::Public(ref addr) => write!(f, "public at {}", addr),
+            Reachability::Private => write!(f, "private"),
+            Reachability::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+struct Message {
+    msg_type: u64,
+    peer: Option<(PeerId, Vec<Multiaddr>)>, // DIAL only
+    status: u64, // DIAL_RESPONSE only, as are the fields below
+    status_text: String,
+    addr: Option<Multiaddr>,
+}
+
+impl Message {
+    fn dial(peer: PeerId, addrs: Vec<Multiaddr>) -> Self {
+        Message {
+            msg_type: TYPE_DIAL,
+            peer: Some((peer, addrs)),
+            status: STATUS_OK,
+            status_text: String::new(),
+            addr: None,
+        }
+    }
+
+    fn response(status: u64, status_text: &str, addr: Option<Multiaddr>) -> Self {
+        Message {
+            msg_type: TYPE_DIAL_RESPONSE,
+            peer: None,
+            status: status,
+            status_text: status_text.to_string(),
+            addr: addr,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut msg = PbWriter::new();
+        msg.uint64(1, self.msg_type);
+        if self.msg_type == TYPE_DIAL {
+            let mut peer_info = PbWriter::new();
+            if let Some((ref id, ref addrs)) = self.peer {
+                peer_info.bytes(1, &id.clone().into_bytes());
+                for addr in addrs {
+                    peer_info.bytes(2, &addr.to_bytes());
+                }
+            }
+            let mut dial = PbWriter::new();
+            dial.message(1, &peer_info);
+            msg.message(2, &dial);
+        } else {
+            let mut response = PbWriter::new();
+            response.uint64(1, self.status).string(2, &self.status_text);
+            if let Some(ref addr) = self.addr {
+                response.bytes(3, &addr.to_bytes());
+            }
+            msg.message(3, &response);
+        }
+        msg.into_bytes()
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut msg = Message::response(STATUS_OK, "", None);
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => msg.msg_type = try!(value.as_u64()),
+                2 => {
+                    let mut dial = PbReader::new(try!(value.as_bytes()));
+                    while let Some((field, value)) = try!(dial.next_field()) {
+                        if field == 1 {
+                            msg.peer = Some(try!(decode_peer_info(try!(value.as_bytes()))));
+                        }
+                    }
+                }
+                3 => {
+                    let mut response = PbReader::new(try!(value.as_bytes()));
+                    while let Some((field, value)) = try!(response.next_field()) {
+                        match field {
+                            1 => msg.status = try!(value.as_u64()),
+                            2 => msg.status_text = try!(value.as_string()),
+                            3 => msg.addr = Multiaddr::from_bytes(try!(value.as_bytes())).ok(),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(msg)
+    }
+}
+
+fn decode_peer_info(buf: &[u8]) -> Result<(PeerId, Vec<Multiaddr>), Error> {
+    let mut id = None;
+    let mut addrs = Vec::new();
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => id = Some(Multihash::from_vec(try!(value.as_bytes()).to_vec())),
+            2 => {
+                // skip addresses using protocols we don't support
+                if let Ok(addr) = Multiaddr::from_bytes(try!(value.as_bytes())) {
+                    addrs.push(addr);
+                }
+            }
+            _ => {}
+        }
+    }
+    match id {
+        None => Err(Error::Decode("AutoNAT dial request has no peer ID".to_string(), None)),
+        Some(id) => Ok((id, addrs)),
+    }
+}
+
+// Dials back the peers asking whether they are reachable
+pub fn register(swarm: &Arc<Swarm>) {
+    let handler_swarm = Arc::downgrade(swarm);
+    swarm.set_stream_handler(PROTOCOL_ID,
+                             Arc::new(move |peer: &PeerId, stream: Stream| {
+                                 if let Some(swarm) = handler_swarm.upgrade() {
+                                     if let Err(e) = handle_dial(&swarm, peer, stream) {
+                                         debug!("Error answering AutoNAT request from {}: {}",
+                                                peer,
+                                                e);
+                                     }
+                                 }
+                             }));
+}
+
+fn handle_dial(swarm: &Swarm, peer: &PeerId, mut stream: Stream) -> Result<(), Error> {
+    let buf = try!(codec::read_length_prefixed(&mut stream).map_err(|e| {
+        Error::Io("Error reading AutoNAT request".to_string(), e)
+    }));
+    let response = match Message::decode(&buf) {
+        Ok(Message { msg_type: TYPE_DIAL, peer: Some((ref id, ref addrs)), .. }) if id == peer => {
+            dial_back(swarm, peer, addrs)
+        }
+        _ => Message::response(STATUS_BAD_REQUEST, "invalid dial request", None),
+    };
+    codec::write_length_prefixed(&mut stream, &response.encode())
+        .map_err(|e| Error::Io("Error sending AutoNAT response".to_string(), e))
+}
+
+// Tries the addresses `peer` gave with the IP address it connected from
+fn dial_back(swarm: &Swarm, peer: &PeerId, addrs: &[Multiaddr]) -> Message {
+    let observed_ip = match swarm.connection(peer) {
+        Some(ref conn) if !conn.remote_addr.is_circuit() => {
+            conn.remote_addr.protocols().first().cloned()
+        }
+        _ => None,
+    };
+    let observed_ip = match observed_ip {
+        Some(ip @ Protocol::Ip4(_)) | Some(ip @ Protocol::Ip6(_)) => ip,
+        _ => return Message::response(STATUS_DIAL_REFUSED, "no observed address", None),
+    };
+
+    let candidates: Vec<Multiaddr> = swarm.resolve_unspecified(peer, addrs)
+                                          .into_iter()
+                                          .filter(|addr| {
+                                              !addr.is_circuit() &&
+                                              addr.protocols().first() == Some(&observed_ip)
+                                          })
+                                          .collect();
+    if candidates.is_empty() {
+        return Message::response(STATUS_DIAL_REFUSED, "no addresses to dial", None);
+    }
+
+    for addr in candidates {
+        let mut target = addr.without_peer_id();
+        target.push(Protocol::P2p(peer.clone()));
+        match swarm.probe(&target) {
+            Ok(_) => return Message::response(STATUS_OK, "", Some(addr.without_peer_id())),
+            Err(e) => debug!("AutoNAT dial back to {} failed: {}", target, e),
+        }
+    }
+    Message::response(STATUS_DIAL_ERROR, "dial back failed", None)
+}
+
+// Asks connected peers to dial us back at our listen addresses. We're public
+// if any of them manages to.
+pub fn probe(swarm: &Swarm) -> Result<Reachability, Error> {
+    let addrs: Vec<Multiaddr> = swarm.listen_addrs()
+                                     .into_iter()
+                                     .filter(|addr| !addr.is_circuit())
+                                     .collect();
+    if addrs.is_empty() {
+        return Ok(Reachability::Private);
+    }
+
+    // peers connected through a relay can't see our address
+    let servers: Vec<PeerId> = swarm.connections()
+                                    .into_iter()
+                                    .filter(|conn| !conn.remote_addr.is_circuit())
+                                    .map(|conn| conn.peer.clone())
+                                    .take(MAX_SERVERS)
+                                    .collect();
+    let mut reachability = Reachability::Unknown;
+    for server in servers {
+        match ask(swarm, &server, &addrs) {
+            Err(e) => debug!("Error asking {} to dial us back: {}", server, e),
+            Ok(msg) => {
+                match (msg.status, msg.addr) {
+                    (STATUS_OK, Some(addr)) => return Ok(Reachability::Public(addr)),
+                    (STATUS_DIAL_ERROR, _) => reachability = Reachability::Private,
+                    (status, _) => {
+                        debug!("{} did not dial us back ({}): {}",
+                               server,
+                               status,
+                               msg.status_text)
+                    }
+                }
+            }
+        }
+    }
+    Ok(reachability)
+}
+
+fn ask(swarm: &Swarm, server: &PeerId, addrs: &[Multiaddr]) -> Result<Message, Error> {
+    let mut stream = try!(swarm.new_stream(server, PROTOCOL_ID));
+    let request = Message::dial(swarm.local_peer().clone(), addrs.to_vec());
+    try!(codec::write_length_prefixed(&mut stream, &request.encode())
+             .map_err(|e| Error::Io("Error sending AutoNAT request".to_string(), e)));
+    let buf = try!(codec::read_length_prefixed(&mut stream).map_err(|e| {
+        Error::Io(format!("Error reading AutoNAT response from {}", server), e)
+    }));
+    let msg = try!(Message::decode(&buf));
+    if msg.msg_type != TYPE_DIAL_RESPONSE {
+        return Err(Error::Network(format!("Unexpected AutoNAT message from {}", server)));
+    }
+    Ok(msg)
+}
@@ -0,0 +1,86 @@
+// The connection manager keeps the number of open connections in check.
+// Once there are more than `high_water`, connections are closed until only
+// `low_water` are left, starting with those that have been idle longest.
+// Connections younger than the grace period are left alone, since they may
+// not have been used yet.
+
+use super::{Connection, Swarm};
+
+use std::cmp::Ordering;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_LOW_WATER: usize = 600;
+pub const DEFAULT_HIGH_WATER: usize = 900;
+pub const DEFAULT_GRACE_PERIOD_SECS: u64 = 20;
+
+const TRIM_INTERVAL_SECS: u64 = 10;
+
+#[derive(Clone, Debug)]
+pub struct ConnManager {
+    pub low_water: usize,
+    pub high_water: usize,
+    pub grace_period: Duration,
+}
+
+impl ConnManager {
+    pub fn new(low_water: usize, high_water: usize, grace_period: Duration) -> Self {
+        ConnManager {
+            low_water: low_water,
+            high_water: high_water,
+            grace_period: grace_period,
+        }
+    }
+
+    // Closes connections if there are too many, returning how many were
+    // closed
+    pub fn trim(&self, swarm: &Swarm) -> usize {
+        let mut conns = swarm.connections();
+        if conns.len() <= self.high_water {
+            return 0;
+        }
+        let excess = conns.len().saturating_sub(self.low_water);
+
+        conns.retain(|conn| conn.opened.elapsed() >= self.grace_period);
+        // connections without open streams first, then the longest idle
+        conns.sort_by(|a, b| {
+            match (a.num_streams() > 0).cmp(&(b.num_streams() > 0)) {
+                Ordering::Equal => b.idle_time().cmp(&a.idle_time()),
+                ordering => ordering,
+            }
+        });
+
+        let to_close: Vec<&Arc<Connection>> = conns.iter().take(excess).collect();
+        for conn in &to_close {
+            debug!("Connection manager closing connection to {}", conn.peer);
+            conn.close();
+        }
+        to_close.len()
+    }
+
+    // Trims the swarm's connections periodically, for as long as the swarm
+    // exists
+    pub fn start(self, swarm: &Arc<Swarm>) {
+        let swarm: Weak<Swarm> = Arc::downgrade(swarm);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(Duration::from_secs(TRIM_INTERVAL_SECS));
+                match swarm.upgrade() {
+                    None => return,
+                    Some(swarm) => {
+                        self.trim(&swarm);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl Default for ConnManager {
+    fn default() -> Self {
+        ConnManager::new(DEFAULT_LOW_WATER,
+                         DEFAULT_HIGH_WATER,
+                         Duration::from_secs(DEFAULT_GRACE_PERIOD_SECS))
+    }
+}
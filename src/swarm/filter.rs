@@ -0,0 +1,132 @@
+// Address filters: CIDR ranges (written as multiaddrs such as
+// /ip4/10.0.0.0/ipcidr/8) that we never dial or accept connections from.
+
+use error::Error;
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::RwLock;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IpFilter {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpFilter {
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let invalid = |msg: &str| {
+            Error::InvalidArgument(format!("Invalid address filter {}: {}", s, msg))
+        };
+
+        let parts: Vec<&str> = s.split('/').collect();
+        if parts.len() != 5 || parts[0] != "" || parts[3] != "ipcidr" {
+            return Err(invalid("expected /ip4/<address>/ipcidr/<prefix length> or \
+                                /ip6/<address>/ipcidr/<prefix length>"));
+        }
+        let (network, max_len) = match parts[1] {
+            "ip4" => {
+                let ip: Ipv4Addr = try!(parts[2].parse().map_err(|_| invalid("bad IPv4 address")));
+                (IpAddr::V4(ip), 32)
+            }
+            "ip6" => {
+                let ip: Ipv6Addr = try!(parts[2].parse().map_err(|_| invalid("bad IPv6 address")));
+                (IpAddr::V6(ip), 128)
+            }
+            _ => return Err(invalid("must start with /ip4 or /ip6")),
+        };
+        let prefix_len: u8 = try!(parts[4].parse().map_err(|_| invalid("bad prefix length")));
+        if prefix_len > max_len {
+            return Err(invalid("prefix length is too long"));
+        }
+
+        Ok(IpFilter {
+            network: mask(&network, prefix_len),
+            prefix_len: prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (&self.network, ip) {
+            (&IpAddr::V4(_), &IpAddr::V4(_)) |
+            (&IpAddr::V6(_), &IpAddr::V6(_)) => mask(ip, self.prefix_len) == self.network,
+            _ => false,
+        }
+    }
+}
+
+// Zeroes all but the first `prefix_len` bits of `ip`
+fn mask(ip: &IpAddr, prefix_len: u8) -> IpAddr {
+    match *ip {
+        IpAddr::V4(ip) => {
+            let mut octets = ip.octets();
+            mask_bytes(&mut octets, prefix_len);
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        IpAddr::V6(ip) => {
+            let mut octets = ip.octets();
+            mask_bytes(&mut octets, prefix_len);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    }
+}
+
+fn mask_bytes(bytes: &mut [u8], prefix_len: u8) {
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        let start = i * 8;
+        let keep = if prefix_len as usize >= start + 8 {
+            8
+        } else if (prefix_len as usize) <= start {
+            0
+        } else {
+            prefix_len as usize - start
+        };
+        *byte &= !(0xffu16 >> keep) as u8;
+    }
+}
+
+impl fmt::Display for IpFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.network {
+            IpAddr::V4(ip) => write!(f, "/ip4/{}/ipcidr/{}", ip, self.prefix_len),
+            IpAddr::V6(ip) => write!(f, "/ip6/{}/ipcidr/{}", ip, self.prefix_len),
+        }
+    }
+}
+
+// The filters a swarm applies to the addresses it dials and accepts
+pub struct Filters {
+    filters: RwLock<Vec<IpFilter>>,
+}
+
+impl Filters {
+    pub fn new() -> Self {
+        Filters { filters: RwLock::new(Vec::new()) }
+    }
+
+    // Returns false if the filter was already there
+    pub fn add(&self, filter: IpFilter) -> bool {
+        let mut filters = self.filters.write().unwrap();
+        if filters.contains(&filter) {
+            return false;
+        }
+        filters.push(filter);
+        true
+    }
+
+    // Returns false if there was no such filter
+    pub fn remove(&self, filter: &IpFilter) -> bool {
+        let mut filters = self.filters.write().unwrap();
+        let len = filters.len();
+        filters.retain(|f| f != filter);
+        filters.len() != len
+    }
+
+    pub fn list(&self) -> Vec<IpFilter> {
+        self.filters.read().unwrap().clone()
+    }
+
+    pub fn is_denied(&self, ip: &IpAddr) -> bool {
+        self.filters.read().unwrap().iter().any(|f| f.contains(ip))
+    }
+}
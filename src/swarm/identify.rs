@@ -0,0 +1,145 @@
+// Identify: peers tell each other their public key, the addresses they
+// listen on, the protocols they support and their versions. Every peer we
+// connect to is identified, so that we learn the addresses to reach it at.
+
+use super::{Notifiee, Stream, Swarm};
+use codec::{self, PbReader, PbWriter};
+use error::Error;
+use multiaddr::Multiaddr;
+use peer::PeerId;
+use util;
+
+use std::sync::{Arc, Weak};
+use std::thread;
+
+pub const PROTOCOL_ID: &'static str = "/ipfs/id/1.0.0";
+pub const PROTOCOL_VERSION: &'static str = "ipfs/0.1.0";
+pub const AGENT_VERSION: &'static str = concat!("rust-ipfs/", env!("CARGO_PKG_VERSION"));
+
+pub struct IdentifyInfo {
+    pub public_key: Vec<u8>, // DER encoding
+    pub listen_addrs: Vec<Multiaddr>,
+    pub protocols: Vec<String>,
+    pub observed_addr: Option<Multiaddr>,
+    pub protocol_version: String,
+    pub agent_version: String,
+}
+
+impl IdentifyInfo {
+    // What we tell peers about ourselves. `observed_addr` is the address we
+    // see the peer at.
+    pub fn local(swarm: &Swarm, observed_addr: Option<Multiaddr>) -> Self {
+        IdentifyInfo {
+            public_key: swarm.public_key(),
+            listen_addrs: swarm.listen_addrs(),
+            protocols: swarm.protocols(),
+            observed_addr: observed_addr,
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            agent_version: AGENT_VERSION.to_string(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut msg = PbWriter::new();
+        msg.string(5, &self.protocol_version)
+           .string(6, &self.agent_version)
+           .bytes(1, &self.public_key);
+        for addr in &self.listen_addrs {
+            msg.bytes(2, &addr.to_bytes());
+        }
+        if let Some(ref addr) = self.observed_addr {
+            msg.bytes(4, &addr.to_bytes());
+        }
+        for protocol in &self.protocols {
+            msg.string(3, protocol);
+        }
+        msg.into_bytes()
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut info = IdentifyInfo {
+            public_key: Vec::new(),
+            listen_addrs: Vec::new(),
+            protocols: Vec::new(),
+            observed_addr: None,
+            protocol_version: String::new(),
+            agent_version: String::new(),
+        };
+
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match field {
+                1 => info.public_key = try!(value.as_bytes()).to_vec(),
+                2 => {
+                    // skip addresses using protocols we don't support
+                    if let Ok(addr) = Multiaddr::from_bytes(try!(value.as_bytes())) {
+                        info.listen_addrs.push(addr);
+                    }
+                }
+                3 => info.protocols.push(try!(value.as_string())),
+                4 => info.observed_addr = Multiaddr::from_bytes(try!(value.as_bytes())).ok(),
+                5 => info.protocol_version = try!(value.as_string()),
+                6 => info.agent_version = try!(value.as_string()),
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+// Answers identify requests, and identifies every peer we connect to
+pub fn register(swarm: &Arc<Swarm>) {
+    let handler_swarm = Arc::downgrade(swarm);
+    swarm.set_stream_handler(PROTOCOL_ID,
+                             Arc::new(move |peer: &PeerId, mut stream: Stream| {
+                                 if let Some(swarm) = handler_swarm.upgrade() {
+                                     let observed = swarm.connection(peer)
+                                                         .map(|conn| conn.remote_addr.clone());
+                                     let info = IdentifyInfo::local(&swarm, observed);
+                                     if let Err(e) = codec::write_length_prefixed(&mut stream,
+                                                                                  &info.encode()) {
+                                         debug!("Error sending identify message to {}: {}",
+                                                peer,
+                                                e);
+                                     }
+                                 }
+                             }));
+    swarm.add_notifiee(Arc::new(IdentifyOnConnect { swarm: Arc::downgrade(swarm) }));
+}
+
+// Asks a connected peer to identify itself, and remembers its listen
+// addresses
+pub fn identify(swarm: &Swarm, peer: &PeerId) -> Result<IdentifyInfo, Error> {
+    let mut stream = try!(swarm.new_stream(peer, PROTOCOL_ID));
+    let buf = try!(codec::read_length_prefixed(&mut stream).map_err(|e| {
+        Error::Io(format!("Error reading identify message from {}", peer), e)
+    }));
+    let info = try!(IdentifyInfo::decode(&buf));
+
+    if util::hash(&info.public_key) != *peer {
+        return Err(Error::Network(format!("Public key sent by {} does not match its ID", peer)));
+    }
+    let addrs = swarm.resolve_unspecified(peer, &info.listen_addrs);
+    swarm.add_peer_addrs(peer, &addrs);
+    Ok(info)
+}
+
+struct IdentifyOnConnect {
+    swarm: Weak<Swarm>,
+}
+
+impl Notifiee for IdentifyOnConnect {
+    fn connected(&self, peer: &PeerId) {
+        let swarm = self.swarm.clone();
+        let peer = peer.clone();
+        thread::spawn(move || {
+            if let Some(swarm) = swarm.upgrade() {
+                if let Err(e) = identify(&swarm, &peer) {
+                    debug!("Error identifying {}: {}", peer, e);
+                }
+            }
+        });
+    }
+
+    fn disconnected(&self, _peer: &PeerId) {}
+}
@@ -0,0 +1,476 @@
+// The swarm: connections to other peers. Connections are made over TCP,
+// secured with Noise and multiplexed with mplex, with multistream-select
+// used to agree on each of these. Protocols (bitswap, ...) then open
+// streams to peers, and register handlers for the streams peers open to us.
+// Connections to and from addresses matching the swarm's filters are
+// refused. In a private network, connections are encrypted with the
+// network's pre-shared key before anything else (see pnet). Peers that can't
+// be dialed directly can be reached through relays at /p2p-circuit addresses
+// (see relay), and can ask other peers whether they are reachable (see
+// autonat).
+
+use crypto;
+use error::Error;
+use multiaddr::{Multiaddr, Protocol};
+use peer::PeerId;
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use self::filter::Filters;
+use self::mplex::Muxer;
+use self::noise::SecureStream;
+use self::pnet::Psk;
+use self::transport::RawConn;
+pub use self::connmgr::ConnManager;
+pub use self::mplex::Stream;
+
+pub mod autonat;
+pub mod connmgr;
+pub mod filter;
+pub mod identify;
+pub mod mplex;
+pub mod multistream;
+pub mod noise;
+pub mod ping;
+pub mod pnet;
+pub mod relay;
+pub mod transport;
+
+// How long probing an address waits for the peer to answer
+const PROBE_TIMEOUT_SECS: u64 = 15;
+
+// Handles a stream a peer opened to us for a protocol
+pub type StreamHandler = Arc<Fn(&PeerId, Stream) + Send + Sync>;
+
+// Told about peers connecting and disconnecting
+pub trait Notifiee: Send + Sync {
+    fn connected(&self, peer: &PeerId);
+    fn disconnected(&self, peer: &PeerId);
+}
+
+pub struct Connection {
+    pub peer: PeerId,
+    pub remote_addr: Multiaddr,
+    pub outbound: bool, // whether we dialed the peer
+    pub opened: Instant,
+    muxer: Arc<Muxer>,
+    latency: Mutex<Option<Duration>>, // the last round trip time measured
+}
+
+impl Connection {
+    pub fn close(&self) {
+        self.muxer.close();
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        *self.latency.lock().unwrap()
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().unwrap() = Some(latency);
+    }
+
+    // The number of streams open on the connection
+    pub fn num_streams(&self) -> usize {
+        self.muxer.num_streams()
+    }
+
+    // How long since anything was sent or received on the connection
+    pub fn idle_time(&self) -> Duration {
+        self.muxer.idle_time()
+    }
+}
+
+pub struct Swarm {
+    local_peer: PeerId,
+    identity_key: Vec<u8>, // DER encoding of our private key
+    psk: Option<Psk>, // the private network key
+    listen_addrs: Mutex<Vec<Multiaddr>>,
+    conns: Mutex<HashMap<PeerId, Arc<Connection>>>,
+    handlers: RwLock<HashMap<String, StreamHandler>>,
+    notifiees: RwLock<Vec<Arc<Notifiee>>>,
+    pub filters: Filters,
+    peer_addrs: Mutex<HashMap<PeerId, HashSet<Multiaddr>>>, // addresses peers are known at
+}
+
+impl Swarm {
+    pub fn new(local_peer: PeerId, identity_key: Vec<u8>, psk: Option<Psk>) -> Arc<Self> {
+        Arc::new(Swarm {
+            local_peer: local_peer,
+            identity_key: identity_key,
+            psk: psk,
+            listen_addrs: Mutex::new(Vec::new()),
+            conns: Mutex::new(HashMap::new()),
+            handlers: RwLock::new(HashMap::new()),
+            notifiees: RwLock::new(Vec::new()),
+            filters: Filters::new(),
+            peer_addrs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn local_peer(&self) -> &PeerId {
+        &self.local_peer
+    }
+
+    // Whether we only connect to peers with the private network key
+    pub fn is_private(&self) -> bool {
+        self.psk.is_some()
+    }
+
+    pub fn listen_addrs(&self) -> Vec<Multiaddr> {
+        self.listen_addrs.lock().unwrap().clone()
+    }
+
+    // The DER encoding of our public key
+    pub fn public_key(&self) -> Vec<u8> {
+        crypto::load_private_key(&self.identity_key).save_pub()
+    }
+
+    // The protocols we handle streams for
+    pub fn protocols(&self) -> Vec<String> {
+        let mut protocols: Vec<String> = self.handlers.read().unwrap().keys().cloned().collect();
+        protocols.sort();
+        protocols
+    }
+
+    pub fn set_stream_handler(&self, protocol: &str, handler: StreamHandler) {
+        self.handlers.write().unwrap().insert(protocol.to_string(), handler);
+    }
+
+    pub fn add_notifiee(&self, notifiee: Arc<Notifiee>) {
+        self.notifiees.write().unwrap().push(notifiee);
+    }
+
+    pub fn peers(&self) -> Vec<PeerId> {
+        self.conns.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn connections(&self) -> Vec<Arc<Connection>> {
+        self.conns.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn connection(&self, peer: &PeerId) -> Option<Arc<Connection>> {
+        self.conns.lock().unwrap().get(peer).cloned()
+    }
+
+    // Remembers addresses `peer` can be reached at. Addresses are stored
+    // without the /p2p part.
+    pub fn add_peer_addrs(&self, peer: &PeerId, addrs: &[Multiaddr]) {
+        let mut peer_addrs = self.peer_addrs.lock().unwrap();
+        let known = peer_addrs.entry(peer.clone()).or_insert_with(HashSet::new);
+        for addr in addrs {
+            known.insert(addr.without_peer_id());
+        }
+    }
+
+    // Fixes up the addresses a peer gave for itself. Peers listening on all
+    // interfaces give unspecified addresses (0.0.0.0), for which we use the
+    // IP address it connected to us from, unless it connected through a
+    // relay.
+    pub fn resolve_unspecified(&self, peer: &PeerId, addrs: &[Multiaddr]) -> Vec<Multiaddr> {
+        let remote_ip = self.connection(peer).and_then(|conn| {
+            if conn.remote_addr.is_circuit() {
+                return None;
+            }
+            match conn.remote_addr.protocols().first() {
+                Some(&Protocol::Ip4(ip)) => Some(IpAddr::V4(ip)),
+                Some(&Protocol::Ip6(ip)) => Some(IpAddr::V6(ip)),
+                _ => None,
+            }
+        });
+
+        addrs.iter()
+             .filter_map(|addr| {
+                 let unspecified = match addr.protocols().first() {
+                     Some(&Protocol::Ip4(ip)) => ip.is_unspecified(),
+                     Some(&Protocol::Ip6(ip)) => ip.is_unspecified(),
+                     _ => false,
+                 };
+                 if !unspecified {
+                     return Some(addr.clone());
+                 }
+                 remote_ip.map(|ip| {
+                     let mut protocols = addr.protocols().to_vec();
+                     protocols[0] = match ip {
+                         IpAddr::V4(ip) => Protocol::Ip4(ip),
+                         IpAddr::V6(ip) => Protocol::Ip6(ip),
+                     };
+                     Multiaddr::new(protocols)
+                 })
+             })
+             .collect()
+    }
+
+    pub fn peer_addrs(&self, peer: &PeerId) -> Vec<Multiaddr> {
+        match self.peer_addrs.lock().unwrap().get(peer) {
+            None => vec![],
+            Some(addrs) => addrs.iter().cloned().collect(),
+        }
+    }
+
+    // Every peer we know addresses for
+    pub fn known_peers(&self) -> Vec<PeerId> {
+        self.peer_addrs.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn is_connected(&self, peer: &PeerId) -> bool {
+        self.conns.lock().unwrap().contains_key(peer)
+    }
+
+    pub fn disconnect(&self, peer: &PeerId) -> Result<(), Error> {
+        match self.conns.lock().unwrap().get(peer) {
+            None => Err(Error::NotFound(format!("connection to peer {}", peer))),
+            Some(conn) => {
+                conn.close();
+                Ok(())
+            }
+        }
+    }
+
+    // Accepts connections on `addr` in the background, returning the address
+    // actually listened on
+    pub fn listen(swarm: &Arc<Self>, addr: &Multiaddr) -> Result<Multiaddr, Error> {
+        let socket_addrs = try!(addr.to_socket_addrs());
+        let listener = try!(TcpListener::bind(&socket_addrs[..])
+                                .map_err(|e| Error::Io(format!("Error listening on {}", addr), e)));
+        let local_addr = try!(listener.local_addr().map_err(|e| {
+            Error::Io(format!("Error getting address of listener on {}", addr), e)
+        }));
+        let listen_addr = Multiaddr::from_socket_addr(&local_addr);
+        swarm.listen_addrs.lock().unwrap().push(listen_addr.clone());
+
+        let swarm = swarm.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Err(e) => {
+                        warn!("Error accepting connection: {}", e);
+                        continue;
+                    }
+                    Ok(stream) => stream,
+                };
+                let remote_addr = match stream.peer_addr() {
+                    Err(e) => {
+                        debug!("Error getting address of peer: {}", e);
+                        continue;
+                    }
+                    Ok(addr) if swarm.filters.is_denied(&addr.ip()) => {
+                        debug!("Refusing connection from filtered address {}", addr);
+                        continue;
+                    }
+                    Ok(addr) => Multiaddr::from_socket_addr(&addr),
+                };
+                let swarm = swarm.clone();
+                thread::spawn(move || {
+                    let conn = RawConn::Tcp(stream);
+                    if let Err(e) = Swarm::add_connection(&swarm, conn, remote_addr, None) {
+                        debug!("Error setting up inbound connection: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(listen_addr)
+    }
+
+    // Connects to the peer at `addr`. If the address ends with /p2p/<peer
+    // id>, the peer must have that ID. Addresses of the form <relay
+    // address>/p2p/<relay id>/p2p-circuit/p2p/<peer id> are dialed through
+    // the relay.
+    pub fn dial(swarm: &Arc<Self>, addr: &Multiaddr) -> Result<PeerId, Error> {
+        if let Some(peer) = addr.peer_id() {
+            if *peer == swarm.local_peer {
+                return Err(Error::InvalidArgument("Cannot dial self".to_string()));
+            }
+            if swarm.is_connected(peer) {
+                return Ok(peer.clone());
+            }
+        }
+
+        let (conn, remote_addr) = if addr.is_circuit() {
+            let stream = try!(relay::open_circuit(swarm, addr));
+            (RawConn::Relayed(stream), addr.without_peer_id())
+        } else {
+            let stream = try!(swarm.connect_tcp(addr));
+            let remote_addr = match stream.peer_addr() {
+                Ok(addr) => Multiaddr::from_socket_addr(&addr),
+                Err(e) => return Err(Error::Io("Error getting address of peer".to_string(), e)),
+            };
+            (RawConn::Tcp(stream), remote_addr)
+        };
+        let peer = try!(Swarm::add_connection(swarm, conn, remote_addr, Some(addr)));
+        swarm.add_peer_addrs(&peer, &[addr.clone()]);
+        Ok(peer)
+    }
+
+    // Checks that the peer at the TCP address `addr` can be dialed, by
+    // securing a connection to it that is then dropped rather than
+    // multiplexed. Peers ask us to do this to learn whether they are
+    // reachable (see autonat).
+    pub fn probe(&self, addr: &Multiaddr) -> Result<PeerId, Error> {
+        let stream = try!(self.connect_tcp(addr));
+        let timeout = Some(Duration::from_secs(PROBE_TIMEOUT_SECS));
+        try!(stream.set_read_timeout(timeout)
+                   .map_err(|e| Error::Io("Error setting probe timeout".to_string(), e)));
+        let (peer, _) = try!(self.secure(RawConn::Tcp(stream), true));
+        try!(check_peer_id(addr, &peer));
+        Ok(peer)
+    }
+
+    fn connect_tcp(&self, addr: &Multiaddr) -> Result<TcpStream, Error> {
+        let socket_addrs: Vec<_> = try!(addr.to_socket_addrs())
+                                       .into_iter()
+                                       .filter(|a| !self.filters.is_denied(&a.ip()))
+                                       .collect();
+        if socket_addrs.is_empty() {
+            return Err(Error::Network(format!("{} is blocked by the swarm address filters",
+                                              addr)));
+        }
+        TcpStream::connect(&socket_addrs[..])
+            .map_err(|e| Error::Io(format!("Error dialing {}", addr), e))
+    }
+
+    // Runs the private network and Noise handshakes over a new connection
+    fn secure(&self, conn: RawConn, outbound: bool) -> Result<(PeerId, SecureStream), Error> {
+        let mut stream = try!(pnet::protect(conn, self.psk.as_ref()));
+        let identity = crypto::load_private_key(&self.identity_key);
+        let (peer, secure_stream) = if outbound {
+            try!(multistream::select(&mut stream, noise::PROTOCOL_ID));
+            try!(noise::handshake(stream, &identity, true))
+        } else {
+            try!(multistream::handle(&mut stream, &[noise::PROTOCOL_ID.to_string()]));
+            try!(noise::handshake(stream, &identity, false))
+        };
+        if peer == self.local_peer {
+            return Err(Error::Network("Connected to self".to_string()));
+        }
+        Ok((peer, secure_stream))
+    }
+
+    // Secures and multiplexes a new connection, from a peer at
+    // `remote_addr`. `dialed_addr` is the address we dialed for outbound
+    // connections, and None for inbound ones.
+    fn add_connection(swarm: &Arc<Self>,
+                      conn: RawConn,
+                      remote_addr: Multiaddr,
+                      dialed_addr: Option<&Multiaddr>)
+                      -> Result<PeerId, Error> {
+        let outbound = dialed_addr.is_some();
+        let (peer, mut secure_stream) = try!(swarm.secure(conn, outbound));
+        if let Some(addr) = dialed_addr {
+            try!(check_peer_id(addr, &peer));
+        }
+
+        let latency = if outbound {
+            // selecting the muxer takes one round trip, which gives us a
+            // first measure of the latency
+            let start = Instant::now();
+            try!(multistream::select(&mut secure_stream, mplex::PROTOCOL_ID));
+            Some(start.elapsed())
+        } else {
+            try!(multistream::handle(&mut secure_stream, &[mplex::PROTOCOL_ID.to_string()]));
+            None
+        };
+
+        let (reader, writer) = secure_stream.split();
+        let muxer = Muxer::new(writer);
+        let conn = Arc::new(Connection {
+            peer: peer.clone(),
+            remote_addr: remote_addr,
+            outbound: outbound,
+            opened: Instant::now(),
+            muxer: muxer.clone(),
+            latency: Mutex::new(latency),
+        });
+        // a newer connection replaces an older one. The peer stays connected
+        // throughout, so notifiees are only told about the first.
+        let replaced = swarm.conns.lock().unwrap().insert(peer.clone(), conn.clone());
+        debug!("Connected to {} at {}", peer, conn.remote_addr);
+        match replaced {
+            Some(old) => old.close(),
+            None => {
+                for notifiee in swarm.notifiees.read().unwrap().iter() {
+                    notifiee.connected(&peer);
+                }
+            }
+        }
+
+        let swarm = swarm.clone();
+        let reader_peer = peer.clone();
+        thread::spawn(move || {
+            {
+                let swarm = swarm.clone();
+                let peer = reader_peer.clone();
+                Muxer::run(&muxer, reader, move |stream| {
+                    let swarm = swarm.clone();
+                    let peer = peer.clone();
+                    thread::spawn(move || swarm.handle_stream(&peer, stream));
+                });
+            }
+            swarm.remove_connection(&conn);
+        });
+
+        Ok(peer)
+    }
+
+    fn remove_connection(&self, conn: &Arc<Connection>) {
+        {
+            let mut conns = self.conns.lock().unwrap();
+            let is_current = match conns.get(&conn.peer) {
+                Some(current) => &**current as *const Connection == &**conn as *const Connection,
+                None => false,
+            };
+            if !is_current {
+                return;
+            }
+            conns.remove(&conn.peer);
+        }
+        debug!("Disconnected from {}", conn.peer);
+        for notifiee in self.notifiees.read().unwrap().iter() {
+            notifiee.disconnected(&conn.peer);
+        }
+    }
+
+    // Negotiates the protocol of a stream a peer opened, and passes it to the
+    // protocol's handler
+    fn handle_stream(&self, peer: &PeerId, mut stream: Stream) {
+        let supported: Vec<String> = self.handlers.read().unwrap().keys().cloned().collect();
+        let protocol = match multistream::handle(&mut stream, &supported) {
+            Err(e) => {
+                debug!("Error negotiating stream protocol with {}: {}", peer, e);
+                return;
+            }
+            Ok(protocol) => protocol,
+        };
+
+        let handler = self.handlers.read().unwrap().get(&protocol).cloned();
+        if let Some(handler) = handler {
+            handler(peer, stream);
+        }
+    }
+
+    // Opens a stream to a connected peer for `protocol`
+    pub fn new_stream(&self, peer: &PeerId, protocol: &str) -> Result<Stream, Error> {
+        let conn = match self.conns.lock().unwrap().get(peer) {
+            None => return Err(Error::Network(format!("Not connected to peer {}", peer))),
+            Some(conn) => conn.clone(),
+        };
+        let mut stream = try!(Muxer::open_stream(&conn.muxer));
+        try!(multistream::select(&mut stream, protocol));
+        Ok(stream)
+    }
+}
+
+// Fails if `addr` ends with /p2p/<peer id> for a peer other than `peer`
+fn check_peer_id(addr: &Multiaddr, peer: &PeerId) -> Result<(), Error> {
+    match addr.peer_id() {
+        Some(expected) if expected != peer => {
+            Err(Error::Network(format!("Dialed {} but reached peer {}", expected, peer)))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,394 @@
+// mplex: multiplexing many streams over one secured connection. Each frame
+// is a varint header of (stream id << 3 | flag), a varint length, and the
+// data. A stream is identified by its id along with which side opened it.
+
+use super::noise::{SecureReader, SecureWriter};
+use codec;
+use error::Error;
+
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub const PROTOCOL_ID: &'static str = "/mplex/6.7.0";
+
+const NEW_STREAM: u64 = 0;
+const MESSAGE_RECEIVER: u64 = 1;
+const MESSAGE_INITIATOR: u64 = 2;
+const CLOSE_RECEIVER: u64 = 3;
+const CLOSE_INITIATOR: u64 = 4;
+const RESET_RECEIVER: u64 = 5;
+const RESET_INITIATOR: u64 = 6;
+
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+// Unread data a stream may hold before it's reset, so that a peer writing
+// faster than we read can't take all our memory
+const MAX_STREAM_BUFFER: usize = 4 * MAX_FRAME_SIZE;
+// Streams the remote may have open at once. Each one is handled on a thread
+// of its own, so further streams are reset until some are closed.
+const MAX_INBOUND_STREAMS: usize = 256;
+
+// (stream id, whether we opened it)
+type StreamKey = (u64, bool);
+
+pub struct Muxer {
+    writer: Mutex<SecureWriter>,
+    streams: Mutex<HashMap<StreamKey, Arc<StreamBuffer>>>,
+    next_id: Mutex<u64>,
+    closed: AtomicBool,
+    last_activity: Mutex<Instant>, // when a frame was last sent or received
+}
+
+// Data received for a stream that hasn't been read yet
+struct StreamBuffer {
+    state: Mutex<BufferState>,
+    readable: Condvar,
+}
+
+struct BufferState {
+    data: VecDeque<u8>,
+    remote_closed: bool, // the remote won't write any more
+    reset: bool,
+}
+
+impl StreamBuffer {
+    fn new() -> Self {
+        StreamBuffer {
+            state: Mutex::new(BufferState {
+                data: VecDeque::new(),
+                remote_closed: false,
+                reset: false,
+            }),
+            readable: Condvar::new(),
+        }
+    }
+
+    // Adds received data, returning false if there's no room for it
+    fn push(&self, data: &[u8]) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.data.len() + data.len() > MAX_STREAM_BUFFER {
+                return false;
+            }
+            state.data.extend(data.iter().cloned());
+        }
+        self.readable.notify_all();
+        true
+    }
+
+    fn close(&self, reset: bool) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.remote_closed = true;
+            state.reset = state.reset || reset;
+        }
+        self.readable.notify_all();
+    }
+
+    // Waits for data, returning 0 at the end of the stream
+    fn read(&self, out: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.reset {
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "stream was reset"));
+            }
+            if !state.data.is_empty() {
+                let n = cmp::min(out.len(), state.data.len());
+                for (i, byte) in state.data.drain(..n).enumerate() {
+                    out[i] = byte;
+                }
+                return Ok(n);
+            }
+            if state.remote_closed {
+                return Ok(0);
+            }
+            state = self.readable.wait(state).unwrap();
+        }
+    }
+}
+
+impl Muxer {
+    pub fn new(writer: SecureWriter) -> Arc<Self> {
+        Arc::new(Muxer {
+            writer: Mutex::new(writer),
+            streams: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(0),
+            closed: AtomicBool::new(false),
+            last_activity: Mutex::new(Instant::now()),
+        })
+    }
+
+    pub fn open_stream(muxer: &Arc<Self>) -> Result<Stream, Error> {
+        if muxer.is_closed() {
+            return Err(Error::Network("Connection is closed".to_string()));
+        }
+
+        let id = {
+            let mut next_id = muxer.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        let buffer = Arc::new(StreamBuffer::new());
+        muxer.streams.lock().unwrap().insert((id, true), buffer.clone());
+        try!(muxer.write_frame(id, NEW_STREAM, id.to_string().as_bytes())
+                  .map_err(|e| Error::Io("Error opening stream".to_string(), e)));
+
+        Ok(Stream::new(id, true, muxer.clone(), buffer))
+    }
+
+    pub fn num_streams(&self) -> usize {
+        self.streams.lock().unwrap().len()
+    }
+
+    // How long since a frame was last sent or received
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.lock().unwrap().elapsed()
+    }
+
+    fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    // Closes the connection, resetting every stream on it
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let _ = self.writer.lock().unwrap().shutdown();
+        for (_, buffer) in self.streams.lock().unwrap().drain() {
+            buffer.close(true);
+        }
+    }
+
+    // Reads frames until the connection closes, passing streams opened by
+    // the remote to `on_stream`
+    pub fn run<F>(muxer: &Arc<Self>, mut reader: SecureReader, mut on_stream: F)
+        where F: FnMut(Stream)
+    {
+        loop {
+            let (id, flag, data) = match read_frame(&mut reader) {
+                Err(e) => {
+                    debug!("mplex connection closed: {}", e);
+                    break;
+                }
+                Ok(frame) => frame,
+            };
+            muxer.touch();
+
+            // flags are from the sender's point of view: "initiator" frames
+            // are for streams the remote opened
+            let key = match flag {
+                NEW_STREAM | MESSAGE_INITIATOR | CLOSE_INITIATOR | RESET_INITIATOR => (id, false),
+                MESSAGE_RECEIVER | CLOSE_RECEIVER | RESET_RECEIVER => (id, true),
+                _ => {
+                    warn!("Invalid mplex flag {}", flag);
+                    break;
+                }
+            };
+
+            if flag == NEW_STREAM {
+                let buffer = Arc::new(StreamBuffer::new());
+                {
+                    let mut streams = muxer.streams.lock().unwrap();
+                    if streams.keys().filter(|key| !key.1).count() >= MAX_INBOUND_STREAMS {
+                        debug!("Resetting mplex stream {}: too many open streams", id);
+                        drop(streams);
+                        muxer.reset_stream(key);
+                        continue;
+                    }
+                    streams.insert(key, buffer.clone());
+                }
+                on_stream(Stream::new(id, false, muxer.clone(), buffer));
+                continue;
+            }
+
+            let buffer = match muxer.streams.lock().unwrap().get(&key) {
+                None => continue, // a stream we've already dropped
+                Some(buffer) => buffer.clone(),
+            };
+            match flag {
+                MESSAGE_INITIATOR | MESSAGE_RECEIVER => {
+                    if !buffer.push(&data) {
+                        debug!("Resetting mplex stream {}: too much unread data", id);
+                        buffer.close(true);
+                        muxer.streams.lock().unwrap().remove(&key);
+                        muxer.reset_stream(key);
+                    }
+                }
+                CLOSE_INITIATOR | CLOSE_RECEIVER => buffer.close(false),
+                _ => {
+                    buffer.close(true);
+                    muxer.streams.lock().unwrap().remove(&key);
+                }
+            }
+        }
+        muxer.close();
+    }
+
+    // Tells the remote a stream is reset, for streams no Stream is left to
+    // do it for
+    fn reset_stream(&self, key: StreamKey) {
+        let (id, initiator) = key;
+        let flag = if initiator {
+            RESET_INITIATOR
+        } else {
+            RESET_RECEIVER
+        };
+        if let Err(e) = self.write_frame(id, flag, &[]) {
+            debug!("Error resetting mplex stream {}: {}", id, e);
+        }
+    }
+
+    fn write_frame(&self, id: u64, flag: u64, data: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(data.len() + 20);
+        codec::encode_varint(id << 3 | flag, &mut frame);
+        codec::encode_varint(data.len() as u64, &mut frame);
+        frame.extend_from_slice(data);
+
+        self.touch();
+        let mut writer = self.writer.lock().unwrap();
+        try!(writer.write_all(&frame));
+        writer.flush()
+    }
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<(u64, u64, Vec<u8>)> {
+    let header = try!(codec::read_varint(reader));
+    let len = try!(codec::read_varint(reader)) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "mplex frame is too large"));
+    }
+    let mut data = vec![0; len];
+    try!(reader.read_exact(&mut data));
+    Ok((header >> 3, header & 0x7, data))
+}
+
+// A bidirectional stream over a connection
+pub struct Stream {
+    id: u64,
+    initiator: bool, // whether we opened the stream
+    muxer: Arc<Muxer>,
+    buffer: Arc<StreamBuffer>,
+    write_closed: bool,
+}
+
+impl Stream {
+    fn new(id: u64, initiator: bool, muxer: Arc<Muxer>, buffer: Arc<StreamBuffer>) -> Self {
+        Stream {
+            id: id,
+            initiator: initiator,
+            muxer: muxer,
+            buffer: buffer,
+            write_closed: false,
+        }
+    }
+
+    fn flag(&self, initiator_flag: u64, receiver_flag: u64) -> u64 {
+        if self.initiator {
+            initiator_flag
+        } else {
+            receiver_flag
+        }
+    }
+
+    // Tells the remote we won't write any more. The stream can still be read.
+    pub fn close_write(&mut self) -> io::Result<()> {
+        if self.write_closed {
+            return Ok(());
+        }
+        self.write_closed = true;
+        let flag = self.flag(CLOSE_INITIATOR, CLOSE_RECEIVER);
+        self.muxer.write_frame(self.id, flag, &[])
+    }
+
+    // Aborts the stream in both directions
+    pub fn reset(mut self) {
+        let _ = self.send_reset();
+    }
+
+    fn send_reset(&mut self) -> io::Result<()> {
+        if self.write_closed {
+            return Ok(());
+        }
+        self.write_closed = true;
+        let flag = self.flag(RESET_INITIATOR, RESET_RECEIVER);
+        self.muxer.write_frame(self.id, flag, &[])
+    }
+
+    // Separates the stream into halves that can be used from different
+    // threads
+    pub fn split(self) -> (StreamReader, StreamWriter) {
+        (StreamReader { buffer: self.buffer.clone() }, StreamWriter { stream: self })
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.buffer.read(out)
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.write_closed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "stream is closed for writing"));
+        }
+        let n = cmp::min(buf.len(), MAX_FRAME_SIZE);
+        let flag = self.flag(MESSAGE_INITIATOR, MESSAGE_RECEIVER);
+        try!(self.muxer.write_frame(self.id, flag, &buf[..n]));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let _ = self.close_write();
+        self.muxer.streams.lock().unwrap().remove(&(self.id, self.initiator));
+    }
+}
+
+pub struct StreamReader {
+    buffer: Arc<StreamBuffer>,
+}
+
+impl Read for StreamReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.buffer.read(out)
+    }
+}
+
+// The stream is closed once its writer is dropped
+pub struct StreamWriter {
+    stream: Stream,
+}
+
+impl StreamWriter {
+    pub fn close_write(&mut self) -> io::Result<()> {
+        self.stream.close_write()
+    }
+
+    // Resets the stream, which also ends reading from it
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.stream.buffer.close(true);
+        self.stream.send_reset()
+    }
+}
+
+impl Write for StreamWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
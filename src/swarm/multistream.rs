@@ -0,0 +1,79 @@
+// multistream-select: agreeing on the protocol spoken over a connection or
+// stream. Each message is a varint length-prefixed line. Both sides send the
+// multistream header, then the dialer proposes a protocol and the listener
+// echoes it back to accept, or answers "na".
+
+use codec;
+use error::Error;
+
+use std::io::{self, Read, Write};
+
+pub const PROTOCOL_ID: &'static str = "/multistream/1.0.0";
+const NOT_AVAILABLE: &'static str = "na";
+const MAX_MESSAGE_SIZE: usize = 1024;
+
+fn write_message<W: Write>(writer: &mut W, msg: &str) -> io::Result<()> {
+    let mut line = msg.as_bytes().to_vec();
+    line.push(b'\n');
+    codec::write_length_prefixed(writer, &line)
+}
+
+fn read_message<R: Read>(reader: &mut R) -> Result<String, Error> {
+    let len = try!(codec::read_varint(reader)
+                       .map_err(|e| Error::Io("Error reading multistream message".to_string(), e)));
+    if len as usize > MAX_MESSAGE_SIZE {
+        return Err(Error::Network("multistream message too long".to_string()));
+    }
+    let mut line = vec![0; len as usize];
+    try!(reader.read_exact(&mut line)
+               .map_err(|e| Error::Io("Error reading multistream message".to_string(), e)));
+
+    if line.pop() != Some(b'\n') {
+        return Err(Error::Network("multistream message is missing its newline".to_string()));
+    }
+    String::from_utf8(line).map_err(|e| {
+        Error::decode("Invalid UTF-8 in multistream message".to_string(), e)
+    })
+}
+
+fn expect_header<R: Read>(reader: &mut R) -> Result<(), Error> {
+    let header = try!(read_message(reader));
+    if header != PROTOCOL_ID {
+        return Err(Error::Network(format!("Unsupported multistream version {}", header)));
+    }
+    Ok(())
+}
+
+// Proposes `protocol` as the dialer, failing if the listener doesn't
+// support it
+pub fn select<S: Read + Write>(stream: &mut S, protocol: &str) -> Result<(), Error> {
+    try!(write_message(stream, PROTOCOL_ID)
+             .and_then(|_| write_message(stream, protocol))
+             .map_err(|e| Error::Io("Error writing multistream message".to_string(), e)));
+
+    try!(expect_header(stream));
+    let response = try!(read_message(stream));
+    if response == protocol {
+        Ok(())
+    } else {
+        Err(Error::Network(format!("Peer does not support protocol {}", protocol)))
+    }
+}
+
+// Waits for the dialer to propose one of the `supported` protocols, returning
+// the one agreed on
+pub fn handle<S: Read + Write>(stream: &mut S, supported: &[String]) -> Result<String, Error> {
+    try!(expect_header(stream));
+    try!(write_message(stream, PROTOCOL_ID)
+             .map_err(|e| Error::Io("Error writing multistream message".to_string(), e)));
+
+    loop {
+        let protocol = try!(read_message(stream));
+        let accepted = supported.contains(&protocol);
+        try!(write_message(stream, if accepted { &protocol } else { NOT_AVAILABLE })
+                 .map_err(|e| Error::Io("Error writing multistream message".to_string(), e)));
+        if accepted {
+            return Ok(protocol);
+        }
+    }
+}
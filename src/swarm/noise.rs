@@ -0,0 +1,429 @@
+// Securing connections with the Noise XX handshake
+// (Noise_XX_25519_ChaChaPoly_SHA256), following libp2p's use of it: each
+// side proves its identity by signing its Noise static key with its identity
+// key (see noise.proto). Afterwards every message is encrypted, and sent
+// with a 2-byte big-endian length prefix.
+//
+// This uses the original ChaCha20-Poly1305 construction (64-bit nonces), so
+// it only interoperates with other nodes built from this crate.
+
+use super::pnet::{ProtectedReader, ProtectedStream, ProtectedWriter};
+use codec::{PbReader, PbWriter};
+use crypto::{self, PublicKey};
+use error::Error;
+use peer::PeerId;
+use util;
+
+use openssl::crypto::hash;
+use openssl::crypto::hmac;
+use openssl::crypto::pkey::PKey;
+use rust_crypto::aead::{AeadDecryptor, AeadEncryptor};
+use rust_crypto::chacha20poly1305::ChaCha20Poly1305;
+use rust_crypto::curve25519::{curve25519, curve25519_base};
+use std::cmp;
+use std::io::{self, Read, Write};
+
+pub const PROTOCOL_ID: &'static str = "/noise";
+
+const PROTOCOL_NAME: &'static [u8] = b"Noise_XX_25519_ChaChaPoly_SHA256";
+const STATIC_KEY_SIGNATURE_PREFIX: &'static [u8] = b"noise-libp2p-static-key:";
+const MAX_MESSAGE_SIZE: usize = 65535;
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+const MAX_PLAINTEXT_SIZE: usize = MAX_MESSAGE_SIZE - TAG_SIZE;
+
+fn to_key(bytes: &[u8]) -> [u8; KEY_SIZE] {
+    let mut key = [0u8; KEY_SIZE];
+    key.copy_from_slice(&bytes[..KEY_SIZE]);
+    key
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::hmac(hash::Type::SHA256, key, data)
+}
+
+// HKDF with two outputs, as defined by the Noise spec
+fn hkdf(chaining_key: &[u8], input_key_material: &[u8]) -> ([u8; KEY_SIZE], [u8; KEY_SIZE]) {
+    let temp_key = hmac_sha256(chaining_key, input_key_material);
+    let output1 = hmac_sha256(&temp_key, &[1]);
+    let mut input2 = output1.clone();
+    input2.push(2);
+    let output2 = hmac_sha256(&temp_key, &input2);
+    (to_key(&output1), to_key(&output2))
+}
+
+struct CipherState {
+    key: [u8; KEY_SIZE],
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: [u8; KEY_SIZE]) -> Self {
+        CipherState {
+            key: key,
+            nonce: 0,
+        }
+    }
+
+    fn next_nonce(&mut self) -> [u8; 8] {
+        let mut nonce = [0u8; 8];
+        for (i, byte) in nonce.iter_mut().enumerate() {
+            *byte = (self.nonce >> (8 * i)) as u8;
+        }
+        self.nonce += 1;
+        nonce
+    }
+
+    fn encrypt(&mut self, ad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let nonce = self.next_nonce();
+        let mut cipher = ChaCha20Poly1305::new(&self.key, &nonce, ad);
+        let mut out = vec![0; plaintext.len()];
+        let mut tag = [0u8; TAG_SIZE];
+        cipher.encrypt(plaintext, &mut out, &mut tag);
+        out.extend_from_slice(&tag);
+        out
+    }
+
+    fn decrypt(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        if ciphertext.len() < TAG_SIZE {
+            return Err(Error::Network("Noise message is too short".to_string()));
+        }
+        let (data, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
+        let nonce = self.next_nonce();
+        let mut cipher = ChaCha20Poly1305::new(&self.key, &nonce, ad);
+        let mut out = vec![0; data.len()];
+        if !cipher.decrypt(data, &mut out, tag) {
+            return Err(Error::Network("Noise message failed authentication".to_string()));
+        }
+        Ok(out)
+    }
+}
+
+// The hashing and key derivation state shared by the handshake messages
+struct SymmetricState {
+    chaining_key: [u8; KEY_SIZE],
+    hash: [u8; KEY_SIZE],
+    cipher: Option<CipherState>,
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        // the protocol name is exactly 32 bytes, so it's used as is
+        let h = to_key(PROTOCOL_NAME);
+        let mut state = SymmetricState {
+            chaining_key: h,
+            hash: h,
+            cipher: None,
+        };
+        state.mix_hash(&[]); // empty prologue
+        state
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        let mut input = self.hash.to_vec();
+        input.extend_from_slice(data);
+        self.hash = to_key(&crypto::sha256(&input));
+    }
+
+    fn mix_key(&mut self, input_key_material: &[u8]) {
+        let (chaining_key, key) = hkdf(&self.chaining_key, input_key_material);
+        self.chaining_key = chaining_key;
+        self.cipher = Some(CipherState::new(key));
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        let ciphertext = match self.cipher {
+            None => plaintext.to_vec(),
+            Some(ref mut cipher) => cipher.encrypt(&self.hash, plaintext),
+        };
+        self.mix_hash(&ciphertext);
+        ciphertext
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = match self.cipher {
+            None => ciphertext.to_vec(),
+            Some(ref mut cipher) => try!(cipher.decrypt(&self.hash, ciphertext)),
+        };
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    // The ciphers for each direction: initiator to responder, and back
+    fn split(&self) -> (CipherState, CipherState) {
+        let (k1, k2) = hkdf(&self.chaining_key, &[]);
+        (CipherState::new(k1), CipherState::new(k2))
+    }
+}
+
+struct KeyPair {
+    secret: [u8; KEY_SIZE],
+    public: [u8; KEY_SIZE],
+}
+
+impl KeyPair {
+    fn generate() -> Self {
+        let secret = to_key(&crypto::random_bytes(KEY_SIZE));
+        KeyPair {
+            secret: secret,
+            public: curve25519_base(&secret),
+        }
+    }
+
+    fn dh(&self, public: &[u8]) -> [u8; KEY_SIZE] {
+        curve25519(&self.secret, public)
+    }
+}
+
+fn write_handshake_message(stream: &mut ProtectedStream, msg: &[u8]) -> Result<(), Error> {
+    write_frame(stream, msg).map_err(|e| Error::Io("Error sending Noise handshake".to_string(), e))
+}
+
+fn read_handshake_message(stream: &mut ProtectedStream) -> Result<Vec<u8>, Error> {
+    match read_frame(stream) {
+        Ok(Some(msg)) => Ok(msg),
+        Ok(None) => {
+            Err(Error::Io("Error reading Noise handshake".to_string(),
+                          io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")))
+        }
+        Err(e) => Err(Error::Io("Error reading Noise handshake".to_string(), e)),
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(data.len() + 2);
+    frame.push((data.len() >> 8) as u8);
+    frame.push(data.len() as u8);
+    frame.extend_from_slice(data);
+    try!(writer.write_all(&frame));
+    writer.flush()
+}
+
+// Reads a frame, or returns None if the stream ends before the next one.
+// Ending within a frame is an UnexpectedEof error.
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 2];
+    loop {
+        match reader.read(&mut len[..1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    try!(reader.read_exact(&mut len[1..]));
+    let mut data = vec![0; (len[0] as usize) << 8 | len[1] as usize];
+    try!(reader.read_exact(&mut data));
+    Ok(Some(data))
+}
+
+// The payload proving that `static_key` belongs to the holder of `identity`
+fn make_payload(identity: &PKey, static_key: &[u8]) -> Vec<u8> {
+    let mut signed = STATIC_KEY_SIGNATURE_PREFIX.to_vec();
+    signed.extend_from_slice(static_key);
+
+    let mut payload = PbWriter::new();
+    payload.bytes(1, &identity.save_pub())
+           .bytes(2, &crypto::sign(identity, &signed));
+    payload.into_bytes()
+}
+
+// Checks the payload against the remote static key, returning the remote
+// peer's ID
+fn verify_payload(payload: &[u8], static_key: &[u8]) -> Result<PeerId, Error> {
+    let mut identity_key = None;
+    let mut identity_sig = None;
+    let mut reader = PbReader::new(payload);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => identity_key = Some(try!(value.as_bytes())),
+            2 => identity_sig = Some(try!(value.as_bytes())),
+            _ => {}
+        }
+    }
+
+    let (identity_key, identity_sig) = match (identity_key, identity_sig) {
+        (Some(key), Some(sig)) => (key, sig),
+        _ => return Err(Error::Network("Noise payload is missing the identity".to_string())),
+    };
+
+    let mut signed = STATIC_KEY_SIGNATURE_PREFIX.to_vec();
+    signed.extend_from_slice(static_key);
+    let key = try!(PublicKey::from_bytes(identity_key));
+    if !key.verify(&signed, identity_sig) {
+        return Err(Error::Network("Invalid signature in Noise payload".to_string()));
+    }
+    Ok(util::hash(identity_key))
+}
+
+// Runs the handshake over `stream`, returning the authenticated ID of the
+// remote peer and the encrypted channel
+pub fn handshake(mut stream: ProtectedStream,
+                 identity: &PKey,
+                 initiator: bool)
+                 -> Result<(PeerId, SecureStream), Error> {
+    let ephemeral = KeyPair::generate();
+    let static_keys = KeyPair::generate();
+    let payload = make_payload(identity, &static_keys.public);
+    let mut state = SymmetricState::new();
+
+    let (remote_peer, send, receive) = if initiator {
+        // -> e
+        state.mix_hash(&ephemeral.public);
+        let mut msg = ephemeral.public.to_vec();
+        msg.extend(state.encrypt_and_hash(&[]));
+        try!(write_handshake_message(&mut stream, &msg));
+
+        // <- e, ee, s, es
+        let msg = try!(read_handshake_message(&mut stream));
+        if msg.len() < 2 * KEY_SIZE + TAG_SIZE {
+            return Err(Error::Network("Noise handshake message is too short".to_string()));
+        }
+        let (remote_ephemeral, rest) = msg.split_at(KEY_SIZE);
+        state.mix_hash(remote_ephemeral);
+        state.mix_key(&ephemeral.dh(remote_ephemeral));
+        let (encrypted_static, encrypted_payload) = rest.split_at(KEY_SIZE + TAG_SIZE);
+        let remote_static = try!(state.decrypt_and_hash(encrypted_static));
+        state.mix_key(&ephemeral.dh(&remote_static));
+        let remote_payload = try!(state.decrypt_and_hash(encrypted_payload));
+        let remote_peer = try!(verify_payload(&remote_payload, &remote_static));
+
+        // -> s, se
+        let mut msg = state.encrypt_and_hash(&static_keys.public);
+        state.mix_key(&static_keys.dh(remote_ephemeral));
+        msg.extend(state.encrypt_and_hash(&payload));
+        try!(write_handshake_message(&mut stream, &msg));
+
+        let (c1, c2) = state.split();
+        (remote_peer, c1, c2)
+    } else {
+        // -> e
+        let msg = try!(read_handshake_message(&mut stream));
+        if msg.len() < KEY_SIZE {
+            return Err(Error::Network("Noise handshake message is too short".to_string()));
+        }
+        let (remote_ephemeral, rest) = msg.split_at(KEY_SIZE);
+        state.mix_hash(remote_ephemeral);
+        try!(state.decrypt_and_hash(rest));
+
+        // <- e, ee, s, es
+        state.mix_hash(&ephemeral.public);
+        let mut reply = ephemeral.public.to_vec();
+        state.mix_key(&ephemeral.dh(remote_ephemeral));
+        reply.extend(state.encrypt_and_hash(&static_keys.public));
+        state.mix_key(&static_keys.dh(remote_ephemeral));
+        reply.extend(state.encrypt_and_hash(&payload));
+        try!(write_handshake_message(&mut stream, &reply));
+
+        // -> s, se
+        let msg = try!(read_handshake_message(&mut stream));
+        if msg.len() < KEY_SIZE + TAG_SIZE {
+            return Err(Error::Network("Noise handshake message is too short".to_string()));
+        }
+        let (encrypted_static, encrypted_payload) = msg.split_at(KEY_SIZE + TAG_SIZE);
+        let remote_static = try!(state.decrypt_and_hash(encrypted_static));
+        state.mix_key(&ephemeral.dh(&remote_static));
+        let remote_payload = try!(state.decrypt_and_hash(encrypted_payload));
+        let remote_peer = try!(verify_payload(&remote_payload, &remote_static));
+
+        let (c1, c2) = state.split();
+        (remote_peer, c2, c1)
+    };
+
+    let (read_stream, write_stream) = stream.split();
+    let secure_stream = SecureStream {
+        reader: SecureReader {
+            stream: read_stream,
+            cipher: receive,
+            buf: Vec::new(),
+            pos: 0,
+        },
+        writer: SecureWriter {
+            stream: write_stream,
+            cipher: send,
+        },
+    };
+    Ok((remote_peer, secure_stream))
+}
+
+// An encrypted channel established by `handshake`
+pub struct SecureStream {
+    reader: SecureReader,
+    writer: SecureWriter,
+}
+
+impl SecureStream {
+    // Separates the channel into halves that can be used from different
+    // threads
+    pub fn split(self) -> (SecureReader, SecureWriter) {
+        (self.reader, self.writer)
+    }
+}
+
+impl Read for SecureStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for SecureStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct SecureReader {
+    stream: ProtectedReader,
+    cipher: CipherState,
+    buf: Vec<u8>, // decrypted data not read yet, starting at `pos`
+    pos: usize,
+}
+
+impl Read for SecureReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            let frame = match try!(read_frame(&mut self.stream)) {
+                None => return Ok(0),
+                Some(frame) => frame,
+            };
+            self.buf = try!(self.cipher
+                                .decrypt(&[], &frame)
+                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+            self.pos = 0;
+        }
+
+        let n = cmp::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+pub struct SecureWriter {
+    stream: ProtectedWriter,
+    cipher: CipherState,
+}
+
+impl SecureWriter {
+    // Closes the underlying connection in both directions
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.stream.shutdown()
+    }
+}
+
+impl Write for SecureWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = cmp::min(buf.len(), MAX_PLAINTEXT_SIZE);
+        let frame = self.cipher.encrypt(&[], &buf[..n]);
+        try!(write_frame(&mut self.stream, &frame));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
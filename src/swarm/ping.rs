@@ -0,0 +1,73 @@
+// Ping: the dialer sends 32 random bytes, and the peer sends them back. The
+// same stream is used for consecutive pings. Each round trip updates the
+// latency of the connection.
+
+use super::{Connection, Stream, Swarm};
+use crypto;
+use error::Error;
+use peer::PeerId;
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const PROTOCOL_ID: &'static str = "/ipfs/ping/1.0.0";
+
+const PING_SIZE: usize = 32;
+
+pub struct Pinger {
+    peer: PeerId,
+    conn: Arc<Connection>,
+    stream: Stream,
+}
+
+impl Pinger {
+    pub fn new(swarm: &Swarm, peer: &PeerId) -> Result<Self, Error> {
+        let stream = try!(swarm.new_stream(peer, PROTOCOL_ID));
+        let conn = match swarm.connection(peer) {
+            None => return Err(Error::Network(format!("Not connected to peer {}", peer))),
+            Some(conn) => conn,
+        };
+        Ok(Pinger {
+            peer: peer.clone(),
+            conn: conn,
+            stream: stream,
+        })
+    }
+
+    // Returns the round trip time
+    pub fn ping(&mut self) -> Result<Duration, Error> {
+        let payload = crypto::random_bytes(PING_SIZE);
+        let mut echo = vec![0; PING_SIZE];
+
+        let start = Instant::now();
+        try!(self.stream
+                 .write_all(&payload)
+                 .and_then(|_| self.stream.read_exact(&mut echo))
+                 .map_err(|e| Error::Io(format!("Error pinging {}", self.peer), e)));
+        let rtt = start.elapsed();
+
+        if echo != payload {
+            return Err(Error::Network(format!("{} answered a ping with different bytes",
+                                              self.peer)));
+        }
+        self.conn.set_latency(rtt);
+        Ok(rtt)
+    }
+}
+
+// Echoes pings until the peer closes the stream
+pub fn register(swarm: &Swarm) {
+    swarm.set_stream_handler(PROTOCOL_ID,
+                             Arc::new(|peer: &PeerId, mut stream: Stream| {
+                                 let mut buf = [0; PING_SIZE];
+                                 loop {
+                                     let result = stream.read_exact(&mut buf)
+                                                        .and_then(|_| stream.write_all(&buf));
+                                     if let Err(e) = result {
+                                         trace!("Ping stream from {} closed: {}", peer, e);
+                                         return;
+                                     }
+                                 }
+                             }));
+}
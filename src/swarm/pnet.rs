@@ -0,0 +1,211 @@
+// Private networks, as in go-libp2p-pnet: nodes sharing a pre-shared key
+// (the repo's swarm.key) encrypt every connection with XSalsa20 before
+// anything else is sent. Each side sends a random 24-byte nonce, then
+// encrypts what it sends with the key and its own nonce. Peers without the
+// key can't get past the multistream negotiation that follows.
+//
+// The key file has the libp2p format:
+//
+//     /key/swarm/psk/1.0.0/
+//     /base16/
+//     <64 hex digits>
+
+use super::transport::{RawConn, RawReader, RawWriter};
+use crypto;
+use error::Error;
+
+use rust_crypto::salsa20::Salsa20;
+use rust_crypto::symmetriccipher::SynchronousStreamCipher;
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::{FromHex, ToHex};
+use std::env;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// Set to 1 to refuse to go online without a swarm key
+pub const ENV_NAME_FORCE_PNET: &'static str = "LIBP2P_FORCE_PNET";
+
+const PSK_HEADER: &'static str = "/key/swarm/psk/1.0.0/";
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 24;
+
+// Whether the environment requires a private network
+pub fn is_forced() -> bool {
+    match env::var(ENV_NAME_FORCE_PNET) {
+        Ok(value) => value == "1",
+        Err(_) => false,
+    }
+}
+
+#[derive(Clone)]
+pub struct Psk {
+    key: [u8; KEY_SIZE],
+}
+
+impl Psk {
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_SIZE];
+        key.copy_from_slice(&crypto::random_bytes(KEY_SIZE));
+        Psk { key: key }
+    }
+
+    // Parses the contents of a swarm.key file. The key may be encoded as
+    // /base16/, /base64/ or /bin/.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidArgument(format!("Invalid swarm key: {}", msg));
+
+        let (header, rest) = split_line(data);
+        if header != PSK_HEADER {
+            return Err(invalid(&format!("expected the header {}", PSK_HEADER)));
+        }
+        let (encoding, rest) = split_line(rest);
+        let key = match &encoding[..] {
+            "/base16/" => try!(split_line(rest).0.from_hex().map_err(|e| invalid(&e.to_string()))),
+            "/base64/" => {
+                try!(split_line(rest).0.from_base64().map_err(|e| invalid(&e.to_string())))
+            }
+            "/bin/" => rest.to_vec(),
+            _ => return Err(invalid(&format!("unknown encoding {}", encoding))),
+        };
+        if key.len() != KEY_SIZE {
+            return Err(invalid(&format!("expected {} bytes, got {}", KEY_SIZE, key.len())));
+        }
+
+        let mut psk = [0u8; KEY_SIZE];
+        psk.copy_from_slice(&key);
+        Ok(Psk { key: psk })
+    }
+
+    // A fingerprint of the key, safe to show
+    pub fn fingerprint(&self) -> String {
+        crypto::sha256(&self.key)[..16].to_hex()
+    }
+}
+
+// The first line of `data`, trimmed, and what follows it
+fn split_line(data: &[u8]) -> (String, &[u8]) {
+    let (line, rest) = match data.iter().position(|&b| b == b'\n') {
+        Some(end) => (&data[..end], &data[end + 1..]),
+        None => (data, &data[data.len()..]),
+    };
+    (String::from_utf8_lossy(line).trim().to_string(), rest)
+}
+
+// The contents of a swarm.key file, hex-encoded
+impl fmt::Display for Psk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n/base16/\n{}\n", PSK_HEADER, self.key.to_hex())
+    }
+}
+
+// Sets up the encryption of a new connection, exchanging nonces with the
+// peer. Without a key, the connection is passed through as is.
+pub fn protect(conn: RawConn, psk: Option<&Psk>) -> Result<ProtectedStream, Error> {
+    let (mut read_stream, mut write_stream) = try!(conn.split().map_err(|e| {
+        Error::Io("Error splitting connection".to_string(), e)
+    }));
+    let (read_cipher, write_cipher) = match psk {
+        None => (None, None),
+        Some(psk) => {
+            let nonce = crypto::random_bytes(NONCE_SIZE);
+            try!(write_stream.write_all(&nonce).map_err(|e| {
+                Error::Io("Error sending private network nonce".to_string(), e)
+            }));
+            let mut remote_nonce = [0u8; NONCE_SIZE];
+            try!(read_stream.read_exact(&mut remote_nonce).map_err(|e| {
+                Error::Io("Error reading private network nonce".to_string(), e)
+            }));
+            (Some(Salsa20::new_xsalsa20(&psk.key, &remote_nonce)),
+             Some(Salsa20::new_xsalsa20(&psk.key, &nonce)))
+        }
+    };
+
+    Ok(ProtectedStream {
+        reader: ProtectedReader {
+            stream: read_stream,
+            cipher: read_cipher,
+        },
+        writer: ProtectedWriter {
+            stream: write_stream,
+            cipher: write_cipher,
+        },
+    })
+}
+
+// A connection set up by `protect`
+pub struct ProtectedStream {
+    reader: ProtectedReader,
+    writer: ProtectedWriter,
+}
+
+impl ProtectedStream {
+    // Separates the connection into halves that can be used from different
+    // threads
+    pub fn split(self) -> (ProtectedReader, ProtectedWriter) {
+        (self.reader, self.writer)
+    }
+}
+
+impl Read for ProtectedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for ProtectedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct ProtectedReader {
+    stream: RawReader,
+    cipher: Option<Salsa20>,
+}
+
+impl Read for ProtectedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.stream.read(buf));
+        if let Some(ref mut cipher) = self.cipher {
+            let encrypted = buf[..n].to_vec();
+            cipher.process(&encrypted, &mut buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+pub struct ProtectedWriter {
+    stream: RawWriter,
+    cipher: Option<Salsa20>,
+}
+
+impl ProtectedWriter {
+    // Closes the connection in both directions
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.stream.shutdown()
+    }
+}
+
+impl Write for ProtectedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.cipher {
+            None => self.stream.write(buf),
+            Some(ref mut cipher) => {
+                // the keystream has advanced once encrypted, so everything
+                // must be sent
+                let mut encrypted = vec![0; buf.len()];
+                cipher.process(buf, &mut encrypted);
+                try!(self.stream.write_all(&encrypted));
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
@@ -0,0 +1,717 @@
+// Circuit relay v2, as in libp2p. A peer that can't be reached directly
+// (behind a NAT, say) reserves a slot on a relay it's connected to, and can
+// then be reached at <relay address>/p2p/<relay id>/p2p-circuit. A peer
+// dialing such an address asks the relay to connect it to the peer (the hop
+// protocol), the relay opens a stream to the peer (the stop protocol), and
+// passes data between the two streams. The relayed stream is then secured
+// and multiplexed like a TCP connection.
+//
+// Relays limit the number of reservations, the number of circuits each peer
+// takes part in, and how long and how much data each circuit relays.
+
+use super::{Stream, Swarm};
+use super::mplex::{StreamReader, StreamWriter};
+use super::transport::RawConn;
+use codec::{self, PbReader, PbWriter};
+use error::Error;
+use multiaddr::{Multiaddr, Protocol};
+use peer::PeerId;
+use util;
+
+use rust_multihash::Multihash;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const HOP_PROTOCOL_ID: &'static str = "/libp2p/circuit/relay/0.2.0/hop";
+pub const STOP_PROTOCOL_ID: &'static str = "/libp2p/circuit/relay/0.2.0/stop";
+
+// The relay service's defaults, as in go-libp2p
+pub const DEFAULT_MAX_RESERVATIONS: usize = 128;
+pub const DEFAULT_MAX_CIRCUITS: usize = 16;
+pub const DEFAULT_RESERVATION_TTL_SECS: u64 = 60 * 60;
+pub const DEFAULT_LIMIT_DURATION_SECS: u64 = 2 * 60;
+pub const DEFAULT_LIMIT_DATA_BYTES: u64 = 128 * 1024;
+
+// How often the client checks whether its reservations need renewing, and
+// how long it waits before trying a relay again after failing
+const CHECK_INTERVAL_SECS: u64 = 10;
+const RETRY_INTERVAL_SECS: u64 = 60;
+
+const HOP_RESERVE: u64 = 0;
+const HOP_CONNECT: u64 = 1;
+const HOP_STATUS: u64 = 2;
+const STOP_CONNECT: u64 = 0;
+const STOP_STATUS: u64 = 1;
+
+const STATUS_OK: u64 = 100;
+const STATUS_RESERVATION_REFUSED: u64 = 200;
+const STATUS_RESOURCE_LIMIT_EXCEEDED: u64 = 201;
+const STATUS_CONNECTION_FAILED: u64 = 203;
+const STATUS_NO_RESERVATION: u64 = 204;
+const STATUS_MALFORMED_MESSAGE: u64 = 400;
+const STATUS_UNEXPECTED_MESSAGE: u64 = 401;
+
+#[derive(Clone)]
+pub struct Reservation {
+    pub expire: u64, // Unix time in seconds
+    pub addrs: Vec<Multiaddr>, // the relay's addresses
+}
+
+// What a relay allows each circuit
+#[derive(Clone, Copy)]
+pub struct Limit {
+    pub duration: Duration,
+    pub data: u64, // bytes relayed in each direction
+}
+
+// What a relay allows altogether
+#[derive(Clone, Copy)]
+pub struct Resources {
+    pub max_reservations: usize,
+    pub max_circuits: usize, // per peer
+    pub reservation_ttl: Duration,
+    pub limit: Limit,
+}
+
+impl Default for Resources {
+    fn default() -> Self {
+        Resources {
+            max_reservations: DEFAULT_MAX_RESERVATIONS,
+            max_circuits: DEFAULT_MAX_CIRCUITS,
+            reservation_ttl: Duration::from_secs(DEFAULT_RESERVATION_TTL_SECS),
+            limit: Limit {
+                duration: Duration::from_secs(DEFAULT_LIMIT_DURATION_SECS),
+                data: DEFAULT_LIMIT_DATA_BYTES,
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Role {
+    Hop,
+    Stop,
+}
+
+// A hop or stop message. The fields that don't apply are left empty.
+struct Message {
+    msg_type: u64,
+    peer: Option<(PeerId, Vec<Multiaddr>)>,
+    reservation: Option<Reservation>, // hop messages only
+    limit: Option<Limit>,
+    status: Option<u64>,
+}
+
+impl Message {
+    fn new(msg_type: u64) -> Self {
+        Message {
+            msg_type: msg_type,
+            peer: None,
+            reservation: None,
+            limit: None,
+            status: None,
+        }
+    }
+
+    fn status(msg_type: u64, status: u64) -> Self {
+        let mut msg = Message::new(msg_type);
+        msg.status = Some(status);
+        msg
+    }
+
+    fn encode(&self, role: Role) -> Vec<u8> {
+        let (limit_field, status_field) = match role {
+            Role::Hop => (4, 5),
+            Role::Stop => (3, 4),
+        };
+        let mut msg = PbWriter::new();
+        msg.uint64(1, self.msg_type);
+        if let Some((ref id, ref addrs)) = self.peer {
+            let mut peer = PbWriter::new();
+            peer.bytes(1, &id.clone().into_bytes());
+            for addr in addrs {
+                peer.bytes(2, &addr.to_bytes());
+            }
+            msg.message(2, &peer);
+        }
+        if let (Role::Hop, Some(reservation)) = (role, self.reservation.as_ref()) {
+            let mut r = PbWriter::new();
+            r.uint64(1, reservation.expire);
+            for addr in &reservation.addrs {
+                r.bytes(2, &addr.to_bytes());
+            }
+            msg.message(3, &r);
+        }
+        if let Some(limit) = self.limit {
+            let mut l = PbWriter::new();
+            l.uint64(1, limit.duration.as_secs()).uint64(2, limit.data);
+            msg.message(limit_field, &l);
+        }
+        if let Some(status) = self.status {
+            msg.uint64(status_field, status);
+        }
+        msg.into_bytes()
+    }
+
+    fn decode(buf: &[u8], role: Role) -> Result<Self, Error> {
+        let (limit_field, status_field) = match role {
+            Role::Hop => (4, 5),
+            Role::Stop => (3, 4),
+        };
+        let mut msg = Message::new(0);
+        let mut reader = PbReader::new(buf);
+        while let Some((field, value)) = try!(reader.next_field()) {
+            match (role, field) {
+                (_, 1) => msg.msg_type = try!(value.as_u64()),
+                (_, 2) => msg.peer = Some(try!(decode_peer(try!(value.as_bytes())))),
+                (Role::Hop, 3) => {
+                    msg.reservation = Some(try!(decode_reservation(try!(value.as_bytes()))))
+                }
+                (_, f) if f == limit_field => {
+                    msg.limit = Some(try!(decode_limit(try!(value.as_bytes()))))
+                }
+                (_, f) if f == status_field => msg.status = Some(try!(value.as_u64())),
+                _ => {}
+            }
+        }
+        Ok(msg)
+    }
+}
+
+fn decode_peer(buf: &[u8]) -> Result<(PeerId, Vec<Multiaddr>), Error> {
+    let mut id = None;
+    let mut addrs = Vec::new();
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => id = Some(Multihash::from_vec(try!(value.as_bytes()).to_vec())),
+            2 => {
+                // skip addresses using protocols we don't support
+                if let Ok(addr) = Multiaddr::from_bytes(try!(value.as_bytes())) {
+                    addrs.push(addr);
+                }
+            }
+            _ => {}
+        }
+    }
+    match id {
+        None => Err(Error::Decode("Relay message peer has no ID".to_string(), None)),
+        Some(id) => Ok((id, addrs)),
+    }
+}
+
+fn decode_reservation(buf: &[u8]) -> Result<Reservation, Error> {
+    let mut reservation = Reservation {
+        expire: 0,
+        addrs: Vec::new(),
+    };
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => reservation.expire = try!(value.as_u64()),
+            2 => {
+                if let Ok(addr) = Multiaddr::from_bytes(try!(value.as_bytes())) {
+                    reservation.addrs.push(addr);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(reservation)
+}
+
+fn decode_limit(buf: &[u8]) -> Result<Limit, Error> {
+    let mut limit = Limit {
+        duration: Duration::new(0, 0),
+        data: 0,
+    };
+    let mut reader = PbReader::new(buf);
+    while let Some((field, value)) = try!(reader.next_field()) {
+        match field {
+            1 => limit.duration = Duration::from_secs(try!(value.as_u64())),
+            2 => limit.data = try!(value.as_u64()),
+            _ => {}
+        }
+    }
+    Ok(limit)
+}
+
+fn write_message(stream: &mut Stream, role: Role, msg: &Message) -> Result<(), Error> {
+    codec::write_length_prefixed(stream, &msg.encode(role))
+        .map_err(|e| Error::Io("Error sending relay message".to_string(), e))
+}
+
+fn read_message(stream: &mut Stream, role: Role) -> Result<Message, Error> {
+    let buf = try!(codec::read_length_prefixed(stream).map_err(|e| {
+        Error::Io("Error reading relay message".to_string(), e)
+    }));
+    Message::decode(&buf, role)
+}
+
+fn status_name(status: u64) -> &'static str {
+    match status {
+        STATUS_OK => "OK",
+        STATUS_RESERVATION_REFUSED => "reservation refused",
+        STATUS_RESOURCE_LIMIT_EXCEEDED => "resource limit exceeded",
+        STATUS_CONNECTION_FAILED => "connection failed",
+        STATUS_NO_RESERVATION => "no reservation",
+        STATUS_MALFORMED_MESSAGE => "malformed message",
+        STATUS_UNEXPECTED_MESSAGE => "unexpected message",
+        _ => "unknown status",
+    }
+}
+
+// Fails unless `msg` is a status message with an OK status
+fn check_status(msg: &Message, status_type: u64, peer: &PeerId) -> Result<(), Error> {
+    if msg.msg_type != status_type {
+        return Err(Error::Network(format!("Unexpected relay message from {}", peer)));
+    }
+    match msg.status {
+        Some(STATUS_OK) => Ok(()),
+        Some(status) => {
+            Err(Error::Network(format!("Relay error from {}: {}", peer, status_name(status))))
+        }
+        None => Err(Error::Network(format!("Relay message from {} has no status", peer))),
+    }
+}
+
+// Relays circuits between other peers
+pub struct RelayService {
+    swarm: Weak<Swarm>,
+    resources: Resources,
+    reservations: Mutex<HashMap<PeerId, Instant>>, // when each one expires
+    circuits: Mutex<HashMap<PeerId, usize>>, // the number each peer takes part in
+}
+
+impl RelayService {
+    // Accepts reservations and relays circuits within `resources`
+    pub fn enable(swarm: &Arc<Swarm>, resources: Resources) -> Arc<Self> {
+        let service = Arc::new(RelayService {
+            swarm: Arc::downgrade(swarm),
+            resources: resources,
+            reservations: Mutex::new(HashMap::new()),
+            circuits: Mutex::new(HashMap::new()),
+        });
+        let handler_service = service.clone();
+        swarm.set_stream_handler(HOP_PROTOCOL_ID,
+                                 Arc::new(move |peer: &PeerId, stream: Stream| {
+                                     handler_service.handle_hop(peer, stream)
+                                 }));
+        service
+    }
+
+    // The peers holding a reservation
+    pub fn reservations(&self) -> Vec<PeerId> {
+        let now = Instant::now();
+        let mut reservations = self.reservations.lock().unwrap();
+        reservations.retain(|_, expire| *expire > now);
+        reservations.keys().cloned().collect()
+    }
+
+    fn handle_hop(&self, peer: &PeerId, mut stream: Stream) {
+        let result = match read_message(&mut stream, Role::Hop) {
+            Err(e) => {
+                let _ = write_message(&mut stream,
+                                      Role::Hop,
+                                      &Message::status(HOP_STATUS, STATUS_MALFORMED_MESSAGE));
+                Err(e)
+            }
+            Ok(ref msg) if msg.msg_type == HOP_RESERVE => self.reserve(peer, &mut stream),
+            Ok(ref msg) if msg.msg_type == HOP_CONNECT => self.connect(peer, msg, stream),
+            Ok(_) => {
+                write_message(&mut stream,
+                              Role::Hop,
+                              &Message::status(HOP_STATUS, STATUS_UNEXPECTED_MESSAGE))
+            }
+        };
+        if let Err(e) = result {
+            debug!("Error handling relay request from {}: {}", peer, e);
+        }
+    }
+
+    fn reserve(&self, peer: &PeerId, stream: &mut Stream) -> Result<(), Error> {
+        let swarm = match self.swarm.upgrade() {
+            None => return Ok(()),
+            Some(swarm) => swarm,
+        };
+        {
+            let now = Instant::now();
+            let mut reservations = self.reservations.lock().unwrap();
+            reservations.retain(|_, expire| *expire > now);
+            if !reservations.contains_key(peer) &&
+               reservations.len() >= self.resources.max_reservations {
+                return write_message(stream,
+                                     Role::Hop,
+                                     &Message::status(HOP_STATUS, STATUS_RESERVATION_REFUSED));
+            }
+            reservations.insert(peer.clone(), now + self.resources.reservation_ttl);
+        }
+
+        let mut addrs: Vec<Multiaddr> = swarm.listen_addrs()
+                                             .into_iter()
+                                             .filter(|addr| !addr.is_circuit())
+                                             .collect();
+        for addr in &mut addrs {
+            addr.push(Protocol::P2p(swarm.local_peer().clone()));
+        }
+        let mut reply = Message::status(HOP_STATUS, STATUS_OK);
+        reply.reservation = Some(Reservation {
+            expire: util::unix_time() + self.resources.reservation_ttl.as_secs(),
+            addrs: addrs,
+        });
+        reply.limit = Some(self.resources.limit);
+        debug!("Accepted relay reservation from {}", peer);
+        write_message(stream, Role::Hop, &reply)
+    }
+
+    fn connect(&self, src: &PeerId, msg: &Message, mut stream: Stream) -> Result<(), Error> {
+        let swarm = match self.swarm.upgrade() {
+            None => return Ok(()),
+            Some(swarm) => swarm,
+        };
+        let dst = match msg.peer {
+            None => {
+                return write_message(&mut stream,
+                                     Role::Hop,
+                                     &Message::status(HOP_STATUS, STATUS_MALFORMED_MESSAGE))
+            }
+            Some((ref id, _)) => id.clone(),
+        };
+
+        let reserved = match self.reservations.lock().unwrap().get(&dst) {
+            Some(expire) => *expire > Instant::now(),
+            None => false,
+        };
+        if !reserved || !swarm.is_connected(&dst) {
+            return write_message(&mut stream,
+                                 Role::Hop,
+                                 &Message::status(HOP_STATUS, STATUS_NO_RESERVATION));
+        }
+        if !self.add_circuit(src, &dst) {
+            return write_message(&mut stream,
+                                 Role::Hop,
+                                 &Message::status(HOP_STATUS, STATUS_RESOURCE_LIMIT_EXCEEDED));
+        }
+        let result = self.bridge(&swarm, src, &dst, stream);
+        self.remove_circuit(src, &dst);
+        result
+    }
+
+    // Counts a circuit between `src` and `dst`, unless either takes part in
+    // too many already
+    fn add_circuit(&self, src: &PeerId, dst: &PeerId) -> bool {
+        let mut circuits = self.circuits.lock().unwrap();
+        let max = self.resources.max_circuits;
+        if circuits.get(src).cloned().unwrap_or(0) >= max ||
+           circuits.get(dst).cloned().unwrap_or(0) >= max {
+            return false;
+        }
+        *circuits.entry(src.clone()).or_insert(0) += 1;
+        *circuits.entry(dst.clone()).or_insert(0) += 1;
+        true
+    }
+
+    fn remove_circuit(&self, src: &PeerId, dst: &PeerId) {
+        let mut circuits = self.circuits.lock().unwrap();
+        for peer in &[src, dst] {
+            let remaining = match circuits.get_mut(*peer) {
+                None => continue,
+                Some(n) => {
+                    *n -= 1;
+                    *n
+                }
+            };
+            if remaining == 0 {
+                circuits.remove(*peer);
+            }
+        }
+    }
+
+    // Opens a stop stream to `dst`, and relays data between it and the hop
+    // stream from `src` until the circuit ends
+    fn bridge(&self,
+              swarm: &Swarm,
+              src: &PeerId,
+              dst: &PeerId,
+              mut src_stream: Stream)
+              -> Result<(), Error> {
+        let limit = self.resources.limit;
+        let dst_stream = swarm.new_stream(dst, STOP_PROTOCOL_ID).and_then(|mut dst_stream| {
+            let mut connect = Message::new(STOP_CONNECT);
+            connect.peer = Some((src.clone(), vec![]));
+            connect.limit = Some(limit);
+            try!(write_message(&mut dst_stream, Role::Stop, &connect));
+            let reply = try!(read_message(&mut dst_stream, Role::Stop));
+            try!(check_status(&reply, STOP_STATUS, dst));
+            Ok(dst_stream)
+        });
+        let dst_stream = match dst_stream {
+            Err(e) => {
+                let _ = write_message(&mut src_stream,
+                                      Role::Hop,
+                                      &Message::status(HOP_STATUS, STATUS_CONNECTION_FAILED));
+                return Err(e);
+            }
+            Ok(dst_stream) => dst_stream,
+        };
+
+        let mut reply = Message::status(HOP_STATUS, STATUS_OK);
+        reply.limit = Some(limit);
+        try!(write_message(&mut src_stream, Role::Hop, &reply));
+        debug!("Relaying a circuit from {} to {}", src, dst);
+        splice(src_stream, dst_stream, limit);
+        Ok(())
+    }
+}
+
+// Passes data between two streams until both ends are closed, or the
+// circuit reaches its limit
+fn splice(a: Stream, b: Stream, limit: Limit) {
+    let (a_reader, a_writer) = a.split();
+    let (b_reader, b_writer) = b.split();
+    let a_writer = Arc::new(Mutex::new(a_writer));
+    let b_writer = Arc::new(Mutex::new(b_writer));
+
+    {
+        // once the circuit has lasted long enough, resetting both streams
+        // stops the copies
+        let a_writer = Arc::downgrade(&a_writer);
+        let b_writer = Arc::downgrade(&b_writer);
+        thread::spawn(move || {
+            thread::sleep(limit.duration);
+            for writer in &[a_writer, b_writer] {
+                if let Some(writer) = writer.upgrade() {
+                    let _ = writer.lock().unwrap().shutdown();
+                }
+            }
+        });
+    }
+
+    let forward = {
+        let a_writer = a_writer.clone();
+        let b_writer = b_writer.clone();
+        thread::spawn(move || copy_limited(a_reader, &b_writer, &a_writer, limit.data))
+    };
+    copy_limited(b_reader, &a_writer, &b_writer, limit.data);
+    let _ = forward.join();
+}
+
+// Copies data from `reader` to `writer` until the end of the stream, then
+// closes `writer`. Both streams are reset if more than `max_data` bytes are
+// sent, or on error.
+fn copy_limited(mut reader: StreamReader,
+                writer: &Mutex<StreamWriter>,
+                reader_writer: &Mutex<StreamWriter>,
+                max_data: u64) {
+    if let Err(e) = copy_until_limit(&mut reader, writer, max_data) {
+        debug!("Relayed circuit ended: {}", e);
+        let _ = writer.lock().unwrap().shutdown();
+        let _ = reader_writer.lock().unwrap().shutdown();
+    }
+}
+
+fn copy_until_limit(reader: &mut StreamReader,
+                    writer: &Mutex<StreamWriter>,
+                    max_data: u64)
+                    -> io::Result<()> {
+    let mut buf = [0; 4096];
+    let mut total = 0;
+    loop {
+        let n = try!(reader.read(&mut buf));
+        if n == 0 {
+            return writer.lock().unwrap().close_write();
+        }
+        total += n as u64;
+        if total > max_data {
+            return Err(io::Error::new(io::ErrorKind::Other, "data limit reached"));
+        }
+        try!(writer.lock().unwrap().write_all(&buf[..n]));
+    }
+}
+
+// Accepts circuits relays open to us, and sets up connections over them
+pub fn register(swarm: &Arc<Swarm>) {
+    let handler_swarm = Arc::downgrade(swarm);
+    swarm.set_stream_handler(STOP_PROTOCOL_ID,
+                             Arc::new(move |relay: &PeerId, stream: Stream| {
+                                 if let Some(swarm) = handler_swarm.upgrade() {
+                                     if let Err(e) = accept_circuit(&swarm, relay, stream) {
+                                         debug!("Error accepting circuit from relay {}: {}",
+                                                relay,
+                                                e);
+                                     }
+                                 }
+                             }));
+}
+
+fn accept_circuit(swarm: &Arc<Swarm>, relay: &PeerId, mut stream: Stream) -> Result<(), Error> {
+    let msg = try!(read_message(&mut stream, Role::Stop));
+    if msg.msg_type != STOP_CONNECT {
+        return write_message(&mut stream,
+                             Role::Stop,
+                             &Message::status(STOP_STATUS, STATUS_UNEXPECTED_MESSAGE));
+    }
+    let src = match msg.peer {
+        None => {
+            return write_message(&mut stream,
+                                 Role::Stop,
+                                 &Message::status(STOP_STATUS, STATUS_MALFORMED_MESSAGE))
+        }
+        Some((id, _)) => id,
+    };
+    try!(write_message(&mut stream, Role::Stop, &Message::status(STOP_STATUS, STATUS_OK)));
+
+    // the peer's address, as seen from here
+    let mut remote_addr = match swarm.connection(relay) {
+        None => return Err(Error::Network(format!("Not connected to relay {}", relay))),
+        Some(conn) => conn.remote_addr.clone(),
+    };
+    remote_addr.push(Protocol::P2p(relay.clone()));
+    remote_addr.push(Protocol::P2pCircuit);
+    let peer = try!(Swarm::add_connection(swarm, RawConn::Relayed(stream), remote_addr, None));
+    if peer != src {
+        debug!("Relay {} announced a circuit from {}, but {} connected", relay, src, peer);
+    }
+    Ok(())
+}
+
+// Asks the relay of the /p2p-circuit address `addr` to connect us to the
+// peer at the end of it, returning the relayed stream
+pub fn open_circuit(swarm: &Arc<Swarm>, addr: &Multiaddr) -> Result<Stream, Error> {
+    let invalid = || {
+        Error::InvalidArgument(format!("Invalid relay address {}: expected <relay \
+                                        address>/p2p/<relay id>/p2p-circuit/p2p/<peer id>",
+                                       addr))
+    };
+    let relay_addr = try!(addr.relay_addr().ok_or_else(&invalid));
+    let relay = try!(relay_addr.peer_id().cloned().ok_or_else(&invalid));
+    let dst = match addr.protocols().last() {
+        Some(&Protocol::P2p(ref dst)) => dst.clone(),
+        _ => return Err(invalid()),
+    };
+
+    try!(Swarm::dial(swarm, &relay_addr));
+    let mut stream = try!(swarm.new_stream(&relay, HOP_PROTOCOL_ID));
+    let mut connect = Message::new(HOP_CONNECT);
+    connect.peer = Some((dst, vec![]));
+    try!(write_message(&mut stream, Role::Hop, &connect));
+    let reply = try!(read_message(&mut stream, Role::Hop));
+    try!(check_status(&reply, HOP_STATUS, &relay));
+    Ok(stream)
+}
+
+// Reserves a slot on the relay at `relay_addr` (which ends with /p2p/<relay
+// id>), returning the relay's ID and the reservation
+pub fn reserve(swarm: &Arc<Swarm>,
+               relay_addr: &Multiaddr)
+               -> Result<(PeerId, Reservation), Error> {
+    let relay = try!(Swarm::dial(swarm, relay_addr));
+    let mut stream = try!(swarm.new_stream(&relay, HOP_PROTOCOL_ID));
+    try!(write_message(&mut stream, Role::Hop, &Message::new(HOP_RESERVE)));
+    let reply = try!(read_message(&mut stream, Role::Hop));
+    try!(check_status(&reply, HOP_STATUS, &relay));
+    match reply.reservation {
+        None => Err(Error::Network(format!("Relay {} accepted without a reservation", relay))),
+        Some(reservation) => Ok((relay, reservation)),
+    }
+}
+
+// The addresses we can be reached at through a relay we have a reservation
+// on
+fn circuit_addrs(swarm: &Swarm,
+                 relay_addr: &Multiaddr,
+                 relay: &PeerId,
+                 reservation: &Reservation)
+                 -> Vec<Multiaddr> {
+    let relay_addrs: Vec<Multiaddr> = reservation.addrs
+                                                 .iter()
+                                                 .map(|addr| addr.without_peer_id())
+                                                 .collect();
+    let mut addrs = vec![];
+    for addr in swarm.resolve_unspecified(relay, &relay_addrs)
+                     .into_iter()
+                     .chain(Some(relay_addr.without_peer_id())) {
+        let mut addr = addr;
+        addr.push(Protocol::P2p(relay.clone()));
+        addr.push(Protocol::P2pCircuit);
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    addrs
+}
+
+struct RelayState {
+    addrs: Vec<Multiaddr>, // the circuit addresses through the relay
+    next_attempt: Instant,
+}
+
+// Keeps reservations on a set of relays, and advertises the circuit
+// addresses through them as our listen addresses
+pub struct RelayClient {
+    relays: Vec<(Multiaddr, RelayState)>,
+}
+
+impl RelayClient {
+    // `relays` are the addresses of the relays, ending with /p2p/<relay id>
+    pub fn new(relays: Vec<Multiaddr>) -> Self {
+        RelayClient {
+            relays: relays.into_iter()
+                          .map(|addr| {
+                              (addr,
+                               RelayState {
+                                   addrs: vec![],
+                                   next_attempt: Instant::now(),
+                               })
+                          })
+                          .collect(),
+        }
+    }
+
+    // Renews the reservations in the background, for as long as the swarm
+    // exists
+    pub fn start(mut self, swarm: &Arc<Swarm>) {
+        let swarm: Weak<Swarm> = Arc::downgrade(swarm);
+        thread::spawn(move || {
+            loop {
+                let swarm = match swarm.upgrade() {
+                    None => return,
+                    Some(swarm) => swarm,
+                };
+                for &mut (ref addr, ref mut state) in &mut self.relays {
+                    if state.next_attempt <= Instant::now() {
+                        RelayClient::renew(&swarm, addr, state);
+                    }
+                }
+                drop(swarm);
+                thread::sleep(Duration::from_secs(CHECK_INTERVAL_SECS));
+            }
+        });
+    }
+
+    fn renew(swarm: &Arc<Swarm>, relay_addr: &Multiaddr, state: &mut RelayState) {
+        let mut listen_addrs = swarm.listen_addrs.lock().unwrap();
+        listen_addrs.retain(|addr| !state.addrs.contains(addr));
+        drop(listen_addrs);
+
+        match reserve(swarm, relay_addr) {
+            Err(e) => {
+                warn!("Error reserving a slot on relay {}: {}", relay_addr, e);
+                state.addrs.clear();
+                state.next_attempt = Instant::now() + Duration::from_secs(RETRY_INTERVAL_SECS);
+            }
+            Ok((relay, reservation)) => {
+                state.addrs = circuit_addrs(swarm, relay_addr, &relay, &reservation);
+                // renewing halfway through the reservation
+                let ttl = reservation.expire.saturating_sub(util::unix_time());
+                state.next_attempt = Instant::now() + Duration::from_secs(ttl / 2);
+                let mut listen_addrs = swarm.listen_addrs.lock().unwrap();
+                listen_addrs.extend(state.addrs.iter().cloned());
+                info!("Reachable through relay {}", relay_addr);
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// The raw connections the swarm secures and multiplexes: TCP connections,
+// and streams relayed through another peer (see relay).
+
+use super::mplex::{Stream, StreamReader, StreamWriter};
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+
+pub enum RawConn {
+    Tcp(TcpStream),
+    Relayed(Stream),
+}
+
+impl RawConn {
+    // Separates the connection into halves that can be used from different
+    // threads
+    pub fn split(self) -> io::Result<(RawReader, RawWriter)> {
+        match self {
+            RawConn::Tcp(stream) => {
+                let reader = try!(stream.try_clone());
+                Ok((RawReader::Tcp(reader), RawWriter::Tcp(stream)))
+            }
+            RawConn::Relayed(stream) => {
+                let (reader, writer) = stream.split();
+                Ok((RawReader::Relayed(reader), RawWriter::Relayed(writer)))
+            }
+        }
+    }
+}
+
+pub enum RawReader {
+    Tcp(TcpStream),
+    Relayed(StreamReader),
+}
+
+impl Read for RawReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            RawReader::Tcp(ref mut stream) => stream.read(buf),
+            RawReader::Relayed(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+pub enum RawWriter {
+    Tcp(TcpStream),
+    Relayed(StreamWriter),
+}
+
+impl RawWriter {
+    // Closes the connection in both directions
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        match *self {
+            RawWriter::Tcp(ref stream) => stream.shutdown(Shutdown::Both),
+            RawWriter::Relayed(ref mut stream) => stream.shutdown(),
+        }
+    }
+}
+
+impl Write for RawWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            RawWriter::Tcp(ref mut stream) => stream.write(buf),
+            RawWriter::Relayed(ref mut stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            RawWriter::Tcp(ref mut stream) => stream.flush(),
+            RawWriter::Relayed(ref mut stream) => stream.flush(),
+        }
+    }
+}
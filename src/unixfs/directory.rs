@@ -0,0 +1,111 @@
+// Unixfs directories, basic or sharded (see hamt). A basic directory is a
+// single node linking to its entries by name. Once the estimated size of
+// its links goes over the sharding threshold, it's stored as a HAMT
+// instead, as go-ipfs does. Sharded directories aren't turned back into
+// basic ones when entries are removed.
+
+use error::Error;
+use merkledag::{DagService, Link, Node};
+use super::{hamt, pb};
+
+use protobuf::Message;
+use std::sync::Arc;
+
+pub const DEFAULT_SHARDING_THRESHOLD: u64 = 256 * 1024; // same as go-ipfs
+
+pub fn is_directory(ty: pb::Data_DataType) -> bool {
+    ty == pb::Data_DataType::Directory || ty == pb::Data_DataType::HAMTShard
+}
+
+// An empty basic directory. It isn't added to the DAG.
+pub fn empty() -> Result<Node, Error> {
+    Ok(Node::from_data(try!(directory_data())))
+}
+
+// Builds a directory holding `entries`, sharded if they go over
+// `shard_threshold`, and adds it to the DAG
+pub fn build(ds: &DagService,
+             mut entries: Vec<Link>,
+             shard_threshold: u64)
+             -> Result<Arc<Node>, Error> {
+    if estimated_size(&entries) > shard_threshold {
+        return hamt::build(ds, entries, hamt::DEFAULT_FANOUT);
+    }
+    entries.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+    let node = Node::new(try!(directory_data()), entries);
+    try!(ds.add(&node));
+    Ok(Arc::new(node))
+}
+
+// Finds the entry named `name` in the directory `dir`. Nodes that aren't
+// sharded directories are searched for a link of that name, so this works
+// for any DAG node.
+pub fn find_link(ds: &DagService, dir: &Node, name: &str) -> Result<Option<Link>, Error> {
+    if is_sharded(dir) {
+        return hamt::find(ds, dir, name);
+    }
+    Ok(dir.get_links().iter().find(|link| link.get_name() == name).cloned())
+}
+
+// The entries of the directory `dir`, fetching all of its shards if it's
+// sharded
+pub fn entries(ds: &DagService, dir: &Node) -> Result<Vec<Link>, Error> {
+    if is_sharded(dir) {
+        return hamt::entries(ds, dir);
+    }
+    Ok(dir.get_links().to_vec())
+}
+
+// Replaces the entry named `name` in the directory `dir` with `entry`, or
+// removes it if `entry` is None, and adds the new directory to the DAG. The
+// new directory keeps the mode and modification time of `dir`.
+pub fn set_link(ds: &DagService,
+                dir: &Node,
+                name: &str,
+                entry: Option<Link>,
+                shard_threshold: u64)
+                -> Result<Arc<Node>, Error> {
+    let new_dir = if is_sharded(dir) {
+        try!(hamt::set(ds, dir, name, entry))
+    } else {
+        let mut entries: Vec<Link> = dir.get_links()
+                                        .iter()
+                                        .filter(|link| link.get_name() != name)
+                                        .cloned()
+                                        .collect();
+        if let Some(entry) = entry {
+            entries.push(entry);
+        }
+        try!(build(ds, entries, shard_threshold))
+    };
+
+    let attrs = super::attributes(&try!(super::node_data(dir)));
+    if attrs.is_empty() {
+        return Ok(new_dir);
+    }
+    let new_dir = try!(super::with_attributes(&new_dir, &attrs));
+    try!(ds.add(&new_dir));
+    Ok(Arc::new(new_dir))
+}
+
+fn is_sharded(node: &Node) -> bool {
+    match super::node_data(node) {
+        Ok(data) => data.get_Type() == pb::Data_DataType::HAMTShard,
+        Err(_) => false,
+    }
+}
+
+// Only the type is set, as in go-ipfs, so that directories hash the same
+fn directory_data() -> Result<Vec<u8>, Error> {
+    let mut data = pb::Data::new();
+    data.set_Type(pb::Data_DataType::Directory);
+    data.write_to_bytes().map_err(|e| Error::encode("Error encoding directory".to_string(), e))
+}
+
+// The size of the links of a directory holding `entries`, as estimated by
+// go-ipfs: the length of each name and CID
+fn estimated_size(entries: &[Link]) -> u64 {
+    entries.iter()
+           .map(|link| (link.get_name().len() + link.cid().to_bytes().len()) as u64)
+           .sum()
+}
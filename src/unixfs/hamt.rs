@@ -0,0 +1,374 @@
+// Sharded directories, as in go-ipfs: a directory too large for one node is
+// stored as a hash array mapped trie (HAMT) of HAMTShard nodes. The murmur3
+// hash of an entry's name picks one of `fanout` slots in the root shard, and
+// further bits of it pick a slot in each shard below, so an entry can be
+// found by fetching only the shards on its path.
+//
+// A shard's data holds a bitfield of the slots in use, and its links are the
+// used slots in order. A link to an entry is named with the slot's index as
+// uppercase hex (padded to the width of fanout - 1) followed by the entry's
+// name; a link to a child shard is named with the index alone.
+
+use error::Error;
+use merkledag::{DagService, Link, Node};
+use super::pb;
+
+use protobuf::{self, Message};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub const HASH_MURMUR3: u64 = 0x22; // the multicodec of murmur3-x64-64
+pub const DEFAULT_FANOUT: usize = 256;
+
+const HASH_BITS: u32 = 64;
+
+struct Shard {
+    fanout: usize,
+    children: BTreeMap<usize, Child>,
+}
+
+enum Child {
+    Entry(Link), // named with the entry's name, without the index
+    Stored(Link), // a child shard that hasn't been loaded
+    Loaded(Shard),
+}
+
+// Builds a sharded directory holding `entries`, adding its shards to the
+// DAG. Returns the root shard.
+pub fn build(ds: &DagService, entries: Vec<Link>, fanout: usize) -> Result<Arc<Node>, Error> {
+    let mut root = try!(Shard::new(fanout));
+    for entry in entries {
+        let name = entry.clone_name();
+        try!(root.set(ds, &name, hash(&name), 0, Some(entry)));
+    }
+    root.store(ds)
+}
+
+// Finds the entry named `name` under the shard `node`, fetching only the
+// shards on its path
+pub fn find(ds: &DagService, node: &Node, name: &str) -> Result<Option<Link>, Error> {
+    let hash = hash(name);
+    let mut shard = try!(Shard::decode(node));
+    let mut consumed = 0;
+    loop {
+        let bits = shard.bits();
+        let next = match shard.children.remove(&try!(index(hash, consumed, bits))) {
+            Some(Child::Entry(link)) => {
+                return Ok(if link.get_name() == name { Some(link) } else { None })
+            }
+            Some(Child::Stored(link)) => try!(link.get_node(ds)),
+            Some(Child::Loaded(_)) | None => return Ok(None),
+        };
+        shard = try!(Shard::decode(&next));
+        consumed += bits;
+    }
+}
+
+// The entries under the shard `node`, in the order of their slots
+pub fn entries(ds: &DagService, node: &Node) -> Result<Vec<Link>, Error> {
+    let mut entries = Vec::new();
+    try!(collect_entries(ds, node, &mut entries));
+    Ok(entries)
+}
+
+fn collect_entries(ds: &DagService, node: &Node, entries: &mut Vec<Link>) -> Result<(), Error> {
+    for (_, child) in try!(Shard::decode(node)).children {
+        match child {
+            Child::Entry(link) => entries.push(link),
+            Child::Stored(link) => try!(collect_entries(ds, &*try!(link.get_node(ds)), entries)),
+            Child::Loaded(_) => {}
+        }
+    }
+    Ok(())
+}
+
+// Replaces the entry named `name` under the shard `node` with `entry`, or
+// removes it if `entry` is None. The new shards on its path are added to the
+// DAG, and the new root shard is returned.
+pub fn set(ds: &DagService,
+           node: &Node,
+           name: &str,
+           entry: Option<Link>)
+           -> Result<Arc<Node>, Error> {
+    let mut root = try!(Shard::decode(node));
+    try!(root.set(ds, name, hash(name), 0, entry));
+    root.store(ds)
+}
+
+impl Shard {
+    fn new(fanout: usize) -> Result<Self, Error> {
+        if fanout < 8 || !fanout.is_power_of_two() {
+            return Err(Error::InvalidArgument(format!("Invalid HAMT fanout {} (must be a power \
+                                                       of two of at least 8)",
+                                                      fanout)));
+        }
+        Ok(Shard {
+            fanout: fanout,
+            children: BTreeMap::new(),
+        })
+    }
+
+    fn decode(node: &Node) -> Result<Self, Error> {
+        let data = try!(protobuf::parse_from_bytes::<pb::Data>(node.get_data()).map_err(|e| {
+            Error::decode(format!("Error parsing unixfs data of {}", node.multihash()), e)
+        }));
+        if data.get_Type() != pb::Data_DataType::HAMTShard {
+            return Err(Error::InvalidArgument(format!("{} is not a HAMT shard",
+                                                      node.multihash())));
+        }
+        if data.get_hashType() != HASH_MURMUR3 {
+            return Err(Error::Corrupt(format!("HAMT shard {} uses unsupported hash function \
+                                               {:#x}",
+                                              node.multihash(),
+                                              data.get_hashType())));
+        }
+        let mut shard = try!(Shard::new(data.get_fanout() as usize));
+
+        // the bitfield is a big-endian integer, so bit i is in the i / 8th
+        // byte from the end
+        let bitfield = data.get_Data();
+        let mut used = (0..shard.fanout).filter(|i| {
+            i / 8 < bitfield.len() && bitfield[bitfield.len() - 1 - i / 8] & (1 << (i % 8)) != 0
+        });
+        let prefix_len = shard.prefix_len();
+        for link in node.get_links() {
+            let i = match used.next() {
+                Some(i) => i,
+                None => {
+                    return Err(Error::Corrupt(format!("HAMT shard {} has more links than \
+                                                       slots in use",
+                                                      node.multihash())))
+                }
+            };
+            let name = link.get_name();
+            if !name.starts_with(&shard.prefix(i)) {
+                return Err(Error::Corrupt(format!("HAMT shard {} has link {} in slot {:X}",
+                                                  node.multihash(),
+                                                  name,
+                                                  i)));
+            }
+            let child = if name.len() == prefix_len {
+                Child::Stored(link.clone())
+            } else {
+                Child::Entry(Link::from_cid(name[prefix_len..].to_string(),
+                                            link.cid(),
+                                            link.get_target_size()))
+            };
+            shard.children.insert(i, child);
+        }
+        Ok(shard)
+    }
+
+    // Adds the shard and those loaded below it to the DAG
+    fn store(self, ds: &DagService) -> Result<Arc<Node>, Error> {
+        let mut bitfield = vec![0; self.fanout / 8];
+        let mut links = Vec::with_capacity(self.children.len());
+        for (i, child) in self.children {
+            let len = bitfield.len();
+            bitfield[len - 1 - i / 8] |= 1 << (i % 8);
+            let prefix = Shard::prefix_for(self.fanout, i);
+            links.push(match child {
+                Child::Entry(link) => {
+                    Link::from_cid(prefix + link.get_name(),
+                                   link.cid(),
+                                   link.get_target_size())
+                }
+                Child::Stored(link) => link,
+                Child::Loaded(shard) => try!(Link::from_node(prefix, try!(shard.store(ds)))),
+            });
+        }
+
+        // go-ipfs writes the bitfield without its leading zero bytes
+        let unused = bitfield.iter().take_while(|&&b| b == 0).count();
+        bitfield.drain(..unused);
+
+        let mut data = pb::Data::new();
+        data.set_Type(pb::Data_DataType::HAMTShard);
+        data.set_Data(bitfield);
+        data.set_hashType(HASH_MURMUR3);
+        data.set_fanout(self.fanout as u64);
+        let data = try!(data.write_to_bytes().map_err(|e| {
+            Error::encode("Error encoding HAMT shard".to_string(), e)
+        }));
+
+        let node = Node::new(data, links);
+        try!(ds.add(&node));
+        Ok(Arc::new(node))
+    }
+
+    // Sets the entry `name`, whose hash is `hash`, in this shard at the
+    // depth where `consumed` bits of the hash have been used
+    fn set(&mut self,
+           ds: &DagService,
+           name: &str,
+           hash: u64,
+           consumed: u32,
+           entry: Option<Link>)
+           -> Result<(), Error> {
+        let bits = self.bits();
+        let i = try!(index(hash, consumed, bits));
+        let child = match self.children.remove(&i) {
+            None => {
+                if let Some(entry) = entry {
+                    self.children.insert(i, Child::Entry(entry));
+                }
+                return Ok(());
+            }
+            Some(Child::Entry(existing)) => {
+                if existing.get_name() == name {
+                    if let Some(entry) = entry {
+                        self.children.insert(i, Child::Entry(entry));
+                    }
+                    return Ok(());
+                }
+                let entry = match entry {
+                    None => {
+                        self.children.insert(i, Child::Entry(existing));
+                        return Ok(());
+                    }
+                    Some(entry) => entry,
+                };
+
+                // two entries in one slot: they go in a new shard below, by
+                // the next bits of their hashes
+                let mut shard = try!(Shard::new(self.fanout));
+                let existing_name = existing.clone_name();
+                try!(shard.set(ds,
+                               &existing_name,
+                               self::hash(&existing_name),
+                               consumed + bits,
+                               Some(existing)));
+                try!(shard.set(ds, name, hash, consumed + bits, Some(entry)));
+                self.children.insert(i, Child::Loaded(shard));
+                return Ok(());
+            }
+            Some(Child::Stored(link)) => try!(Shard::decode(&*try!(link.get_node(ds)))),
+            Some(Child::Loaded(shard)) => shard,
+        };
+
+        let mut child = child;
+        let removing = entry.is_none();
+        try!(child.set(ds, name, hash, consumed + bits, entry));
+
+        // after a removal, a shard left with a single entry is replaced by
+        // the entry, and an empty one is dropped
+        let collapse = match child.children.values().next() {
+            None => true,
+            Some(&Child::Entry(_)) => child.children.len() == 1,
+            Some(_) => false,
+        };
+        if !(removing && collapse) {
+            self.children.insert(i, Child::Loaded(child));
+        } else if let Some((_, entry)) = child.children.into_iter().next() {
+            self.children.insert(i, entry);
+        }
+        Ok(())
+    }
+
+    // The number of hash bits used to pick a slot
+    fn bits(&self) -> u32 {
+        self.fanout.trailing_zeros()
+    }
+
+    fn prefix_len(&self) -> usize {
+        self.prefix(0).len()
+    }
+
+    fn prefix(&self, i: usize) -> String {
+        Shard::prefix_for(self.fanout, i)
+    }
+
+    // The index `i` in hex, padded to the width of the last index
+    fn prefix_for(fanout: usize, i: usize) -> String {
+        let width = format!("{:X}", fanout - 1).len();
+        format!("{:0width$X}", i, width = width)
+    }
+}
+
+// The slot picked by the `bits` bits of `hash` after the first `consumed`
+fn index(hash: u64, consumed: u32, bits: u32) -> Result<usize, Error> {
+    if consumed + bits > HASH_BITS {
+        return Err(Error::InvalidArgument("HAMT too deep: too many entries whose names have \
+                                           the same hash"
+                                              .to_string()));
+    }
+    Ok(((hash << consumed) >> (HASH_BITS - bits)) as usize)
+}
+
+// The first 64 bits of the 128-bit x64 murmur3 hash of `name`, with seed 0,
+// as used by go-ipfs
+fn hash(name: &str) -> u64 {
+    const C1: u64 = 0x87c37b91114253d5;
+    const C2: u64 = 0x4cf5ad432745937f;
+
+    let data = name.as_bytes();
+    let mut h1: u64 = 0;
+    let mut h2: u64 = 0;
+    let blocks = data.len() / 16;
+    for i in 0..blocks {
+        let k1 = read_u64_le(&data[i * 16..i * 16 + 8]);
+        let k2 = read_u64_le(&data[i * 16 + 8..i * 16 + 16]);
+
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 = h1.rotate_left(27).wrapping_add(h2).wrapping_mul(5).wrapping_add(0x52dce729);
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 = h2.rotate_left(31).wrapping_add(h1).wrapping_mul(5).wrapping_add(0x38495ab5);
+    }
+
+    let tail = &data[blocks * 16..];
+    if tail.len() > 8 {
+        let k2 = read_u64_le(&tail[8..]);
+        h2 ^= k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+    }
+    if !tail.is_empty() {
+        let k1 = read_u64_le(&tail[..if tail.len() > 8 { 8 } else { tail.len() }]);
+        h1 ^= k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+    }
+
+    h1 ^= data.len() as u64;
+    h2 ^= data.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    fmix(h1).wrapping_add(fmix(h2))
+}
+
+fn fmix(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51afd7ed558ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ceb9fe1a85ec53);
+    k ^ (k >> 33)
+}
+
+// Reads up to 8 bytes as a little-endian integer
+fn read_u64_le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |n, &b| (n << 8) | b as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash, index};
+
+    #[test]
+    fn hash_is_murmur3_x64_64() {
+        // the first half of the x64 128-bit murmur3 hashes, with seed 0
+        assert_eq!(hash(""), 0);
+        assert_eq!(hash("a"), 0x85555565f6597889);
+        assert_eq!(hash("hello"), 0xcbd8a7b341bd9b02);
+        assert_eq!(hash("file-1.txt"), 0xc853837405db7a88);
+        assert_eq!(hash("0123456789abcdef"), 0x4be06d94cf4ad1a7);
+        assert_eq!(hash("0123456789abcdef0"), 0xeb24ae8785a5c075);
+        assert_eq!(hash("a longer name with more than two blocks.txt"),
+                   0x8ebc172bdbc941ef);
+    }
+
+    #[test]
+    fn index_takes_bits_from_the_top() {
+        let hash = 0xcbd8a7b341bd9b02;
+        assert_eq!(index(hash, 0, 8).unwrap(), 0xcb);
+        assert_eq!(index(hash, 8, 8).unwrap(), 0xd8);
+        assert_eq!(index(hash, 0, 4).unwrap(), 0xc);
+        assert_eq!(index(hash, 56, 8).unwrap(), 0x02);
+        assert!(index(hash, 64, 8).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use error::Error;
+use merkledag::{DagService, Node};
+use super::{pb, FSNode};
+
+use std::cmp;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+/// Reads the contents of a unixfs file, fetching the blocks of the DAG
+/// lazily as the reader advances.
+pub struct DagReader {
+    dagservice: Arc<DagService>,
+    // nodes being read, along with the index of the next link to descend into
+    stack: Vec<(Arc<Node>, usize)>,
+    buf: Vec<u8>, // data of the node most recently pushed onto the stack
+    pos: usize,
+}
+
+impl DagReader {
+    // A Metadata node is read as the file it links to
+    pub fn new(node: Arc<Node>, dagservice: Arc<DagService>) -> Result<Self, Error> {
+        let node = try!(super::unwrap_metadata(&dagservice, node));
+        let mut reader = DagReader {
+            dagservice: dagservice,
+            stack: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        };
+        try!(reader.push(node));
+        Ok(reader)
+    }
+
+    fn push(&mut self, node: Arc<Node>) -> Result<(), Error> {
+        let fs_node = try!(FSNode::from_node(&node));
+        match fs_node.get_type() {
+            pb::Data_DataType::File | pb::Data_DataType::Raw => {}
+            pb::Data_DataType::Directory | pb::Data_DataType::HAMTShard => {
+                return Err(Error::InvalidArgument(format!("{} is a directory",
+                                                          node.multihash())))
+            }
+            ty => {
+                return Err(Error::InvalidArgument(format!("Cannot read unixfs node of type \
+                                                           {:?}",
+                                                          ty)))
+            }
+        }
+
+        self.buf = fs_node.take_data();
+        self.pos = 0;
+        self.stack.push((node, 0));
+        Ok(())
+    }
+
+    // Moves on to the next node with data. Returns false once the whole DAG
+    // has been read.
+    fn advance(&mut self) -> Result<bool, Error> {
+        loop {
+            let next = match self.stack.last_mut() {
+                None => return Ok(false),
+                Some(&mut (ref node, ref mut index)) => {
+                    let links = node.get_links();
+                    if *index < links.len() {
+                        *index += 1;
+                        Some(try!(links[*index - 1].get_node(&self.dagservice)))
+                    } else {
+                        None
+                    }
+                }
+            };
+
+            match next {
+                None => {
+                    self.stack.pop();
+                }
+                Some(child) => {
+                    try!(self.push(child));
+                    if !self.buf.is_empty() {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Read for DagReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.buf.len() {
+            match self.advance() {
+                Ok(true) => {}
+                Ok(false) => return Ok(0),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+            }
+        }
+
+        let n = cmp::min(out.len(), self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
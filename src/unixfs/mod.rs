@@ -1,6 +1,17 @@
+use cid::Codec;
+use error::Error;
+use ipns;
+use merkledag::{DagService, Node};
+
 use protobuf::{self, Message};
 use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+pub mod directory;
+pub mod hamt;
+pub mod io;
+pub mod modifier;
 pub mod pb;
 
 pub struct FSNode {
@@ -8,44 +19,288 @@ pub struct FSNode {
     blocksizes: Vec<u64>,
     subtotal: u64,
     ty: pb::Data_DataType,
+    attributes: Attributes,
 }
 
 impl FSNode {
+    pub fn new(ty: pb::Data_DataType) -> FSNode {
+        FSNode {
+            data: vec![],
+            blocksizes: vec![],
+            subtotal: 0,
+            ty: ty,
+            attributes: Attributes::default(),
+        }
+    }
+
     pub fn file_from_bytes(bytes: Vec<u8>) -> FSNode {
         FSNode {
             data: bytes,
             blocksizes: vec![],
             subtotal: 0,
             ty: pb::Data_DataType::File,
+            attributes: Attributes::default(),
         }
     }
 
-    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, String> {
-        let mut pb_node = try!(from_reader(reader));
+    // records the size of a child block of a file
+    pub fn add_block_size(&mut self, size: u64) {
+        self.subtotal += size;
+        self.blocksizes.push(size);
+    }
+
+    pub fn file_size(&self) -> u64 {
+        self.subtotal + self.data.len() as u64
+    }
+
+    pub fn get_type(&self) -> pb::Data_DataType {
+        self.ty
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data[..]
+    }
+
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.data = data;
+    }
+
+    // the sizes of the child blocks of a file, in order
+    pub fn block_sizes(&self) -> &[u64] {
+        &self.blocksizes[..]
+    }
+
+    pub fn take_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn attributes(&self) -> Attributes {
+        self.attributes
+    }
+
+    pub fn set_attributes(&mut self, attributes: Attributes) {
+        self.attributes = attributes;
+    }
+
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Ok(FSNode::from_pb(try!(from_reader(reader))))
+    }
+
+    pub fn from_node(node: &Node) -> Result<Self, Error> {
+        Ok(FSNode::from_pb(try!(node_data(node))))
+    }
+
+    fn from_pb(mut pb_node: pb::Data) -> Self {
         let data = pb_node.take_Data();
         let data_len = data.len() as u64;
-        Ok(FSNode {
+        FSNode {
             data: data,
             blocksizes: pb_node.take_blocksizes(),
-            subtotal: pb_node.get_filesize() - data_len,
+            // directories have data but no file size
+            subtotal: pb_node.get_filesize().saturating_sub(data_len),
             ty: pb_node.get_Type(),
-        })
+            attributes: attributes(&pb_node),
+        }
     }
 
     // GetBytes in go-ipfs
-    pub fn encode_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+    pub fn encode_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         let mut pb_node = pb::Data::new();
         pb_node.set_Data(self.data.clone());
         pb_node.set_Type(self.ty);
         pb_node.set_filesize(self.subtotal + self.data.len() as u64);
         pb_node.set_blocksizes(self.blocksizes.clone());
+        set_attributes(&mut pb_node, &self.attributes);
 
         pb_node.write_to_writer(writer)
-              .map_err(|e| format!("Error cloning node to writer: {}", e))
+              .map_err(|e| Error::encode("Error cloning node to writer".to_string(), e))
     }
 }
 
-pub fn from_reader<R: Read>(reader: &mut R) -> Result<pb::Data, String> {
+pub fn from_reader<R: Read>(reader: &mut R) -> Result<pb::Data, Error> {
     protobuf::parse_from_reader::<pb::Data>(reader)
-        .map_err(|e| format!("Error parsing encoded Unixfs data: {}", e))
+        .map_err(|e| Error::decode("Error parsing encoded Unixfs data".to_string(), e))
+}
+
+// The unixfs data of `node`. A raw block is taken as a Raw node holding its
+// data, as in go-ipfs.
+pub fn node_data(node: &Node) -> Result<pb::Data, Error> {
+    if node.codec() == Codec::Raw {
+        let mut data = pb::Data::new();
+        data.set_Type(pb::Data_DataType::Raw);
+        data.set_filesize(node.get_data().len() as u64);
+        data.set_Data(node.get_data().to_vec());
+        return Ok(data);
+    }
+    from_reader(&mut node.get_data())
+}
+
+// How a node type is shown in listings
+pub fn type_name(ty: pb::Data_DataType) -> &'static str {
+    match ty {
+        pb::Data_DataType::Raw | pb::Data_DataType::File => "file",
+        pb::Data_DataType::Directory | pb::Data_DataType::HAMTShard => "directory",
+        pb::Data_DataType::Metadata => "metadata",
+        pb::Data_DataType::Symlink => "symlink",
+    }
+}
+
+// The data of a symlink to `target`. Only the type and target are set, as in
+// go-ipfs.
+pub fn symlink_data(target: &str) -> Result<Vec<u8>, Error> {
+    let mut data = pb::Data::new();
+    data.set_Type(pb::Data_DataType::Symlink);
+    data.set_Data(target.as_bytes().to_vec());
+    data.write_to_bytes().map_err(|e| Error::encode("Error encoding symlink".to_string(), e))
+}
+
+// The target of `node` if it's a symlink
+pub fn symlink_target(node: &Node) -> Result<Option<String>, Error> {
+    let mut data = try!(node_data(node));
+    if data.get_Type() != pb::Data_DataType::Symlink {
+        return Ok(None);
+    }
+    match String::from_utf8(data.take_Data()) {
+        Ok(target) => Ok(Some(target)),
+        Err(_) => Err(Error::Corrupt(format!("Symlink {} has a target that isn't UTF-8",
+                                             node.multihash()))),
+    }
+}
+
+// The data of a Metadata node, which gives the MIME type of the file it
+// links to, as in go-ipfs
+pub fn metadata_data(mime_type: &str) -> Result<Vec<u8>, Error> {
+    let mut metadata = pb::Metadata::new();
+    metadata.set_MimeType(mime_type.to_string());
+    let encode_error = |e| Error::encode("Error encoding metadata".to_string(), e);
+    let metadata = try!(metadata.write_to_bytes().map_err(&encode_error));
+    let mut data = pb::Data::new();
+    data.set_Type(pb::Data_DataType::Metadata);
+    data.set_Data(metadata);
+    data.write_to_bytes().map_err(&encode_error)
+}
+
+// The MIME type given by `node` if it's a Metadata node
+pub fn mime_type(node: &Node) -> Result<Option<String>, Error> {
+    let data = try!(node_data(node));
+    if data.get_Type() != pb::Data_DataType::Metadata {
+        return Ok(None);
+    }
+    let mut metadata = try!(protobuf::parse_from_bytes::<pb::Metadata>(data.get_Data())
+                                .map_err(|e| {
+                                    Error::decode(format!("Error parsing metadata of {}",
+                                                          node.multihash()),
+                                                  e)
+                                }));
+    Ok(Some(metadata.take_MimeType()))
+}
+
+// The file `node` gives the MIME type of if it's a Metadata node, or else
+// `node` itself
+pub fn unwrap_metadata(ds: &DagService, node: Arc<Node>) -> Result<Arc<Node>, Error> {
+    if try!(node_data(&node)).get_Type() != pb::Data_DataType::Metadata {
+        return Ok(node);
+    }
+    match node.get_links().first() {
+        Some(link) => link.get_node(ds),
+        None => Err(Error::Corrupt(format!("Metadata node {} links to no file", node.multihash()))),
+    }
+}
+
+// The permissions and modification time of a file, directory or symlink,
+// kept in the unixfs 1.5 `mode` and `mtime` fields. Nodes added without
+// them have neither, so they hash as before.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attributes {
+    pub mode: Option<u32>, // permission bits only (0o7777), as in go-ipfs
+    pub mtime: Option<Mtime>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mtime {
+    pub seconds: i64, // since the Unix epoch
+    pub nanoseconds: u32,
+}
+
+impl Attributes {
+    pub fn is_empty(&self) -> bool {
+        self.mode.is_none() && self.mtime.is_none()
+    }
+
+    // How the mode is shown in listings: in octal, or - if there's none
+    pub fn mode_string(&self) -> String {
+        match self.mode {
+            Some(mode) => format!("{:04o}", mode),
+            None => "-".to_string(),
+        }
+    }
+
+    // How the modification time is shown in listings: in RFC 3339, or - if
+    // there's none or it can't be represented
+    pub fn mtime_string(&self) -> String {
+        match self.mtime.and_then(|mtime| mtime.to_system_time()) {
+            Some(time) => ipns::format_rfc3339(&time),
+            None => "-".to_string(),
+        }
+    }
+}
+
+impl Mtime {
+    // None if the time is out of the range of SystemTime, which the
+    // seconds, coming from a node, can be
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let time = if self.seconds >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(self.seconds as u64, 0))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::new(self.seconds.wrapping_neg() as u64, 0))
+        };
+        time.and_then(|time| time.checked_add(Duration::new(0, self.nanoseconds)))
+    }
+}
+
+pub fn attributes(data: &pb::Data) -> Attributes {
+    Attributes {
+        mode: if data.has_mode() {
+            Some(data.get_mode() & 0o7777)
+        } else {
+            None
+        },
+        mtime: if data.has_mtime() {
+            let mtime = data.get_mtime();
+            Some(Mtime {
+                seconds: mtime.get_Seconds(),
+                nanoseconds: mtime.get_FractionalNanoseconds(),
+            })
+        } else {
+            None
+        },
+    }
+}
+
+// `node` with `attrs` set in its data, replacing any it had. The new node
+// isn't added to the DAG.
+pub fn with_attributes(node: &Node, attrs: &Attributes) -> Result<Node, Error> {
+    let mut data = try!(node_data(node));
+    set_attributes(&mut data, attrs);
+    let data = try!(data.write_to_bytes()
+                        .map_err(|e| Error::encode("Error encoding unixfs data".to_string(), e)));
+    Ok(Node::new(data, node.get_links().to_vec()))
+}
+
+fn set_attributes(data: &mut pb::Data, attrs: &Attributes) {
+    match attrs.mode {
+        Some(mode) => data.set_mode(mode & 0o7777),
+        None => data.clear_mode(),
+    }
+    match attrs.mtime {
+        Some(mtime) => {
+            let mut unix_time = pb::UnixTime::new();
+            unix_time.set_Seconds(mtime.seconds);
+            if mtime.nanoseconds != 0 {
+                unix_time.set_FractionalNanoseconds(mtime.nanoseconds);
+            }
+            data.set_mtime(unix_time);
+        }
+        None => data.clear_mtime(),
+    }
 }
@@ -0,0 +1,229 @@
+// Writing to unixfs files. Nodes can't change, so writing creates new nodes
+// for the leaves written to and their ancestors, and the new root links to
+// the other leaves as they were. Data written past the end of the file is
+// added as new leaves after the last one (which is filled up first), and
+// the tree is balanced again above the leaves. Leaves stay raw blocks if
+// they were (see importer), and the root keeps its mode and modification
+// time. Used by MFS (see mfs).
+
+use cid::Codec;
+use error::Error;
+use importer::{self, AddOptions, Imported};
+use merkledag::{DagService, Link, Node};
+use super::{pb, Attributes, FSNode};
+
+use std::io::{self, Read};
+use std::sync::Arc;
+
+// The root of an empty file with `attributes`. It isn't added to the DAG.
+pub fn empty_file(attributes: Attributes) -> Result<Arc<Node>, Error> {
+    let mut fs_node = FSNode::file_from_bytes(vec![]);
+    fs_node.set_attributes(attributes);
+    let mut data = Vec::new();
+    try!(fs_node.encode_to_writer(&mut data));
+    Ok(Arc::new(Node::from_data(data)))
+}
+
+// Writes everything read from `reader` to the file under `root`, starting at
+// `offset`. Writing past the end of the file leaves a gap of zeros. Returns
+// the new root.
+pub fn write_at<R: Read>(ds: &DagService,
+                         root: Arc<Node>,
+                         offset: u64,
+                         reader: &mut R)
+                         -> Result<Imported, Error> {
+    let (size, attributes) = {
+        let fs_node = try!(file_node(&root));
+        (fs_node.file_size(), fs_node.attributes())
+    };
+    let mut root = root;
+    if offset < size {
+        let mut pos = offset;
+        let mut done = false;
+        if let Some(new_root) = try!(overwrite(ds, &root, 0, &mut pos, reader, &mut done)) {
+            root = new_root;
+        }
+        if done {
+            return imported(&root);
+        }
+    }
+
+    // whatever is left extends the file
+    let first = try!(importer::read_chunk(reader, 1).map_err(read_error));
+    if first.is_empty() && offset <= size {
+        // the root may be a new empty file
+        try!(ds.add(&root));
+        return imported(&root);
+    }
+    let gap = if offset > size { offset - size } else { 0 };
+    let mut rest = io::repeat(0).take(gap).chain(&first[..]).chain(reader);
+    let written = try!(append(ds, &root, &mut rest));
+
+    // the new root is built over the leaves, without what the old one had
+    let (cid, cumulative_size) = try!(importer::add_attributes(ds,
+                                                               written.cid,
+                                                               written.cumulative_size,
+                                                               &attributes,
+                                                               &AddOptions::default()));
+    Ok(Imported {
+        cid: cid,
+        cumulative_size: cumulative_size,
+        file_size: written.file_size,
+    })
+}
+
+// Writes data from `reader` over the part of the file under `node`, which
+// starts at `start` in the file, from `*pos` on. Returns the new node, or
+// None if nothing under it was written to. `*done` is set once the reader
+// is exhausted.
+fn overwrite<R: Read>(ds: &DagService,
+                      node: &Node,
+                      start: u64,
+                      pos: &mut u64,
+                      reader: &mut R,
+                      done: &mut bool)
+                      -> Result<Option<Arc<Node>>, Error> {
+    let mut fs_node = try!(file_node(node));
+    if node.get_links().is_empty() {
+        let mut data = fs_node.get_data().to_vec();
+        let from = (*pos - start) as usize;
+        if from >= data.len() {
+            return Ok(None);
+        }
+        let chunk = try!(importer::read_chunk(reader, data.len() - from).map_err(read_error));
+        if chunk.len() < data.len() - from {
+            *done = true;
+        }
+        if chunk.is_empty() {
+            return Ok(None);
+        }
+        data[from..from + chunk.len()].copy_from_slice(&chunk);
+        *pos += chunk.len() as u64;
+        if node.codec() == Codec::Raw {
+            let leaf = Node::raw(data);
+            try!(ds.add(&leaf));
+            return Ok(Some(Arc::new(leaf)));
+        }
+        fs_node.set_data(data);
+        return add_node(ds, fs_node, vec![]).map(Some);
+    }
+
+    let sizes = fs_node.block_sizes().to_vec();
+    if sizes.len() != node.get_links().len() {
+        return Err(Error::Corrupt(format!("File node {} has {} links but {} block sizes",
+                                          node.multihash(),
+                                          node.get_links().len(),
+                                          sizes.len())));
+    }
+    let mut links = node.get_links().to_vec();
+    let mut changed = false;
+    let mut child_start = start + fs_node.get_data().len() as u64;
+    for (i, size) in sizes.iter().enumerate() {
+        let child_end = child_start + size;
+        if !*done && *pos >= child_start && *pos < child_end {
+            let child = try!(links[i].get_node(ds));
+            if let Some(new_child) = try!(overwrite(ds, &child, child_start, pos, reader, done)) {
+                links[i] = try!(Link::from_node(links[i].clone_name(), new_child));
+                changed = true;
+            }
+        }
+        child_start = child_end;
+    }
+    if !changed {
+        return Ok(None);
+    }
+    add_node(ds, fs_node, links).map(Some)
+}
+
+// Adds everything read from `reader` to the end of the file under `root`
+fn append<R: Read>(ds: &DagService, root: &Node, reader: &mut R) -> Result<Imported, Error> {
+    let mut leaves = Vec::new();
+    let mut last_data = Vec::new();
+    let raw_leaves;
+    if root.get_links().is_empty() {
+        // a single leaf, which may not be in the DAG (see empty_file)
+        last_data = try!(file_node(root)).take_data();
+        raw_leaves = root.codec() == Codec::Raw;
+    } else {
+        try!(collect_leaves(ds, root, &mut leaves));
+        raw_leaves = leaves.iter().any(|leaf| leaf.cid.codec == Codec::Raw);
+        let last_is_full = match leaves.last() {
+            Some(last) => last.file_size >= importer::DEFAULT_CHUNK_SIZE as u64,
+            None => true,
+        };
+        if !last_is_full {
+            let last = leaves.pop().unwrap();
+            last_data = try!(file_node(&*try!(ds.get_cid(&last.cid)))).take_data();
+        }
+    }
+
+    let options = AddOptions { raw_leaves: raw_leaves, ..AddOptions::default() };
+    let mut data = (&last_data[..]).chain(reader);
+    let leaves = try!(importer::add_leaves(&mut data,
+                                           ds,
+                                           importer::DEFAULT_CHUNK_SIZE,
+                                           &options,
+                                           leaves));
+    importer::build_tree(leaves, ds, importer::DEFAULT_LINKS_PER_BLOCK, &options)
+}
+
+// Lists the leaves under `node`, without fetching them
+fn collect_leaves(ds: &DagService, node: &Node, leaves: &mut Vec<Imported>) -> Result<(), Error> {
+    let links = node.get_links();
+    if links.is_empty() {
+        leaves.push(try!(imported(node)));
+        return Ok(());
+    }
+
+    // the tree is balanced, so the children of a node are either all leaves
+    // or all internal nodes, and fetching the first one tells which
+    if try!(links[0].get_node(ds)).get_links().is_empty() {
+        let fs_node = try!(file_node(node));
+        if fs_node.block_sizes().len() != links.len() {
+            return Err(Error::Corrupt(format!("File node {} has {} links but {} block sizes",
+                                              node.multihash(),
+                                              links.len(),
+                                              fs_node.block_sizes().len())));
+        }
+        for (link, size) in links.iter().zip(fs_node.block_sizes()) {
+            leaves.push(Imported {
+                cid: link.cid(),
+                cumulative_size: link.get_target_size(),
+                file_size: *size,
+            });
+        }
+    } else {
+        for link in links {
+            try!(collect_leaves(ds, &*try!(link.get_node(ds)), leaves));
+        }
+    }
+    Ok(())
+}
+
+fn file_node(node: &Node) -> Result<FSNode, Error> {
+    let fs_node = try!(FSNode::from_node(node));
+    match fs_node.get_type() {
+        pb::Data_DataType::File | pb::Data_DataType::Raw => Ok(fs_node),
+        _ => Err(Error::InvalidArgument(format!("{} is not a file", node.multihash()))),
+    }
+}
+
+fn add_node(ds: &DagService, fs_node: FSNode, links: Vec<Link>) -> Result<Arc<Node>, Error> {
+    let mut data = Vec::new();
+    try!(fs_node.encode_to_writer(&mut data));
+    let node = Node::new(data, links);
+    try!(ds.add(&node));
+    Ok(Arc::new(node))
+}
+
+fn imported(node: &Node) -> Result<Imported, Error> {
+    Ok(Imported {
+        cid: node.cid(),
+        cumulative_size: try!(node.cumulative_size()),
+        file_size: try!(file_node(node)).file_size(),
+    })
+}
+
+fn read_error(e: io::Error) -> Error {
+    Error::Io("Error reading data to write".to_string(), e)
+}
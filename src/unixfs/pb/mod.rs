@@ -15,6 +15,10 @@ pub struct Data {
     Data: ::protobuf::SingularField<::std::vec::Vec<u8>>,
     filesize: ::std::option::Option<u64>,
     blocksizes: ::std::vec::Vec<u64>,
+    hashType: ::std::option::Option<u64>,
+    fanout: ::std::option::Option<u64>,
+    mode: ::std::option::Option<u32>,
+    mtime: ::protobuf::SingularPtrField<UnixTime>,
     // special fields
     unknown_fields: ::protobuf::UnknownFields,
     cached_size: ::std::cell::Cell<u32>,
@@ -37,6 +41,10 @@ impl Data {
                     Data: ::protobuf::SingularField::none(),
                     filesize: ::std::option::Option::None,
                     blocksizes: ::std::vec::Vec::new(),
+                    hashType: ::std::option::Option::None,
+                    fanout: ::std::option::Option::None,
+                    mode: ::std::option::Option::None,
+                    mtime: ::protobuf::SingularPtrField::none(),
                     unknown_fields: ::protobuf::UnknownFields::new(),
                     cached_size: ::std::cell::Cell::new(0),
                 }
@@ -142,6 +150,96 @@ impl Data {
     pub fn get_blocksizes<'a>(&'a self) -> &'a [u64] {
         &self.blocksizes
     }
+
+    // optional uint64 hashType = 5;
+
+    pub fn clear_hashType(&mut self) {
+        self.hashType = ::std::option::Option::None;
+    }
+
+    pub fn has_hashType(&self) -> bool {
+        self.hashType.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_hashType(&mut self, v: u64) {
+        self.hashType = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_hashType<'a>(&self) -> u64 {
+        self.hashType.unwrap_or(0)
+    }
+
+    // optional uint64 fanout = 6;
+
+    pub fn clear_fanout(&mut self) {
+        self.fanout = ::std::option::Option::None;
+    }
+
+    pub fn has_fanout(&self) -> bool {
+        self.fanout.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_fanout(&mut self, v: u64) {
+        self.fanout = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_fanout<'a>(&self) -> u64 {
+        self.fanout.unwrap_or(0)
+    }
+
+    // optional uint32 mode = 7;
+
+    pub fn clear_mode(&mut self) {
+        self.mode = ::std::option::Option::None;
+    }
+
+    pub fn has_mode(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_mode(&mut self, v: u32) {
+        self.mode = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_mode<'a>(&self) -> u32 {
+        self.mode.unwrap_or(0)
+    }
+
+    // optional .unixfs.pb.UnixTime mtime = 8;
+
+    pub fn clear_mtime(&mut self) {
+        self.mtime.clear();
+    }
+
+    pub fn has_mtime(&self) -> bool {
+        self.mtime.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_mtime(&mut self, v: UnixTime) {
+        self.mtime = ::protobuf::SingularPtrField::some(v);
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_mtime<'a>(&'a mut self) -> &'a mut UnixTime {
+        if self.mtime.is_none() {
+            self.mtime.set_default();
+        };
+        self.mtime.as_mut().unwrap()
+    }
+
+    // Take field
+    pub fn take_mtime(&mut self) -> UnixTime {
+        self.mtime.take().unwrap_or_else(|| UnixTime::new())
+    }
+
+    pub fn get_mtime<'a>(&'a self) -> &'a UnixTime {
+        self.mtime.as_ref().unwrap_or_else(|| UnixTime::default_instance())
+    }
 }
 
 impl ::protobuf::Message for Data {
@@ -180,6 +278,34 @@ impl ::protobuf::Message for Data {
                 4 => {
                     try!(::protobuf::rt::read_repeated_uint64_into(wire_type, is, &mut self.blocksizes));
                 },
+                5 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::ProtobufError::WireError("unexpected wire type".to_string()));
+                    };
+                    let tmp = try!(is.read_uint64());
+                    self.hashType = ::std::option::Option::Some(tmp);
+                },
+                6 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::ProtobufError::WireError("unexpected wire type".to_string()));
+                    };
+                    let tmp = try!(is.read_uint64());
+                    self.fanout = ::std::option::Option::Some(tmp);
+                },
+                7 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::ProtobufError::WireError("unexpected wire type".to_string()));
+                    };
+                    let tmp = try!(is.read_uint32());
+                    self.mode = ::std::option::Option::Some(tmp);
+                },
+                8 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::ProtobufError::WireError("unexpected wire type".to_string()));
+                    };
+                    let tmp = self.mtime.set_default();
+                    try!(is.merge_message(tmp))
+                },
                 _ => {
                     let unknown = try!(is.read_unknown(wire_type));
                     self.mut_unknown_fields().add_value(field_number, unknown);
@@ -205,6 +331,19 @@ impl ::protobuf::Message for Data {
         for value in self.blocksizes.iter() {
             my_size += ::protobuf::rt::value_size(4, *value, ::protobuf::wire_format::WireTypeVarint);
         };
+        for value in self.hashType.iter() {
+            my_size += ::protobuf::rt::value_size(5, *value, ::protobuf::wire_format::WireTypeVarint);
+        };
+        for value in self.fanout.iter() {
+            my_size += ::protobuf::rt::value_size(6, *value, ::protobuf::wire_format::WireTypeVarint);
+        };
+        for value in self.mode.iter() {
+            my_size += ::protobuf::rt::value_size(7, *value, ::protobuf::wire_format::WireTypeVarint);
+        };
+        for value in self.mtime.iter() {
+            let len = value.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -223,6 +362,20 @@ impl ::protobuf::Message for Data {
         for v in self.blocksizes.iter() {
             try!(os.write_uint64(4, *v));
         };
+        if let Some(v) = self.hashType {
+            try!(os.write_uint64(5, v));
+        };
+        if let Some(v) = self.fanout {
+            try!(os.write_uint64(6, v));
+        };
+        if let Some(v) = self.mode {
+            try!(os.write_uint32(7, v));
+        };
+        if let Some(v) = self.mtime.as_ref() {
+            try!(os.write_tag(8, ::protobuf::wire_format::WireTypeLengthDelimited));
+            try!(os.write_raw_varint32(v.get_cached_size()));
+            try!(v.write_to_with_cached_sizes(os));
+        };
         try!(os.write_unknown_fields(self.get_unknown_fields()));
         ::std::result::Result::Ok(())
     }
@@ -284,6 +437,26 @@ impl ::protobuf::MessageStatic for Data {
                     "blocksizes",
                     Data::get_blocksizes,
                 ));
+                fields.push(::protobuf::reflect::accessor::make_singular_u64_accessor(
+                    "hashType",
+                    Data::has_hashType,
+                    Data::get_hashType,
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_u64_accessor(
+                    "fanout",
+                    Data::has_fanout,
+                    Data::get_fanout,
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_u32_accessor(
+                    "mode",
+                    Data::has_mode,
+                    Data::get_mode,
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_message_accessor(
+                    "mtime",
+                    Data::has_mtime,
+                    Data::get_mtime,
+                ));
                 ::protobuf::reflect::MessageDescriptor::new::<Data>(
                     "Data",
                     fields,
@@ -300,6 +473,10 @@ impl ::protobuf::Clear for Data {
         self.clear_Data();
         self.clear_filesize();
         self.clear_blocksizes();
+        self.clear_hashType();
+        self.clear_fanout();
+        self.clear_mode();
+        self.clear_mtime();
         self.unknown_fields.clear();
     }
 }
@@ -310,6 +487,10 @@ impl ::std::cmp::PartialEq for Data {
         self.Data == other.Data &&
         self.filesize == other.filesize &&
         self.blocksizes == other.blocksizes &&
+        self.hashType == other.hashType &&
+        self.fanout == other.fanout &&
+        self.mode == other.mode &&
+        self.mtime == other.mtime &&
         self.unknown_fields == other.unknown_fields
     }
 }
@@ -327,6 +508,7 @@ pub enum Data_DataType {
     File = 2,
     Metadata = 3,
     Symlink = 4,
+    HAMTShard = 5,
 }
 
 impl ::protobuf::ProtobufEnum for Data_DataType {
@@ -341,6 +523,7 @@ impl ::protobuf::ProtobufEnum for Data_DataType {
             2 => ::std::option::Option::Some(Data_DataType::File),
             3 => ::std::option::Option::Some(Data_DataType::Metadata),
             4 => ::std::option::Option::Some(Data_DataType::Symlink),
+            5 => ::std::option::Option::Some(Data_DataType::HAMTShard),
             _ => ::std::option::Option::None
         }
     }
@@ -549,64 +732,316 @@ impl ::std::fmt::Debug for Metadata {
     }
 }
 
+#[derive(Clone,Default)]
+pub struct UnixTime {
+    // message fields
+    Seconds: ::std::option::Option<i64>,
+    FractionalNanoseconds: ::std::option::Option<u32>,
+    // special fields
+    unknown_fields: ::protobuf::UnknownFields,
+    cached_size: ::std::cell::Cell<u32>,
+}
+
+impl UnixTime {
+    pub fn new() -> UnixTime {
+        ::std::default::Default::default()
+    }
+
+    pub fn default_instance() -> &'static UnixTime {
+        static mut instance: ::protobuf::lazy::Lazy<UnixTime> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const UnixTime,
+        };
+        unsafe {
+            instance.get(|| {
+                UnixTime {
+                    Seconds: ::std::option::Option::None,
+                    FractionalNanoseconds: ::std::option::Option::None,
+                    unknown_fields: ::protobuf::UnknownFields::new(),
+                    cached_size: ::std::cell::Cell::new(0),
+                }
+            })
+        }
+    }
+
+    // required int64 Seconds = 1;
+
+    pub fn clear_Seconds(&mut self) {
+        self.Seconds = ::std::option::Option::None;
+    }
+
+    pub fn has_Seconds(&self) -> bool {
+        self.Seconds.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_Seconds(&mut self, v: i64) {
+        self.Seconds = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_Seconds<'a>(&self) -> i64 {
+        self.Seconds.unwrap_or(0)
+    }
+
+    // optional fixed32 FractionalNanoseconds = 2;
+
+    pub fn clear_FractionalNanoseconds(&mut self) {
+        self.FractionalNanoseconds = ::std::option::Option::None;
+    }
+
+    pub fn has_FractionalNanoseconds(&self) -> bool {
+        self.FractionalNanoseconds.is_some()
+    }
+
+    // Param is passed by value, moved
+    pub fn set_FractionalNanoseconds(&mut self, v: u32) {
+        self.FractionalNanoseconds = ::std::option::Option::Some(v);
+    }
+
+    pub fn get_FractionalNanoseconds<'a>(&self) -> u32 {
+        self.FractionalNanoseconds.unwrap_or(0)
+    }
+}
+
+impl ::protobuf::Message for UnixTime {
+    fn is_initialized(&self) -> bool {
+        if self.Seconds.is_none() {
+            return false;
+        };
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream) -> ::protobuf::ProtobufResult<()> {
+        while !try!(is.eof()) {
+            let (field_number, wire_type) = try!(is.read_tag_unpack());
+            match field_number {
+                1 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::ProtobufError::WireError("unexpected wire type".to_string()));
+                    };
+                    let tmp = try!(is.read_int64());
+                    self.Seconds = ::std::option::Option::Some(tmp);
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeFixed32 {
+                        return ::std::result::Result::Err(::protobuf::ProtobufError::WireError("unexpected wire type".to_string()));
+                    };
+                    let tmp = try!(is.read_fixed32());
+                    self.FractionalNanoseconds = ::std::option::Option::Some(tmp);
+                },
+                _ => {
+                    let unknown = try!(is.read_unknown(wire_type));
+                    self.mut_unknown_fields().add_value(field_number, unknown);
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        for value in self.Seconds.iter() {
+            my_size += ::protobuf::rt::value_size(1, *value, ::protobuf::wire_format::WireTypeVarint);
+        };
+        if self.FractionalNanoseconds.is_some() {
+            my_size += 5;
+        };
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream) -> ::protobuf::ProtobufResult<()> {
+        if let Some(v) = self.Seconds {
+            try!(os.write_int64(1, v));
+        };
+        if let Some(v) = self.FractionalNanoseconds {
+            try!(os.write_fixed32(2, v));
+        };
+        try!(os.write_unknown_fields(self.get_unknown_fields()));
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields<'s>(&'s self) -> &'s ::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields<'s>(&'s mut self) -> &'s mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn type_id(&self) -> ::std::any::TypeId {
+        ::std::any::TypeId::of::<UnixTime>()
+    }
+
+    fn as_any(&self) -> &::std::any::Any {
+        self as &::std::any::Any
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        ::protobuf::MessageStatic::descriptor_static(None::<Self>)
+    }
+}
+
+impl ::protobuf::MessageStatic for UnixTime {
+    fn new() -> UnixTime {
+        UnixTime::new()
+    }
+
+    fn descriptor_static(_: ::std::option::Option<UnixTime>) -> &'static ::protobuf::reflect::MessageDescriptor {
+        static mut descriptor: ::protobuf::lazy::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::lazy::Lazy {
+            lock: ::protobuf::lazy::ONCE_INIT,
+            ptr: 0 as *const ::protobuf::reflect::MessageDescriptor,
+        };
+        unsafe {
+            descriptor.get(|| {
+                let mut fields = ::std::vec::Vec::new();
+                fields.push(::protobuf::reflect::accessor::make_singular_i64_accessor(
+                    "Seconds",
+                    UnixTime::has_Seconds,
+                    UnixTime::get_Seconds,
+                ));
+                fields.push(::protobuf::reflect::accessor::make_singular_u32_accessor(
+                    "FractionalNanoseconds",
+                    UnixTime::has_FractionalNanoseconds,
+                    UnixTime::get_FractionalNanoseconds,
+                ));
+                ::protobuf::reflect::MessageDescriptor::new::<UnixTime>(
+                    "UnixTime",
+                    fields,
+                    file_descriptor_proto()
+                )
+            })
+        }
+    }
+}
+
+impl ::protobuf::Clear for UnixTime {
+    fn clear(&mut self) {
+        self.clear_Seconds();
+        self.clear_FractionalNanoseconds();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::cmp::PartialEq for UnixTime {
+    fn eq(&self, other: &UnixTime) -> bool {
+        self.Seconds == other.Seconds &&
+        self.FractionalNanoseconds == other.FractionalNanoseconds &&
+        self.unknown_fields == other.unknown_fields
+    }
+}
+
+impl ::std::fmt::Debug for UnixTime {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
 static file_descriptor_proto_data: &'static [u8] = &[
     0x0a, 0x0c, 0x75, 0x6e, 0x69, 0x78, 0x66, 0x73, 0x2e, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x12, 0x09,
-    0x75, 0x6e, 0x69, 0x78, 0x66, 0x73, 0x2e, 0x70, 0x62, 0x22, 0xab, 0x01, 0x0a, 0x04, 0x44, 0x61,
+    0x75, 0x6e, 0x69, 0x78, 0x66, 0x73, 0x2e, 0x70, 0x62, 0x22, 0x8e, 0x02, 0x0a, 0x04, 0x44, 0x61,
     0x74, 0x61, 0x12, 0x26, 0x0a, 0x04, 0x54, 0x79, 0x70, 0x65, 0x18, 0x01, 0x20, 0x02, 0x28, 0x0e,
     0x32, 0x18, 0x2e, 0x75, 0x6e, 0x69, 0x78, 0x66, 0x73, 0x2e, 0x70, 0x62, 0x2e, 0x44, 0x61, 0x74,
     0x61, 0x2e, 0x44, 0x61, 0x74, 0x61, 0x54, 0x79, 0x70, 0x65, 0x12, 0x0c, 0x0a, 0x04, 0x44, 0x61,
     0x74, 0x61, 0x18, 0x02, 0x20, 0x01, 0x28, 0x0c, 0x12, 0x10, 0x0a, 0x08, 0x66, 0x69, 0x6c, 0x65,
     0x73, 0x69, 0x7a, 0x65, 0x18, 0x03, 0x20, 0x01, 0x28, 0x04, 0x12, 0x12, 0x0a, 0x0a, 0x62, 0x6c,
-    0x6f, 0x63, 0x6b, 0x73, 0x69, 0x7a, 0x65, 0x73, 0x18, 0x04, 0x20, 0x03, 0x28, 0x04, 0x22, 0x47,
-    0x0a, 0x08, 0x44, 0x61, 0x74, 0x61, 0x54, 0x79, 0x70, 0x65, 0x12, 0x07, 0x0a, 0x03, 0x52, 0x61,
-    0x77, 0x10, 0x00, 0x12, 0x0d, 0x0a, 0x09, 0x44, 0x69, 0x72, 0x65, 0x63, 0x74, 0x6f, 0x72, 0x79,
-    0x10, 0x01, 0x12, 0x08, 0x0a, 0x04, 0x46, 0x69, 0x6c, 0x65, 0x10, 0x02, 0x12, 0x0c, 0x0a, 0x08,
-    0x4d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x10, 0x03, 0x12, 0x0b, 0x0a, 0x07, 0x53, 0x79,
-    0x6d, 0x6c, 0x69, 0x6e, 0x6b, 0x10, 0x04, 0x22, 0x1c, 0x0a, 0x08, 0x4d, 0x65, 0x74, 0x61, 0x64,
-    0x61, 0x74, 0x61, 0x12, 0x10, 0x0a, 0x08, 0x4d, 0x69, 0x6d, 0x65, 0x54, 0x79, 0x70, 0x65, 0x18,
-    0x01, 0x20, 0x02, 0x28, 0x09, 0x4a, 0xa2, 0x05, 0x0a, 0x06, 0x12, 0x04, 0x00, 0x00, 0x13, 0x01,
-    0x0a, 0x08, 0x0a, 0x01, 0x02, 0x12, 0x03, 0x00, 0x08, 0x11, 0x0a, 0x0a, 0x0a, 0x02, 0x04, 0x00,
-    0x12, 0x04, 0x02, 0x00, 0x0f, 0x01, 0x0a, 0x0a, 0x0a, 0x03, 0x04, 0x00, 0x01, 0x12, 0x03, 0x02,
-    0x08, 0x0c, 0x0a, 0x0c, 0x0a, 0x04, 0x04, 0x00, 0x04, 0x00, 0x12, 0x04, 0x03, 0x08, 0x09, 0x09,
-    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x04, 0x00, 0x01, 0x12, 0x03, 0x03, 0x0d, 0x15, 0x0a, 0x0d,
-    0x0a, 0x06, 0x04, 0x00, 0x04, 0x00, 0x02, 0x00, 0x12, 0x03, 0x04, 0x10, 0x18, 0x0a, 0x0e, 0x0a,
-    0x07, 0x04, 0x00, 0x04, 0x00, 0x02, 0x00, 0x01, 0x12, 0x03, 0x04, 0x10, 0x13, 0x0a, 0x0e, 0x0a,
-    0x07, 0x04, 0x00, 0x04, 0x00, 0x02, 0x00, 0x02, 0x12, 0x03, 0x04, 0x16, 0x17, 0x0a, 0x0d, 0x0a,
-    0x06, 0x04, 0x00, 0x04, 0x00, 0x02, 0x01, 0x12, 0x03, 0x05, 0x10, 0x1e, 0x0a, 0x0e, 0x0a, 0x07,
-    0x04, 0x00, 0x04, 0x00, 0x02, 0x01, 0x01, 0x12, 0x03, 0x05, 0x10, 0x19, 0x0a, 0x0e, 0x0a, 0x07,
-    0x04, 0x00, 0x04, 0x00, 0x02, 0x01, 0x02, 0x12, 0x03, 0x05, 0x1c, 0x1d, 0x0a, 0x0d, 0x0a, 0x06,
-    0x04, 0x00, 0x04, 0x00, 0x02, 0x02, 0x12, 0x03, 0x06, 0x10, 0x19, 0x0a, 0x0e, 0x0a, 0x07, 0x04,
-    0x00, 0x04, 0x00, 0x02, 0x02, 0x01, 0x12, 0x03, 0x06, 0x10, 0x14, 0x0a, 0x0e, 0x0a, 0x07, 0x04,
-    0x00, 0x04, 0x00, 0x02, 0x02, 0x02, 0x12, 0x03, 0x06, 0x17, 0x18, 0x0a, 0x0d, 0x0a, 0x06, 0x04,
-    0x00, 0x04, 0x00, 0x02, 0x03, 0x12, 0x03, 0x07, 0x10, 0x1d, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00,
-    0x04, 0x00, 0x02, 0x03, 0x01, 0x12, 0x03, 0x07, 0x10, 0x18, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00,
-    0x04, 0x00, 0x02, 0x03, 0x02, 0x12, 0x03, 0x07, 0x1b, 0x1c, 0x0a, 0x0d, 0x0a, 0x06, 0x04, 0x00,
-    0x04, 0x00, 0x02, 0x04, 0x12, 0x03, 0x08, 0x10, 0x1c, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04,
-    0x00, 0x02, 0x04, 0x01, 0x12, 0x03, 0x08, 0x10, 0x17, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04,
-    0x00, 0x02, 0x04, 0x02, 0x12, 0x03, 0x08, 0x1a, 0x1b, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02,
-    0x00, 0x12, 0x03, 0x0b, 0x08, 0x23, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x04, 0x12,
-    0x03, 0x0b, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x06, 0x12, 0x03, 0x0b,
-    0x11, 0x19, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x01, 0x12, 0x03, 0x0b, 0x1a, 0x1e,
-    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x03, 0x12, 0x03, 0x0b, 0x21, 0x22, 0x0a, 0x0b,
-    0x0a, 0x04, 0x04, 0x00, 0x02, 0x01, 0x12, 0x03, 0x0c, 0x08, 0x20, 0x0a, 0x0c, 0x0a, 0x05, 0x04,
-    0x00, 0x02, 0x01, 0x04, 0x12, 0x03, 0x0c, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02,
-    0x01, 0x05, 0x12, 0x03, 0x0c, 0x11, 0x16, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x01, 0x01,
-    0x12, 0x03, 0x0c, 0x17, 0x1b, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x01, 0x03, 0x12, 0x03,
-    0x0c, 0x1e, 0x1f, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x02, 0x12, 0x03, 0x0d, 0x08, 0x25,
-    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x02, 0x04, 0x12, 0x03, 0x0d, 0x08, 0x10, 0x0a, 0x0c,
-    0x0a, 0x05, 0x04, 0x00, 0x02, 0x02, 0x05, 0x12, 0x03, 0x0d, 0x11, 0x17, 0x0a, 0x0c, 0x0a, 0x05,
-    0x04, 0x00, 0x02, 0x02, 0x01, 0x12, 0x03, 0x0d, 0x18, 0x20, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00,
-    0x02, 0x02, 0x03, 0x12, 0x03, 0x0d, 0x23, 0x24, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x03,
-    0x12, 0x03, 0x0e, 0x08, 0x27, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x04, 0x12, 0x03,
-    0x0e, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x05, 0x12, 0x03, 0x0e, 0x11,
-    0x17, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x01, 0x12, 0x03, 0x0e, 0x18, 0x22, 0x0a,
-    0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x03, 0x12, 0x03, 0x0e, 0x25, 0x26, 0x0a, 0x0a, 0x0a,
-    0x02, 0x04, 0x01, 0x12, 0x04, 0x11, 0x00, 0x13, 0x01, 0x0a, 0x0a, 0x0a, 0x03, 0x04, 0x01, 0x01,
-    0x12, 0x03, 0x11, 0x08, 0x10, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x01, 0x02, 0x00, 0x12, 0x03, 0x12,
-    0x08, 0x25, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x04, 0x12, 0x03, 0x12, 0x08, 0x10,
-    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x05, 0x12, 0x03, 0x12, 0x11, 0x17, 0x0a, 0x0c,
-    0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x01, 0x12, 0x03, 0x12, 0x18, 0x20, 0x0a, 0x0c, 0x0a, 0x05,
-    0x04, 0x01, 0x02, 0x00, 0x03, 0x12, 0x03, 0x12, 0x23, 0x24,
+    0x6f, 0x63, 0x6b, 0x73, 0x69, 0x7a, 0x65, 0x73, 0x18, 0x04, 0x20, 0x03, 0x28, 0x04, 0x12, 0x10,
+    0x0a, 0x08, 0x68, 0x61, 0x73, 0x68, 0x54, 0x79, 0x70, 0x65, 0x18, 0x05, 0x20, 0x01, 0x28, 0x04,
+    0x12, 0x0e, 0x0a, 0x06, 0x66, 0x61, 0x6e, 0x6f, 0x75, 0x74, 0x18, 0x06, 0x20, 0x01, 0x28, 0x04,
+    0x12, 0x0c, 0x0a, 0x04, 0x6d, 0x6f, 0x64, 0x65, 0x18, 0x07, 0x20, 0x01, 0x28, 0x0d, 0x12, 0x22,
+    0x0a, 0x05, 0x6d, 0x74, 0x69, 0x6d, 0x65, 0x18, 0x08, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x13, 0x2e,
+    0x75, 0x6e, 0x69, 0x78, 0x66, 0x73, 0x2e, 0x70, 0x62, 0x2e, 0x55, 0x6e, 0x69, 0x78, 0x54, 0x69,
+    0x6d, 0x65, 0x22, 0x56, 0x0a, 0x08, 0x44, 0x61, 0x74, 0x61, 0x54, 0x79, 0x70, 0x65, 0x12, 0x07,
+    0x0a, 0x03, 0x52, 0x61, 0x77, 0x10, 0x00, 0x12, 0x0d, 0x0a, 0x09, 0x44, 0x69, 0x72, 0x65, 0x63,
+    0x74, 0x6f, 0x72, 0x79, 0x10, 0x01, 0x12, 0x08, 0x0a, 0x04, 0x46, 0x69, 0x6c, 0x65, 0x10, 0x02,
+    0x12, 0x0c, 0x0a, 0x08, 0x4d, 0x65, 0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x10, 0x03, 0x12, 0x0b,
+    0x0a, 0x07, 0x53, 0x79, 0x6d, 0x6c, 0x69, 0x6e, 0x6b, 0x10, 0x04, 0x12, 0x0d, 0x0a, 0x09, 0x48,
+    0x41, 0x4d, 0x54, 0x53, 0x68, 0x61, 0x72, 0x64, 0x10, 0x05, 0x22, 0x1c, 0x0a, 0x08, 0x4d, 0x65,
+    0x74, 0x61, 0x64, 0x61, 0x74, 0x61, 0x12, 0x10, 0x0a, 0x08, 0x4d, 0x69, 0x6d, 0x65, 0x54, 0x79,
+    0x70, 0x65, 0x18, 0x01, 0x20, 0x02, 0x28, 0x09, 0x22, 0x3a, 0x0a, 0x08, 0x55, 0x6e, 0x69, 0x78,
+    0x54, 0x69, 0x6d, 0x65, 0x12, 0x0f, 0x0a, 0x07, 0x53, 0x65, 0x63, 0x6f, 0x6e, 0x64, 0x73, 0x18,
+    0x01, 0x20, 0x02, 0x28, 0x03, 0x12, 0x1d, 0x0a, 0x15, 0x46, 0x72, 0x61, 0x63, 0x74, 0x69, 0x6f,
+    0x6e, 0x61, 0x6c, 0x4e, 0x61, 0x6e, 0x6f, 0x73, 0x65, 0x63, 0x6f, 0x6e, 0x64, 0x73, 0x18, 0x02,
+    0x20, 0x01, 0x28, 0x07, 0x4a, 0x87, 0x09, 0x0a, 0x06, 0x12, 0x04, 0x00, 0x00, 0x1f, 0x01, 0x0a,
+    0x08, 0x0a, 0x01, 0x02, 0x12, 0x03, 0x00, 0x08, 0x11, 0x0a, 0x0a, 0x0a, 0x02, 0x04, 0x00, 0x12,
+    0x04, 0x02, 0x00, 0x16, 0x01, 0x0a, 0x0a, 0x0a, 0x03, 0x04, 0x00, 0x01, 0x12, 0x03, 0x02, 0x08,
+    0x0c, 0x0a, 0x0c, 0x0a, 0x04, 0x04, 0x00, 0x04, 0x00, 0x12, 0x04, 0x03, 0x08, 0x0a, 0x09, 0x0a,
+    0x0c, 0x0a, 0x05, 0x04, 0x00, 0x04, 0x00, 0x01, 0x12, 0x03, 0x03, 0x0d, 0x15, 0x0a, 0x0d, 0x0a,
+    0x06, 0x04, 0x00, 0x04, 0x00, 0x02, 0x00, 0x12, 0x03, 0x04, 0x10, 0x18, 0x0a, 0x0e, 0x0a, 0x07,
+    0x04, 0x00, 0x04, 0x00, 0x02, 0x00, 0x01, 0x12, 0x03, 0x04, 0x10, 0x13, 0x0a, 0x0e, 0x0a, 0x07,
+    0x04, 0x00, 0x04, 0x00, 0x02, 0x00, 0x02, 0x12, 0x03, 0x04, 0x16, 0x17, 0x0a, 0x0d, 0x0a, 0x06,
+    0x04, 0x00, 0x04, 0x00, 0x02, 0x01, 0x12, 0x03, 0x05, 0x10, 0x1e, 0x0a, 0x0e, 0x0a, 0x07, 0x04,
+    0x00, 0x04, 0x00, 0x02, 0x01, 0x01, 0x12, 0x03, 0x05, 0x10, 0x19, 0x0a, 0x0e, 0x0a, 0x07, 0x04,
+    0x00, 0x04, 0x00, 0x02, 0x01, 0x02, 0x12, 0x03, 0x05, 0x1c, 0x1d, 0x0a, 0x0d, 0x0a, 0x06, 0x04,
+    0x00, 0x04, 0x00, 0x02, 0x02, 0x12, 0x03, 0x06, 0x10, 0x19, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00,
+    0x04, 0x00, 0x02, 0x02, 0x01, 0x12, 0x03, 0x06, 0x10, 0x14, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00,
+    0x04, 0x00, 0x02, 0x02, 0x02, 0x12, 0x03, 0x06, 0x17, 0x18, 0x0a, 0x0d, 0x0a, 0x06, 0x04, 0x00,
+    0x04, 0x00, 0x02, 0x03, 0x12, 0x03, 0x07, 0x10, 0x1d, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04,
+    0x00, 0x02, 0x03, 0x01, 0x12, 0x03, 0x07, 0x10, 0x18, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04,
+    0x00, 0x02, 0x03, 0x02, 0x12, 0x03, 0x07, 0x1b, 0x1c, 0x0a, 0x0d, 0x0a, 0x06, 0x04, 0x00, 0x04,
+    0x00, 0x02, 0x04, 0x12, 0x03, 0x08, 0x10, 0x1c, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04, 0x00,
+    0x02, 0x04, 0x01, 0x12, 0x03, 0x08, 0x10, 0x17, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04, 0x00,
+    0x02, 0x04, 0x02, 0x12, 0x03, 0x08, 0x1a, 0x1b, 0x0a, 0x0d, 0x0a, 0x06, 0x04, 0x00, 0x04, 0x00,
+    0x02, 0x05, 0x12, 0x03, 0x09, 0x10, 0x1e, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04, 0x00, 0x02,
+    0x05, 0x01, 0x12, 0x03, 0x09, 0x10, 0x19, 0x0a, 0x0e, 0x0a, 0x07, 0x04, 0x00, 0x04, 0x00, 0x02,
+    0x05, 0x02, 0x12, 0x03, 0x09, 0x1c, 0x1d, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x00, 0x12,
+    0x03, 0x0c, 0x08, 0x23, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x04, 0x12, 0x03, 0x0c,
+    0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x06, 0x12, 0x03, 0x0c, 0x11, 0x19,
+    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x01, 0x12, 0x03, 0x0c, 0x1a, 0x1e, 0x0a, 0x0c,
+    0x0a, 0x05, 0x04, 0x00, 0x02, 0x00, 0x03, 0x12, 0x03, 0x0c, 0x21, 0x22, 0x0a, 0x0b, 0x0a, 0x04,
+    0x04, 0x00, 0x02, 0x01, 0x12, 0x03, 0x0d, 0x08, 0x20, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02,
+    0x01, 0x04, 0x12, 0x03, 0x0d, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x01, 0x05,
+    0x12, 0x03, 0x0d, 0x11, 0x16, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x01, 0x01, 0x12, 0x03,
+    0x0d, 0x17, 0x1b, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x01, 0x03, 0x12, 0x03, 0x0d, 0x1e,
+    0x1f, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x02, 0x12, 0x03, 0x0e, 0x08, 0x25, 0x0a, 0x0c,
+    0x0a, 0x05, 0x04, 0x00, 0x02, 0x02, 0x04, 0x12, 0x03, 0x0e, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05,
+    0x04, 0x00, 0x02, 0x02, 0x05, 0x12, 0x03, 0x0e, 0x11, 0x17, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00,
+    0x02, 0x02, 0x01, 0x12, 0x03, 0x0e, 0x18, 0x20, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x02,
+    0x03, 0x12, 0x03, 0x0e, 0x23, 0x24, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x03, 0x12, 0x03,
+    0x0f, 0x08, 0x27, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x04, 0x12, 0x03, 0x0f, 0x08,
+    0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x05, 0x12, 0x03, 0x0f, 0x11, 0x17, 0x0a,
+    0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x03, 0x01, 0x12, 0x03, 0x0f, 0x18, 0x22, 0x0a, 0x0c, 0x0a,
+    0x05, 0x04, 0x00, 0x02, 0x03, 0x03, 0x12, 0x03, 0x0f, 0x25, 0x26, 0x0a, 0x0b, 0x0a, 0x04, 0x04,
+    0x00, 0x02, 0x04, 0x12, 0x03, 0x11, 0x08, 0x25, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x04,
+    0x04, 0x12, 0x03, 0x11, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x04, 0x05, 0x12,
+    0x03, 0x11, 0x11, 0x17, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x04, 0x01, 0x12, 0x03, 0x11,
+    0x18, 0x20, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x04, 0x03, 0x12, 0x03, 0x11, 0x23, 0x24,
+    0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x05, 0x12, 0x03, 0x12, 0x08, 0x23, 0x0a, 0x0c, 0x0a,
+    0x05, 0x04, 0x00, 0x02, 0x05, 0x04, 0x12, 0x03, 0x12, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04,
+    0x00, 0x02, 0x05, 0x05, 0x12, 0x03, 0x12, 0x11, 0x17, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02,
+    0x05, 0x01, 0x12, 0x03, 0x12, 0x18, 0x1e, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x05, 0x03,
+    0x12, 0x03, 0x12, 0x21, 0x22, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00, 0x02, 0x06, 0x12, 0x03, 0x14,
+    0x08, 0x21, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x06, 0x04, 0x12, 0x03, 0x14, 0x08, 0x10,
+    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x06, 0x05, 0x12, 0x03, 0x14, 0x11, 0x17, 0x0a, 0x0c,
+    0x0a, 0x05, 0x04, 0x00, 0x02, 0x06, 0x01, 0x12, 0x03, 0x14, 0x18, 0x1c, 0x0a, 0x0c, 0x0a, 0x05,
+    0x04, 0x00, 0x02, 0x06, 0x03, 0x12, 0x03, 0x14, 0x1f, 0x20, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x00,
+    0x02, 0x07, 0x12, 0x03, 0x15, 0x08, 0x24, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x07, 0x04,
+    0x12, 0x03, 0x15, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x07, 0x06, 0x12, 0x03,
+    0x15, 0x11, 0x19, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x07, 0x01, 0x12, 0x03, 0x15, 0x1a,
+    0x1f, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x00, 0x02, 0x07, 0x03, 0x12, 0x03, 0x15, 0x22, 0x23, 0x0a,
+    0x0a, 0x0a, 0x02, 0x04, 0x01, 0x12, 0x04, 0x18, 0x00, 0x1a, 0x01, 0x0a, 0x0a, 0x0a, 0x03, 0x04,
+    0x01, 0x01, 0x12, 0x03, 0x18, 0x08, 0x10, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x01, 0x02, 0x00, 0x12,
+    0x03, 0x19, 0x08, 0x25, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x04, 0x12, 0x03, 0x19,
+    0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x05, 0x12, 0x03, 0x19, 0x11, 0x17,
+    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x01, 0x12, 0x03, 0x19, 0x18, 0x20, 0x0a, 0x0c,
+    0x0a, 0x05, 0x04, 0x01, 0x02, 0x00, 0x03, 0x12, 0x03, 0x19, 0x23, 0x24, 0x0a, 0x0a, 0x0a, 0x02,
+    0x04, 0x02, 0x12, 0x04, 0x1c, 0x00, 0x1f, 0x01, 0x0a, 0x0a, 0x0a, 0x03, 0x04, 0x02, 0x01, 0x12,
+    0x03, 0x1c, 0x08, 0x10, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x02, 0x02, 0x00, 0x12, 0x03, 0x1d, 0x08,
+    0x23, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x02, 0x02, 0x00, 0x04, 0x12, 0x03, 0x1d, 0x08, 0x10, 0x0a,
+    0x0c, 0x0a, 0x05, 0x04, 0x02, 0x02, 0x00, 0x05, 0x12, 0x03, 0x1d, 0x11, 0x16, 0x0a, 0x0c, 0x0a,
+    0x05, 0x04, 0x02, 0x02, 0x00, 0x01, 0x12, 0x03, 0x1d, 0x17, 0x1e, 0x0a, 0x0c, 0x0a, 0x05, 0x04,
+    0x02, 0x02, 0x00, 0x03, 0x12, 0x03, 0x1d, 0x21, 0x22, 0x0a, 0x0b, 0x0a, 0x04, 0x04, 0x02, 0x02,
+    0x01, 0x12, 0x03, 0x1e, 0x08, 0x33, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x02, 0x02, 0x01, 0x04, 0x12,
+    0x03, 0x1e, 0x08, 0x10, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x02, 0x02, 0x01, 0x05, 0x12, 0x03, 0x1e,
+    0x11, 0x18, 0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x02, 0x02, 0x01, 0x01, 0x12, 0x03, 0x1e, 0x19, 0x2e,
+    0x0a, 0x0c, 0x0a, 0x05, 0x04, 0x02, 0x02, 0x01, 0x03, 0x12, 0x03, 0x1e, 0x31, 0x32,
 ];
 
 static mut file_descriptor_proto_lazy: ::protobuf::lazy::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::lazy::Lazy {
@@ -1,8 +1,10 @@
+use atomicwrites;
 use rust_multihash as multihash;
 
 use std::fs::{self, File};
 use std::io;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn hash<'a>(data: &'a [u8]) -> multihash::Multihash {
     multihash::multihash(data, multihash::HashType::SHA2_256)
@@ -28,3 +30,19 @@ pub fn ensure_dir_writable<P: AsRef<Path>>(path: P) -> io::Result<()> {
     try!(File::create(&path));
     fs::remove_file(path)
 }
+
+// Collapses the error of an atomic write whose closure performs io
+pub fn atomic_write_error(e: atomicwrites::Error<io::Error>) -> io::Error {
+    match e {
+        atomicwrites::Error::Internal(e) => e,
+        atomicwrites::Error::User(e) => e,
+    }
+}
+
+// Seconds since the Unix epoch
+pub fn unix_time() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs(),
+        Err(_) => 0,
+    }
+}
@@ -0,0 +1,93 @@
+// Resolving names without the network: DNSLink records come from a
+// StaticResolver, and IPNS records from an in-memory datastore
+
+extern crate ipfs;
+
+use ipfs::Datastore;
+use ipfs::Error;
+use ipfs::crypto::{KeyType, PrivateKey};
+use ipfs::dns::StaticResolver;
+use ipfs::ipns::{NameSystem, dnslink};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HASH: &'static str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+fn resolver(records: &[(&str, &str)]) -> StaticResolver {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+    for &(name, record) in records {
+        map.entry(name.to_string()).or_insert(vec![]).push(record.to_string());
+    }
+    StaticResolver::new(map)
+}
+
+fn namesys(records: &[(&str, &str)]) -> NameSystem {
+    NameSystem::new(Arc::new(Datastore::in_memory()), None, Arc::new(resolver(records)))
+}
+
+#[test]
+fn dnslink_prefers_the_dnslink_subdomain() {
+    let resolver = resolver(&[("_dnslink.example.com", "dnslink=/ipfs/QmSubdomain"),
+                              ("example.com", "dnslink=/ipfs/QmDomain")]);
+    assert_eq!(dnslink::resolve(&resolver, "example.com").unwrap(), "/ipfs/QmSubdomain");
+}
+
+#[test]
+fn dnslink_falls_back_to_the_domain() {
+    let resolver = resolver(&[("_dnslink.example.com", "v=spf1 -all"),
+                              ("example.com", "other=record"),
+                              ("example.com", "dnslink=/ipfs/QmDomain")]);
+    assert_eq!(dnslink::resolve(&resolver, "example.com").unwrap(), "/ipfs/QmDomain");
+}
+
+#[test]
+fn dnslink_without_a_record_is_not_found() {
+    let resolver = resolver(&[("example.com", "other=record")]);
+    match dnslink::resolve(&resolver, "example.com") {
+        Err(Error::NotFound(_)) => {}
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn namesys_follows_dnslink_chains() {
+    let record = format!("dnslink=/ipfs/{}", HASH);
+    let namesys = namesys(&[("_dnslink.a.example.com", "dnslink=/ipns/b.example.com/docs"),
+                            ("_dnslink.b.example.com", "dnslink=/ipns/c.example.com"),
+                            ("c.example.com", &record[..])]);
+    assert_eq!(namesys.resolve("/ipns/a.example.com/index.html").unwrap(),
+               format!("/ipfs/{}/docs/index.html", HASH));
+}
+
+#[test]
+fn namesys_follows_dnslink_to_an_ipns_name() {
+    let key = PrivateKey::generate(KeyType::Ed25519, 0);
+    let name = key.public_key().id();
+    let record = format!("dnslink=/ipns/{}", name);
+    let namesys = namesys(&[("_dnslink.example.com", &record[..])]);
+    namesys.publish(&key,
+                    &format!("/ipfs/{}", HASH),
+                    Duration::from_secs(60 * 60),
+                    Duration::from_secs(60))
+           .unwrap();
+    assert_eq!(namesys.resolve("/ipns/example.com").unwrap(), format!("/ipfs/{}", HASH));
+}
+
+#[test]
+fn namesys_gives_up_on_dnslink_loops() {
+    let namesys = namesys(&[("_dnslink.a.example.com", "dnslink=/ipns/b.example.com"),
+                            ("_dnslink.b.example.com", "dnslink=/ipns/a.example.com")]);
+    match namesys.resolve("/ipns/a.example.com") {
+        Err(Error::InvalidPath(_)) => {}
+        other => panic!("expected InvalidPath, got {:?}", other),
+    }
+}
+
+#[test]
+fn namesys_leaves_ipfs_paths_alone() {
+    let namesys = namesys(&[]);
+    let path = format!("/ipfs/{}/a", HASH);
+    assert_eq!(namesys.resolve(&path).unwrap(), path);
+}
@@ -0,0 +1,208 @@
+// Nodes talking to each other over loopback TCP connections, each test
+// with a small network of its own
+
+extern crate ipfs;
+
+use ipfs::IpfsNode;
+use ipfs::config::PubsubConfig;
+use ipfs::multiaddr::{Multiaddr, Protocol};
+use ipfs::pubsub::{Message, PubSub};
+use ipfs::swarm::relay;
+
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// An in-memory node online on a free loopback port, and its address ending
+// with /p2p/<peer id>
+fn online_node() -> (IpfsNode, Multiaddr) {
+    go_online(IpfsNode::in_memory())
+}
+
+// Brings `node` online as online_node does. mDNS is turned off so that the
+// nodes of tests running at the same time don't find each other.
+fn go_online(mut node: IpfsNode) -> (IpfsNode, Multiaddr) {
+    node.config_mut().discovery.as_mut().unwrap().mdns.enabled = false;
+    let listen_addr = Multiaddr::parse("/ip4/127.0.0.1/tcp/0").unwrap();
+    let mut addr = node.go_online(&[listen_addr]).unwrap().remove(0);
+    addr.push(Protocol::P2p(node.peer_id().clone()));
+    (node, addr)
+}
+
+// `addr` ending with /p2p/<peer> instead of the peer ID it had
+fn with_peer_id(addr: &Multiaddr, peer: &ipfs::peer::PeerId) -> Multiaddr {
+    let mut addr = addr.without_peer_id();
+    addr.push(Protocol::P2p(peer.clone()));
+    addr
+}
+
+#[test]
+fn noise_handshake_rejects_peer_id_mismatch() {
+    let (a, a_addr) = online_node();
+    let (b, _) = online_node();
+    let other = IpfsNode::in_memory();
+
+    let wrong_addr = with_peer_id(&a_addr, other.peer_id());
+    assert!(b.connect(&wrong_addr).is_err());
+    assert!(!b.get_swarm().unwrap().is_connected(a.peer_id()));
+
+    assert_eq!(b.connect(&a_addr).unwrap(), *a.peer_id());
+}
+
+#[test]
+fn bitswap_fetches_blocks_from_connected_peer() {
+    let (a, a_addr) = online_node();
+    let (b, _) = online_node();
+    // more than a chunk, so that the file has several blocks
+    let data: Vec<u8> = (0..300 * 1024).map(|i| (i % 251) as u8).collect();
+    let hash = a.add_reader(&mut &data[..]).unwrap();
+
+    b.connect(&a_addr).unwrap();
+    let mut fetched = Vec::new();
+    b.cat(&format!("/ipfs/{}", hash)).unwrap().read_to_end(&mut fetched).unwrap();
+    assert!(fetched == data);
+    assert!(b.blockstore().has(&hash).unwrap());
+}
+
+#[test]
+fn dht_finds_providers_through_another_node() {
+    let (a, _) = online_node();
+    let (b, b_addr) = online_node();
+    let (c, _) = online_node();
+    // a and c only know of each other through b
+    a.connect(&b_addr).unwrap();
+    c.connect(&b_addr).unwrap();
+
+    let hash = a.add_reader(&mut &b"provided by a"[..]).unwrap();
+    assert!(a.get_dht().unwrap().provide(&hash).unwrap() > 0);
+    let providers = c.get_dht().unwrap().find_providers(&hash, 1).unwrap();
+    assert_eq!(providers, vec![a.peer_id().clone()]);
+}
+
+#[test]
+fn dht_finds_providers_in_a_larger_network() {
+    // every node only connects to the first, and learns of the others
+    // through the DHT
+    let nodes: Vec<(IpfsNode, Multiaddr)> = (0..12).map(|_| online_node()).collect();
+    for &(ref node, _) in &nodes[1..] {
+        node.connect(&nodes[0].1).unwrap();
+    }
+
+    let (ref provider, _) = nodes[3];
+    let hash = provider.add_reader(&mut &b"provided by one of twelve"[..]).unwrap();
+    assert!(provider.get_dht().unwrap().provide(&hash).unwrap() > 0);
+    let (ref seeker, _) = nodes[9];
+    let providers = seeker.get_dht().unwrap().find_providers(&hash, 1).unwrap();
+    assert_eq!(providers, vec![provider.peer_id().clone()]);
+
+    // bitswap only asks connected peers, found through the DHT as well
+    seeker.connect_peer(provider.peer_id()).unwrap();
+    let mut fetched = Vec::new();
+    seeker.cat(&format!("/ipfs/{}", hash)).unwrap().read_to_end(&mut fetched).unwrap();
+    assert_eq!(fetched, b"provided by one of twelve".to_vec());
+}
+
+#[test]
+fn ipns_name_published_by_one_node_resolves_on_another() {
+    let (a, a_addr) = online_node();
+    let (b, _) = online_node();
+    b.connect(&a_addr).unwrap();
+
+    let path = format!("/ipfs/{}", a.add_reader(&mut &b"named by a"[..]).unwrap());
+    let (name, _) = a.publish_name("self",
+                                   &path,
+                                   Duration::from_secs(60 * 60),
+                                   Duration::from_secs(60))
+                     .unwrap();
+    assert_eq!(name, *a.peer_id());
+    assert_eq!(b.resolve_name(&name.to_string()).unwrap(), path);
+}
+
+#[test]
+fn relay_connects_peers_through_a_circuit() {
+    let mut relay = IpfsNode::in_memory();
+    relay.config_mut().relay.as_mut().unwrap().service.enabled = true;
+    let (relay, relay_addr) = go_online(relay);
+    let (a, _) = online_node();
+    let (b, _) = online_node();
+
+    // b can only be reached through the relay it has a reservation on
+    let (reserved_on, _) = relay::reserve(b.get_swarm().unwrap(), &relay_addr).unwrap();
+    assert_eq!(reserved_on, *relay.peer_id());
+    let mut circuit_addr = relay_addr.clone();
+    circuit_addr.push(Protocol::P2pCircuit);
+    circuit_addr.push(Protocol::P2p(b.peer_id().clone()));
+
+    assert_eq!(a.connect(&circuit_addr).unwrap(), *b.peer_id());
+    assert!(a.get_swarm().unwrap().is_connected(b.peer_id()));
+}
+
+// Three nodes all connected to each other, subscribed to one topic. Each
+// message reaches a subscriber both directly and through the third node,
+// and must only be delivered once.
+fn pubsub_delivers_each_message_once(router: &str) {
+    let mut nodes = Vec::new();
+    for _ in 0..3 {
+        let mut node = IpfsNode::in_memory();
+        node.config_mut().pubsub = Some(PubsubConfig { router: router.to_string() });
+        nodes.push(go_online(node));
+    }
+    nodes[0].0.connect(&nodes[1].1).unwrap();
+    nodes[0].0.connect(&nodes[2].1).unwrap();
+    nodes[1].0.connect(&nodes[2].1).unwrap();
+
+    let received: Vec<Receiver<Message>> =
+        nodes.iter().map(|&(ref node, _)| subscribe(node, "test-topic")).collect();
+    for &(ref node, _) in &nodes {
+        wait_for_topic_peers(node, "test-topic", 2);
+    }
+    // a few heartbeats for the gossipsub meshes to form
+    thread::sleep(Duration::from_secs(3));
+
+    let publisher = &nodes[0].0;
+    publisher.get_pubsub().unwrap().publish("test-topic", b"hello".to_vec()).unwrap();
+    thread::sleep(Duration::from_secs(2));
+    for receiver in &received {
+        let mut messages = Vec::new();
+        while let Ok(msg) = receiver.try_recv() {
+            messages.push(msg);
+        }
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].from, *publisher.peer_id());
+        assert_eq!(messages[0].data, b"hello".to_vec());
+    }
+}
+
+// Messages of `topic` received by `node`, passed on from a thread of their
+// own so that they can be counted without blocking
+fn subscribe(node: &IpfsNode, topic: &str) -> Receiver<Message> {
+    let subscription = PubSub::subscribe(node.get_pubsub().unwrap(), topic);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for msg in subscription {
+            if sender.send(msg).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+fn wait_for_topic_peers(node: &IpfsNode, topic: &str, n: usize) {
+    let start = Instant::now();
+    while node.get_pubsub().unwrap().peers(Some(topic)).len() < n {
+        assert!(start.elapsed() < Duration::from_secs(10), "peers didn't subscribe");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn floodsub_delivers_each_message_once() {
+    pubsub_delivers_each_message_once("floodsub");
+}
+
+#[test]
+fn gossipsub_delivers_each_message_once() {
+    pubsub_delivers_each_message_once("gossipsub");
+}
@@ -0,0 +1,48 @@
+// Directories added the way go-ipfs adds them must get the same CIDs. The
+// expected CIDs are those of go-ipfs's sharding tests (t0260-sharding.sh).
+
+extern crate ipfs;
+
+use ipfs::IpfsNode;
+use ipfs::config::UnixfsConfig;
+use ipfs::importer::AddOptions;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNSHARDED: &'static str = "QmavrTrQG4VhoJmantURAYuw3bowq3E2WcvP36NRQDAC1N";
+const SHARDED: &'static str = "QmSCJD1KYLhVVHqBK3YyXuoEqHt7vggyJhzoFYbT8v1XYL";
+
+// A directory of 2000 files, file<i> holding "<i>\n"
+fn make_test_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+    let dir = env::temp_dir().join(format!("ipfs-{}-{}", name, nanos));
+    fs::create_dir_all(&dir).unwrap();
+    for i in 1..2001 {
+        let mut file = File::create(dir.join(format!("file{}", i))).unwrap();
+        writeln!(file, "{}", i).unwrap();
+    }
+    dir
+}
+
+fn add_test_dir(name: &str, sharding_threshold: u64) -> String {
+    let mut node = IpfsNode::in_memory();
+    node.config_mut().unixfs = Some(UnixfsConfig { sharding_threshold_bytes: sharding_threshold });
+    let dir = make_test_dir(name);
+    let added = node.add_directory(&dir, &AddOptions::default()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    added.last().unwrap().1.to_string()
+}
+
+#[test]
+fn basic_directory_matches_go_ipfs() {
+    assert_eq!(add_test_dir("unsharded", u64::max_value()), UNSHARDED);
+}
+
+#[test]
+fn sharded_directory_matches_go_ipfs() {
+    assert_eq!(add_test_dir("sharded", 0), SHARDED);
+}